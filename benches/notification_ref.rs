@@ -0,0 +1,53 @@
+//! Compares reading a large list as `NotificationRef` with reading it as `Notification`.
+//!
+//! Run with `cargo bench --bench notification_ref`.
+
+use ironnotify::{Notification, NotificationRef};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITEMS: usize = 10_000;
+const ROUNDS: u32 = 20;
+
+fn body() -> Vec<u8> {
+    let items: Vec<_> = (0..ITEMS)
+        .map(|i| {
+            serde_json::json!({
+                "id": format!("notif_{}", i),
+                "eventType": "order.created",
+                "title": format!("Order #{}", i),
+                "message": "A new order was placed and is waiting for review",
+                "severity": "info",
+                "metadata": { "orderId": i, "total": 99.5 },
+                "userId": format!("user_{}", i % 100),
+                "groupKey": "orders",
+                "tags": ["billing", "eu"],
+                "category": "orders",
+                "deliveredVia": ["email", "push"],
+                "read": false,
+                "createdAt": "2026-10-14T12:00:00Z",
+            })
+        })
+        .collect();
+    serde_json::to_vec(&items).unwrap()
+}
+
+fn time(name: &str, body: &[u8], read: impl Fn(&[u8]) -> usize) {
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let started = Instant::now();
+        black_box(read(black_box(body)));
+        best = best.min(started.elapsed());
+    }
+    println!("{:<18} {:>10.2?} per {} items", name, best, ITEMS);
+}
+
+fn main() {
+    let body = body();
+    time("NotificationRef", &body, |body| {
+        serde_json::from_slice::<Vec<NotificationRef<'_>>>(body).unwrap().len()
+    });
+    time("Notification", &body, |body| {
+        serde_json::from_slice::<Vec<Notification>>(body).unwrap().len()
+    });
+}
@@ -0,0 +1,73 @@
+//! Counts the allocations made building an API request with the `Authorization` header
+//! formatted per request, as the transport used to, and with one computed up front.
+//!
+//! Run with `cargo bench --bench request_headers`.
+
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Method};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const API_KEY: &str = "ak_live_0123456789abcdef0123456789abcdef";
+const API_ROOT: &str = "https://api.ironnotify.com/api/v1";
+const REQUESTS: usize = 10_000;
+
+/// Counts every allocation made through the global allocator.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn count(name: &str, build: impl Fn() -> reqwest::Request) -> usize {
+    black_box(build());
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..REQUESTS {
+        black_box(build());
+    }
+    let per_request = (ALLOCATIONS.load(Ordering::Relaxed) - before) / REQUESTS;
+    println!("{:<12} {:>3} allocations per request", name, per_request);
+    per_request
+}
+
+fn main() {
+    let client = Client::new();
+
+    let formatted = count("formatted", || {
+        client
+            .request(Method::GET, format!("{}/{}", API_ROOT, "notifications/unread-count"))
+            .header(AUTHORIZATION, format!("Bearer {}", API_KEY))
+            .build()
+            .unwrap()
+    });
+
+    let mut header = HeaderValue::from_str(&format!("Bearer {}", API_KEY)).unwrap();
+    header.set_sensitive(true);
+    let path = "notifications/unread-count";
+    let precomputed = count("precomputed", || {
+        let mut url = String::with_capacity(API_ROOT.len() + path.len() + 1);
+        url.push_str(API_ROOT);
+        url.push('/');
+        url.push_str(path);
+        client
+            .request(Method::GET, url)
+            .header(AUTHORIZATION, header.clone())
+            .build()
+            .unwrap()
+    });
+
+    assert!(precomputed < formatted);
+}
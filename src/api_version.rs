@@ -0,0 +1,186 @@
+//! API versions for IronNotify SDK.
+
+use crate::error::Error;
+use crate::types::{Notification, NotificationAction};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Header selecting the API version on every request.
+pub(crate) const API_VERSION_HEADER: &str = "X-API-Version";
+
+/// API version requested from the server with the `X-API-Version` header.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    /// The original API (the default).
+    #[default]
+    V1,
+    /// The v2 API, which returns notifications with `threadId` and nested actions.
+    V2,
+    /// Any other version. Responses are read with the v1 shape.
+    Custom(String),
+}
+
+impl ApiVersion {
+    /// Gets the header value for this version.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::V1 => "1",
+            Self::V2 => "2",
+            Self::Custom(version) => version,
+        }
+    }
+
+    /// Decodes a notification list response in this version's shape.
+    pub(crate) fn decode_notifications(&self, value: Value) -> Result<Vec<Notification>, Error> {
+        match self {
+            Self::V2 => {
+                let items: Vec<NotificationV2> =
+                    serde_json::from_value(value).map_err(|e| Error::Codec(e.to_string()))?;
+                items.into_iter().map(NotificationV2::into_notification).collect()
+            }
+            Self::V1 | Self::Custom(_) => {
+                serde_json::from_value(value).map_err(|e| Error::Codec(e.to_string()))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A notification in the v2 shape.
+///
+/// v2 renames `groupKey` to `threadId` and wraps actions in an object:
+/// `"actions": { "items": [...] }`. Other fields are unchanged.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationV2 {
+    #[serde(default)]
+    thread_id: Option<String>,
+    #[serde(default)]
+    actions: Option<ActionsV2>,
+    #[serde(flatten)]
+    fields: Map<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct ActionsV2 {
+    #[serde(default)]
+    items: Vec<NotificationAction>,
+}
+
+/// Actions in either the v1 list shape or the v2 object shape.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyActions {
+    V1(Vec<NotificationAction>),
+    V2(ActionsV2),
+}
+
+/// Parses raw actions JSON in the v1 or v2 shape.
+pub(crate) fn parse_actions(raw: &str) -> Result<Vec<NotificationAction>, serde_json::Error> {
+    Ok(match serde_json::from_str(raw)? {
+        AnyActions::V1(items) | AnyActions::V2(ActionsV2 { items }) => items,
+    })
+}
+
+impl NotificationV2 {
+    /// Converts the notification to the public type through the v1 shape.
+    fn into_notification(self) -> Result<Notification, Error> {
+        let mut fields = self.fields;
+        fields.remove("groupKey");
+        if let Some(thread_id) = self.thread_id {
+            fields.insert("groupKey".to_string(), Value::String(thread_id));
+        }
+        if let Some(actions) = self.actions {
+            let actions =
+                serde_json::to_value(actions.items).map_err(|e| Error::Codec(e.to_string()))?;
+            fields.insert("actions".to_string(), actions);
+        }
+        serde_json::from_value(Value::Object(fields)).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A notification list as the v1 API returns it.
+    const V1_LIST: &str = r#"[{
+        "id": "n1", "eventType": "order.shipped", "title": "Order shipped",
+        "message": "Order #1234 is on its way", "severity": "info",
+        "metadata": { "orderId": 1234 }, "groupKey": "orders",
+        "actions": [
+            { "label": "Track", "url": "/orders/1234/track" },
+            { "label": "Dismiss", "action": "dismiss", "style": "secondary" }
+        ],
+        "tags": ["shipping"], "read": false, "createdAt": "2026-10-14T12:00:00Z"
+    }]"#;
+
+    /// The same list as the v2 API returns it.
+    const V2_LIST: &str = r#"[{
+        "id": "n1", "eventType": "order.shipped", "title": "Order shipped",
+        "message": "Order #1234 is on its way", "severity": "info",
+        "metadata": { "orderId": 1234 }, "threadId": "orders",
+        "actions": { "items": [
+            { "label": "Track", "url": "/orders/1234/track" },
+            { "label": "Dismiss", "action": "dismiss", "style": "secondary" }
+        ] },
+        "tags": ["shipping"], "read": false, "createdAt": "2026-10-14T12:00:00Z"
+    }]"#;
+
+    fn decode(version: ApiVersion, fixture: &str) -> Value {
+        let list = version
+            .decode_notifications(serde_json::from_str(fixture).unwrap())
+            .unwrap();
+        serde_json::to_value(list).unwrap()
+    }
+
+    #[test]
+    fn v1_lists_decode_field_for_field() {
+        let list = ApiVersion::V1
+            .decode_notifications(serde_json::from_str(V1_LIST).unwrap())
+            .unwrap();
+        let item = &list[0];
+        assert_eq!(item.group_key.as_deref(), Some("orders"));
+        let actions = item.actions.as_deref().unwrap();
+        assert_eq!(actions[0].url.as_deref(), Some("/orders/1234/track"));
+        assert_eq!(actions[1].action.as_deref(), Some("dismiss"));
+        assert_eq!(actions[1].style.as_deref(), Some("secondary"));
+        assert_eq!(item.metadata.as_ref().unwrap()["orderId"], 1234);
+        assert!(item.extra.is_empty());
+    }
+
+    #[test]
+    fn v2_lists_decode_to_the_same_notifications_as_v1() {
+        let v1 = decode(ApiVersion::V1, V1_LIST);
+        assert_eq!(decode(ApiVersion::V2, V2_LIST), v1);
+        assert_eq!(decode(ApiVersion::Custom("1.1".to_string()), V1_LIST), v1);
+    }
+
+    #[test]
+    fn v2_thread_ids_replace_group_keys() {
+        let fixture = V2_LIST.replace(r#""threadId": "orders""#, r#""groupKey": "stale""#);
+        assert_eq!(decode(ApiVersion::V2, &fixture)[0]["groupKey"], Value::Null);
+
+        let fixture = V2_LIST.replace(
+            r#""threadId": "orders""#,
+            r#""groupKey": "stale", "threadId": "orders""#,
+        );
+        assert_eq!(decode(ApiVersion::V2, &fixture)[0]["groupKey"], "orders");
+    }
+
+    #[test]
+    fn actions_parse_in_either_shape() {
+        let v1 = r#"[{ "label": "Track", "url": "/track" }]"#;
+        let v2 = r#"{ "items": [{ "label": "Track", "url": "/track" }] }"#;
+        let expected = serde_json::json!([{ "label": "Track", "url": "/track" }]);
+        let parsed = |raw| serde_json::to_value(parse_actions(raw).unwrap()).unwrap();
+        assert_eq!(parsed(v1), expected);
+        assert_eq!(parsed(v2), expected);
+        assert!(parse_actions(r#""Track""#).is_err());
+    }
+}
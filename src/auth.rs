@@ -0,0 +1,179 @@
+//! Bearer token providers and authentication state for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use crate::runtime::BoxFuture;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::RwLock;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// How long before expiry a cached token is refreshed.
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 30;
+
+/// A bearer token and when it expires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthToken {
+    /// The bearer token.
+    pub token: String,
+    /// When the token stops being accepted.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AuthToken {
+    /// Creates a token.
+    pub fn new(token: impl Into<String>, expires_at: DateTime<Utc>) -> Self {
+        Self {
+            token: token.into(),
+            expires_at,
+        }
+    }
+}
+
+/// Mints short-lived bearer tokens, used instead of a static API key.
+///
+/// Implement this to authenticate with tokens from your own auth service.
+pub trait TokenProvider: Debug + Send + Sync + 'static {
+    /// Gets a fresh token.
+    fn token(&self) -> BoxFuture<Result<AuthToken, String>>;
+}
+
+/// Caches the provider's token until shortly before it expires.
+///
+/// Refreshes hold the lock, so concurrent requests wait for one provider call
+/// instead of each making their own.
+pub(crate) struct TokenCache {
+    provider: Arc<dyn TokenProvider>,
+    debug: DebugSwitch,
+    current: Mutex<Option<AuthToken>>,
+}
+
+impl TokenCache {
+    /// Creates an empty cache for a provider.
+    pub fn new(provider: Arc<dyn TokenProvider>, debug: DebugSwitch) -> Self {
+        Self {
+            provider,
+            debug,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Gets a token, refreshing it if it is close to expiry.
+    ///
+    /// When `rejected` is set, that token is replaced even if it has not expired,
+    /// unless another request already replaced it.
+    pub async fn get(&self, rejected: Option<&str>) -> Result<String, String> {
+        let mut current = self.current.lock().await;
+
+        if let Some(ref token) = *current {
+            let refresh_at = token.expires_at - Duration::seconds(TOKEN_REFRESH_MARGIN_SECS);
+            let fresh = refresh_at > Utc::now();
+            let replaced = rejected.is_some_and(|rejected| rejected != token.token);
+            if (fresh && rejected.is_none()) || replaced {
+                return Ok(token.token.clone());
+            }
+        }
+
+        if self.debug.is_enabled() {
+            println!("[IronNotify] Refreshing auth token");
+        }
+        let token = self
+            .provider
+            .token()
+            .await
+            .map_err(|e| format!("Token provider failed: {}", e))?;
+        let value = token.token.clone();
+        *current = Some(token);
+        Ok(value)
+    }
+}
+
+/// Callback invoked when the API starts rejecting the client's credentials.
+pub type AuthFailureCallback = Arc<dyn Fn(&AuthFailure) + Send + Sync>;
+
+/// A send rejected by the API with `401 Unauthorized` or `403 Forbidden`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthFailure {
+    /// HTTP status code of the rejection.
+    pub status: u16,
+    /// The error the API returned.
+    pub error: String,
+    /// When the credentials were first rejected.
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Whether the API accepts the client's credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthStatus {
+    /// No send has been rejected for its credentials since the last accepted request.
+    Ok,
+    /// Sends are rejected; they are not queued and flushing is stopped.
+    Failed(AuthFailure),
+}
+
+/// Tracks credential rejections, invoking the callback once per failure.
+pub(crate) struct AuthState {
+    debug: DebugSwitch,
+    failure: RwLock<Option<AuthFailure>>,
+    callback: RwLock<Option<AuthFailureCallback>>,
+}
+
+impl AuthState {
+    pub fn new(debug: DebugSwitch) -> Self {
+        Self {
+            debug,
+            failure: RwLock::new(None),
+            callback: RwLock::new(None),
+        }
+    }
+
+    /// Sets the auth failure callback.
+    pub fn set_callback(&self, callback: AuthFailureCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    pub fn status(&self) -> AuthStatus {
+        match *self.failure.read() {
+            Some(ref failure) => AuthStatus::Failed(failure.clone()),
+            None => AuthStatus::Ok,
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failure.read().is_some()
+    }
+
+    /// Records a rejection. Only the first one after the credentials were last
+    /// accepted is reported.
+    pub fn fail(&self, status: u16, error: String) {
+        let failure = {
+            let mut current = self.failure.write();
+            if current.is_some() {
+                return;
+            }
+            let failure = AuthFailure {
+                status,
+                error,
+                timestamp: Utc::now(),
+            };
+            *current = Some(failure.clone());
+            failure
+        };
+
+        eprintln!(
+            "[IronNotify] Warning: credentials rejected ({}), sending is stopped until they are accepted",
+            failure.status
+        );
+        let callback = self.callback.read().clone();
+        if let Some(callback) = callback {
+            callback(&failure);
+        }
+    }
+
+    /// Records that the credentials were accepted.
+    pub fn clear(&self) {
+        if self.failure.write().take().is_some() && self.debug.is_enabled() {
+            println!("[IronNotify] Credentials accepted, resuming sends");
+        }
+    }
+}
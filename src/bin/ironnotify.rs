@@ -0,0 +1,238 @@
+//! Command-line tool for IronNotify.
+//!
+//! Built with the `cli` feature. Configuration is read from the environment, as by
+//! `NotifyOptions::from_env`. Every command exits with a non-zero status when it fails.
+
+use clap::{Args, Parser, Subcommand};
+use ironnotify::{NotifyClient, NotifyOptions, SeverityLevel};
+use serde_json::{json, Value};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "ironnotify", version, about = "Send notifications and manage the offline queue")]
+struct Cli {
+    /// Print machine-readable JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Send a notification.
+    Send(SendArgs),
+    /// Inspect or drain the offline queue.
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommand,
+    },
+    /// Read notifications.
+    Notifications {
+        #[command(subcommand)]
+        command: NotificationsCommand,
+    },
+    /// Check that the API is reachable and accepts the credentials.
+    Health,
+}
+
+#[derive(Args)]
+struct SendArgs {
+    event_type: String,
+    title: String,
+    #[arg(long, default_value = "info")]
+    severity: SeverityLevel,
+    #[arg(long)]
+    message: Option<String>,
+    /// Metadata entry; values that parse as JSON are sent as such.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_metadata)]
+    metadata: Vec<(String, Value)>,
+    /// ID of the recipient.
+    #[arg(long)]
+    user: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// List the queued notifications, oldest first.
+    List,
+    /// Send the queued notifications.
+    Flush,
+    /// Remove every queued notification without sending it.
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum NotificationsCommand {
+    /// List notifications, newest first.
+    List {
+        /// Only list unread notifications.
+        #[arg(long)]
+        unread: bool,
+        #[arg(long)]
+        limit: Option<i32>,
+    },
+}
+
+/// Parses a `KEY=VALUE` metadata argument.
+fn parse_metadata(arg: &str) -> Result<(String, Value), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Expected KEY=VALUE, got {}", arg))?;
+    if key.is_empty() {
+        return Err("Metadata key must not be empty".to_string());
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => return fail(cli.json, &e.to_string()),
+    };
+    match runtime.block_on(run(&cli)) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => fail(cli.json, &e),
+    }
+}
+
+fn fail(json: bool, error: &str) -> ExitCode {
+    if json {
+        println!("{}", json!({ "success": false, "error": error }));
+    } else {
+        eprintln!("error: {}", error);
+    }
+    ExitCode::FAILURE
+}
+
+/// Runs a command, returning whether it succeeded. Failures that were already
+/// reported return `Ok(false)`.
+async fn run(cli: &Cli) -> Result<bool, String> {
+    let options = NotifyOptions::from_env().map_err(|e| e.to_string())?;
+    let client = NotifyClient::new(options).map_err(|e| e.to_string())?;
+
+    match cli.command {
+        Command::Send(ref args) => send(&client, args, cli.json).await,
+        Command::Queue { ref command } => queue(&client, command, cli.json).await,
+        Command::Notifications {
+            command: NotificationsCommand::List { unread, limit },
+        } => {
+            let notifications = client.get_notifications(limit, None, unread).await?;
+            if cli.json {
+                print_json(&notifications)?;
+            } else {
+                for n in &notifications {
+                    let state = if n.read { "read" } else { "unread" };
+                    println!("{}\t{}\t{}\t{}\t{}", n.id, n.created_at, n.severity, state, n.title);
+                }
+            }
+            Ok(true)
+        }
+        Command::Health => health(&client, cli.json).await,
+    }
+}
+
+async fn send(client: &NotifyClient, args: &SendArgs, json: bool) -> Result<bool, String> {
+    let mut event = client
+        .event(args.event_type.as_str())
+        .with_title(args.title.as_str())
+        .with_severity(args.severity);
+    if let Some(ref message) = args.message {
+        event = event.with_message(message.as_str());
+    }
+    for (key, value) in &args.metadata {
+        event = event.with_metadata(key.as_str(), value.clone());
+    }
+    if let Some(ref user) = args.user {
+        event = event.for_user(user.as_str());
+    }
+
+    let result = event.send().await;
+    if json {
+        println!(
+            "{}",
+            json!({
+                "success": result.success,
+                "notificationId": result.notification_id,
+                "queued": result.queued,
+                "queueReason": result.queue_reason.map(|reason| reason.to_string()),
+                "error": result.error,
+            })
+        );
+    } else if result.success {
+        println!("{}", result);
+    } else {
+        eprintln!("{}", result);
+    }
+    Ok(result.success)
+}
+
+async fn queue(client: &NotifyClient, command: &QueueCommand, json: bool) -> Result<bool, String> {
+    match command {
+        QueueCommand::List => {
+            let items = client.queued_notifications();
+            if json {
+                print_json(&items)?;
+            } else {
+                for item in &items {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        item.id,
+                        item.enqueued_at.map_or_else(|| "-".to_string(), |at| at.to_string()),
+                        item.payload.severity.unwrap_or_default(),
+                        item.payload.event_type,
+                        item.payload.title
+                    );
+                }
+            }
+            Ok(true)
+        }
+        QueueCommand::Flush => {
+            let before = client.queue_size();
+            client.flush().await;
+            let remaining = client.queue_size();
+            let sent = before.saturating_sub(remaining);
+            if json {
+                println!("{}", json!({ "sent": sent, "remaining": remaining }));
+            } else {
+                println!("sent {}, {} remaining", sent, remaining);
+            }
+            Ok(remaining == 0)
+        }
+        QueueCommand::Clear => {
+            let cleared = client.clear_queue();
+            if json {
+                println!("{}", json!({ "cleared": cleared }));
+            } else {
+                println!("cleared {}", cleared);
+            }
+            Ok(true)
+        }
+    }
+}
+
+async fn health(client: &NotifyClient, json: bool) -> Result<bool, String> {
+    let online = client.check_connectivity().await;
+    let project = if online {
+        client.verify_credentials().await.map_err(|e| e.to_string())
+    } else {
+        Err("API is unreachable".to_string())
+    };
+
+    match (json, &project) {
+        (true, Ok(project)) => println!("{}", json!({ "online": true, "project": project })),
+        (true, Err(e)) => println!("{}", json!({ "online": online, "error": e })),
+        (false, Ok(project)) => println!("ok: {} ({})", project.name, project.environment),
+        (false, Err(e)) => eprintln!("error: {}", e),
+    }
+    Ok(project.is_ok())
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
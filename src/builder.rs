@@ -1,129 +1,851 @@
 //! Event builder for IronNotify SDK.
 
 use crate::client::NotifyClient;
-use crate::types::{NotificationAction, NotificationPayload, SendResult, SeverityLevel};
-use chrono::{DateTime, Duration, Utc};
-use std::collections::HashMap;
+use crate::config::NotifyOptions;
+use crate::debug::DebugLevel;
+use crate::delivery::{DeliveryCallbacks, ReadReceipt};
+use crate::event_types::validate_event_type;
+use crate::overflow::OverflowStrategy;
+use crate::rollout;
+use crate::tenant::{self, Tenant};
+use crate::text::{
+    self, MAX_MESSAGE_CHARS, MAX_TITLE_CHARS, TRUNCATED_MESSAGE_KEY, TRUNCATED_TITLE_KEY,
+};
+use crate::transport::is_reserved_header;
+use crate::types::{
+    Attachment, DeliveryChannels, ExpiresIn, GroupBehavior, Notification, NotificationAction,
+    NotificationPayload, SendResult, SeverityLevel, PAYLOAD_FIELDS,
+};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use tokio::sync::{oneshot, Semaphore};
 
-/// Builder for creating notifications with a fluent API.
-pub struct EventBuilder {
-    client: Arc<NotifyClient>,
+/// Maximum length of a single tag.
+const MAX_TAG_LENGTH: usize = 64;
+
+/// Maximum number of request headers on one notification.
+const MAX_REQUEST_HEADERS: usize = 16;
+
+/// Maximum total size of the names and values of one notification's request headers.
+const MAX_REQUEST_HEADER_BYTES: usize = 4096;
+
+/// Shortest deduplication window the server accepts.
+const MIN_DEDUPLICATION_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Longest deduplication window the server accepts, 7 days.
+const MAX_DEDUPLICATION_WINDOW: std::time::Duration = std::time::Duration::from_secs(7 * 86_400);
+
+/// A problem that prevents a notification from being built or sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The event type does not follow the naming rules.
+    InvalidEventType(&'static str),
+    /// No title was set.
+    MissingTitle,
+    /// More than one of user, device, or session is targeted.
+    MultipleTargets,
+    /// A tag is empty or whitespace.
+    EmptyTag,
+    /// A tag is longer than 64 characters.
+    TagTooLong,
+    /// The payload exceeds `max_payload_bytes` and `metadata_overflow` is `Fail`.
+    PayloadTooLarge { size: usize, limit: usize },
+    /// The metadata has more entries than `max_metadata_keys`.
+    TooManyMetadataKeys { count: usize, limit: usize },
+    /// There are more actions than `max_actions`.
+    TooManyActions { count: usize, limit: usize },
+    /// Acknowledgement is required on a notification below `Error` severity.
+    AckRequiresSeverity,
+    /// `via` and `suppress` leave no delivery channel.
+    NoDeliveryChannels,
+    /// A request header has an invalid name or value.
+    InvalidRequestHeader(String),
+    /// A request header is one the SDK sets, such as `Authorization` or `Host`.
+    ReservedRequestHeader(String),
+    /// There are more than 16 request headers.
+    TooManyRequestHeaders { count: usize, limit: usize },
+    /// The request headers are larger than 4096 bytes in total.
+    RequestHeadersTooLarge { size: usize, limit: usize },
+    /// The rollout percentage is not in (0, 100].
+    InvalidRolloutPercentage,
+    /// A group summary was set without a group key.
+    GroupSummaryWithoutKey,
+    /// An extra field has the wire name of a modeled payload field, e.g. `title`.
+    ExtraFieldCollision(String),
+    /// The deduplication window is shorter than 1 second or longer than 7 days.
+    InvalidDeduplicationWindow,
+    /// An attachment has no file name or an invalid content type.
+    InvalidAttachment(String),
+    /// The attachments are larger than `max_attachment_bytes` in total.
+    AttachmentsTooLarge { size: usize, limit: usize },
+}
+
+impl ValidationIssue {
+    /// Gets the message `build()` returns for this issue.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidEventType(message) => message,
+            Self::MissingTitle => "Notification title is required",
+            Self::MultipleTargets => "Only one of user, device, or session may be targeted",
+            Self::EmptyTag => "Tags must not be empty",
+            Self::TagTooLong => "Tags must be at most 64 characters",
+            Self::PayloadTooLarge { .. } => "Payload exceeds the maximum size",
+            Self::TooManyMetadataKeys { .. } => "Metadata has too many entries",
+            Self::TooManyActions { .. } => "Notification has too many actions",
+            Self::AckRequiresSeverity => "Acknowledgement requires Error or Critical severity",
+            Self::NoDeliveryChannels => "At least one delivery channel is required",
+            Self::InvalidRequestHeader(_) => "Request header name or value is invalid",
+            Self::ReservedRequestHeader(_) => "Request header is set by the SDK",
+            Self::TooManyRequestHeaders { .. } => "Notification has too many request headers",
+            Self::RequestHeadersTooLarge { .. } => "Request headers exceed the maximum size",
+            Self::InvalidRolloutPercentage => "Rollout percentage must be above 0 and at most 100",
+            Self::GroupSummaryWithoutKey => "Group summary requires a group key",
+            Self::ExtraFieldCollision(_) => "Extra field has the name of a payload field",
+            Self::InvalidDeduplicationWindow => {
+                "Deduplication window must be between 1 second and 7 days"
+            }
+            Self::InvalidAttachment(_) => "Attachment needs a file name and a valid content type",
+            Self::AttachmentsTooLarge { .. } => "Attachments exceed the maximum size",
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PayloadTooLarge { size, limit } => {
+                write!(f, "Payload is {} bytes, exceeding the {} byte limit", size, limit)
+            }
+            Self::TooManyMetadataKeys { count, limit } => {
+                write!(f, "Metadata has {} entries, exceeding the limit of {}", count, limit)
+            }
+            Self::TooManyActions { count, limit } => {
+                write!(f, "Notification has {} actions, exceeding the limit of {}", count, limit)
+            }
+            Self::InvalidRequestHeader(name) => {
+                write!(f, "Request header {} has an invalid name or value", name)
+            }
+            Self::ReservedRequestHeader(name) => {
+                write!(f, "Request header {} is set by the SDK and cannot be overridden", name)
+            }
+            Self::TooManyRequestHeaders { count, limit } => {
+                write!(
+                    f,
+                    "Notification has {} request headers, exceeding the limit of {}",
+                    count, limit
+                )
+            }
+            Self::RequestHeadersTooLarge { size, limit } => {
+                write!(f, "Request headers are {} bytes, exceeding the {} byte limit", size, limit)
+            }
+            Self::ExtraFieldCollision(key) => {
+                write!(f, "Extra field {} has the name of a payload field", key)
+            }
+            Self::InvalidAttachment(name) => {
+                write!(f, "Attachment '{}' needs a file name and a valid content type", name)
+            }
+            Self::AttachmentsTooLarge { size, limit } => {
+                write!(f, "Attachments are {} bytes, exceeding the {} byte limit", size, limit)
+            }
+            other => write!(f, "{}", other.message()),
+        }
+    }
+}
+
+/// Builds a notification payload without a client, e.g. in a library that leaves
+/// sending to the application.
+///
+/// Has the same setters as `EventBuilder`, which wraps it, and ends in `build()`
+/// instead of `send()`. Without a client, `check` and `build` validate against the
+/// default options; pass the builder to `NotifyClient::event_from` to validate
+/// against the client's options and send it.
+///
+/// ```
+/// use ironnotify::{PayloadBuilder, SeverityLevel};
+///
+/// // In a library:
+/// fn order_shipped(order_id: &str) -> PayloadBuilder {
+///     PayloadBuilder::new("order.shipped")
+///         .with_title("Your order has shipped")
+///         .with_severity(SeverityLevel::Success)
+///         .with_metadata("order_id", order_id)
+/// }
+///
+/// let payload = order_shipped("1234").for_user("user-123").build().unwrap();
+/// assert_eq!(payload.user_id.as_deref(), Some("user-123"));
+/// assert_eq!(payload.metadata.unwrap()["order_id"], "1234");
+/// ```
+#[derive(Debug, Clone)]
+pub struct PayloadBuilder {
     event_type: String,
     title: Option<String>,
+    auto_title: bool,
     message: Option<String>,
     severity: SeverityLevel,
     metadata: HashMap<String, serde_json::Value>,
     actions: Vec<NotificationAction>,
     user_id: Option<String>,
+    device_id: Option<String>,
+    session_id: Option<String>,
     group_key: Option<String>,
     deduplication_key: Option<String>,
+    deduplication_window: Option<std::time::Duration>,
     expires_at: Option<DateTime<Utc>>,
+    tags: Vec<String>,
+    category: Option<String>,
+    ordered: bool,
+    ack_deadline: Option<std::time::Duration>,
+    channels: Option<DeliveryChannels>,
+    suppressed: DeliveryChannels,
+    request_headers: BTreeMap<String, String>,
+    truncate: bool,
+    keep_full_text: bool,
+    rollout: Option<f32>,
+    group_summary: Option<String>,
+    group_behavior: Option<GroupBehavior>,
+    attachments: Vec<Attachment>,
+    extra: HashMap<String, serde_json::Value>,
 }
 
-impl EventBuilder {
-    /// Creates a new EventBuilder.
-    pub(crate) fn new(client: Arc<NotifyClient>, event_type: impl Into<String>) -> Self {
-        Self {
-            client,
-            event_type: event_type.into(),
-            title: None,
-            message: None,
-            severity: SeverityLevel::Info,
-            metadata: HashMap::new(),
-            actions: Vec::new(),
-            user_id: None,
-            group_key: None,
-            deduplication_key: None,
-            expires_at: None,
+/// Builder for creating notifications with a fluent API.
+///
+/// Wraps a `PayloadBuilder`, adding the client that sends the notification and the
+/// callbacks for its result. Cloning is cheap: the client is shared, so one builder
+/// can be reused as a template.
+#[derive(Clone)]
+pub struct EventBuilder {
+    client: NotifyClient,
+    payload: PayloadBuilder,
+    delivery: DeliveryCallbacks,
+    track_read: bool,
+    tenant: Option<Tenant>,
+}
+
+/// Defines each payload setter once on `PayloadBuilder`, and on `EventBuilder` as a
+/// method with the same docs that forwards to it.
+macro_rules! payload_setters {
+    ($(
+        $(#[$attr:meta])*
+        fn $name:ident(&mut $builder:ident $(, $arg:ident: $ty:ty)*) $body:block
+    )*) => {
+        impl PayloadBuilder {
+            $(
+                $(#[$attr])*
+                pub fn $name(mut self $(, $arg: $ty)*) -> Self {
+                    let $builder = &mut self;
+                    $body
+                    self
+                }
+            )*
         }
-    }
 
+        impl EventBuilder {
+            $(
+                $(#[$attr])*
+                pub fn $name(mut self $(, $arg: $ty)*) -> Self {
+                    self.payload = self.payload.$name($($arg),*);
+                    self
+                }
+            )*
+        }
+    };
+}
+
+payload_setters! {
     /// Sets the notification title.
-    pub fn with_title(mut self, title: impl Into<String>) -> Self {
-        self.title = Some(title.into());
-        self
+    fn with_title(&mut builder, title: impl Into<String>) {
+        builder.title = Some(title.into());
+    }
+
+    /// Derives the title from the event type when none is set, e.g. `Order created`
+    /// for `order.created`, instead of failing the build. An explicit title is
+    /// always used instead.
+    ///
+    /// ```
+    /// use ironnotify::PayloadBuilder;
+    ///
+    /// let payload = PayloadBuilder::new("order.created").auto_title().build().unwrap();
+    /// assert_eq!(payload.title, "Order created");
+    ///
+    /// let payload = PayloadBuilder::new("order.created")
+    ///     .auto_title()
+    ///     .with_title("Order #1234 placed")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(payload.title, "Order #1234 placed");
+    /// ```
+    fn auto_title(&mut builder) {
+        builder.auto_title = true;
     }
 
     /// Sets the notification message.
-    pub fn with_message(mut self, message: impl Into<String>) -> Self {
-        self.message = Some(message.into());
-        self
+    fn with_message(&mut builder, message: impl Into<String>) {
+        builder.message = Some(message.into());
     }
 
     /// Sets the severity level.
-    pub fn with_severity(mut self, severity: SeverityLevel) -> Self {
-        self.severity = severity;
-        self
+    fn with_severity(&mut builder, severity: SeverityLevel) {
+        builder.severity = severity;
     }
 
     /// Adds a metadata entry.
-    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
-        self.metadata.insert(key.into(), value.into());
-        self
+    fn with_metadata(
+        &mut builder,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>
+    ) {
+        builder.metadata.insert(key.into(), value.into());
+    }
+
+    /// Adds a signed integer metadata entry, kept exact beyond 2^53.
+    fn with_metadata_i64(&mut builder, key: impl Into<String>, value: i64) {
+        builder.metadata.insert(key.into(), serde_json::Value::from(value));
+    }
+
+    /// Adds an unsigned integer metadata entry, kept exact beyond 2^53.
+    fn with_metadata_u64(&mut builder, key: impl Into<String>, value: u64) {
+        builder.metadata.insert(key.into(), serde_json::Value::from(value));
+    }
+
+    /// Adds a decimal metadata entry as its string form, so no precision is lost to `f64`.
+    ///
+    /// The entry is a JSON string, not a number, wherever the notification is read back.
+    /// Accepts any decimal type that formats exactly, e.g. `rust_decimal::Decimal`.
+    fn with_metadata_decimal(
+        &mut builder,
+        key: impl Into<String>,
+        value: impl std::fmt::Display
+    ) {
+        builder
+            .metadata
+            .insert(key.into(), serde_json::Value::String(value.to_string()));
     }
 
     /// Adds an action button.
-    pub fn with_action(mut self, action: NotificationAction) -> Self {
-        self.actions.push(action);
-        self
+    fn with_action(&mut builder, action: NotificationAction) {
+        builder.actions.push(action);
     }
 
     /// Adds an action button with a URL.
-    pub fn with_url_action(mut self, label: impl Into<String>, url: impl Into<String>) -> Self {
-        self.actions.push(NotificationAction::with_url(label, url));
-        self
+    fn with_url_action(&mut builder, label: impl Into<String>, url: impl Into<String>) {
+        builder.actions.push(NotificationAction::with_url(label, url));
     }
 
     /// Adds an action button with a handler.
-    pub fn with_handler_action(mut self, label: impl Into<String>, handler: impl Into<String>) -> Self {
-        self.actions.push(NotificationAction::with_handler(label, handler));
-        self
+    fn with_handler_action(
+        &mut builder,
+        label: impl Into<String>,
+        handler: impl Into<String>
+    ) {
+        builder.actions.push(NotificationAction::with_handler(label, handler));
     }
 
     /// Sets the target user ID.
-    pub fn for_user(mut self, user_id: impl Into<String>) -> Self {
-        self.user_id = Some(user_id.into());
-        self
+    fn for_user(&mut builder, user_id: impl Into<String>) {
+        builder.user_id = Some(user_id.into());
+    }
+
+    /// Sets the target device ID, for anonymous recipients.
+    fn for_device(&mut builder, device_id: impl Into<String>) {
+        builder.device_id = Some(device_id.into());
+    }
+
+    /// Sets the target session ID, for anonymous recipients.
+    fn for_session(&mut builder, session_id: impl Into<String>) {
+        builder.session_id = Some(session_id.into());
     }
 
     /// Sets the group key for grouping related notifications.
-    pub fn with_group_key(mut self, group_key: impl Into<String>) -> Self {
-        self.group_key = Some(group_key.into());
-        self
+    fn with_group_key(&mut builder, group_key: impl Into<String>) {
+        builder.group_key = Some(group_key.into());
     }
 
-    /// Sets the deduplication key.
-    pub fn with_deduplication_key(mut self, key: impl Into<String>) -> Self {
-        self.deduplication_key = Some(key.into());
-        self
+    /// Sets the summary shown when the server collapses the group, e.g.
+    /// `"{{count}} builds failed on {{branch}}"`. `{{count}}` is the number of
+    /// notifications in the group; other placeholders are metadata keys.
+    ///
+    /// Requires a group key.
+    fn with_group_summary(&mut builder, template: impl Into<String>) {
+        builder.group_summary = Some(template.into());
+    }
+
+    /// Sets how the server collapses notifications with the same group key.
+    fn with_group_behavior(&mut builder, behavior: GroupBehavior) {
+        builder.group_behavior = Some(behavior);
+    }
+
+    /// Adds a body field the SDK does not model, e.g. `workflow_id` for a self-hosted
+    /// server. The key is sent as is, so it must not be the wire name of a payload
+    /// field such as `title` or `groupKey`.
+    fn with_extra_field(
+        &mut builder,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>
+    ) {
+        builder.extra.insert(key.into(), value.into());
+    }
+
+    /// Attaches a file, sent with the notification in a multipart request.
+    ///
+    /// The files of one notification may total at most `max_attachment_bytes`, 1 MB
+    /// by default. They are kept in the offline queue with the notification.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ironnotify::NotifyClient, chart: Vec<u8>) {
+    /// let result = client
+    ///     .event("report.ready")
+    ///     .with_title("Weekly error report")
+    ///     .attach_file("errors.png", "image/png", chart)
+    ///     .send()
+    ///     .await;
+    /// println!("Chart stored at {:?}", result.attachment_urls.first());
+    /// # }
+    /// ```
+    fn attach_file(
+        &mut builder,
+        name: impl Into<String>,
+        content_type: impl Into<String>,
+        content: impl Into<Vec<u8>>
+    ) {
+        builder
+            .attachments
+            .push(Attachment::new(name, content_type, content));
+    }
+
+    /// Sends strictly after earlier ordered notifications with the same group key.
+    ///
+    /// A later send waits until earlier ones have been sent, failed, or queued, and
+    /// the offline queue flushes each group key in the order notifications were
+    /// queued. Has no effect without a group key.
+    fn ordered(&mut builder) {
+        builder.ordered = true;
+    }
+
+    /// Requires a recipient to acknowledge the notification within `deadline`.
+    ///
+    /// The server escalates notifications left unacknowledged past the deadline.
+    /// Only allowed at `Error` or `Critical` severity.
+    fn requires_ack(&mut builder, deadline: std::time::Duration) {
+        builder.ack_deadline = Some(deadline);
+    }
+
+    /// Delivers only through the given channels instead of the project default.
+    fn via(&mut builder, channels: DeliveryChannels) {
+        builder.channels = Some(channels);
+    }
+
+    /// Excludes channels from delivery. Without `via`, all other channels are used.
+    fn suppress(&mut builder, channels: DeliveryChannels) {
+        builder.suppressed |= channels;
+    }
+
+    /// Adds an HTTP header to this notification's request, e.g. for routing through an
+    /// API gateway. Header names are case-insensitive; the headers are kept with the
+    /// notification in the offline queue and sent again on flush.
+    fn with_request_header(&mut builder, name: impl Into<String>, value: impl Into<String>) {
+        builder
+            .request_headers
+            .insert(name.into().to_ascii_lowercase(), value.into());
+    }
+
+    /// Shortens a title longer than 200 characters or a message longer than 2000, the
+    /// server's limits, between grapheme clusters and with an ellipsis.
+    fn truncate_to_limits(&mut builder, truncate: bool) {
+        builder.truncate = truncate;
+    }
+
+    /// Keeps the full text of a title or message shortened by `truncate_to_limits` in
+    /// the `truncated_title_full` or `truncated_message_full` metadata entry.
+    fn keep_full_text(&mut builder, keep: bool) {
+        builder.keep_full_text = keep;
+    }
+
+    /// Delivers the notification to only a percentage of its audience, in (0, 100].
+    /// The server picks the recipients. To pick them in the application, see
+    /// `rollout::in_bucket`.
+    fn rollout_percentage(&mut builder, percentage: f32) {
+        builder.rollout = Some(percentage);
+    }
+
+    /// Sets the deduplication key, deduplicated over the server's default window.
+    fn with_deduplication_key(&mut builder, key: impl Into<String>) {
+        builder.deduplication_key = Some(key.into());
+    }
+
+    /// Sets the deduplication key and how long the server drops later notifications
+    /// with the same key, between 1 second and 7 days. The window is sent in whole
+    /// seconds.
+    ///
+    /// A heartbeat check can run every minute and still alert at most every 15
+    /// minutes while the heartbeat stays missing:
+    ///
+    /// ```no_run
+    /// # async fn example(client: ironnotify::NotifyClient) {
+    /// use std::time::Duration;
+    ///
+    /// client
+    ///     .event("heartbeat.missed")
+    ///     .with_title("API heartbeat missed")
+    ///     .with_deduplication("heartbeat:api-1", Duration::from_secs(15 * 60))
+    ///     .send()
+    ///     .await;
+    /// # }
+    /// ```
+    fn with_deduplication(
+        &mut builder,
+        key: impl Into<String>,
+        window: std::time::Duration
+    ) {
+        builder.deduplication_key = Some(key.into());
+        builder.deduplication_window = Some(window);
+    }
+
+    /// Adds a tag. Duplicate tags are ignored.
+    fn with_tag(&mut builder, tag: impl Into<String>) {
+        let tag = tag.into();
+        if !builder.tags.contains(&tag) {
+            builder.tags.push(tag);
+        }
+    }
+
+    /// Sets the category, overriding the server default for the event type.
+    fn with_category(&mut builder, category: impl Into<String>) {
+        builder.category = Some(category.into());
     }
 
     /// Sets the expiration time from now.
-    pub fn expires_in(mut self, duration: Duration) -> Self {
-        self.expires_at = Some(Utc::now() + duration);
+    ///
+    /// Accepts chrono and std durations as well as an absolute `DateTime<Utc>`.
+    /// Durations too large to represent saturate instead of expiring immediately.
+    fn expires_in(&mut builder, expires: impl Into<ExpiresIn>) {
+        builder.expires_at = Some(expires.into().resolve(Utc::now()));
+    }
+
+    /// Sets the expiration time.
+    fn expires_at(&mut builder, time: DateTime<Utc>) {
+        builder.expires_at = Some(time);
+    }
+}
+
+impl PayloadBuilder {
+    /// Creates a payload builder for an event type.
+    pub fn new(event_type: impl Into<String>) -> Self {
+        Self {
+            event_type: event_type.into(),
+            title: None,
+            auto_title: false,
+            message: None,
+            severity: SeverityLevel::Info,
+            metadata: HashMap::new(),
+            actions: Vec::new(),
+            user_id: None,
+            device_id: None,
+            session_id: None,
+            group_key: None,
+            deduplication_key: None,
+            deduplication_window: None,
+            expires_at: None,
+            tags: Vec::new(),
+            category: None,
+            ordered: false,
+            ack_deadline: None,
+            channels: None,
+            suppressed: DeliveryChannels::NONE,
+            request_headers: BTreeMap::new(),
+            truncate: false,
+            keep_full_text: false,
+            rollout: None,
+            group_summary: None,
+            group_behavior: None,
+            attachments: Vec::new(),
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Creates a builder pre-filled with a received notification's content, as
+    /// `NotificationPayload::from(&Notification)` copies it.
+    pub(crate) fn from_notification(notification: &Notification) -> Self {
+        let payload = NotificationPayload::from(notification);
+        Self {
+            title: Some(payload.title),
+            message: payload.message,
+            severity: notification.severity,
+            metadata: payload.metadata.unwrap_or_default(),
+            actions: payload.actions.unwrap_or_default(),
+            group_key: payload.group_key,
+            expires_at: payload.expires_at,
+            tags: payload.tags.unwrap_or_default(),
+            category: payload.category,
+            ..Self::new(payload.event_type)
+        }
+    }
+
+    /// Adds multiple tags. Duplicate tags are ignored.
+    pub fn with_tags<I, T>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        for tag in tags {
+            self = self.with_tag(tag);
+        }
         self
     }
 
     /// Sets the expiration time from now (std Duration).
-    pub fn expires_in_std(mut self, duration: std::time::Duration) -> Self {
-        self.expires_at = Some(Utc::now() + Duration::from_std(duration).unwrap_or(Duration::zero()));
-        self
+    #[deprecated(note = "use `expires_in`, which accepts std durations")]
+    pub fn expires_in_std(self, duration: std::time::Duration) -> Self {
+        self.expires_in(duration)
     }
 
-    /// Sets the expiration time.
-    pub fn expires_at(mut self, time: DateTime<Utc>) -> Self {
-        self.expires_at = Some(time);
-        self
+    /// Gets the size in bytes of the payload this builder would build.
+    pub fn estimated_size(&self) -> usize {
+        self.size(&NotifyOptions::default(), DebugLevel::Off)
     }
 
-    /// Builds the notification payload.
+    /// Checks the notification against the default options without building it,
+    /// reporting every issue that would make `build()` or sending fail.
+    pub fn check(&self) -> Result<(), Vec<ValidationIssue>> {
+        self.check_against(&NotifyOptions::default(), DebugLevel::Off)
+    }
+
+    /// Builds the notification payload, validated against the default options.
     pub fn build(self) -> Result<NotificationPayload, &'static str> {
-        let title = self.title.ok_or("Notification title is required")?;
+        self.build_for(&NotifyOptions::default(), DebugLevel::Off)
+    }
+
+    fn size(&self, options: &NotifyOptions, debug: DebugLevel) -> usize {
+        self.clone().into_payload(options, debug).estimated_wire_size()
+    }
+
+    /// Checks the notification against client options, like `check`.
+    fn check_against(
+        &self,
+        options: &NotifyOptions,
+        debug: DebugLevel,
+    ) -> Result<(), Vec<ValidationIssue>> {
+        let mut issues = self.issues(options);
+
+        if options.metadata_overflow == OverflowStrategy::Fail {
+            let size = self.size(options, debug);
+            if size > options.max_payload_bytes {
+                issues.push(ValidationIssue::PayloadTooLarge {
+                    size,
+                    limit: options.max_payload_bytes,
+                });
+            }
+        }
+        if let Some(limit) = options.max_metadata_keys {
+            if self.metadata.len() > limit {
+                issues.push(ValidationIssue::TooManyMetadataKeys {
+                    count: self.metadata.len(),
+                    limit,
+                });
+            }
+        }
+        if let Some(limit) = options.max_actions {
+            if self.actions.len() > limit {
+                issues.push(ValidationIssue::TooManyActions {
+                    count: self.actions.len(),
+                    limit,
+                });
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Builds the notification payload, validated against client options.
+    fn build_for(
+        self,
+        options: &NotifyOptions,
+        debug: DebugLevel,
+    ) -> Result<NotificationPayload, &'static str> {
+        let issues = self.issues(options);
+        if let Some(issue) = issues.first() {
+            if debug.is_verbose() {
+                for issue in &issues {
+                    println!("[IronNotify] Invalid notification {}: {}", self.event_type, issue);
+                }
+            } else if debug.is_enabled() {
+                println!("[IronNotify] Invalid notification {}: {}", self.event_type, issue);
+            }
+            return Err(issue.message());
+        }
+        Ok(self.into_payload(options, debug))
+    }
+
+    /// Checks whether a missing title is derived from the event type.
+    fn derives_title(&self, options: &NotifyOptions) -> bool {
+        self.auto_title || options.derive_title_from_event_type
+    }
+
+    /// Gets the channels selected by `via` and `suppress`, if either was called.
+    fn delivery_channels(&self) -> Option<DeliveryChannels> {
+        if self.channels.is_none() && self.suppressed.is_empty() {
+            return None;
+        }
+        let channels = self.channels.unwrap_or(DeliveryChannels::ALL);
+        Some(channels.without(self.suppressed))
+    }
+
+    /// Gets the issues that prevent the payload from being built, in the order `build()` reports them.
+    fn issues(&self, options: &NotifyOptions) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+
+        if let Err(e) = validate_event_type(&self.event_type) {
+            issues.push(ValidationIssue::InvalidEventType(e));
+        }
+        if self.title.is_none() && !self.derives_title(options) {
+            issues.push(ValidationIssue::MissingTitle);
+        }
+
+        let targets = [&self.user_id, &self.device_id, &self.session_id]
+            .iter()
+            .filter(|target| target.is_some())
+            .count();
+        if targets > 1 {
+            issues.push(ValidationIssue::MultipleTargets);
+        }
+
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            issues.push(ValidationIssue::EmptyTag);
+        }
+        if self.tags.iter().any(|tag| tag.chars().count() > MAX_TAG_LENGTH) {
+            issues.push(ValidationIssue::TagTooLong);
+        }
+        if self.ack_deadline.is_some() && self.severity < SeverityLevel::Error {
+            issues.push(ValidationIssue::AckRequiresSeverity);
+        }
+        if self.delivery_channels().is_some_and(DeliveryChannels::is_empty) {
+            issues.push(ValidationIssue::NoDeliveryChannels);
+        }
+        self.check_request_headers(options.tenant_header.as_deref(), &mut issues);
+        if self.rollout.is_some_and(|percentage| !rollout::is_valid_percentage(percentage)) {
+            issues.push(ValidationIssue::InvalidRolloutPercentage);
+        }
+        if self.group_summary.is_some() && self.group_key.is_none() {
+            issues.push(ValidationIssue::GroupSummaryWithoutKey);
+        }
+        if self.deduplication_window.is_some_and(|window| {
+            !(MIN_DEDUPLICATION_WINDOW..=MAX_DEDUPLICATION_WINDOW).contains(&window)
+        }) {
+            issues.push(ValidationIssue::InvalidDeduplicationWindow);
+        }
+        let mut collisions: Vec<&String> = self
+            .extra
+            .keys()
+            .filter(|key| PAYLOAD_FIELDS.contains(&key.as_str()))
+            .collect();
+        collisions.sort();
+        issues.extend(
+            collisions
+                .into_iter()
+                .map(|key| ValidationIssue::ExtraFieldCollision(key.clone())),
+        );
+        self.check_attachments(options.max_attachment_bytes, &mut issues);
+
+        issues
+    }
+
+    fn check_request_headers(
+        &self,
+        tenant_header: Option<&str>,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        for (name, value) in &self.request_headers {
+            if is_reserved_header(name, tenant_header) {
+                issues.push(ValidationIssue::ReservedRequestHeader(name.clone()));
+            } else if HeaderName::from_bytes(name.as_bytes()).is_err()
+                || HeaderValue::from_str(value).is_err()
+            {
+                issues.push(ValidationIssue::InvalidRequestHeader(name.clone()));
+            }
+        }
+
+        let count = self.request_headers.len();
+        if count > MAX_REQUEST_HEADERS {
+            issues.push(ValidationIssue::TooManyRequestHeaders {
+                count,
+                limit: MAX_REQUEST_HEADERS,
+            });
+        }
+        let size: usize = self
+            .request_headers
+            .iter()
+            .map(|(name, value)| name.len() + value.len())
+            .sum();
+        if size > MAX_REQUEST_HEADER_BYTES {
+            issues.push(ValidationIssue::RequestHeadersTooLarge {
+                size,
+                limit: MAX_REQUEST_HEADER_BYTES,
+            });
+        }
+    }
+
+    fn check_attachments(&self, limit: usize, issues: &mut Vec<ValidationIssue>) {
+        for attachment in &self.attachments {
+            let valid_type = attachment.content_type.contains('/')
+                && HeaderValue::from_str(&attachment.content_type).is_ok();
+            if attachment.name.trim().is_empty() || !valid_type {
+                issues.push(ValidationIssue::InvalidAttachment(attachment.name.clone()));
+            }
+        }
+
+        let size: usize = self
+            .attachments
+            .iter()
+            .map(|attachment| attachment.content.len())
+            .sum();
+        if size > limit {
+            issues.push(ValidationIssue::AttachmentsTooLarge { size, limit });
+        }
+    }
 
-        Ok(NotificationPayload {
+    /// Shortens the title and message to the server's limits.
+    fn truncate_text(&mut self, debug: DebugLevel) {
+        let fields = [
+            (&mut self.title, "title", MAX_TITLE_CHARS, TRUNCATED_TITLE_KEY),
+            (&mut self.message, "message", MAX_MESSAGE_CHARS, TRUNCATED_MESSAGE_KEY),
+        ];
+        for (text, field, max, key) in fields {
+            let Some(text) = text else {
+                continue;
+            };
+            if let Some(short) = text::shorten(text, max) {
+                if debug.is_enabled() {
+                    println!(
+                        "[IronNotify] Truncated {} of {} characters to {}",
+                        field,
+                        text.chars().count(),
+                        short.chars().count()
+                    );
+                }
+                let full = std::mem::replace(text, short);
+                if self.keep_full_text {
+                    self.metadata.insert(key.to_string(), full.into());
+                }
+            }
+        }
+    }
+
+    fn into_payload(mut self, options: &NotifyOptions, debug: DebugLevel) -> NotificationPayload {
+        if self.title.is_none() && self.derives_title(options) {
+            self.title = Some(text::humanize_event_type(&self.event_type));
+        }
+        if self.truncate {
+            self.truncate_text(debug);
+        }
+        let channels = self.delivery_channels().map(DeliveryChannels::names);
+        NotificationPayload {
             event_type: self.event_type,
-            title,
+            title: self.title.unwrap_or_default(),
             message: self.message,
             severity: Some(self.severity),
             metadata: if self.metadata.is_empty() {
@@ -137,17 +859,605 @@ impl EventBuilder {
                 Some(self.actions)
             },
             user_id: self.user_id,
+            device_id: self.device_id,
+            session_id: self.session_id,
             group_key: self.group_key,
             deduplication_key: self.deduplication_key,
+            deduplication_window_seconds: self.deduplication_window.map(|window| window.as_secs()),
             expires_at: self.expires_at,
-        })
+            tags: if self.tags.is_empty() {
+                None
+            } else {
+                Some(self.tags)
+            },
+            category: self.category,
+            ordered: self.ordered,
+            requires_ack: self.ack_deadline.is_some(),
+            ack_deadline_seconds: self.ack_deadline.map(|deadline| deadline.as_secs()),
+            channels,
+            request_headers: if self.request_headers.is_empty() {
+                None
+            } else {
+                Some(self.request_headers)
+            },
+            rollout: self.rollout,
+            group_summary: self.group_summary,
+            group_behavior: self.group_behavior,
+            attachments: if self.attachments.is_empty() {
+                None
+            } else {
+                Some(self.attachments)
+            },
+            extra: self.extra,
+        }
+    }
+}
+
+impl EventBuilder {
+    /// Creates a new EventBuilder.
+    pub(crate) fn new(client: NotifyClient, event_type: impl Into<String>) -> Self {
+        Self::wrap(client, PayloadBuilder::new(event_type))
+    }
+
+    /// Creates a builder pre-filled with a received notification's content, as
+    /// `NotificationPayload::from(&Notification)` copies it.
+    pub(crate) fn from_notification(client: NotifyClient, notification: &Notification) -> Self {
+        Self::wrap(client, PayloadBuilder::from_notification(notification))
+    }
+
+    /// Creates an event builder that sends a payload builder's notification.
+    pub(crate) fn wrap(client: NotifyClient, payload: PayloadBuilder) -> Self {
+        Self {
+            client,
+            payload,
+            delivery: DeliveryCallbacks::default(),
+            track_read: false,
+            tenant: None,
+        }
+    }
+
+    /// Gets the payload builder this builder wraps.
+    pub fn into_payload_builder(self) -> PayloadBuilder {
+        self.payload
+    }
+
+    /// Adds multiple tags. Duplicate tags are ignored.
+    pub fn with_tags<I, T>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.payload = self.payload.with_tags(tags);
+        self
+    }
+
+    /// Sets the expiration time from now (std Duration).
+    #[deprecated(note = "use `expires_in`, which accepts std durations")]
+    pub fn expires_in_std(self, duration: std::time::Duration) -> Self {
+        self.expires_in(duration)
+    }
+
+    /// Sets a callback invoked with the final result of `send`.
+    ///
+    /// When the notification is queued, the callback waits in memory until the queued
+    /// copy is sent by `flush` or dropped. It does not survive a restart, and is not
+    /// invoked when another process sharing the queue sends the notification.
+    pub fn on_result<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&SendResult) + Send + Sync + 'static,
+    {
+        self.delivery.on_result = Some(Arc::new(callback));
+        self
+    }
+
+    /// Checks whether the recipient read the notification after it is sent, every
+    /// `read_poll_interval` for up to `read_tracking_timeout`, and invokes the
+    /// `on_read` callback once when they have.
+    pub fn track_read(mut self, track: bool) -> Self {
+        self.track_read = track;
+        self
+    }
+
+    /// Sets the callback invoked when a notification sent with `track_read` is read.
+    pub fn on_read<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ReadReceipt) + Send + Sync + 'static,
+    {
+        self.delivery.on_read = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sends as a tenant of `NotifyClient::with_tenant`.
+    pub(crate) fn tenant(mut self, tenant: Tenant) -> Self {
+        self.tenant = Some(tenant);
+        self
+    }
+
+    fn debug(&self) -> DebugLevel {
+        self.client.debug_levels().builder
+    }
+
+    /// Gets the size in bytes of the payload this builder would send.
+    pub fn estimated_size(&self) -> usize {
+        self.payload.size(self.client.options(), self.debug())
+    }
+
+    /// Checks the notification without building it, reporting every issue that
+    /// would make `build()` or sending fail.
+    pub fn check(&self) -> Result<(), Vec<ValidationIssue>> {
+        self.payload.check_against(self.client.options(), self.debug())
+    }
+
+    /// Builds the notification payload.
+    pub fn build(self) -> Result<NotificationPayload, &'static str> {
+        let debug = self.debug();
+        self.payload.build_for(self.client.options(), debug)
     }
 
     /// Sends the notification.
-    pub async fn send(self) -> SendResult {
-        match self.build() {
-            Ok(payload) => self.client.send_payload(&payload).await,
-            Err(e) => SendResult::failure(e),
+    pub async fn send(mut self) -> SendResult {
+        let client = self.client.clone();
+        let tenant = self.tenant.take();
+        let mut delivery = std::mem::take(&mut self.delivery);
+        if !self.track_read {
+            delivery.on_read = None;
+        }
+
+        let payload = self.build();
+        tenant::scoped(tenant, async move {
+            match payload {
+                Ok(payload) => client.send_with_callbacks(&payload, delivery).await,
+                Err(e) => {
+                    let result = SendResult::failure(e);
+                    if let Some(on_result) = delivery.on_result {
+                        on_result(&result);
+                    }
+                    result
+                }
+            }
+        })
+        .await
+    }
+
+    /// Sends the notification to each user, as if `for_user` and `send` were called
+    /// on a copy of the builder for each of them.
+    ///
+    /// Each copy is validated on its own, so a builder that also targets a device or
+    /// session fails for every user. `on_result` and `on_read` are called once per user.
+    ///
+    /// Sends run concurrently up to the client's fan-out limit. Results are returned
+    /// in the same order as `user_ids`.
+    pub async fn send_to_users(
+        self,
+        user_ids: impl IntoIterator<Item = String>,
+    ) -> Vec<(String, SendResult)> {
+        let user_ids: Vec<String> = user_ids.into_iter().collect();
+        let client = self.client.clone();
+
+        let semaphore = Arc::new(Semaphore::new(client.options().fan_out_concurrency.max(1)));
+        let mut tasks = Vec::with_capacity(user_ids.len());
+
+        for user_id in &user_ids {
+            let builder = self.clone().for_user(user_id.clone());
+            let semaphore = Arc::clone(&semaphore);
+
+            let (sender, receiver) = oneshot::channel();
+            client.spawn(Box::pin(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let _ = sender.send(builder.send().await);
+            }));
+            tasks.push(receiver);
         }
+
+        let mut results = Vec::with_capacity(user_ids.len());
+        for (user_id, task) in user_ids.into_iter().zip(tasks) {
+            let result = task
+                .await
+                .unwrap_or_else(|_| SendResult::failure("Send task was cancelled"));
+            results.push((user_id, result));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PayloadBuilder, ValidationIssue};
+    use crate::types::{
+        DeliveryChannels, GroupBehavior, NotificationAction, NotificationPayload, SeverityLevel,
+    };
+    use crate::test_support::{self, MockResponse, MockServer};
+    use crate::NotifyClient;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn client() -> (NotifyClient, MockServer) {
+        let server =
+            MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n" })));
+        let mut options = test_support::options(&server);
+        options.enable_offline_queue = false;
+        (NotifyClient::new(options).unwrap(), server)
+    }
+
+    #[test]
+    fn send_to_users_sends_a_validated_copy_per_user() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let (client, server) = client();
+            let calls = Arc::new(AtomicUsize::new(0));
+            let counted = Arc::clone(&calls);
+            let users = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+            let results = client
+                .event("e")
+                .with_title("t")
+                .on_result(move |_| {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                })
+                .send_to_users(users.clone())
+                .await;
+
+            let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+            assert_eq!(ids, ["a", "b", "c"]);
+            assert!(results.iter().all(|(_, result)| result.success));
+            assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+            let mut sent: Vec<String> = server
+                .requests_to("POST", "/api/v1/notify")
+                .iter()
+                .map(|request| request.json()["userId"].as_str().unwrap().to_string())
+                .collect();
+            sent.sort();
+            assert_eq!(sent, users);
+        });
+    }
+
+    #[test]
+    fn send_to_users_rejects_conflicting_targets() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let (client, server) = client();
+            let results = client
+                .event("e")
+                .with_title("t")
+                .for_device("device-1")
+                .send_to_users(vec!["a".to_string(), "b".to_string()])
+                .await;
+
+            assert_eq!(results.len(), 2);
+            assert!(results.iter().all(|(_, result)| !result.success));
+            assert!(server.requests().is_empty());
+        });
+    }
+
+    fn payload() -> PayloadBuilder {
+        PayloadBuilder::new("order.created").with_title("t")
+    }
+
+    #[test]
+    fn resends_carry_the_received_content_to_the_new_recipient() {
+        use crate::test_support::{received_notification, resent_payload};
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let (client, server) = client();
+            let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+            let result = client
+                .resend(&received_notification(expires_at))
+                .for_user("u2")
+                .send()
+                .await;
+            assert!(result.success);
+
+            let mut expected = resent_payload(Some(expires_at));
+            expected.user_id = Some("u2".to_string());
+            let sent = server.requests_to("POST", "/api/v1/notify");
+            assert_eq!(sent[0].json(), expected.wire_value());
+        });
+    }
+
+    #[test]
+    fn exact_numeric_metadata_survives_the_offline_queue() {
+        let _storage = test_support::TempStorage::new();
+        let server =
+            MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n" })));
+        let payload = payload()
+            .with_metadata_u64("order_id", 9_007_199_254_740_993)
+            .with_metadata_i64("balance", -9_007_199_254_740_993)
+            .with_metadata_decimal("amount", "1234.567890123456789012")
+            .build()
+            .unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let queued = NotifyClient::new(test_support::options(&server)).unwrap();
+            let receipt = queued.enqueue_only(&payload).await.unwrap();
+            drop(queued);
+
+            // A new client reads the entry back from the queue file.
+            let loaded = NotifyClient::new(test_support::options(&server)).unwrap();
+            assert!(loaded.confirm(&receipt).await.unwrap());
+            loaded.flush().await;
+        });
+
+        let sent = server.requests_to("POST", "/api/v1/notify");
+        assert_eq!(sent.len(), 1);
+        let body = String::from_utf8(sent[0].body.clone()).unwrap();
+        assert!(body.contains(r#""order_id":9007199254740993"#), "{}", body);
+        assert!(body.contains(r#""balance":-9007199254740993"#), "{}", body);
+        assert!(body.contains(r#""amount":"1234.567890123456789012""#), "{}", body);
+    }
+
+    #[test]
+    fn request_headers_the_sdk_sets_are_rejected() {
+        let issues = payload()
+            .with_request_header("Authorization", "Bearer other")
+            .with_request_header("x-route", "eu")
+            .check()
+            .unwrap_err();
+        assert_eq!(
+            issues,
+            [ValidationIssue::ReservedRequestHeader("authorization".to_string())]
+        );
+    }
+
+    #[test]
+    fn the_tenant_header_is_reserved_when_configured() {
+        let (_, server) = client();
+        let mut options = test_support::options(&server);
+        options.enable_offline_queue = false;
+        options.tenant_header = Some("X-Tenant-Id".to_string());
+        let client = NotifyClient::new(options).unwrap();
+
+        let issues = client
+            .event("order.created")
+            .with_title("t")
+            .with_request_header("X-Tenant-Id", "other")
+            .check()
+            .unwrap_err();
+        assert_eq!(
+            issues,
+            [ValidationIssue::ReservedRequestHeader("x-tenant-id".to_string())]
+        );
+        assert!(payload().with_request_header("X-Tenant-Id", "other").check().is_ok());
+    }
+
+    #[test]
+    fn invalid_request_headers_are_rejected() {
+        let issues = payload()
+            .with_request_header("bad header", "v")
+            .with_request_header("x-value", "line\nbreak")
+            .check()
+            .unwrap_err();
+        assert_eq!(
+            issues,
+            [
+                ValidationIssue::InvalidRequestHeader("bad header".to_string()),
+                ValidationIssue::InvalidRequestHeader("x-value".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn request_headers_are_limited_in_count_and_size() {
+        let mut builder = payload();
+        for i in 0..16 {
+            builder = builder.with_request_header(format!("x-h{}", i), "v");
+        }
+        assert!(builder.check().is_ok());
+        assert_eq!(
+            builder.with_request_header("x-h16", "v").check().unwrap_err(),
+            [ValidationIssue::TooManyRequestHeaders {
+                count: 17,
+                limit: 16
+            }]
+        );
+
+        let builder = payload().with_request_header("x-big", "v".repeat(4091));
+        assert!(builder.check().is_ok());
+        assert_eq!(
+            builder.with_request_header("x-a", "").check().unwrap_err(),
+            [ValidationIssue::RequestHeadersTooLarge {
+                size: 4099,
+                limit: 4096
+            }]
+        );
+    }
+
+    #[test]
+    fn request_headers_are_sent_with_the_request_but_not_in_the_body() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let (client, server) = client();
+            let result = client
+                .event("order.created")
+                .with_title("t")
+                .with_request_header("X-Route", "eu-west")
+                .send()
+                .await;
+            assert!(result.success);
+
+            let requests = server.requests_to("POST", "/api/v1/notify");
+            assert_eq!(requests[0].headers["x-route"], "eu-west");
+            let body = requests[0].json();
+            assert!(body.get("requestHeaders").is_none());
+        });
+    }
+
+    #[test]
+    fn the_estimated_size_is_the_size_of_the_sent_body() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let (client, server) = client();
+            let events = vec![
+                client.event("e").with_title("t"),
+                client
+                    .event("order.created")
+                    .with_title("Order \"#1234\" \\ placed\n")
+                    .with_message("Caf\u{e9} \u{1f680} \u{7}\t</script>")
+                    .with_severity(SeverityLevel::Critical),
+                client
+                    .event("build.failed")
+                    .with_title("Build failed")
+                    .with_metadata("branch", "main")
+                    .with_metadata("nested", json!({ "a": [1, 2.5, null, true], "b": {} }))
+                    .with_metadata_u64("big", u64::MAX)
+                    .with_metadata_decimal("price", "19.990")
+                    .with_action(NotificationAction::with_url("Open", "https://x.test/?a=1&b=2"))
+                    .with_tags(["ci", "nightly", "\u{fc}ber"])
+                    .with_category("builds")
+                    .with_group_key("main"),
+                client
+                    .event("e")
+                    .with_title("t")
+                    .with_message("m".repeat(5_000))
+                    .for_user("user-1")
+                    .with_deduplication("key", Duration::from_secs(60))
+                    .expires_in(Duration::from_secs(3600))
+                    .with_extra_field("workflowId", json!("wf-1")),
+                client
+                    .event("e")
+                    .with_title("t")
+                    .with_request_header("X-Route", "eu-west"),
+            ];
+
+            for (i, event) in events.into_iter().enumerate() {
+                let estimated = event.estimated_size();
+                assert!(event.send().await.success, "event {}", i);
+                let sent = server.requests_to("POST", "/api/v1/notify");
+                assert_eq!(estimated, sent[i].body.len(), "event {}", i);
+            }
+        });
+    }
+
+    #[test]
+    fn tags_survive_a_serde_round_trip() {
+        let built = payload()
+            .with_tags(["billing", "\u{fc}ber", "billing", "a b"])
+            .build()
+            .unwrap();
+        let value = serde_json::to_value(&built).unwrap();
+        assert_eq!(value["tags"], json!(["billing", "\u{fc}ber", "a b"]));
+        let read: NotificationPayload = serde_json::from_value(value).unwrap();
+        assert_eq!(read.tags, built.tags);
+
+        let untagged = serde_json::to_value(payload().build().unwrap()).unwrap();
+        assert!(untagged.get("tags").is_none());
+        let read: NotificationPayload = serde_json::from_value(untagged).unwrap();
+        assert_eq!(read.tags, None);
+    }
+
+    #[test]
+    fn tags_are_limited_to_64_characters() {
+        let longest = "\u{e9}".repeat(64);
+        assert!(payload().with_tag(&longest).check().is_ok());
+        assert_eq!(
+            payload().with_tag(format!("{}x", longest)).check(),
+            Err(vec![ValidationIssue::TagTooLong])
+        );
+        assert_eq!(
+            payload().with_tag(" ").with_tag("x".repeat(65)).check(),
+            Err(vec![ValidationIssue::EmptyTag, ValidationIssue::TagTooLong])
+        );
+        assert_eq!(
+            payload().with_tag("").build().unwrap_err(),
+            ValidationIssue::EmptyTag.message()
+        );
+    }
+
+    #[test]
+    fn via_limits_delivery_to_its_channels() {
+        let built = payload()
+            .via(DeliveryChannels::EMAIL | DeliveryChannels::PUSH)
+            .build()
+            .unwrap();
+        assert_eq!(built.channels.unwrap(), ["email", "push"]);
+        assert!(payload().build().unwrap().channels.is_none());
+    }
+
+    #[test]
+    fn suppress_removes_channels_from_the_selection() {
+        let built = payload().suppress(DeliveryChannels::EMAIL).build().unwrap();
+        assert_eq!(built.channels.unwrap(), ["in_app", "push", "webhook"]);
+
+        let built = payload()
+            .via(DeliveryChannels::EMAIL | DeliveryChannels::PUSH)
+            .suppress(DeliveryChannels::PUSH)
+            .suppress(DeliveryChannels::WEBHOOK)
+            .build()
+            .unwrap();
+        assert_eq!(built.channels.unwrap(), ["email"]);
+    }
+
+    #[test]
+    fn suppressing_every_channel_is_rejected() {
+        let issues = payload()
+            .via(DeliveryChannels::EMAIL)
+            .suppress(DeliveryChannels::EMAIL)
+            .check()
+            .unwrap_err();
+        assert_eq!(issues, [ValidationIssue::NoDeliveryChannels]);
+        assert!(payload().via(DeliveryChannels::NONE).check().is_err());
+    }
+
+    #[test]
+    fn a_group_summary_requires_a_group_key() {
+        let issues = payload().with_group_summary("{count} orders").check().unwrap_err();
+        assert_eq!(issues, [ValidationIssue::GroupSummaryWithoutKey]);
+
+        let built = payload()
+            .with_group_key("orders")
+            .with_group_summary("{count} orders")
+            .with_group_behavior(GroupBehavior::Count)
+            .build()
+            .unwrap();
+        let body = built.wire_value();
+        assert_eq!(body["groupSummaryTemplate"], "{count} orders");
+        assert_eq!(body["groupCountBehavior"], "count");
+    }
+
+    #[test]
+    fn a_group_behavior_alone_is_sent_without_a_summary() {
+        let body = payload()
+            .with_group_behavior(GroupBehavior::Stack)
+            .build()
+            .unwrap()
+            .wire_value();
+        assert_eq!(body["groupCountBehavior"], "stack");
+        assert!(body.get("groupSummaryTemplate").is_none());
+    }
+
+    #[test]
+    fn acknowledgement_requires_error_severity() {
+        for severity in [SeverityLevel::Info, SeverityLevel::Warning] {
+            let issues = payload()
+                .with_severity(severity)
+                .requires_ack(Duration::from_secs(600))
+                .check()
+                .unwrap_err();
+            assert_eq!(issues, [ValidationIssue::AckRequiresSeverity]);
+        }
+        for severity in [SeverityLevel::Error, SeverityLevel::Critical] {
+            let built = payload()
+                .with_severity(severity)
+                .requires_ack(Duration::from_secs(600))
+                .build()
+                .unwrap();
+            assert!(built.requires_ack);
+            assert_eq!(built.ack_deadline_seconds, Some(600));
+        }
+    }
+
+    #[test]
+    fn notifications_without_a_deadline_do_not_require_acknowledgement() {
+        let body = payload()
+            .with_severity(SeverityLevel::Critical)
+            .build()
+            .unwrap()
+            .wire_value();
+        assert!(body.get("requiresAck").is_none());
+        assert!(body.get("ackDeadlineSeconds").is_none());
     }
 }
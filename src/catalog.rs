@@ -0,0 +1,468 @@
+//! Notification catalog for IronNotify SDK.
+
+use crate::builder::EventBuilder;
+use crate::client::NotifyClient;
+use crate::error::Error;
+use crate::event_types::validate_event_type;
+use crate::types::{SendResult, SeverityLevel};
+use std::collections::HashMap;
+
+/// Defines typed senders for a catalog of notifications.
+///
+/// Each entry generates a parameters struct and an async function that sends the
+/// notification through the normal [`EventBuilder`](crate::EventBuilder), so defaults,
+/// interceptors, and validation still apply. Every parameter is added as metadata under
+/// its field name. The title is a format string that can use the parameters by name,
+/// so a missing parameter or a placeholder that is not one fails to compile.
+/// `severity` is optional and defaults to `Info`.
+///
+/// ```rust,no_run
+/// mod catalog {
+///     ironnotify::notification_catalog! {
+///         /// A customer placed an order.
+///         order_created => OrderCreatedParams {
+///             event_type: "order.created",
+///             severity: Success,
+///             title: "Order {order_id} received ({amount:.2})",
+///             params: { order_id: String, amount: f64 },
+///         }
+///     }
+/// }
+///
+/// # async fn run(client: ironnotify::NotifyClient) {
+/// let params = catalog::OrderCreatedParams { order_id: "1234".to_string(), amount: 99.5 };
+/// catalog::order_created(&client, params).await;
+/// # }
+/// ```
+///
+/// Use `into_event` on the parameters struct to target a recipient or set other fields
+/// before sending.
+#[macro_export]
+macro_rules! notification_catalog {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident => $params:ident {
+            event_type: $event_type:literal,
+            $(severity: $severity:ident,)?
+            title: $title:literal,
+            params: { $($field:ident: $ty:ty),* $(,)? } $(,)?
+        }
+    )*) => {$(
+        #[doc = concat!("Parameters for the `", $event_type, "` notification.")]
+        #[derive(Debug, Clone)]
+        pub struct $params {
+            $(pub $field: $ty,)*
+        }
+
+        impl $params {
+            /// Event type of the notification.
+            pub const EVENT_TYPE: &'static str = $event_type;
+
+            /// Creates the notification's event builder without sending it.
+            pub fn into_event(self, client: &$crate::NotifyClient) -> $crate::EventBuilder {
+                $(#[allow(unused_variables)] let $field = &self.$field;)*
+                let title = format!($title);
+                client
+                    .event($event_type)
+                    .with_severity($crate::SeverityLevel::Info)
+                    $(.with_severity($crate::SeverityLevel::$severity))?
+                    .with_title(title)
+                    $(.with_metadata(stringify!($field), self.$field))*
+            }
+        }
+
+        $(#[$meta])*
+        pub async fn $name(client: &$crate::NotifyClient, params: $params) -> $crate::SendResult {
+            params.into_event(client).send().await
+        }
+    )*};
+}
+
+/// Expected JSON type of a catalog parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    String,
+    Integer,
+    Number,
+    Bool,
+    /// Any JSON value.
+    Any,
+}
+
+impl ParamType {
+    /// Checks whether a value has this type.
+    pub fn matches(&self, value: &serde_json::Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Integer => value.is_i64() || value.is_u64(),
+            Self::Number => value.is_number(),
+            Self::Bool => value.is_boolean(),
+            Self::Any => true,
+        }
+    }
+}
+
+impl std::fmt::Display for ParamType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::Any => "any",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A notification definition in a [`NotificationCatalog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogEntry {
+    pub event_type: String,
+    pub severity: SeverityLevel,
+    /// Title with `{param}` placeholders. `{{` and `}}` are literal braces.
+    pub title: String,
+    /// Required parameters and their types, added as metadata.
+    pub params: Vec<(String, ParamType)>,
+}
+
+impl CatalogEntry {
+    /// Creates an entry with `Info` severity and no parameters.
+    pub fn new(event_type: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            event_type: event_type.into(),
+            severity: SeverityLevel::Info,
+            title: title.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Sets the default severity.
+    pub fn with_severity(mut self, severity: SeverityLevel) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Adds a required parameter.
+    pub fn with_param(mut self, name: impl Into<String>, param_type: ParamType) -> Self {
+        self.params.push((name.into(), param_type));
+        self
+    }
+}
+
+/// Registry of notification definitions checked at runtime, for when
+/// [`notification_catalog!`](crate::notification_catalog) cannot be used.
+#[derive(Debug, Clone, Default)]
+pub struct NotificationCatalog {
+    entries: HashMap<String, CatalogEntry>,
+}
+
+impl NotificationCatalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an entry, replacing any previous one for its event type.
+    ///
+    /// Fails if the event type is invalid or the title uses a placeholder that is not
+    /// a parameter.
+    pub fn register(&mut self, entry: CatalogEntry) -> Result<(), Error> {
+        validate_event_type(&entry.event_type)
+            .map_err(|e| Error::InvalidEventType(e.to_string()))?;
+
+        let problems = match parse_title(&entry.title) {
+            Ok(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    TitlePart::Param(name) if !entry.params.iter().any(|(p, _)| p == name) => {
+                        Some(format!("title placeholder '{}' is not a parameter", name))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Err(e) => vec![e.to_string()],
+        };
+        if !problems.is_empty() {
+            return Err(Error::CatalogParams {
+                event_type: entry.event_type,
+                problems,
+            });
+        }
+
+        self.entries.insert(entry.event_type.clone(), entry);
+        Ok(())
+    }
+
+    /// Gets the entry for an event type.
+    pub fn get(&self, event_type: &str) -> Option<&CatalogEntry> {
+        self.entries.get(event_type)
+    }
+
+    /// Creates the event builder for a catalog notification without sending it.
+    ///
+    /// Fails if the event type is not in the catalog, or if a parameter is missing,
+    /// has the wrong type, or is not declared.
+    pub fn event(
+        &self,
+        client: &NotifyClient,
+        event_type: &str,
+        params: HashMap<String, serde_json::Value>,
+    ) -> Result<EventBuilder, Error> {
+        let entry = self.entries.get(event_type).ok_or_else(|| {
+            Error::InvalidEventType(format!("'{}' is not in the catalog", event_type))
+        })?;
+
+        let mut problems = Vec::new();
+        for (name, param_type) in &entry.params {
+            match params.get(name) {
+                None => problems.push(format!("missing parameter '{}'", name)),
+                Some(value) if !param_type.matches(value) => {
+                    problems.push(format!("parameter '{}' must be of type {}", name, param_type))
+                }
+                Some(_) => {}
+            }
+        }
+        for name in params.keys() {
+            if !entry.params.iter().any(|(p, _)| p == name) {
+                problems.push(format!("unknown parameter '{}'", name));
+            }
+        }
+        if !problems.is_empty() {
+            problems.sort();
+            return Err(Error::CatalogParams {
+                event_type: entry.event_type.clone(),
+                problems,
+            });
+        }
+
+        let title = render_title(&entry.title, &params);
+        let mut builder = client
+            .event(&entry.event_type)
+            .with_severity(entry.severity)
+            .with_title(title);
+        for (key, value) in params {
+            builder = builder.with_metadata(key, value);
+        }
+        Ok(builder)
+    }
+
+    /// Sends a catalog notification.
+    pub async fn send(
+        &self,
+        client: &NotifyClient,
+        event_type: &str,
+        params: HashMap<String, serde_json::Value>,
+    ) -> SendResult {
+        match self.event(client, event_type, params) {
+            Ok(builder) => builder.send().await,
+            Err(e) => SendResult::failure(e.to_string()),
+        }
+    }
+}
+
+/// A piece of a title format.
+enum TitlePart<'a> {
+    Text(String),
+    Param(&'a str),
+}
+
+/// Splits a title format into literal text and `{param}` placeholders.
+fn parse_title(title: &str) -> Result<Vec<TitlePart<'_>>, &'static str> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = title;
+
+    while let Some(index) = rest.find(['{', '}']) {
+        text.push_str(&rest[..index]);
+        let brace = &rest[index..index + 1];
+        let after = &rest[index + 1..];
+        if after.starts_with(brace) {
+            text.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        if brace == "}" {
+            return Err("title has an unmatched '}'");
+        }
+
+        let end = after.find('}').ok_or("title has an unmatched '{'")?;
+        if !text.is_empty() {
+            parts.push(TitlePart::Text(std::mem::take(&mut text)));
+        }
+        parts.push(TitlePart::Param(after[..end].trim()));
+        rest = &after[end + 1..];
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        parts.push(TitlePart::Text(text));
+    }
+    Ok(parts)
+}
+
+/// Fills a title format's placeholders from the parameters.
+fn render_title(title: &str, params: &HashMap<String, serde_json::Value>) -> String {
+    let Ok(parts) = parse_title(title) else {
+        return title.to_string();
+    };
+
+    let mut rendered = String::new();
+    for part in parts {
+        match part {
+            TitlePart::Text(text) => rendered.push_str(&text),
+            TitlePart::Param(name) => match params.get(name) {
+                Some(serde_json::Value::String(value)) => rendered.push_str(value),
+                Some(value) => rendered.push_str(&value.to_string()),
+                None => {}
+            },
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyOptions;
+    use crate::test_support::{self, MockResponse, MockServer};
+    use serde_json::json;
+
+    crate::notification_catalog! {
+        order_created => OrderCreatedParams {
+            event_type: "order.created",
+            severity: Success,
+            title: "Order {order_id} received ({amount:.2})",
+            params: { order_id: String, amount: f64 },
+        }
+        ping => PingParams {
+            event_type: "system.ping",
+            title: "Ping",
+            params: {},
+        }
+    }
+
+    fn client() -> NotifyClient {
+        let mut options = NotifyOptions::new("ak_test_catalog");
+        options.enable_offline_queue = false;
+        NotifyClient::new(options).unwrap()
+    }
+
+    fn catalog() -> NotificationCatalog {
+        let mut catalog = NotificationCatalog::new();
+        let entry = CatalogEntry::new("invoice.paid", "Invoice {{{invoice}}} paid: {amount}")
+            .with_severity(SeverityLevel::Success)
+            .with_param("invoice", ParamType::String)
+            .with_param("amount", ParamType::Number);
+        catalog.register(entry).unwrap();
+        catalog
+    }
+
+    fn params(pairs: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(pairs).unwrap()
+    }
+
+    #[test]
+    fn macro_entries_format_their_title_and_add_their_params_as_metadata() {
+        let params = OrderCreatedParams {
+            order_id: "1234".to_string(),
+            amount: 99.5,
+        };
+        let payload = params.into_event(&client()).build().unwrap();
+        assert_eq!(payload.event_type, OrderCreatedParams::EVENT_TYPE);
+        assert_eq!(payload.title, "Order 1234 received (99.50)");
+        assert_eq!(payload.severity, Some(SeverityLevel::Success));
+        let metadata = payload.metadata.unwrap();
+        assert_eq!(metadata["order_id"], "1234");
+        assert_eq!(metadata["amount"], 99.5);
+
+        let payload = PingParams {}.into_event(&client()).build().unwrap();
+        assert_eq!(payload.severity, Some(SeverityLevel::Info));
+    }
+
+    #[test]
+    fn macro_senders_send_through_the_client() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "notificationId": "n1" }))
+        });
+        let mut options = test_support::options(&server);
+        options.enable_offline_queue = false;
+        let client = NotifyClient::new(options).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let params = OrderCreatedParams {
+                order_id: "1".to_string(),
+                amount: 2.0,
+            };
+            assert!(order_created(&client, params).await.success);
+            assert!(ping(&client, PingParams {}).await.success);
+        });
+        let sent: Vec<serde_json::Value> = server
+            .requests_to("POST", "/api/v1/notify")
+            .iter()
+            .map(|request| request.json()["eventType"].clone())
+            .collect();
+        assert_eq!(sent, [OrderCreatedParams::EVENT_TYPE, PingParams::EVENT_TYPE]);
+    }
+
+    #[test]
+    fn registered_entries_render_their_title() {
+        let params = params(json!({ "invoice": "INV-7", "amount": 12.5 }));
+        let payload = catalog().event(&client(), "invoice.paid", params).unwrap().build().unwrap();
+        assert_eq!(payload.title, "Invoice {INV-7} paid: 12.5");
+        assert_eq!(payload.severity, Some(SeverityLevel::Success));
+        assert_eq!(payload.metadata.unwrap()["invoice"], "INV-7");
+    }
+
+    #[test]
+    fn entries_with_bad_titles_or_event_types_are_not_registered() {
+        let cases = [
+            (CatalogEntry::new("a.b", "Hi {name}"), "title placeholder 'name' is not a parameter"),
+            (CatalogEntry::new("a.b", "Hi {name"), "title has an unmatched '{'"),
+            (CatalogEntry::new("a.b", "Hi name}"), "title has an unmatched '}'"),
+        ];
+        for (entry, problem) in cases {
+            match NotificationCatalog::new().register(entry) {
+                Err(Error::CatalogParams { problems, .. }) => assert_eq!(problems, [problem]),
+                other => panic!("{}: {:?}", problem, other),
+            }
+        }
+        let invalid = NotificationCatalog::new().register(CatalogEntry::new("a b", "Hi"));
+        assert!(matches!(invalid, Err(Error::InvalidEventType(_))));
+    }
+
+    #[test]
+    fn params_are_checked_against_the_entry() {
+        let params = params(json!({ "invoice": 7, "total": 1 }));
+        match catalog().event(&client(), "invoice.paid", params) {
+            Err(Error::CatalogParams { problems, .. }) => assert_eq!(
+                problems,
+                [
+                    "missing parameter 'amount'",
+                    "parameter 'invoice' must be of type string",
+                    "unknown parameter 'total'",
+                ]
+            ),
+            Err(e) => panic!("{}", e),
+            Ok(_) => panic!("invalid params were accepted"),
+        }
+        let missing = catalog().event(&client(), "invoice.void", HashMap::new());
+        assert!(matches!(missing, Err(Error::InvalidEventType(_))));
+    }
+
+    #[test]
+    fn param_types_match_their_json_values() {
+        let cases = [
+            (ParamType::String, json!("a"), json!(1)),
+            (ParamType::Integer, json!(-3), json!(1.5)),
+            (ParamType::Number, json!(1.5), json!("1.5")),
+            (ParamType::Bool, json!(true), json!(null)),
+        ];
+        for (param_type, matching, other) in cases {
+            assert!(param_type.matches(&matching), "{} {}", param_type, matching);
+            assert!(!param_type.matches(&other), "{} {}", param_type, other);
+        }
+        assert!(ParamType::Any.matches(&json!(null)));
+    }
+}
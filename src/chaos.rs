@@ -0,0 +1,177 @@
+//! Failure injection for resilience testing of IronNotify SDK users.
+
+use parking_lot::Mutex;
+use reqwest::Method;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Which requests a chaos rule applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaosScope {
+    /// Sends and other writes.
+    Sends,
+    /// Reads (`GET` and `HEAD` requests).
+    Reads,
+    /// All requests.
+    All,
+}
+
+impl ChaosScope {
+    fn indices(self) -> &'static [usize] {
+        match self {
+            Self::Sends => &[SENDS],
+            Self::Reads => &[READS],
+            Self::All => &[SENDS, READS],
+        }
+    }
+}
+
+const SENDS: usize = 0;
+const READS: usize = 1;
+
+/// What to do with one request.
+pub(crate) enum ChaosOutcome {
+    /// Send the request normally.
+    Pass,
+    /// Fail the request as if the connection failed.
+    Fail,
+    /// Respond with this status without sending the request.
+    Status(u16),
+}
+
+#[derive(Debug, Default, Clone)]
+struct ChaosRule {
+    failure_rate: f64,
+    latency: Option<(Duration, Duration)>,
+    forced_status: Option<(u16, usize)>,
+}
+
+#[derive(Debug)]
+struct ChaosState {
+    rules: [ChaosRule; 2],
+    fail_persistence: bool,
+    rng: u64,
+}
+
+impl ChaosState {
+    /// Gets a pseudo-random number in `[0, 1)` (xorshift64).
+    fn next_f64(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Handle for injecting failures into a client's requests and queue persistence.
+///
+/// Set it with `NotifyOptionsBuilder::chaos` and adjust it at runtime through
+/// `NotifyClient::chaos`. Clones share the same rules.
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    state: Arc<Mutex<ChaosState>>,
+}
+
+impl Default for Chaos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chaos {
+    /// Creates a handle that injects nothing until configured.
+    pub fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::with_seed(seed)
+    }
+
+    /// Creates a handle whose random failures and latencies are reproducible.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ChaosState {
+                rules: Default::default(),
+                fail_persistence: false,
+                rng: seed | 1,
+            })),
+        }
+    }
+
+    /// Fails requests with the given probability, from 0.0 to 1.0.
+    pub fn fail_requests(&self, scope: ChaosScope, probability: f64) -> &Self {
+        let mut state = self.state.lock();
+        for &i in scope.indices() {
+            state.rules[i].failure_rate = probability.clamp(0.0, 1.0);
+        }
+        self
+    }
+
+    /// Delays requests by a random duration between `min` and `max`.
+    pub fn inject_latency(&self, scope: ChaosScope, min: Duration, max: Duration) -> &Self {
+        let mut state = self.state.lock();
+        for &i in scope.indices() {
+            state.rules[i].latency = Some((min, max.max(min)));
+        }
+        self
+    }
+
+    /// Answers the next `count` requests with `status` without sending them, e.g. 429 or 500.
+    pub fn force_status(&self, scope: ChaosScope, status: u16, count: usize) -> &Self {
+        let mut state = self.state.lock();
+        for &i in scope.indices() {
+            state.rules[i].forced_status = Some((status, count));
+        }
+        self
+    }
+
+    /// Makes writes of the offline queue files fail while enabled.
+    pub fn fail_persistence(&self, enable: bool) -> &Self {
+        self.state.lock().fail_persistence = enable;
+        self
+    }
+
+    /// Removes all rules.
+    pub fn reset(&self) {
+        let mut state = self.state.lock();
+        state.rules = Default::default();
+        state.fail_persistence = false;
+    }
+
+    /// Checks whether queue persistence should fail.
+    pub(crate) fn persistence_fails(&self) -> bool {
+        self.state.lock().fail_persistence
+    }
+
+    /// Decides the delay and outcome of a request.
+    pub(crate) fn plan(&self, method: &Method) -> (Option<Duration>, ChaosOutcome) {
+        let index = if method == Method::GET || method == Method::HEAD {
+            READS
+        } else {
+            SENDS
+        };
+
+        let mut state = self.state.lock();
+        let rule = state.rules[index].clone();
+
+        let delay = rule.latency.map(|(min, max)| {
+            let spread = (max - min).as_secs_f64() * state.next_f64();
+            min + Duration::from_secs_f64(spread)
+        });
+
+        if let Some((status, remaining)) = rule.forced_status {
+            state.rules[index].forced_status = match remaining {
+                0 | 1 => None,
+                n => Some((status, n - 1)),
+            };
+            if remaining > 0 {
+                return (delay, ChaosOutcome::Status(status));
+            }
+        }
+
+        if rule.failure_rate > 0.0 && state.next_f64() < rule.failure_rate {
+            return (delay, ChaosOutcome::Fail);
+        }
+        (delay, ChaosOutcome::Pass)
+    }
+}
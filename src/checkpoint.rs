@@ -0,0 +1,214 @@
+//! Consumer checkpoints for IronNotify SDK.
+
+use crate::error::Error;
+use crate::runtime::BoxFuture;
+use crate::storage::JsonFile;
+use crate::types::Notification;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A position in the notification stream: the last processed notification's creation
+/// time and ID.
+///
+/// Cursors order by creation time, then by ID.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: String,
+}
+
+impl NotificationCursor {
+    /// Creates a cursor.
+    pub fn new(created_at: DateTime<Utc>, id: impl Into<String>) -> Self {
+        Self {
+            created_at,
+            id: id.into(),
+        }
+    }
+
+    /// Creates the cursor pointing at a notification.
+    pub fn of(notification: &Notification) -> Self {
+        Self::new(notification.created_at, notification.id.clone())
+    }
+
+    /// Checks whether a notification comes after this cursor.
+    pub fn is_before(&self, notification: &Notification) -> bool {
+        (self.created_at, self.id.as_str()) < (notification.created_at, notification.id.as_str())
+    }
+}
+
+/// Persists a consumer's cursor.
+///
+/// Implement this to keep checkpoints somewhere other than a local file, e.g. in the
+/// database the handler writes to. `save` must replace the cursor atomically.
+pub trait CheckpointBackend: Debug + Send + Sync + 'static {
+    /// Loads the last committed cursor, if any.
+    fn load(&self) -> BoxFuture<Result<Option<NotificationCursor>, Error>>;
+
+    /// Replaces the committed cursor.
+    fn save(&self, cursor: NotificationCursor) -> BoxFuture<Result<(), Error>>;
+}
+
+/// Keeps the cursor in a JSON file, written to a temporary file and renamed into place.
+#[derive(Debug)]
+pub struct FileCheckpoint {
+    file: Arc<JsonFile>,
+}
+
+impl FileCheckpoint {
+    /// Creates a backend storing the cursor at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            file: Arc::new(JsonFile::at(path.into())),
+        }
+    }
+}
+
+impl CheckpointBackend for FileCheckpoint {
+    fn load(&self) -> BoxFuture<Result<Option<NotificationCursor>, Error>> {
+        let file = self.file.clone();
+        Box::pin(async move {
+            match file.read() {
+                Some(data) => serde_json::from_str(&data).map(Some).map_err(|e| {
+                    Error::Checkpoint(format!("{}: {}", file.path().display(), e))
+                }),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn save(&self, cursor: NotificationCursor) -> BoxFuture<Result<(), Error>> {
+        let file = self.file.clone();
+        Box::pin(async move {
+            if file.write(&cursor) {
+                Ok(())
+            } else {
+                Err(Error::Checkpoint(format!(
+                    "could not write {}",
+                    file.path().display()
+                )))
+            }
+        })
+    }
+}
+
+/// A consumer's committed position, used by `consume_notifications`.
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    backend: Arc<dyn CheckpointBackend>,
+    debug: bool,
+}
+
+impl CheckpointStore {
+    /// Creates a store over a backend.
+    pub(crate) fn new(backend: Arc<dyn CheckpointBackend>, debug: bool) -> Self {
+        Self { backend, debug }
+    }
+
+    /// Gets the cursor of the last notification whose processing was committed.
+    pub async fn last_processed_cursor(&self) -> Result<Option<NotificationCursor>, Error> {
+        self.backend.load().await
+    }
+
+    /// Records that processing has finished up to and including `cursor`.
+    pub async fn commit_cursor(&self, cursor: NotificationCursor) -> Result<(), Error> {
+        if self.debug {
+            println!("[IronNotify] Committing checkpoint at {}", cursor.id);
+        }
+        self.backend.save(cursor).await
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::NotifyClient;
+    use crate::query::NotificationQuery;
+    use crate::test_support::{self, notification_json, MockResponse, MockServer, TempStorage};
+    use chrono::TimeZone;
+    use parking_lot::Mutex;
+    use serde_json::json;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, minute, 0).unwrap()
+    }
+
+    fn notification(id: &str, minute: u32) -> Notification {
+        serde_json::from_value(notification_json(id, at(minute))).unwrap()
+    }
+
+    #[test]
+    fn cursors_order_by_creation_time_then_id() {
+        let cursor = NotificationCursor::new(at(1), "b");
+        assert!(cursor.is_before(&notification("c", 1)));
+        assert!(cursor.is_before(&notification("a", 2)));
+        assert!(!cursor.is_before(&notification("b", 1)));
+        assert!(!cursor.is_before(&notification("z", 0)));
+        assert!(NotificationCursor::new(at(0), "z") < cursor);
+        assert_eq!(NotificationCursor::of(&notification("b", 1)), cursor);
+    }
+
+    #[test]
+    fn file_checkpoints_keep_the_last_committed_cursor() {
+        let storage = TempStorage::new();
+        let path = storage.path().join("consumer.json");
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let backend = FileCheckpoint::new(&path);
+            assert_eq!(backend.load().await.unwrap(), None);
+            backend.save(NotificationCursor::new(at(1), "a")).await.unwrap();
+            backend.save(NotificationCursor::new(at(2), "b")).await.unwrap();
+            let reopened = FileCheckpoint::new(&path);
+            let loaded = reopened.load().await.unwrap();
+            assert_eq!(loaded, Some(NotificationCursor::new(at(2), "b")));
+
+            std::fs::write(&path, "not json").unwrap();
+            assert!(matches!(reopened.load().await, Err(Error::Checkpoint(_))));
+        });
+    }
+
+    #[test]
+    fn consuming_resumes_after_the_last_committed_notification() {
+        let storage = TempStorage::new();
+        let server = MockServer::start(|_| {
+            let listed: Vec<_> = [("n3", 3), ("n1", 1), ("n2", 2)]
+                .iter()
+                .map(|&(id, minute)| notification_json(id, at(minute)))
+                .collect();
+            MockResponse::json(200, json!(listed))
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let checkpoint = client.checkpoint_store(FileCheckpoint::new(storage.path().join("c")));
+        let handled = Arc::new(Mutex::new(Vec::new()));
+
+        let query = NotificationQuery::new();
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let consume = |fail_on: &'static str| {
+                let handled = Arc::clone(&handled);
+                client.consume_notifications(&query, &checkpoint, move |n| {
+                    let failed = n.id == fail_on;
+                    if !failed {
+                        handled.lock().push(n.id);
+                    }
+                    async move { if failed { Err("handler failed") } else { Ok(()) } }
+                })
+            };
+
+            let stopped = consume("n2").await;
+            assert!(matches!(stopped, Err(Error::Handler(ref e)) if e == "handler failed"));
+            assert_eq!(
+                checkpoint.last_processed_cursor().await.unwrap(),
+                Some(NotificationCursor::new(at(1), "n1"))
+            );
+            assert_eq!(consume("none").await.unwrap(), 2);
+            assert_eq!(consume("none").await.unwrap(), 0);
+        });
+        assert_eq!(*handled.lock(), ["n1", "n2", "n3"]);
+    }
+}
@@ -0,0 +1,151 @@
+//! Circuit breaker for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// State of the send circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    #[default]
+    Closed,
+    /// Requests are rejected without touching the network.
+    Open,
+    /// A single probe request is allowed through to test recovery.
+    HalfOpen,
+}
+
+impl std::fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started_at: Option<Instant>,
+    times_opened: u64,
+}
+
+/// Circuit breaker that stops sending after repeated consecutive failures.
+pub(crate) struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    debug: DebugSwitch,
+    inner: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Creates a new CircuitBreaker. A threshold of zero disables the breaker.
+    pub fn new(threshold: u32, cooldown: Duration, debug: DebugSwitch) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            debug,
+            inner: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probe_started_at: None,
+                times_opened: 0,
+            }),
+        }
+    }
+
+    /// Checks whether a request may be sent, moving to half-open after the cool-down.
+    pub fn allow_request(&self) -> bool {
+        if self.threshold == 0 {
+            return true;
+        }
+
+        let mut inner = self.inner.lock();
+        match inner.state {
+            CircuitState::Closed => true,
+            CircuitState::Open => {
+                let cooled_down = match inner.opened_at {
+                    Some(opened) => opened.elapsed() >= self.cooldown,
+                    None => true,
+                };
+                if cooled_down {
+                    self.transition(&mut inner, CircuitState::HalfOpen);
+                    inner.probe_started_at = Some(Instant::now());
+                }
+                cooled_down
+            }
+            CircuitState::HalfOpen => {
+                // Allow a new probe if the previous one never reported back (e.g. it was cancelled).
+                let probe_stale = match inner.probe_started_at {
+                    Some(started) => started.elapsed() >= self.cooldown,
+                    None => true,
+                };
+                if probe_stale {
+                    inner.probe_started_at = Some(Instant::now());
+                }
+                probe_stale
+            }
+        }
+    }
+
+    /// Records a successful request, closing the circuit.
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = 0;
+        inner.probe_started_at = None;
+        if inner.state != CircuitState::Closed {
+            self.transition(&mut inner, CircuitState::Closed);
+        }
+    }
+
+    /// Records a failed request, opening the circuit once the threshold is reached.
+    pub fn record_failure(&self) {
+        if self.threshold == 0 {
+            return;
+        }
+
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        inner.probe_started_at = None;
+
+        let should_open = match inner.state {
+            CircuitState::Closed => inner.consecutive_failures >= self.threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+
+        if should_open {
+            inner.opened_at = Some(Instant::now());
+            inner.times_opened += 1;
+            self.transition(&mut inner, CircuitState::Open);
+        }
+    }
+
+    /// Resets the circuit to closed.
+    pub fn reset(&self) {
+        self.record_success();
+    }
+
+    /// Gets the current circuit state.
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().state
+    }
+
+    /// Gets the number of times the circuit has opened.
+    pub fn times_opened(&self) -> u64 {
+        self.inner.lock().times_opened
+    }
+
+    fn transition(&self, inner: &mut BreakerState, to: CircuitState) {
+        if self.debug.is_enabled() {
+            println!("[IronNotify] Circuit breaker {} -> {}", inner.state, to);
+        }
+        inner.state = to;
+    }
+}
@@ -1,59 +1,271 @@
 //! Main client for IronNotify SDK.
 
-use crate::builder::EventBuilder;
-use crate::config::NotifyOptions;
-use crate::queue::OfflineQueue;
+use crate::auth::{AuthFailure, AuthStatus};
+use crate::builder::{EventBuilder, PayloadBuilder};
+use crate::debug::{DebugConfig, DebugSwitch};
+use crate::decay;
+use crate::delivery::{
+    DeliveryCallbacks, DeliveryKey, PendingDeliveries, ReadReceipt, ReadReceiptCallback,
+};
+use crate::checkpoint::{CheckpointBackend, CheckpointStore, NotificationCursor};
+use crate::digest::DigestBuilder;
+#[cfg(any(test, feature = "chaos"))]
+use crate::chaos::Chaos;
+use crate::circuit::{CircuitBreaker, CircuitState};
+use crate::config::{NotifyOptions, OptionsError};
+#[cfg(feature = "postgres")]
+use crate::drops::DropReason;
+use crate::drops::{DropLog, DroppedNotification};
+use crate::error::Error;
+use crate::event_types::{validate_event_type, EventTypeDefinition};
+use crate::inbox::{self, InboxCache, InboxSummary};
+#[cfg(feature = "journal")]
+use crate::journal::{JournalEntry, SendJournal};
+use crate::limits::{LimitMonitor, LimitWarning};
+use crate::metrics::{Metrics, MetricsSnapshot, SlowRequest, TenantMetrics};
+use crate::mutations::{Mutation, MutationQueue, PendingMutation};
+use crate::ordering::KeyedLocks;
+use crate::overflow::{self, OverflowStrategy};
+use crate::pager::{Cursor, Pager};
+#[cfg(feature = "postgres")]
+use crate::postgres::PostgresQueueStorage;
+use crate::process::{InFlight, ProcessErrorPolicy, ProcessOptions, ProcessSummary};
+use crate::query::{CountGroupBy, NotificationQuery};
+use crate::queue::{
+    drain_order, is_high_priority, CompactionReport, DeferredNotification, EnqueueReceipt,
+    OfflineQueue, QueueStats, QueuedNotification, ReceiptStorage,
+};
+use crate::rate_limit::RateLimitStatus;
+use crate::read_cache::{CachedResult, ReadCache};
+use crate::realtime::{
+    self, Authorizer, ConnectSettings, ConnectionEvent, Realtime, RealtimeMessage, Replay,
+};
+use crate::reconfigure::{LiveConfig, RuntimeConfig};
+use crate::redact::Redactor;
+use crate::resolver::{self, UserContext, UserResolver};
+use crate::runtime::{blocking, default_runtime, timeout, BoxFuture, Interval, Runtime};
+#[cfg(feature = "schema")]
+use crate::schema::MetadataSchemas;
+use crate::search::{self, SearchHit};
+use crate::snapshot::NotificationSnapshot;
+use crate::state::WatchedState;
+use crate::tenant::{self, Tenant, TenantScopedClient};
+#[cfg(feature = "otel")]
+use crate::trace_context::{TraceContext, TRACING_METADATA_KEY};
 use crate::transport::Transport;
-use crate::types::{ConnectionState, Notification, NotificationPayload, SendResult, SeverityLevel};
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use crate::types::{
+    ConnectionState, DownloadInfo, KeyEnvironment, LocalEvent, Notification, NotificationEvent,
+    NotificationPayload, NotificationRef, ProjectInfo, QueueReason, SendResult, SeverityLevel,
+};
+use crate::websocket::TlsSettings;
+use chrono::{DateTime, Utc};
+use futures_util::future::join3;
+use parking_lot::{Mutex, RwLock};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::AsyncWrite;
+use tokio::sync::{broadcast, oneshot, watch, Mutex as AsyncMutex, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+/// Page size `consume_notifications` uses when the query sets no limit.
+const CONSUME_PAGE_SIZE: i32 = 100;
+
+/// Number of notifications claimed from the Postgres queue at a time when flushing.
+#[cfg(feature = "postgres")]
+const POSTGRES_CLAIM_BATCH: usize = 50;
+
+thread_local! {
+    /// Set while `emit_best_effort` runs on this thread.
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
 
 /// IronNotify client for sending and receiving notifications.
+///
+/// The client is a cheap handle: clones share the same connection pool,
+/// offline queue, and state.
+#[derive(Clone)]
 pub struct NotifyClient {
+    inner: Arc<ClientInner>,
+}
+
+struct ClientInner {
     options: NotifyOptions,
     transport: Transport,
     queue: Option<OfflineQueue>,
-    is_online: RwLock<bool>,
-    connection_state: RwLock<ConnectionState>,
+    mutations: Option<MutationQueue>,
+    mutation_replay: AsyncMutex<()>,
+    read_cache: Option<ReadCache>,
+    inbox_cache: Option<InboxCache>,
+    is_online: WatchedState<bool>,
+    realtime: Realtime,
+    metrics: Arc<Metrics>,
+    drops: Arc<DropLog>,
+    deliveries: Arc<PendingDeliveries>,
+    breaker: CircuitBreaker,
+    redactor: Redactor,
+    limits: LimitMonitor,
+    local_events: broadcast::Sender<LocalEvent>,
+    runtime: Arc<dyn Runtime>,
+    scheduled_flush: Mutex<Option<DateTime<Utc>>>,
+    paused: AtomicBool,
+    warming: AtomicBool,
+    user_resolver: RwLock<Option<Arc<UserResolver>>>,
+    ordering: KeyedLocks,
+    in_flight: Arc<InFlight>,
+    config: Arc<LiveConfig>,
+    #[cfg(feature = "journal")]
+    journal: Option<SendJournal>,
+    #[cfg(feature = "schema")]
+    schemas: MetadataSchemas,
+}
+
+impl ClientInner {
+    /// Gets the debug log levels in effect.
+    fn debug(&self) -> DebugConfig {
+        self.config.debug_levels()
+    }
 }
 
 impl NotifyClient {
     /// Creates a new NotifyClient.
-    pub fn new(options: NotifyOptions) -> Result<Arc<Self>, &'static str> {
-        if options.api_key.is_empty() {
-            return Err("API key is required");
-        }
+    ///
+    /// The options are normalized and checked like `NotifyOptionsBuilder::build` does.
+    pub fn new(mut options: NotifyOptions) -> Result<Self, OptionsError> {
+        options.normalize();
+        options.validate()?;
 
+        let config = LiveConfig::new(&options);
+        let metrics = Arc::new(Metrics::new(
+            options.slow_request_threshold,
+            DebugSwitch::transport(&config),
+        ));
+        let redactor = Redactor::new(options.redact_metadata_keys.clone());
+        let deliveries = Arc::new(PendingDeliveries::default());
+        let drops = Arc::new(DropLog::new(
+            options.recent_drops_capacity,
+            DebugSwitch::queue(&config),
+            Arc::clone(&deliveries),
+        ));
+
+        let realtime = Realtime::new(Arc::clone(&metrics), DebugSwitch::realtime(&config));
+        let runtime = options.runtime.clone().unwrap_or_else(default_runtime);
         let transport = Transport::new(
-            options.api_base_url.clone(),
-            options.api_key.clone(),
-            options.http_timeout,
-            options.debug,
+            &options,
+            Arc::clone(&metrics),
+            redactor.clone(),
+            Arc::clone(&runtime),
+            &config,
+        );
+
+        let breaker = CircuitBreaker::new(
+            options.circuit_breaker_threshold,
+            options.circuit_breaker_cooldown,
+            DebugSwitch::transport(&config),
         );
 
+        let limits = LimitMonitor::new(&options);
+        let (local_events, _) = broadcast::channel(options.local_echo_capacity.max(1));
+        let at_rest_redactor = if options.redact_at_rest {
+            Some(redactor.clone())
+        } else {
+            None
+        };
+
         let queue = if options.enable_offline_queue {
-            Some(OfflineQueue::new(options.max_offline_queue_size, options.debug))
+            Some(OfflineQueue::new(
+                options.max_offline_queue_size,
+                options.max_offline_queue_bytes,
+                DebugSwitch::queue(&config),
+                KeyEnvironment::from_api_key(&options.api_key),
+                at_rest_redactor.clone(),
+                Arc::clone(&drops),
+                Arc::clone(&deliveries),
+            ))
+        } else {
+            None
+        };
+
+        #[cfg(any(test, feature = "chaos"))]
+        let queue = queue.map(|queue| queue.with_chaos(options.chaos.clone()));
+
+        if let Some(ref queue) = queue {
+            let limit = options.offline_queue_compaction_bytes;
+            let oversized = queue.file_size().zip(limit).is_some_and(|(size, limit)| size > limit);
+            if oversized {
+                queue.compact(Utc::now(), options.max_queue_attempts);
+            }
+        }
+
+        let mutations = if options.write_behind_mutations {
+            Some(MutationQueue::new(
+                KeyEnvironment::from_api_key(&options.api_key),
+                DebugSwitch::queue(&config),
+                Arc::clone(&drops),
+            ))
         } else {
             None
         };
 
-        if options.debug {
+        let read_cache = options
+            .read_cache_ttl
+            .map(|ttl| ReadCache::new(ttl, options.read_cache_persist, at_rest_redactor));
+        let inbox_cache = if options.inbox_summary_ttl.is_zero() {
+            None
+        } else {
+            Some(InboxCache::new(options.inbox_summary_ttl))
+        };
+
+        #[cfg(feature = "journal")]
+        let journal = options.journal_path.clone().map(|path| {
+            SendJournal::open(path, options.journal_max_bytes, options.journal_max_files)
+        });
+
+        if options.debug_levels().is_enabled() {
             println!("[IronNotify] Client initialized");
         }
 
-        Ok(Arc::new(Self {
-            options,
-            transport,
-            queue,
-            is_online: RwLock::new(true),
-            connection_state: RwLock::new(ConnectionState::Disconnected),
-        }))
+        Ok(Self {
+            inner: Arc::new(ClientInner {
+                options,
+                transport,
+                queue,
+                mutations,
+                mutation_replay: AsyncMutex::new(()),
+                read_cache,
+                inbox_cache,
+                is_online: WatchedState::new(true),
+                realtime,
+                metrics,
+                drops,
+                deliveries,
+                breaker,
+                redactor,
+                limits,
+                local_events,
+                runtime,
+                scheduled_flush: Mutex::new(None),
+                paused: AtomicBool::new(false),
+                warming: AtomicBool::new(false),
+                user_resolver: RwLock::new(None),
+                ordering: KeyedLocks::default(),
+                in_flight: Arc::default(),
+                config,
+                #[cfg(feature = "journal")]
+                journal,
+                #[cfg(feature = "schema")]
+                schemas: MetadataSchemas::new(),
+            }),
+        })
     }
 
     /// Sends a simple notification.
     pub async fn notify(
-        self: &Arc<Self>,
+        &self,
         event_type: impl Into<String>,
         title: impl Into<String>,
     ) -> SendResult {
@@ -63,7 +275,7 @@ impl NotifyClient {
 
     /// Sends a notification with options.
     pub async fn notify_with_options(
-        self: &Arc<Self>,
+        &self,
         event_type: impl Into<String>,
         title: impl Into<String>,
         message: Option<String>,
@@ -77,105 +289,3007 @@ impl NotifyClient {
         self.send_payload(&payload).await
     }
 
+    /// Gets the client options.
+    ///
+    /// These are the options the client was created with. For the settings changed
+    /// since with `reconfigure`, use `runtime_config`.
+    pub fn options(&self) -> &NotifyOptions {
+        &self.inner.options
+    }
+
+    /// Gets the settings in effect that `reconfigure` can change.
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        self.inner.config.get()
+    }
+
+    /// Changes debug logging, the rate limit threshold, flush fairness, or quiet hours
+    /// on the running client, keeping its queues and in-flight requests.
+    ///
+    /// `change` edits a copy of the settings in effect, which then replaces them in one
+    /// step: the next send and every background task use the new settings. Another
+    /// `reconfigure` waits for this one, so `change` must not call it.
+    ///
+    /// ```
+    /// use ironnotify::{DebugLevel, NotifyClient, NotifyOptions};
+    ///
+    /// # let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// # let _guard = runtime.enter();
+    /// let client = NotifyClient::new(NotifyOptions::new("ak_test_example")).unwrap();
+    /// client.on_reconfigured(|config| {
+    ///     assert_eq!(config.rate_limit_threshold, Some(50));
+    /// });
+    ///
+    /// client.reconfigure(|config| {
+    ///     config.rate_limit_threshold = Some(50);
+    ///     config.debug_config = config.debug_config.transport(DebugLevel::Verbose);
+    /// });
+    /// assert_eq!(client.runtime_config().rate_limit_threshold, Some(50));
+    /// ```
+    pub fn reconfigure<F>(&self, change: F)
+    where
+        F: FnOnce(&mut RuntimeConfig),
+    {
+        self.inner.config.update(change);
+    }
+
+    /// Sets a callback invoked with the settings in effect after each `reconfigure`.
+    pub fn on_reconfigured<F>(&self, callback: F)
+    where
+        F: Fn(&RuntimeConfig) + Send + Sync + 'static,
+    {
+        self.inner.config.set_callback(Arc::new(callback));
+    }
+
+    /// Gets the debug log levels in effect.
+    pub(crate) fn debug_levels(&self) -> DebugConfig {
+        self.inner.debug()
+    }
+
+    /// Returns a copy of the payload with sensitive metadata redacted, for logging or inspection.
+    pub fn redact(&self, payload: &NotificationPayload) -> NotificationPayload {
+        self.inner.redactor.redact_payload(payload)
+    }
+
     /// Creates an event builder.
-    pub fn event(self: &Arc<Self>, event_type: impl Into<String>) -> EventBuilder {
-        EventBuilder::new(Arc::clone(self), event_type)
+    pub fn event(&self, event_type: impl Into<String>) -> EventBuilder {
+        EventBuilder::new(self.clone(), event_type)
+    }
+
+    /// Creates an event builder from a payload builder made without a client, e.g. by
+    /// a library, so it is validated against this client's options and can be sent.
+    pub fn event_from(&self, payload: PayloadBuilder) -> EventBuilder {
+        EventBuilder::wrap(self.clone(), payload)
+    }
+
+    /// Creates an event builder with the content of a received notification, for
+    /// sending it again to another recipient.
+    pub fn resend(&self, notification: &Notification) -> EventBuilder {
+        EventBuilder::from_notification(self.clone(), notification)
+    }
+
+    /// Creates a client that makes requests on behalf of a tenant, sharing this
+    /// client's connections and offline queue.
+    ///
+    /// The tenant is an API key, or an ID sent in the `tenant_header` when it is set.
+    /// Without `tenant_header`, the ID of a tenant registered with
+    /// `register_tenant_key` sends with the registered key; see `TenantScopedClient`
+    /// for why that keeps keys out of the offline queue.
+    pub fn with_tenant(&self, tenant: impl Into<String>) -> TenantScopedClient {
+        let tenant: String = tenant.into();
+        let registered = self.inner.options.tenant_header.is_none()
+            && self.inner.transport.tenant_keys().contains(&tenant);
+        let tenant = Tenant {
+            name: tenant.into(),
+            registered,
+        };
+        TenantScopedClient::new(self.clone(), tenant)
+    }
+
+    /// Registers a tenant's API key under a stable ID, for `with_tenant(tenant_id)`.
+    ///
+    /// Only the ID is written to the offline queue and mutation files. Keys are kept in
+    /// memory, so register them again after a restart, before creating the tenant's
+    /// client; queued notifications of a tenant whose key is not registered wait in the
+    /// queue until it is.
+    pub fn register_tenant_key(&self, tenant_id: impl Into<String>, api_key: impl Into<String>) {
+        self.inner
+            .transport
+            .tenant_keys()
+            .register(&tenant_id.into(), &api_key.into());
+    }
+
+    /// Checks whether a queue entry waits for its tenant's key to be registered.
+    fn awaits_tenant_key(&self, item: &QueuedNotification) -> bool {
+        item.tenant_id
+            .as_deref()
+            .is_some_and(|id| !self.inner.transport.tenant_keys().contains(id))
+    }
+
+    /// Creates a builder for one notification summarizing many events.
+    pub fn digest(&self, event_type: impl Into<String>) -> DigestBuilder {
+        DigestBuilder::new(self.clone(), event_type)
+    }
+
+    /// Registers a JSON Schema that metadata for the given event type must satisfy.
+    #[cfg(feature = "schema")]
+    pub fn register_metadata_schema(
+        &self,
+        event_type: impl Into<String>,
+        schema: serde_json::Value,
+    ) -> Result<(), Error> {
+        self.inner.schemas.register(event_type, &schema)
+    }
+
+    /// Validates a payload's metadata against the registered schemas.
+    #[cfg(feature = "schema")]
+    pub fn validate_metadata(&self, payload: &NotificationPayload) -> Result<(), Error> {
+        self.inner.schemas.validate(payload, self.inner.options.strict_metadata)
     }
 
     /// Sends a notification payload.
-    pub async fn send_payload(self: &Arc<Self>, payload: &NotificationPayload) -> SendResult {
-        let result = self.transport.send(payload).await;
+    ///
+    /// Payloads targeting a user are enriched by the user resolver, if one is set.
+    ///
+    /// Ordered payloads wait for earlier ordered sends with the same group key, and are
+    /// queued behind ones still in the offline queue.
+    ///
+    /// Dropping the future aborts the request. The payload is only queued once the
+    /// send has failed, so a send dropped before then is never queued; if the server
+    /// received it before the drop, it is still delivered. Queuing runs on a blocking
+    /// thread and finishes even if the future is dropped while it waits for the queue
+    /// file's lock.
+    pub async fn send_payload(&self, payload: &NotificationPayload) -> SendResult {
+        self.send_with_callbacks(payload, DeliveryCallbacks::default())
+            .await
+    }
+
+    /// Sends a payload like `send_payload`, invoking `callbacks` with the result once
+    /// it is final: right away, or when the queued copy is sent or dropped.
+    pub(crate) async fn send_with_callbacks(
+        &self,
+        payload: &NotificationPayload,
+        callbacks: DeliveryCallbacks,
+    ) -> SendResult {
+        self.keep_connection_warm();
+        let enriched = self.resolve_recipient(payload).await;
+        let payload = enriched.as_ref().unwrap_or(payload);
+
+        let ordering_key = payload.ordering_key();
+        let _ordering = match ordering_key {
+            Some(key) => Some(self.inner.ordering.lock(key).await),
+            None => None,
+        };
+
+        #[cfg(feature = "quiet-hours")]
+        if let Some(result) = self.defer_if_quiet(payload, &callbacks).await {
+            #[cfg(feature = "journal")]
+            self.journal_result(payload, &result, 1);
+            return result;
+        }
 
-        if !result.success {
-            if let Some(ref queue) = self.queue {
-                queue.add(payload.clone());
-                *self.is_online.write() = false;
-                return SendResult::queued(result.error.unwrap_or_default());
+        let queued_ahead = ordering_key.is_some_and(|key| {
+            self.inner
+                .queue
+                .as_ref()
+                .is_some_and(|queue| queue.has_ordered(key))
+        });
+
+        let mut result = if self.is_paused() {
+            let error = "Sending is paused".to_string();
+            self.queue_or_fail(payload, &callbacks, QueueReason::Paused, error)
+                .await
+        } else if queued_ahead {
+            self.queue_or_fail(
+                payload,
+                &callbacks,
+                QueueReason::GroupOrder,
+                "Queued behind an earlier notification in the same group".to_string(),
+            )
+            .await
+        } else if let Some(reset_at) = self.rate_limited_until(payload) {
+            self.schedule_flush(reset_at);
+            self.queue_or_fail(
+                payload,
+                &callbacks,
+                QueueReason::RateLimited,
+                format!("Rate limited until {}", reset_at),
+            )
+            .await
+        } else {
+            match self.attempt_send(payload).await {
+                Ok(result) if result.success || self.inner.transport.auth().is_failed() => {
+                    result
+                }
+                Ok(failed) => {
+                    let error = failed.error.unwrap_or_default();
+                    let reason = QueueReason::Offline;
+                    let mut result =
+                        self.queue_or_fail(payload, &callbacks, reason, error).await;
+                    result.status = failed.status;
+                    result
+                }
+                Err(e) if self.inner.transport.auth().is_failed() => {
+                    SendResult::failure(e.to_string())
+                }
+                Err(e @ Error::CircuitOpen) => {
+                    let reason = QueueReason::CircuitOpen;
+                    self.queue_or_fail(payload, &callbacks, reason, e.to_string()).await
+                }
+                Err(e) => SendResult::failure(e.to_string()),
             }
+        };
+
+        if result.content_hash.is_none() {
+            result.content_hash = Some(payload.content_hash());
         }
 
+        if self.inner.debug().transport.is_enabled() {
+            println!("[IronNotify] {}: {}", payload.event_type, result);
+        }
+
+        #[cfg(feature = "journal")]
+        self.journal_result(payload, &result, 1);
+
+        if !result.queued && !callbacks.is_empty() {
+            self.complete_delivery(&result, callbacks);
+        }
         result
     }
 
-    /// Gets notifications.
-    pub async fn get_notifications(
+    /// Sends a payload like `send_payload`, failing with `Error::Cancelled` if `token` is
+    /// cancelled first. A cancelled send is not queued.
+    pub async fn send_payload_with_cancel(
         &self,
-        limit: Option<i32>,
-        offset: Option<i32>,
-        unread_only: bool,
-    ) -> Result<Vec<Notification>, String> {
-        self.transport.get_notifications(limit, offset, unread_only).await
+        payload: &NotificationPayload,
+        token: CancellationToken,
+    ) -> Result<SendResult, Error> {
+        cancellable(token, self.send_payload(payload)).await
     }
 
-    /// Gets the unread notification count.
-    pub async fn get_unread_count(&self) -> Result<i32, String> {
-        self.transport.get_unread_count().await
+    /// Sets a callback that looks up recipient details by user ID.
+    ///
+    /// The result is added to the metadata of user-targeted notifications under the
+    /// `recipient` key and cached for `user_resolver_ttl`. When the callback fails, the
+    /// notification is sent without it and `resolver_failures` is incremented.
+    pub fn set_user_resolver<F, Fut, E>(&self, resolve: F)
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<UserContext, E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let resolver = UserResolver::new(self.inner.options.user_resolver_ttl, resolve);
+        *self.inner.user_resolver.write() = Some(Arc::new(resolver));
     }
 
-    /// Marks a notification as read.
-    pub async fn mark_as_read(&self, notification_id: &str) -> Result<bool, String> {
-        self.transport.mark_as_read(notification_id).await
-    }
+    /// Adds the resolved recipient details to a user-targeted payload.
+    async fn resolve_recipient(
+        &self,
+        payload: &NotificationPayload,
+    ) -> Option<NotificationPayload> {
+        let user_id = payload.user_id.as_deref()?;
+        let user_resolver = self.inner.user_resolver.read().clone()?;
 
-    /// Marks all notifications as read.
-    pub async fn mark_all_as_read(&self) -> Result<bool, String> {
-        self.transport.mark_all_as_read().await
+        match user_resolver.resolve(user_id).await {
+            Ok(context) => Some(resolver::enrich(payload, &context)),
+            Err(e) => {
+                self.inner.metrics.record_resolver_failure();
+                if self.inner.debug().builder.is_enabled() {
+                    println!(
+                        "[IronNotify] Warning: user resolver failed for {}: {}",
+                        user_id, e
+                    );
+                }
+                None
+            }
+        }
     }
 
-    /// Gets the current connection state.
-    pub fn connection_state(&self) -> ConnectionState {
-        *self.connection_state.read()
+    /// Defers a payload when quiet hours are active and its severity does not bypass them.
+    #[cfg(feature = "quiet-hours")]
+    async fn defer_if_quiet(
+        &self,
+        payload: &NotificationPayload,
+        callbacks: &DeliveryCallbacks,
+    ) -> Option<SendResult> {
+        let quiet_hours = self.inner.config.quiet_hours()?;
+        self.inner.queue.as_ref()?;
+
+        let now = Utc::now();
+        if !quiet_hours.contains(now) || quiet_hours.bypasses(payload.severity.unwrap_or_default()) {
+            return None;
+        }
+
+        let release_at = quiet_hours.next_end(now);
+        let (payload, callbacks) = (payload.clone(), callbacks.clone());
+        self.on_queue(move |queue| queue.defer(payload, release_at, callbacks))
+            .await?;
+        self.schedule_flush(release_at);
+        Some(SendResult::queued_with_reason(
+            QueueReason::QuietHours,
+            format!("Deferred until {}", release_at),
+        ))
     }
 
-    /// Connects to real-time notifications.
-    pub fn connect(&self) {
-        *self.connection_state.write() = ConnectionState::Connected;
-        if self.options.debug {
-            println!("[IronNotify] Connected (WebSocket not implemented)");
+    /// Queues a payload that was not sent, or fails if the offline queue is disabled.
+    ///
+    /// Only `Offline` and `CircuitOpen` payloads mark the client offline.
+    async fn queue_or_fail(
+        &self,
+        payload: &NotificationPayload,
+        callbacks: &DeliveryCallbacks,
+        reason: QueueReason,
+        error: String,
+    ) -> SendResult {
+        let (payload, callbacks) = (payload.clone(), callbacks.clone());
+        let queued = if matches!(reason, QueueReason::Offline | QueueReason::CircuitOpen) {
+            self.enqueue(payload, callbacks).await
+        } else {
+            self.add_to_queue(payload, callbacks).await
+        };
+        match queued {
+            Ok(true) => SendResult::queued_with_reason(reason, error),
+            Ok(false) => SendResult::failure(error),
+            Err(e) => SendResult::failure(e.to_string()),
         }
     }
 
-    /// Disconnects from real-time notifications.
-    pub fn disconnect(&self) {
-        *self.connection_state.write() = ConnectionState::Disconnected;
+    /// Pauses sending. Notifications are queued until `resume` is called.
+    ///
+    /// The paused state is not persisted; a restarted process sends normally.
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::SeqCst);
+        if self.inner.debug().queue.is_enabled() {
+            println!("[IronNotify] Sending paused");
+        }
     }
 
-    /// Subscribes to a user's notifications.
-    pub fn subscribe_to_user(&self, user_id: &str) {
-        if self.options.debug {
-            println!("[IronNotify] Subscribed to user: {}", user_id);
+    /// Resumes sending and flushes the notifications queued while paused.
+    pub async fn resume(&self) {
+        self.inner.paused.store(false, Ordering::SeqCst);
+        if self.inner.debug().queue.is_enabled() {
+            println!("[IronNotify] Sending resumed");
         }
+        self.flush().await;
     }
 
-    /// Subscribes to app-wide notifications.
-    pub fn subscribe_to_app(&self) {
-        if self.options.debug {
-            println!("[IronNotify] Subscribed to app notifications");
+    /// Checks whether sending is paused.
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+
+    /// Gets the rate limit reset time if a payload should wait for it.
+    ///
+    /// Error and Critical payloads are never held back.
+    fn rate_limited_until(&self, payload: &NotificationPayload) -> Option<DateTime<Utc>> {
+        if is_high_priority(payload) {
+            return None;
         }
+        self.inner.transport.rate_limits().throttled_until(Utc::now())
     }
 
-    /// Flushes the offline queue.
-    pub async fn flush(&self) {
-        if let Some(ref queue) = self.queue {
-            if queue.is_empty() {
+    /// Starts a background task that flushes the queue at `at`, unless an earlier
+    /// flush is already scheduled.
+    fn schedule_flush(&self, at: DateTime<Utc>) {
+        {
+            let mut scheduled = self.inner.scheduled_flush.lock();
+            if scheduled.is_some_and(|scheduled| scheduled <= at) {
                 return;
             }
+            *scheduled = Some(at);
+        }
 
-            if !self.transport.is_online().await {
-                return;
+        let inner = Arc::downgrade(&self.inner);
+        let runtime = Arc::clone(&self.inner.runtime);
+        self.spawn(Box::pin(async move {
+            let delay = (at - Utc::now()).to_std().unwrap_or_default();
+            runtime.sleep(delay).await;
+            if let Some(inner) = inner.upgrade() {
+                {
+                    let mut scheduled = inner.scheduled_flush.lock();
+                    if *scheduled == Some(at) {
+                        *scheduled = None;
+                    }
+                }
+                NotifyClient { inner }.flush().await;
             }
+        }));
+    }
 
-            *self.is_online.write() = true;
-            let notifications = queue.get_all();
+    /// Starts the background task pinging the API while the client is idle, if
+    /// `keep_connection_warm` is set and it is not running yet.
+    ///
+    /// Started by the first send rather than by `new`, which may run outside the
+    /// runtime.
+    fn keep_connection_warm(&self) {
+        let Some(interval) = self.inner.options.keep_connection_warm else {
+            return;
+        };
+        if self.inner.warming.swap(true, Ordering::SeqCst) {
+            return;
+        }
 
-            for (i, payload) in notifications.iter().enumerate().rev() {
-                let result = self.transport.send(payload).await;
-                if result.success {
-                    queue.remove(i);
-                } else {
-                    break;
+        let inner = Arc::downgrade(&self.inner);
+        let runtime = Arc::clone(&self.inner.runtime);
+        self.spawn(Box::pin(async move {
+            let mut ticks = Interval::new(runtime, interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                if inner.transport.idle_for().is_some_and(|idle| idle >= interval) {
+                    inner.transport.keep_warm().await;
+                }
+            }
+        }));
+    }
+
+    /// Runs a future in the background on the client's runtime.
+    pub(crate) fn spawn(&self, future: BoxFuture<()>) {
+        self.inner.runtime.spawn(future);
+    }
+
+    fn complete_delivery(&self, result: &SendResult, callbacks: DeliveryCallbacks) {
+        if let Some(on_result) = callbacks.on_result {
+            on_result(result);
+        }
+        if let (true, Some(id), Some(on_read)) =
+            (result.success, result.notification_id.clone(), callbacks.on_read)
+        {
+            self.track_read(id, on_read);
+        }
+    }
+
+    /// Polls a notification's history until it is read or `read_tracking_timeout`
+    /// passes.
+    fn track_read(&self, notification_id: String, on_read: ReadReceiptCallback) {
+        let client = self.clone();
+        let tenant = tenant::current();
+        self.spawn(Box::pin(tenant::scoped(tenant, async move {
+            let interval = client.inner.options.read_poll_interval;
+            let deadline = Instant::now() + client.inner.options.read_tracking_timeout;
+            while Instant::now() < deadline {
+                client.inner.runtime.sleep(interval).await;
+                let Ok(events) = client.get_notification_history(&notification_id).await else {
+                    continue;
+                };
+                let read_at = events.iter().find_map(|event| match *event {
+                    NotificationEvent::Read { timestamp } => Some(timestamp),
+                    _ => None,
+                });
+                if let Some(read_at) = read_at {
+                    on_read(&ReadReceipt {
+                        notification_id,
+                        read_at,
+                    });
+                    return;
+                }
+            }
+        })));
+    }
+
+    /// Sends a notification without waiting, for `Drop` implementations and shutdown
+    /// handlers that cannot await.
+    ///
+    /// Inside a running runtime, the send is spawned in the background and queued if it
+    /// does not finish within `best_effort_budget`. Otherwise the payload is written
+    /// straight to the offline queue for the next `flush`, unless the queue stays locked
+    /// for longer than the budget. Never panics; a nested call on the same thread, e.g.
+    /// from a drop callback, is ignored. Returns whether the payload was handed off.
+    pub fn emit_best_effort(&self, payload: NotificationPayload) -> bool {
+        if EMITTING.with(|emitting| emitting.replace(true)) {
+            return false;
+        }
+        let handed_off =
+            panic::catch_unwind(AssertUnwindSafe(|| self.hand_off(payload))).unwrap_or(false);
+        EMITTING.with(|emitting| emitting.set(false));
+        handed_off
+    }
+
+    fn hand_off(&self, payload: NotificationPayload) -> bool {
+        let budget = self.inner.options.best_effort_budget;
+        if self.inner.runtime.is_available() {
+            let client = self.clone();
+            self.spawn(Box::pin(async move {
+                let runtime = Arc::clone(&client.inner.runtime);
+                let sent = timeout(&*runtime, budget, client.send_payload(&payload)).await;
+                if sent.is_none() {
+                    let _ = client.add_to_queue(payload, DeliveryCallbacks::default()).await;
+                }
+            }));
+            return true;
+        }
+
+        match self.inner.queue {
+            Some(ref queue) => queue
+                .add_by(payload, Instant::now() + budget)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Gets notifications deferred by quiet hours.
+    pub fn deferred_notifications(&self) -> Vec<DeferredNotification> {
+        self.inner
+            .queue
+            .as_ref()
+            .map(|queue| queue.deferred())
+            .unwrap_or_default()
+    }
+
+    /// Cancels a deferred notification so that it is never sent.
+    pub fn cancel_deferred(&self, id: &str) -> bool {
+        self.inner
+            .queue
+            .as_ref()
+            .is_some_and(|queue| queue.cancel_deferred(id))
+    }
+
+    /// Records a send outcome in the journal, if enabled.
+    #[cfg(feature = "journal")]
+    fn journal_result(&self, payload: &NotificationPayload, result: &SendResult, attempt: u32) {
+        if let Some(ref journal) = self.inner.journal {
+            journal.record(JournalEntry::from_result(payload, result, attempt));
+        }
+    }
+
+    /// Reads journaled send attempts at or after `since`.
+    #[cfg(feature = "journal")]
+    pub fn journal_entries(&self, since: DateTime<Utc>) -> Vec<JournalEntry> {
+        self.inner.journal
+            .as_ref()
+            .map(|journal| journal.entries_since(since))
+            .unwrap_or_default()
+    }
+
+    /// Sends a notification payload without falling back to the offline queue.
+    pub async fn try_send_payload(&self, payload: &NotificationPayload) -> Result<SendResult, Error> {
+        let result = self.attempt_send(payload).await?;
+        if result.success {
+            Ok(result)
+        } else {
+            Err(Error::Transport(result.error.unwrap_or_default()))
+        }
+    }
+
+    /// Sends a payload like `try_send_payload`, returning the failed result when the
+    /// request was made but did not succeed.
+    async fn attempt_send(&self, payload: &NotificationPayload) -> Result<SendResult, Error> {
+        validate_event_type(&payload.event_type)
+            .map_err(|e| Error::InvalidEventType(e.to_string()))?;
+        let size = payload.attachment_size();
+        let limit = self.inner.options.max_attachment_bytes;
+        if size > limit {
+            return Err(Error::AttachmentsTooLarge { size, limit });
+        }
+
+        #[cfg(feature = "otel")]
+        let traced = Self::with_trace_metadata(payload)?;
+        #[cfg(feature = "otel")]
+        let payload = traced.as_ref().unwrap_or(payload);
+
+        #[cfg(feature = "schema")]
+        self.validate_metadata(payload)?;
+
+        if !self.inner.breaker.allow_request() {
+            return Err(Error::CircuitOpen);
+        }
+
+        let result = self.transmit(payload).await;
+
+        if result.success {
+            self.inner.breaker.record_success();
+            self.publish_local(payload, &result);
+        } else if !self.inner.transport.auth().is_failed() {
+            // A rejected payload shows the API is up, so only outages count towards
+            // opening the circuit.
+            if result.is_outage() {
+                self.inner.breaker.record_failure();
+            } else {
+                self.inner.breaker.record_success();
+            }
+        }
+        Ok(result)
+    }
+
+    /// Sends a payload, applying the metadata overflow strategy when it is too large
+    /// and warning when it is close to a limit.
+    ///
+    /// Queued payloads are stored unchanged, so overflow handling happens at send time.
+    async fn transmit(&self, payload: &NotificationPayload) -> SendResult {
+        self.inner.limits.check(payload);
+        let adjusted = self.compensate_clock_skew(payload);
+        let payload = adjusted.as_ref().unwrap_or(payload);
+
+        let max_bytes = self.inner.options.max_payload_bytes;
+        if payload.estimated_wire_size() <= max_bytes {
+            return self.inner.transport.send(payload).await;
+        }
+
+        match self.inner.options.metadata_overflow {
+            OverflowStrategy::Fail => self.inner.transport.send(payload).await,
+            OverflowStrategy::Truncate => {
+                let truncated = overflow::truncate(payload, max_bytes);
+                self.inner.transport.send(&truncated).await
+            }
+            OverflowStrategy::Externalize => {
+                match overflow::externalize(&self.inner.transport, payload, max_bytes).await {
+                    Ok(externalized) => self.inner.transport.send(&externalized).await,
+                    Err(e) => SendResult::failure(e.to_string()),
+                }
+            }
+        }
+    }
+
+    /// Lowers the severity of a queued notification for how long it has waited, as
+    /// set by `queue_severity_decay`. Entries from older queue files, whose age is
+    /// unknown, keep their severity.
+    fn decay_severity(
+        &self,
+        item: &QueuedNotification,
+        now: DateTime<Utc>,
+    ) -> Option<NotificationPayload> {
+        let options = &self.inner.options;
+        if options.queue_severity_decay.is_empty() {
+            return None;
+        }
+        let age = decay::queued_for(item.enqueued_at, now)?;
+        let decayed = decay::decayed(
+            &item.payload,
+            age,
+            &options.queue_severity_decay,
+            &options.severity_decay_exempt,
+        )?;
+        if self.inner.debug().queue.is_enabled() {
+            println!(
+                "[IronNotify] Lowering {} from {} to {} after {}s in the queue",
+                item.payload.event_type,
+                item.payload.severity.unwrap_or_default(),
+                decayed.severity.unwrap_or_default(),
+                age.as_secs()
+            );
+        }
+        Some(decayed)
+    }
+
+    /// Shifts `expires_at` into server time when `compensate_clock_skew` is set and the
+    /// local clock is off by more than 30 seconds.
+    fn compensate_clock_skew(&self, payload: &NotificationPayload) -> Option<NotificationPayload> {
+        if !self.inner.options.compensate_clock_skew {
+            return None;
+        }
+        let expires_at = payload.expires_at?;
+        let correction = self.inner.transport.clock_skew().correction()?;
+
+        let mut adjusted = payload.clone();
+        adjusted.expires_at = Some(expires_at.checked_add_signed(correction).unwrap_or(expires_at));
+        Some(adjusted)
+    }
+
+    /// Adds the current trace and span IDs to a payload's metadata, if a span is active.
+    #[cfg(feature = "otel")]
+    fn with_trace_metadata(
+        payload: &NotificationPayload,
+    ) -> Result<Option<NotificationPayload>, Error> {
+        if payload
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.contains_key(TRACING_METADATA_KEY))
+        {
+            return Err(Error::ReservedMetadataKey(TRACING_METADATA_KEY.to_string()));
+        }
+
+        Ok(TraceContext::current().map(|trace| {
+            let mut traced = payload.clone();
+            traced
+                .metadata
+                .get_or_insert_with(HashMap::new)
+                .insert(TRACING_METADATA_KEY.to_string(), trace.metadata());
+            traced
+        }))
+    }
+
+    /// Subscribes to payloads sent by this client, when `local_echo` is enabled.
+    ///
+    /// The channel is bounded; receivers that fall behind get `RecvError::Lagged`.
+    /// Queued and failed sends are not published.
+    pub fn local_events(&self) -> broadcast::Receiver<LocalEvent> {
+        self.inner.local_events.subscribe()
+    }
+
+    fn publish_local(&self, payload: &NotificationPayload, result: &SendResult) {
+        if self.inner.options.local_echo {
+            // Sending only fails when there are no receivers.
+            let _ = self.inner.local_events.send(LocalEvent {
+                notification_id: result.notification_id.clone(),
+                payload: payload.clone(),
+            });
+        }
+    }
+
+    /// Adds a payload to the offline queue and marks the client offline. Returns false
+    /// if the queue is disabled.
+    pub(crate) async fn enqueue(
+        &self,
+        payload: NotificationPayload,
+        callbacks: DeliveryCallbacks,
+    ) -> Result<bool, Error> {
+        let queued = self.add_to_queue(payload, callbacks).await?;
+        if queued {
+            self.inner.is_online.set(false);
+        }
+        Ok(queued)
+    }
+
+    /// Adds a payload to the Postgres queue when one is set, or else to the offline
+    /// queue. Returns false if there is neither.
+    async fn add_to_queue(
+        &self,
+        payload: NotificationPayload,
+        callbacks: DeliveryCallbacks,
+    ) -> Result<bool, Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref storage) = self.inner.options.postgres_queue {
+            self.append_to_postgres(Arc::clone(storage), payload, callbacks);
+            return Ok(true);
+        }
+        match self.on_queue(move |queue| queue.add(payload, callbacks)).await {
+            Some(added) => added.map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Runs `f` on the offline queue on a blocking thread, as the current tenant, since
+    /// taking the queue file's lock can wait on another process. Returns `None` if the
+    /// queue is disabled.
+    async fn on_queue<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&OfflineQueue) -> R + Send + 'static,
+    ) -> Option<R> {
+        self.inner.queue.as_ref()?;
+        let client = self.clone();
+        let tenant = tenant::current();
+        blocking(&*self.inner.runtime, move || {
+            let queue = client.inner.queue.as_ref()?;
+            Some(tenant::with(tenant, || f(queue)))
+        })
+        .await
+        .flatten()
+    }
+
+    /// Appends a payload to the Postgres queue in the background, so sends never wait
+    /// on the database. While it is unreachable, the payload goes to the offline queue
+    /// instead, with a warning.
+    #[cfg(feature = "postgres")]
+    fn append_to_postgres(
+        &self,
+        storage: Arc<PostgresQueueStorage>,
+        payload: NotificationPayload,
+        callbacks: DeliveryCallbacks,
+    ) {
+        let environment = KeyEnvironment::from_api_key(&self.inner.options.api_key);
+        let item = storage.entry(payload, environment);
+        let key = DeliveryKey::Queued(item.id);
+        self.inner.deliveries.register(key.clone(), callbacks);
+        let client = self.clone();
+        self.spawn(Box::pin(async move {
+            let stored = if client.inner.options.redact_at_rest {
+                QueuedNotification {
+                    payload: client.redact(&item.payload),
+                    ..item.clone()
+                }
+            } else {
+                item.clone()
+            };
+            let Err(e) = storage.append(&stored).await else {
+                return;
+            };
+
+            let tenant = Tenant::restore(item.tenant.as_deref(), item.tenant_id.as_deref());
+            let queued = match client.inner.queue {
+                Some(_) => {
+                    eprintln!(
+                        "[IronNotify] Warning: Postgres queue unavailable, queuing locally: {}",
+                        e
+                    );
+                    // The local entry gets an ID of its own; keep a copy of the callbacks
+                    // to fail if it cannot be added either.
+                    let callbacks = client.inner.deliveries.take(&key).unwrap_or_default();
+                    client.inner.deliveries.register(key.clone(), callbacks.clone());
+                    let payload = item.payload.clone();
+                    let added = client.on_queue(move |queue| queue.add(payload, callbacks));
+                    let added = tenant::scoped(tenant, added).await;
+                    if added.as_ref().is_some_and(Result::is_ok) {
+                        client.inner.deliveries.take(&key);
+                    }
+                    added.is_some_and(|added| added.is_ok())
+                }
+                None => {
+                    eprintln!("[IronNotify] Warning: Postgres queue unavailable: {}", e);
+                    false
                 }
+            };
+            if !queued {
+                client.inner.drops.record_entry(
+                    key,
+                    DroppedNotification::new(&item.payload, DropReason::QueueUnavailable),
+                );
+            }
+        }));
+    }
+
+    /// Adds a payload to the queue without sending it, for the outbox pattern: queue
+    /// the notification inside a transaction, then `confirm` it once the transaction
+    /// commits, or `abort` it if it rolls back. Flushes only send confirmed entries.
+    ///
+    /// The payload goes to the Postgres queue when `postgres_queue` is set, or else to
+    /// the offline queue. Fails if there is neither, or if the Postgres queue cannot be
+    /// written; unlike a send, nothing falls back to the other queue.
+    ///
+    /// ```no_run
+    /// # async fn example(client: ironnotify::NotifyClient) -> Result<(), ironnotify::Error> {
+    /// use ironnotify::NotificationPayload;
+    ///
+    /// let payload = NotificationPayload::new("order.shipped", "Your order has shipped");
+    /// let receipt = client.enqueue_only(&payload).await?;
+    /// // ... commit the application's own transaction ...
+    /// client.confirm(&receipt).await?;
+    /// client.flush().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn enqueue_only(
+        &self,
+        payload: &NotificationPayload,
+    ) -> Result<EnqueueReceipt, Error> {
+        #[cfg(feature = "postgres")]
+        if let Some(ref storage) = self.inner.options.postgres_queue {
+            let item = self.outbox_entry(storage, payload, true);
+            storage.append(&item).await?;
+            return Ok(EnqueueReceipt::new(item.id, ReceiptStorage::Postgres));
+        }
+        let payload = payload.clone();
+        let id = self
+            .on_queue(move |queue| queue.add_unconfirmed(payload))
+            .await
+            .ok_or_else(|| Error::Config("The offline queue is disabled".to_string()))??;
+        Ok(EnqueueReceipt::new(id, ReceiptStorage::Local))
+    }
+
+    /// Adds a payload to the Postgres queue as part of the caller's transaction, so it
+    /// is queued if and only if the transaction commits.
+    ///
+    /// The entry needs no `confirm`: committing confirms it, and rolling back discards
+    /// it. Fails if `postgres_queue` is not set.
+    #[cfg(feature = "postgres")]
+    pub async fn enqueue_only_in(
+        &self,
+        transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        payload: &NotificationPayload,
+    ) -> Result<EnqueueReceipt, Error> {
+        let storage = self
+            .inner
+            .options
+            .postgres_queue
+            .as_ref()
+            .ok_or_else(|| Error::Config("No Postgres queue is set".to_string()))?;
+        let item = self.outbox_entry(storage, payload, false);
+        storage.append_in(transaction, &item).await?;
+        Ok(EnqueueReceipt::new(item.id, ReceiptStorage::Postgres))
+    }
+
+    /// Creates the Postgres queue entry for `enqueue_only`, redacted when
+    /// `redact_at_rest` is set.
+    #[cfg(feature = "postgres")]
+    fn outbox_entry(
+        &self,
+        storage: &PostgresQueueStorage,
+        payload: &NotificationPayload,
+        awaiting_confirmation: bool,
+    ) -> QueuedNotification {
+        let environment = KeyEnvironment::from_api_key(&self.inner.options.api_key);
+        let mut item = storage.entry(payload.clone(), environment);
+        if self.inner.options.redact_at_rest {
+            item.payload = self.redact(&item.payload);
+        }
+        item.awaiting_confirmation = awaiting_confirmation;
+        item
+    }
+
+    /// Lets flushes send a notification added with `enqueue_only`. It is sent by the
+    /// next flush.
+    ///
+    /// Returns false if the notification is not waiting for confirmation, e.g. because
+    /// it was aborted or already confirmed.
+    pub async fn confirm(&self, receipt: &EnqueueReceipt) -> Result<bool, Error> {
+        let confirmed = match receipt.storage() {
+            ReceiptStorage::Local => {
+                let id = receipt.id();
+                self.on_queue(move |queue| queue.confirm(id)).await.unwrap_or(false)
             }
+            #[cfg(feature = "postgres")]
+            ReceiptStorage::Postgres => match self.inner.options.postgres_queue {
+                Some(ref storage) => storage.confirm(receipt.id()).await?,
+                None => false,
+            },
+            #[cfg(not(feature = "postgres"))]
+            ReceiptStorage::Postgres => false,
+        };
+        if confirmed && self.inner.debug().queue.is_enabled() {
+            println!("[IronNotify] Confirmed queued notification {}", receipt.id());
+        }
+        Ok(confirmed)
+    }
+
+    /// Removes a notification added with `enqueue_only` without sending it.
+    ///
+    /// Returns false if the notification is not waiting for confirmation, e.g. because
+    /// it was already confirmed.
+    pub async fn abort(&self, receipt: &EnqueueReceipt) -> Result<bool, Error> {
+        let aborted = match receipt.storage() {
+            ReceiptStorage::Local => {
+                let id = receipt.id();
+                self.on_queue(move |queue| queue.abort(id)).await.unwrap_or(false)
+            }
+            #[cfg(feature = "postgres")]
+            ReceiptStorage::Postgres => match self.inner.options.postgres_queue {
+                Some(ref storage) => storage.abort(receipt.id()).await?,
+                None => false,
+            },
+            #[cfg(not(feature = "postgres"))]
+            ReceiptStorage::Postgres => false,
+        };
+        if aborted && self.inner.debug().queue.is_enabled() {
+            println!("[IronNotify] Aborted queued notification {}", receipt.id());
         }
+        Ok(aborted)
+    }
+
+    /// Gets notifications.
+    pub async fn get_notifications(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        unread_only: bool,
+    ) -> Result<Vec<Notification>, String> {
+        let query = NotificationQuery {
+            limit,
+            offset,
+            unread_only,
+            ..Default::default()
+        };
+        self.query_notifications(&query).await
+    }
+
+    /// Gets notifications matching a query.
+    ///
+    /// Snoozed notifications are excluded unless the query includes them.
+    pub async fn query_notifications(
+        &self,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, String> {
+        self.fetch_notifications(query).await.map_err(|e| e.to_string())
+    }
+
+    /// Gets notifications matching a query like `query_notifications`, failing with
+    /// `Error::Cancelled` if `token` is cancelled first.
+    pub async fn query_notifications_with_cancel(
+        &self,
+        query: &NotificationQuery,
+        token: CancellationToken,
+    ) -> Result<Vec<Notification>, Error> {
+        cancellable(token, self.fetch_notifications(query)).await?
+    }
+
+    async fn fetch_notifications(
+        &self,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, Error> {
+        let mut notifications = self.inner.transport.get_notifications(query).await?;
+        if let Some(ref mutations) = self.inner.mutations {
+            mutations.overlay(&mut notifications);
+        }
+        Ok(without_snoozed(notifications, query))
+    }
+
+    /// Pages through the notifications matching a query.
+    ///
+    /// Pages hold up to the query's limit, or 100, notifications, starting at its
+    /// offset. Snoozed notifications are excluded unless the query includes them, so
+    /// a page can be shorter than the limit without being the last.
+    pub fn notifications_pager(&self, query: &NotificationQuery) -> Pager<Notification> {
+        let client = self.clone();
+        let query = query.clone();
+        let page_size = query.limit.unwrap_or(CONSUME_PAGE_SIZE).max(1);
+        let start = query.offset.unwrap_or(0).max(0);
+        Pager::new(move |cursor| {
+            let client = client.clone();
+            let offset = match cursor {
+                Some(Cursor::Offset(offset)) => offset,
+                _ => start,
+            };
+            let page_query = NotificationQuery {
+                limit: Some(page_size),
+                offset: Some(offset),
+                ..query.clone()
+            };
+            async move {
+                let mut page = client.inner.transport.get_notifications(&page_query).await?;
+                let next = if page.len() >= page_size as usize {
+                    offset.checked_add(page_size).map(Cursor::Offset)
+                } else {
+                    None
+                };
+                if let Some(ref mutations) = client.inner.mutations {
+                    mutations.overlay(&mut page);
+                }
+                Ok((without_snoozed(page, &page_query), next))
+            }
+        })
+    }
+
+    /// Pages through the notifications matching a query as the server returns them,
+    /// from the first, ignoring the query's limit and offset.
+    fn server_pager(&self, query: &NotificationQuery, page_size: i32) -> Pager<Notification> {
+        let client = self.clone();
+        let query = query.clone();
+        Pager::by_offset(page_size, 0, move |offset, limit| {
+            let client = client.clone();
+            let page_query = NotificationQuery {
+                limit: Some(limit),
+                offset: Some(offset),
+                ..query.clone()
+            };
+            async move { client.inner.transport.get_notifications(&page_query).await }
+        })
+    }
+
+    /// Searches the title, message, and metadata of notifications for text, e.g. an
+    /// order number, returning the matches that also pass the query's filters.
+    ///
+    /// Fails with `Error::EmptySearch` for blank text. When the server has no search
+    /// endpoint, fails with `Error::Unsupported`, unless `allow_client_side_search` is
+    /// set: then the notifications matching the query are fetched page by page and
+    /// searched locally, ignoring case, and the hits have no score.
+    pub async fn search_notifications(
+        &self,
+        text: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(Error::EmptySearch);
+        }
+
+        let mut hits = match self.inner.transport.search_notifications(text, query).await {
+            Err(Error::Unsupported(_)) if self.inner.options.allow_client_side_search => {
+                if self.inner.debug().transport.is_enabled() {
+                    println!("[IronNotify] Search endpoint unavailable, searching locally");
+                }
+                self.search_locally(text, query).await?
+            }
+            result => result?,
+        };
+
+        if let Some(ref mutations) = self.inner.mutations {
+            let mut scores = HashMap::new();
+            let mut notifications: Vec<Notification> = hits
+                .drain(..)
+                .map(|hit| {
+                    scores.insert(hit.notification.id.clone(), hit.score);
+                    hit.notification
+                })
+                .collect();
+            mutations.overlay(&mut notifications);
+            hits = notifications
+                .into_iter()
+                .map(|notification| SearchHit {
+                    score: scores.get(&notification.id).copied().flatten(),
+                    notification,
+                })
+                .collect();
+        }
+        let now = Utc::now();
+        if !query.include_snoozed {
+            hits.retain(|hit| !hit.notification.is_snoozed_at(now));
+        }
+        Ok(hits)
+    }
+
+    /// Fetches the notifications matching a query and keeps those containing the text,
+    /// applying the query's offset and limit to the matches.
+    async fn search_locally(
+        &self,
+        text: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let skip = query.offset.unwrap_or(0).max(0) as usize;
+        let wanted = query.limit.map(|limit| skip + limit.max(0) as usize);
+        let mut pager = self.server_pager(query, CONSUME_PAGE_SIZE);
+        let mut hits = Vec::new();
+        while let Some(page) = pager.next_page().await? {
+            hits.extend(
+                page.into_iter()
+                    .filter(|n| search::matches(n, text))
+                    .map(SearchHit::unscored),
+            );
+            if wanted.is_some_and(|wanted| hits.len() >= wanted) {
+                break;
+            }
+        }
+
+        Ok(hits
+            .into_iter()
+            .skip(skip)
+            .take(wanted.map_or(usize::MAX, |wanted| wanted - skip))
+            .collect())
+    }
+
+    /// Visits the notifications matching a query without copying them out of the response.
+    ///
+    /// Each notification borrows from the response body and is only valid inside
+    /// `visit`; use `NotificationRef::to_notification` to keep one. Snoozed
+    /// notifications are skipped unless the query includes them. Returns how many
+    /// notifications were visited.
+    pub async fn visit_notifications<F>(
+        &self,
+        query: &NotificationQuery,
+        mut visit: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(NotificationRef<'_>),
+    {
+        let now = Utc::now();
+        let mut visited = 0;
+        self.inner
+            .transport
+            .get_notifications_raw(query, |notification| {
+                if query.include_snoozed || !notification.is_snoozed_at(now) {
+                    visited += 1;
+                    visit(notification);
+                }
+            })
+            .await?;
+        Ok(visited)
+    }
+
+    /// Creates a cursor store for `consume_notifications`.
+    ///
+    /// Pass a `FileCheckpoint` to keep the cursor in a local file, or your own backend.
+    pub fn checkpoint_store(&self, backend: impl CheckpointBackend) -> CheckpointStore {
+        CheckpointStore::new(Arc::new(backend), self.inner.debug().queue.is_enabled())
+    }
+
+    /// Processes the notifications matching a query that come after the checkpoint,
+    /// oldest first, until none are left. Returns how many were processed.
+    ///
+    /// The cursor is committed after each successful `handler` call, and processing
+    /// stops at the first handler error, which is returned as `Error::Handler`.
+    /// Delivery is at least once: if the process stops between a handler finishing
+    /// and its commit, that notification is handled again on the next run, so
+    /// handlers should be idempotent.
+    ///
+    /// Pages are fetched with the query's limit, or 100, as the page size; its offset
+    /// is ignored. Snoozed notifications are included.
+    pub async fn consume_notifications<F, Fut, E>(
+        &self,
+        query: &NotificationQuery,
+        checkpoint: &CheckpointStore,
+        mut handler: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(Notification) -> Fut,
+        Fut: Future<Output = Result<(), E>>,
+        E: std::fmt::Display,
+    {
+        let page_size = query.limit.unwrap_or(CONSUME_PAGE_SIZE).max(1);
+        let mut processed = 0;
+
+        loop {
+            let cursor = checkpoint.last_processed_cursor().await?;
+            let mut page_query = NotificationQuery {
+                limit: Some(page_size),
+                include_snoozed: true,
+                ..query.clone()
+            };
+            if let Some(ref cursor) = cursor {
+                // `since` is exclusive and millisecond precision, so step back to keep
+                // notifications created in the cursor's millisecond.
+                let since = cursor.created_at - chrono::Duration::milliseconds(1);
+                page_query.since = Some(query.since.map_or(since, |s| s.max(since)));
+            }
+
+            let mut batch = Vec::new();
+            let mut seen = HashSet::new();
+            let mut pager = self.server_pager(&page_query, page_size);
+            while let Some(page) = pager.next_page().await? {
+                batch.extend(page.into_iter().filter(|n| seen.insert(n.id.clone())));
+            }
+
+            batch.retain(|n| match cursor {
+                Some(ref cursor) => cursor.is_before(n),
+                None => true,
+            });
+            if batch.is_empty() {
+                return Ok(processed);
+            }
+            batch.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+            for notification in batch {
+                let next = NotificationCursor::of(&notification);
+                handler(notification)
+                    .await
+                    .map_err(|e| Error::Handler(e.to_string()))?;
+                checkpoint.commit_cursor(next).await?;
+                processed += 1;
+            }
+        }
+    }
+
+    /// Handles each unread notification matching a query once, marking it as read when
+    /// `handler` succeeds.
+    ///
+    /// Runs one handler at a time and stops at the first error; see
+    /// `process_unread_with`.
+    pub async fn process_unread<F, Fut, E>(
+        &self,
+        query: &NotificationQuery,
+        handler: F,
+    ) -> Result<ProcessSummary, Error>
+    where
+        F: Fn(Notification) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        self.process_unread_with(query, ProcessOptions::default(), handler)
+            .await
+    }
+
+    /// Handles each unread notification matching a query once, marking it as read only
+    /// when `handler` succeeds.
+    ///
+    /// Pages through the unread notifications with the query's limit, or 100, as the
+    /// page size; its offset is ignored. Up to `options.concurrency` handlers run at
+    /// once. A notification is never given to two handlers at the same time, even by
+    /// concurrent calls on clones of this client: one that another call is handling is
+    /// skipped. Failed notifications stay unread and are not retried within the call.
+    /// Snoozed notifications are left alone unless the query includes them.
+    ///
+    /// Fails only when notifications cannot be fetched.
+    pub async fn process_unread_with<F, Fut, E>(
+        &self,
+        query: &NotificationQuery,
+        options: ProcessOptions,
+        handler: F,
+    ) -> Result<ProcessSummary, Error>
+    where
+        F: Fn(Notification) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let page_size = query.limit.unwrap_or(CONSUME_PAGE_SIZE).max(1);
+        let mut page_query = NotificationQuery {
+            limit: Some(page_size),
+            unread_only: true,
+            ..query.clone()
+        };
+
+        let mut summary = ProcessSummary::default();
+        let mut seen = HashSet::new();
+        let mut offset = 0;
+        loop {
+            page_query.offset = Some(offset);
+            let page = self.inner.transport.get_notifications(&page_query).await?;
+            let fetched = page.len();
+            let now = Utc::now();
+
+            let mut tasks = Vec::new();
+            for notification in page {
+                if notification.read
+                    || (!query.include_snoozed && notification.is_snoozed_at(now))
+                    || !seen.insert(notification.id.clone())
+                {
+                    continue;
+                }
+                let Some(guard) = self.inner.in_flight.claim(&notification.id) else {
+                    summary.skipped += 1;
+                    continue;
+                };
+                let permit = Arc::clone(&semaphore).acquire_owned().await;
+                if stopped.load(Ordering::Acquire) {
+                    summary.skipped += 1;
+                    continue;
+                }
+
+                let client = self.clone();
+                let handler = Arc::clone(&handler);
+                let stopped = Arc::clone(&stopped);
+                let (sender, receiver) = oneshot::channel();
+                self.spawn(Box::pin(async move {
+                    let _permit = permit;
+                    let _guard = guard;
+                    let id = notification.id.clone();
+                    let handled = match handler(notification).await {
+                        Ok(()) => match client.mark_as_read(&id).await {
+                            Ok(_) => true,
+                            Err(e) => {
+                                eprintln!(
+                                    "[IronNotify] Warning: could not mark {} as read after \
+                                     processing it: {}",
+                                    id, e
+                                );
+                                false
+                            }
+                        },
+                        Err(e) => {
+                            if client.inner.debug().queue.is_enabled() {
+                                println!("[IronNotify] Handler failed for {}: {}", id, e);
+                            }
+                            false
+                        }
+                    };
+                    if !handled && options.on_error == ProcessErrorPolicy::Stop {
+                        stopped.store(true, Ordering::Release);
+                    }
+                    let _ = sender.send(handled);
+                }));
+                tasks.push(receiver);
+            }
+
+            let mut processed = 0;
+            for task in tasks {
+                if task.await.unwrap_or(false) {
+                    processed += 1;
+                } else {
+                    summary.failed += 1;
+                }
+            }
+            summary.processed += processed;
+
+            if fetched < page_size as usize || stopped.load(Ordering::Acquire) {
+                return Ok(summary);
+            }
+            // Notifications marked as read drop out of the unread list, so only the
+            // ones left unread shift the next page.
+            offset += (fetched - processed) as i32;
+        }
+    }
+
+    /// Gets notifications matching a query, falling back to the read cache on network errors.
+    ///
+    /// Successful responses are cached when `read_cache_ttl` is set. When the server
+    /// cannot be reached, the last result for the same query is returned with `stale`
+    /// set, as long as it is younger than the TTL.
+    pub async fn get_notifications_cached(
+        &self,
+        query: &NotificationQuery,
+    ) -> Result<CachedResult, Error> {
+        let key = query.to_params().join("&");
+        let now = Utc::now();
+
+        match self.inner.transport.get_notifications(query).await {
+            Ok(mut notifications) => {
+                if let Some(ref mutations) = self.inner.mutations {
+                    mutations.overlay(&mut notifications);
+                }
+                if let Some(ref cache) = self.inner.read_cache {
+                    cache.store(&key, notifications.clone(), now);
+                }
+                Ok(CachedResult {
+                    items: without_snoozed(notifications, query),
+                    fetched_at: now,
+                    stale: false,
+                })
+            }
+            Err(e @ (Error::Transport(_) | Error::Timeout(_))) => {
+                let cached = self
+                    .inner
+                    .read_cache
+                    .as_ref()
+                    .and_then(|cache| cache.get(&key, now));
+                match cached {
+                    Some(mut cached) => {
+                        if self.inner.debug().transport.is_enabled() {
+                            println!("[IronNotify] Serving cached notifications: {}", e);
+                        }
+                        cached.items = without_snoozed(cached.items, query);
+                        Ok(cached)
+                    }
+                    None => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fetches the notifications matching a query and the unread count as a snapshot.
+    ///
+    /// Metadata keys matching `redact_metadata_keys` are redacted, so the snapshot can
+    /// be embedded in pages sent to browsers.
+    pub async fn snapshot(
+        &self,
+        query: &NotificationQuery,
+    ) -> Result<NotificationSnapshot, String> {
+        let notifications = self.query_notifications(query).await?;
+        let unread_count = self.get_unread_count().await?;
+        Ok(NotificationSnapshot::from_notifications(&notifications, unread_count)
+            .redacted(&self.inner.redactor))
+    }
+
+    /// Gets what an inbox badge shows: the unread count, a preview of the newest
+    /// `preview_count` unread notifications, their highest severity, and the
+    /// connection state, for one user or for the whole project.
+    ///
+    /// The count, preview, and severity breakdown are fetched concurrently. When some
+    /// of them fail, the rest is returned with `partial` set and the first error
+    /// attached; only when all of them fail is the error returned. Complete summaries
+    /// are reused for `inbox_summary_ttl`, and dropped when notifications are marked
+    /// or archived through this client.
+    pub async fn inbox_summary(
+        &self,
+        user_id: Option<&str>,
+        preview_count: usize,
+    ) -> Result<InboxSummary, Error> {
+        let now = Utc::now();
+        let cached = self
+            .inner
+            .inbox_cache
+            .as_ref()
+            .and_then(|cache| cache.get(user_id, preview_count, now));
+        if let Some(mut summary) = cached {
+            summary.connection_state = self.connection_state();
+            return Ok(summary);
+        }
+
+        let mut query = NotificationQuery::new()
+            .unread_only(true)
+            .limit(i32::try_from(preview_count).unwrap_or(i32::MAX));
+        query.user_id = user_id.map(str::to_string);
+        let preview = async {
+            if preview_count == 0 {
+                return Ok(Vec::new());
+            }
+            self.fetch_notifications(&query).await
+        };
+        let count = async {
+            self.inner
+                .transport
+                .get_unread_count(user_id)
+                .await
+                .map_err(Error::Transport)
+        };
+        let severities = self.get_unread_counts(CountGroupBy::Severity, user_id);
+        let (count, preview, severities) = join3(count, preview, severities).await;
+
+        let mut errors = Vec::new();
+        let unread_count = count.map_err(|e| errors.push(e)).ok();
+        let preview = preview.map_err(|e| errors.push(e)).unwrap_or_default();
+        let highest_unread_severity = severities
+            .map_err(|e| errors.push(e))
+            .ok()
+            .and_then(|counts| inbox::highest_severity(&counts))
+            .or_else(|| preview.iter().map(|n| n.severity).max());
+        if errors.len() == 3 {
+            return Err(errors.remove(0));
+        }
+
+        let summary = InboxSummary {
+            unread_count,
+            preview,
+            highest_unread_severity,
+            connection_state: self.connection_state(),
+            fetched_at: now,
+            partial: !errors.is_empty(),
+            error: errors.into_iter().next(),
+        };
+        if let (false, Some(cache)) = (summary.partial, self.inner.inbox_cache.as_ref()) {
+            cache.store(user_id, preview_count, &summary);
+        }
+        Ok(summary)
+    }
+
+    fn clear_inbox_summaries(&self) {
+        if let Some(ref cache) = self.inner.inbox_cache {
+            cache.clear();
+        }
+    }
+
+    /// Gets the unread notification count.
+    pub async fn get_unread_count(&self) -> Result<i32, String> {
+        self.inner.transport.get_unread_count(None).await
+    }
+
+    /// Gets unread counts grouped by event type, group key, or severity.
+    ///
+    /// Falls back to a single `"all"` bucket when the server does not support grouping.
+    /// Only groups reported by the server are included.
+    pub async fn get_unread_counts(
+        &self,
+        group_by: CountGroupBy,
+        user_id: Option<&str>,
+    ) -> Result<HashMap<String, i32>, Error> {
+        match self.inner.transport.get_unread_counts(group_by, user_id).await {
+            Err(Error::Http(ref e)) if e.status == 400 || e.status == 404 => {
+                let count = self
+                    .inner
+                    .transport
+                    .get_unread_count(user_id)
+                    .await
+                    .map_err(Error::Transport)?;
+                Ok(HashMap::from([("all".to_string(), count)]))
+            }
+            other => other,
+        }
+    }
+
+    /// Gets the activity history of a notification, oldest first.
+    pub async fn get_notification_history(
+        &self,
+        notification_id: &str,
+    ) -> Result<Vec<NotificationEvent>, Error> {
+        let mut events = self
+            .inner
+            .transport
+            .get_notification_history(notification_id)
+            .await?;
+        events.sort_by_key(NotificationEvent::timestamp);
+        Ok(events)
+    }
+
+    /// Downloads an attachment referenced by a notification, streaming it into `writer`.
+    ///
+    /// `url_or_id` is either a URL from the notification's metadata or an attachment ID.
+    /// The API key is only sent to IronNotify hosts. An expired signed URL fails with
+    /// `Error::AttachmentExpired`; fetch the notification again for a fresh link.
+    pub async fn download_attachment<W>(
+        &self,
+        url_or_id: &str,
+        mut writer: W,
+    ) -> Result<DownloadInfo, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.inner
+            .transport
+            .download_attachment(url_or_id, &mut writer)
+            .await
+    }
+
+    /// Marks a notification as read.
+    ///
+    /// With `write_behind_mutations`, a network failure is queued instead of returned;
+    /// see `pending_mutations`.
+    pub async fn mark_as_read(&self, notification_id: &str) -> Result<bool, String> {
+        self.mutate(notification_id, Mutation::MarkRead).await
+    }
+
+    /// Marks a notification as unread.
+    pub async fn mark_as_unread(&self, notification_id: &str) -> Result<bool, String> {
+        self.mutate(notification_id, Mutation::MarkUnread).await
+    }
+
+    /// Archives a notification.
+    pub async fn archive(&self, notification_id: &str) -> Result<bool, String> {
+        self.mutate(notification_id, Mutation::Archive).await
+    }
+
+    /// Snoozes a notification so it reappears unread at the given time.
+    pub async fn snooze(&self, notification_id: &str, until: DateTime<Utc>) -> Result<bool, String> {
+        if until <= Utc::now() {
+            return Err("Snooze time must be in the future".to_string());
+        }
+        let snoozed = self.inner.transport.snooze(notification_id, until).await?;
+        self.forget_notification(notification_id);
+        Ok(snoozed)
+    }
+
+    /// Cancels a notification's snooze.
+    pub async fn unsnooze(&self, notification_id: &str) -> Result<bool, String> {
+        let unsnoozed = self.inner.transport.unsnooze(notification_id).await?;
+        self.forget_notification(notification_id);
+        Ok(unsnoozed)
+    }
+
+    /// Acknowledges a notification created with `requires_ack`, with an optional note.
+    pub async fn acknowledge(
+        &self,
+        notification_id: &str,
+        note: Option<String>,
+    ) -> Result<bool, String> {
+        self.mutate(notification_id, Mutation::Acknowledge { note }).await
+    }
+
+    /// Sends a mutation, or queues it when `write_behind_mutations` is set and the
+    /// server is unreachable.
+    ///
+    /// A notification with mutations already queued has the new one queued behind
+    /// them, so they reach the server in the order they were made.
+    async fn mutate(&self, notification_id: &str, mutation: Mutation) -> Result<bool, String> {
+        if let Some(ref mutations) = self.inner.mutations {
+            if mutations.has_pending(notification_id) {
+                self.queue_mutation(mutations, notification_id, mutation);
+                self.replay_mutations().await;
+                return Ok(true);
+            }
+        }
+
+        match self.inner.transport.mutate(notification_id, &mutation).await {
+            Ok(()) => {
+                self.forget_notification(notification_id);
+                Ok(true)
+            }
+            Err(e @ (Error::Transport(_) | Error::Timeout(_))) => match self.inner.mutations {
+                Some(ref mutations) => {
+                    if self.inner.debug().queue.is_enabled() {
+                        println!("[IronNotify] Queued {} for later: {}", mutation, e);
+                    }
+                    self.queue_mutation(mutations, notification_id, mutation);
+                    Ok(true)
+                }
+                None => Err(e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Drops cached results holding a notification the server has just changed.
+    fn forget_notification(&self, notification_id: &str) {
+        if let Some(ref cache) = self.inner.read_cache {
+            cache.invalidate_notification(notification_id);
+        }
+        self.clear_inbox_summaries();
+    }
+
+    /// Queues a mutation and applies it to the read cache.
+    fn queue_mutation(
+        &self,
+        mutations: &MutationQueue,
+        notification_id: &str,
+        mutation: Mutation,
+    ) {
+        if let Some(ref cache) = self.inner.read_cache {
+            cache.apply_mutation(notification_id, &mutation);
+        }
+        self.clear_inbox_summaries();
+        mutations.push(notification_id, mutation);
+    }
+
+    /// Replays queued mutations, oldest first, stopping at the first one that fails.
+    ///
+    /// The server treats repeated mutations as successes. Mutations of notifications
+    /// that no longer exist are dropped and reported to the drop callback.
+    async fn replay_mutations(&self) {
+        let Some(ref mutations) = self.inner.mutations else {
+            return;
+        };
+        let _replay = self.inner.mutation_replay.lock().await;
+
+        for item in mutations.pending() {
+            let tenant = Tenant::restore(item.tenant.as_deref(), item.tenant_id.as_deref());
+            let mutation = self
+                .inner
+                .transport
+                .mutate(&item.notification_id, &item.mutation);
+            match tenant::scoped(tenant, mutation).await {
+                Ok(()) => mutations.remove(item.id),
+                Err(Error::Http(ref e)) if matches!(e.status, 404 | 409 | 410) => {
+                    mutations.drop_conflict(&item)
+                }
+                Err(e) => {
+                    if self.inner.debug().queue.is_enabled() {
+                        println!("[IronNotify] Mutation replay stopped: {}", e);
+                    }
+                    break;
+                }
+            }
+            if let Some(ref cache) = self.inner.read_cache {
+                cache.invalidate_notification(&item.notification_id);
+            }
+        }
+    }
+
+    /// Gets the mutations waiting to be replayed, oldest first.
+    pub fn pending_mutations(&self) -> Vec<PendingMutation> {
+        self.inner
+            .mutations
+            .as_ref()
+            .map(MutationQueue::pending)
+            .unwrap_or_default()
+    }
+
+    /// Marks all notifications as read.
+    pub async fn mark_all_as_read(&self) -> Result<bool, String> {
+        let marked = self.inner.transport.mark_all_as_read().await?;
+        if let Some(ref cache) = self.inner.read_cache {
+            cache.clear();
+        }
+        self.clear_inbox_summaries();
+        Ok(marked)
+    }
+
+    /// Archives the notifications matching a query, returning how many were affected.
+    ///
+    /// Queries without filters are refused unless `confirm_delete_all` was called.
+    /// With `dry_run`, the server only reports how many would be affected. Servers
+    /// without bulk operations fail with `Error::Unsupported`.
+    pub async fn archive_matching(&self, query: &NotificationQuery) -> Result<u64, Error> {
+        Self::check_bulk_scope(query)?;
+        let affected = self.inner.transport.archive_matching(query).await?;
+        self.after_bulk(query);
+        Ok(affected)
+    }
+
+    /// Deletes the notifications matching a query, returning how many were affected.
+    ///
+    /// Queries without filters are refused unless `confirm_delete_all` was called.
+    /// With `dry_run`, the server only reports how many would be affected. Servers
+    /// without bulk operations fail with `Error::Unsupported`.
+    pub async fn delete_matching(&self, query: &NotificationQuery) -> Result<u64, Error> {
+        Self::check_bulk_scope(query)?;
+        let affected = self.inner.transport.delete_matching(query).await?;
+        self.after_bulk(query);
+        Ok(affected)
+    }
+
+    fn check_bulk_scope(query: &NotificationQuery) -> Result<(), Error> {
+        if query.has_filters() || query.confirm_all {
+            Ok(())
+        } else {
+            Err(Error::UnscopedBulkOperation)
+        }
+    }
+
+    fn after_bulk(&self, query: &NotificationQuery) {
+        if query.dry_run {
+            return;
+        }
+        if let Some(ref cache) = self.inner.read_cache {
+            cache.clear();
+        }
+        self.clear_inbox_summaries();
+    }
+
+    /// Lists registered event type definitions.
+    pub async fn list_event_types(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<EventTypeDefinition>, Error> {
+        self.inner.transport.list_event_types(limit, offset).await
+    }
+
+    /// Pages through the registered event type definitions, `page_size` at a time.
+    pub fn event_types_pager(&self, page_size: i32) -> Pager<EventTypeDefinition> {
+        let client = self.clone();
+        Pager::by_offset(page_size, 0, move |offset, limit| {
+            let client = client.clone();
+            async move {
+                client
+                    .inner
+                    .transport
+                    .list_event_types(Some(limit), Some(offset))
+                    .await
+            }
+        })
+    }
+
+    /// Gets an event type definition, or `None` if it is not registered.
+    pub async fn get_event_type(&self, name: &str) -> Result<Option<EventTypeDefinition>, Error> {
+        self.inner.transport.get_event_type(name).await
+    }
+
+    /// Creates or replaces an event type definition. Repeating the call with the
+    /// same definition has no further effect.
+    pub async fn upsert_event_type(
+        &self,
+        definition: EventTypeDefinition,
+    ) -> Result<EventTypeDefinition, Error> {
+        validate_event_type(&definition.name)
+            .map_err(|e| Error::InvalidEventType(e.to_string()))?;
+        self.inner.transport.upsert_event_type(&definition).await
+    }
+
+    /// Gets the current connection state.
+    pub fn connection_state(&self) -> ConnectionState {
+        self.inner.realtime.state()
+    }
+
+    /// Subscribes to changes of the real-time connection state.
+    pub fn watch_connection_state(&self) -> watch::Receiver<ConnectionState> {
+        self.inner.realtime.watch_state()
+    }
+
+    /// Gets when the server last answered a real-time heartbeat, or `None` if it
+    /// has not yet.
+    pub fn last_heartbeat_at(&self) -> Option<DateTime<Utc>> {
+        self.inner.realtime.last_heartbeat_at()
+    }
+
+    /// Checks whether the API was reachable at the last connectivity check, and no
+    /// send has since been queued for being offline.
+    pub fn is_online(&self) -> bool {
+        self.inner.is_online.get()
+    }
+
+    /// Subscribes to changes of whether the API is reachable.
+    pub fn watch_online(&self) -> watch::Receiver<bool> {
+        self.inner.is_online.subscribe()
+    }
+
+    /// Gets the number of notifications waiting in the offline queue.
+    pub fn queue_size(&self) -> usize {
+        self.inner.queue.as_ref().map_or(0, OfflineQueue::size)
+    }
+
+    /// Gets the notifications waiting in the offline queue, oldest first.
+    pub fn queued_notifications(&self) -> Vec<QueuedNotification> {
+        self.inner
+            .queue
+            .as_ref()
+            .map(OfflineQueue::get_all)
+            .unwrap_or_default()
+    }
+
+    /// Removes every notification from the offline queue without sending it,
+    /// returning how many were removed.
+    pub fn clear_queue(&self) -> usize {
+        self.inner.queue.as_ref().map_or(0, OfflineQueue::clear)
+    }
+
+    /// Compacts the offline queue: keeps only the newest notification for each
+    /// deduplication key and recipient, removes expired ones and ones that failed to
+    /// send `max_queue_attempts` times, and rewrites the queue file in one step.
+    pub fn compact_queue(&self) -> CompactionReport {
+        self.inner
+            .queue
+            .as_ref()
+            .map(|queue| queue.compact(Utc::now(), self.inner.options.max_queue_attempts))
+            .unwrap_or_default()
+    }
+
+    /// Gets the number, total serialized size, oldest enqueue time, and severities of
+    /// the queued notifications.
+    pub fn queue_stats(&self) -> QueueStats {
+        self.inner
+            .queue
+            .as_ref()
+            .map(OfflineQueue::stats)
+            .unwrap_or_default()
+    }
+
+    /// Gets a snapshot of the request metrics.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let mut snapshot = self.inner.metrics.snapshot();
+        snapshot.circuit_state = self.inner.breaker.state();
+        snapshot.circuit_opened = self.inner.breaker.times_opened();
+        let (sends, reads) = (
+            self.inner.transport.send_limit(),
+            self.inner.transport.read_limit(),
+        );
+        snapshot.in_flight_sends = sends.in_flight();
+        snapshot.peak_in_flight_sends = sends.peak();
+        snapshot.in_flight_reads = reads.in_flight();
+        snapshot.peak_in_flight_reads = reads.peak();
+        snapshot
+    }
+
+    /// Gets the request metrics of a tenant.
+    pub(crate) fn tenant_metrics(&self, tenant: &str) -> TenantMetrics {
+        self.inner.metrics.tenant(tenant)
+    }
+
+    /// Gets the current state of the send circuit breaker.
+    pub fn circuit_state(&self) -> CircuitState {
+        self.inner.breaker.state()
+    }
+
+    /// Verifies the API key, returning details about its project.
+    ///
+    /// A rejected key is reported as `Error::InvalidApiKey`.
+    pub async fn verify_credentials(&self) -> Result<ProjectInfo, Error> {
+        self.inner.transport.get_project().await
+    }
+
+    /// Gets whether the API accepts the client's credentials.
+    ///
+    /// After a send is rejected with `401` or `403`, sends are no longer queued and
+    /// `flush` stops until a request succeeds again, e.g. `verify_credentials` or a
+    /// send after `update_api_key`.
+    pub fn auth_status(&self) -> AuthStatus {
+        self.inner.transport.auth().status()
+    }
+
+    /// Sets a callback invoked when the API starts rejecting the credentials.
+    ///
+    /// It is invoked once per failure, not for every rejected send.
+    pub fn on_auth_failure<F>(&self, callback: F)
+    where
+        F: Fn(&AuthFailure) + Send + Sync + 'static,
+    {
+        self.inner.transport.auth().set_callback(Arc::new(callback));
+    }
+
+    /// Replaces the API key, e.g. after the previous one was revoked, and resumes
+    /// sending.
+    ///
+    /// The new key must belong to the same environment as the one the client was
+    /// created with, since queued notifications are stored per environment.
+    pub fn update_api_key(&self, api_key: impl Into<String>) -> Result<(), Error> {
+        let api_key = api_key.into();
+        if self.inner.options.token_provider.is_some() {
+            return Err(Error::Config(
+                "API keys cannot be used with a token provider".to_string(),
+            ));
+        }
+        if api_key.is_empty() {
+            return Err(Error::Config("API key is required".to_string()));
+        }
+        let environment = KeyEnvironment::from_api_key(&self.inner.options.api_key);
+        if KeyEnvironment::from_api_key(&api_key) != environment {
+            return Err(Error::Config(format!(
+                "API key must be a {} key like the client's",
+                environment
+            )));
+        }
+
+        self.inner.transport.set_api_key(&api_key)?;
+        self.inner.transport.auth().clear();
+        Ok(())
+    }
+
+    /// Checks whether the API is reachable, resetting the circuit breaker if it is.
+    ///
+    /// Queued mutations are replayed when the API becomes reachable again.
+    pub async fn check_connectivity(&self) -> bool {
+        let online = self.inner.transport.is_online().await;
+        if online {
+            self.inner.breaker.reset();
+        }
+        let was_online = self.inner.is_online.set(online);
+        if online && !was_online {
+            self.replay_mutations().await;
+        }
+        online
+    }
+
+    /// Sets a callback invoked whenever a request exceeds the slow request threshold.
+    pub fn on_slow_request<F>(&self, callback: F)
+    where
+        F: Fn(&SlowRequest) + Send + Sync + 'static,
+    {
+        self.inner.metrics.set_on_slow_request(Arc::new(callback));
+    }
+
+    /// Sets a callback invoked whenever a notification is discarded without being sent,
+    /// e.g. evicted from a full offline queue or expired while queued.
+    pub fn on_notification_dropped<F>(&self, callback: F)
+    where
+        F: Fn(&DroppedNotification) + Send + Sync + 'static,
+    {
+        self.inner.drops.set_callback(Arc::new(callback));
+    }
+
+    /// Sets a callback invoked when a payload reaches the soft limit of its size,
+    /// metadata entries, or actions. The payload is still sent.
+    ///
+    /// Warnings are raised at most once per `limit_warning_interval` for each event
+    /// type and field.
+    pub fn on_limit_warning<F>(&self, callback: F)
+    where
+        F: Fn(&LimitWarning) + Send + Sync + 'static,
+    {
+        self.inner.limits.set_callback(Arc::new(callback));
+    }
+
+    /// Gets how far the server clock is ahead of the local clock, smoothed over recent
+    /// responses. Negative when the local clock is fast; `None` before any response.
+    pub fn estimated_clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.transport.clock_skew().estimate()
+    }
+
+    /// Gets the failure injection handle set with `NotifyOptionsBuilder::chaos`.
+    #[cfg(any(test, feature = "chaos"))]
+    pub fn chaos(&self) -> Option<&Chaos> {
+        self.inner.options.chaos.as_ref()
+    }
+
+    /// Gets the rate limit reported by the latest API response.
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.inner.transport.rate_limits().latest()
+    }
+
+    /// Sets a callback invoked when the remaining requests drop below `rate_limit_threshold`.
+    ///
+    /// Fires once per drop; it fires again only after the window resets or the count recovers.
+    pub fn on_rate_limit_low<F>(&self, callback: F)
+    where
+        F: Fn(&RateLimitStatus) + Send + Sync + 'static,
+    {
+        self.inner.transport.rate_limits().set_callback(Arc::new(callback));
+    }
+
+    /// Gets up to `limit` of the most recently dropped notifications, newest first.
+    pub fn recent_drops(&self, limit: usize) -> Vec<DroppedNotification> {
+        self.inner.drops.recent(limit)
+    }
+
+    /// Starts resolving the real-time handshake's `Authorization` header like a
+    /// request's: a token from the provider, or the current API key.
+    fn authorizer(&self) -> Authorizer {
+        let inner = Arc::downgrade(&self.inner);
+        Authorizer::spawn(&*self.inner.runtime, move || {
+            let inner = inner.clone();
+            Box::pin(async move {
+                let Some(inner) = inner.upgrade() else {
+                    return Err("Client was dropped".to_string());
+                };
+                let value = inner.transport.resolve_authorization().await?;
+                Ok(value.and_then(|value| value.to_str().ok().map(str::to_string)))
+            })
+        })
+    }
+
+    /// Connects to real-time notifications.
+    ///
+    /// The WebSocket is kept open on a background thread, which pings the server every
+    /// `heartbeat_interval` and reconnects, following `auto_reconnect`, when it closes
+    /// or a pong does not arrive within `heartbeat_timeout`. Received messages are
+    /// passed to the `on_realtime_message` callback on the client's runtime, so this
+    /// must be called from within it. Does nothing if already connected.
+    ///
+    /// After a reconnect, notifications created since the newest one delivered are
+    /// fetched and passed to the callback, oldest first, before any message that
+    /// arrived meanwhile. Notifications are never passed on twice.
+    pub fn connect(&self) {
+        let settings = ConnectSettings {
+            url: realtime::connect_url(&self.inner.options.ws_url),
+            authorizer: self.authorizer(),
+            tls: TlsSettings::new(&self.inner.options),
+            connect_timeout: self.inner.options.connect_timeout,
+            heartbeat_interval: self.inner.options.heartbeat_interval,
+            heartbeat_timeout: self.inner.options.heartbeat_timeout,
+            auto_reconnect: self.inner.options.auto_reconnect,
+            max_reconnect_attempts: self.inner.options.max_reconnect_attempts,
+            reconnect_delay: self.inner.options.reconnect_delay,
+        };
+        let mut events = match self.inner.realtime.connect(settings) {
+            Ok(Some(events)) => events,
+            Ok(None) => return,
+            Err(e) => {
+                eprintln!("[IronNotify] Warning: Failed to start the real-time connection: {}", e);
+                return;
+            }
+        };
+
+        let inner = Arc::downgrade(&self.inner);
+        self.spawn(Box::pin(async move {
+            // Events are handled one at a time, so messages arriving during a replay
+            // wait for it and stay in order.
+            let mut replay = Replay::default();
+            while let Some(event) = events.recv().await {
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                let client = NotifyClient { inner };
+                match event {
+                    ConnectionEvent::Connected { resumed: true } => {
+                        client.replay_gap(&mut replay).await
+                    }
+                    ConnectionEvent::Connected { resumed: false } => {}
+                    ConnectionEvent::Message(message) => {
+                        if replay.live(&message) {
+                            client.inner.realtime.dispatch(&message);
+                        }
+                    }
+                }
+            }
+        }));
+    }
+
+    /// Fetches the notifications created while the real-time connection was down and
+    /// passes the ones not delivered yet to the message callback.
+    async fn replay_gap(&self, replay: &mut Replay) {
+        let Some(since) = replay.cursor() else {
+            return;
+        };
+        let mut fetched = Vec::new();
+        for query in self.inner.realtime.replay_queries(since) {
+            match self.notifications_pager(&query).try_collect(None).await {
+                Ok(notifications) => fetched.extend(notifications),
+                Err(e) => {
+                    eprintln!(
+                        "[IronNotify] Warning: Failed to fetch notifications missed while \
+                         disconnected: {}",
+                        e
+                    );
+                    return;
+                }
+            }
+        }
+
+        let missed = replay.gap(fetched);
+        if missed.is_empty() {
+            return;
+        }
+        if self.inner.debug().realtime.is_enabled() {
+            println!("[IronNotify] Replaying {} missed notifications", missed.len());
+        }
+        self.inner.realtime.gap_detected(missed.len());
+        for notification in missed {
+            let message = RealtimeMessage::NotificationNew(Box::new(notification));
+            self.inner.realtime.dispatch(&message);
+        }
+    }
+
+    /// Disconnects from real-time notifications.
+    pub fn disconnect(&self) {
+        self.inner.realtime.disconnect();
+    }
+
+    /// Subscribes to a user's notifications.
+    pub fn subscribe_to_user(&self, user_id: &str) {
+        self.inner.realtime.subscribe(realtime::user_topic(user_id));
+        if self.inner.debug().realtime.is_enabled() {
+            println!("[IronNotify] Subscribed to user: {}", user_id);
+        }
+    }
+
+    /// Subscribes to app-wide notifications.
+    pub fn subscribe_to_app(&self) {
+        self.inner.realtime.subscribe(realtime::app_topic());
+        if self.inner.debug().realtime.is_enabled() {
+            println!("[IronNotify] Subscribed to app notifications");
+        }
+    }
+
+    /// Sets a callback invoked with each message received over the real-time
+    /// connection, in order. Heartbeat pings and pongs are not passed on.
+    pub fn on_realtime_message<F>(&self, callback: F)
+    where
+        F: Fn(&RealtimeMessage) + Send + Sync + 'static,
+    {
+        self.inner.realtime.set_callback(Arc::new(callback));
+    }
+
+    /// Sets a callback invoked with the number of notifications missed while the
+    /// real-time connection was down, before they are replayed after a reconnect.
+    pub fn on_gap_detected<F>(&self, callback: F)
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.inner.realtime.set_gap_callback(Arc::new(callback));
+    }
+
+    /// Flushes the offline queue, including deferred notifications whose quiet hours have ended.
+    ///
+    /// Error and Critical notifications are sent first; see `flush_fairness`. While the
+    /// rate limit is below `rate_limit_threshold`, the rest stay queued until it resets.
+    ///
+    /// Notifications queued under a key from another environment (`ak_test_` versus
+    /// `ak_live_`) are never sent; they are dropped with a warning and reported to the
+    /// drop callback.
+    ///
+    /// Queued mutations are replayed first, when `write_behind_mutations` is set. With
+    /// `postgres_queue`, the Postgres queue is flushed after the local one.
+    ///
+    /// Does nothing while sending is paused.
+    ///
+    /// Dropping the future stops after the notification being sent. A notification is
+    /// only removed from the queue once it was sent, so one whose send is dropped stays
+    /// queued and is sent again by the next flush.
+    pub async fn flush(&self) {
+        if self.is_paused() || self.inner.transport.auth().is_failed() {
+            return;
+        }
+
+        self.replay_mutations().await;
+
+        if self.inner.queue.is_some() {
+            self.flush_offline_queue().await;
+        }
+        #[cfg(feature = "postgres")]
+        if let Some(ref storage) = self.inner.options.postgres_queue {
+            self.flush_postgres_queue(storage).await;
+        }
+    }
+
+    /// Flushes the offline queue like `flush`, failing with `Error::Cancelled` if
+    /// `token` is cancelled first.
+    pub async fn flush_with_cancel(&self, token: CancellationToken) -> Result<(), Error> {
+        cancellable(token, self.flush()).await
+    }
+
+    async fn flush_offline_queue(&self) {
+        let maintained = self.on_queue(|queue| {
+            queue.release_due(Utc::now());
+            queue.purge_expired(Utc::now());
+            let foreign = queue.purge_foreign();
+            (foreign, queue.next_release(), queue.get_all())
+        });
+        let Some((foreign, next_release, notifications)) = maintained.await else {
+            return;
+        };
+        if foreign > 0 {
+            eprintln!(
+                "[IronNotify] Warning: dropped {} queued notifications from a different \
+                 key environment",
+                foreign
+            );
+        }
+        if let Some(release_at) = next_release {
+            self.schedule_flush(release_at);
+        }
+
+        if notifications.is_empty() {
+            return;
+        }
+
+        if !self.check_connectivity().await {
+            return;
+        }
+
+        let order = drain_order(&notifications, self.inner.config.flush_fairness());
+
+        let mut held_groups = HashSet::new();
+        for item in order.into_iter().map(|i| &notifications[i]) {
+            let payload = &item.payload;
+            let ordering_key = payload.ordering_key();
+            if ordering_key.is_some_and(|key| held_groups.contains(key)) {
+                continue;
+            }
+            if item.awaiting_confirmation || self.awaits_tenant_key(item) {
+                held_groups.extend(ordering_key);
+                continue;
+            }
+            if let Some(reset_at) = self.rate_limited_until(payload) {
+                held_groups.extend(ordering_key);
+                self.schedule_flush(reset_at);
+                continue;
+            }
+
+            let _ordering = match ordering_key {
+                Some(key) => Some(self.inner.ordering.lock(key).await),
+                None => None,
+            };
+            let tenant = Tenant::restore(item.tenant.as_deref(), item.tenant_id.as_deref());
+            let decayed = self.decay_severity(item, Utc::now());
+            let sent = decayed.as_ref().unwrap_or(payload);
+            let result = tenant::scoped(tenant, self.transmit(sent)).await;
+            #[cfg(feature = "journal")]
+            self.journal_result(payload, &result, 2);
+            let id = item.id;
+            if result.success {
+                self.on_queue(move |queue| queue.remove(id)).await;
+                self.sent_from_queue(item.id, payload, &result);
+            } else {
+                let max_attempts = self.inner.options.max_queue_attempts;
+                self.on_queue(move |queue| queue.record_attempt(id, max_attempts)).await;
+                break;
+            }
+        }
+    }
+
+    /// Sends the notifications in the Postgres queue, claiming them in batches so that
+    /// replicas flushing at the same time never send the same one.
+    ///
+    /// Notifications queued under a key from another environment are left for clients
+    /// using such a key.
+    #[cfg(feature = "postgres")]
+    async fn flush_postgres_queue(&self, storage: &PostgresQueueStorage) {
+        if !self.check_connectivity().await {
+            return;
+        }
+
+        let environment = KeyEnvironment::from_api_key(&self.inner.options.api_key);
+        loop {
+            let items = match storage.claim_for(POSTGRES_CLAIM_BATCH, environment).await {
+                Ok(items) if items.is_empty() => return,
+                Ok(items) => items,
+                Err(e) => {
+                    eprintln!("[IronNotify] Warning: could not read the Postgres queue: {}", e);
+                    return;
+                }
+            };
+
+            let order = drain_order(&items, self.inner.config.flush_fairness());
+            let mut unsent = Vec::new();
+            let mut held_groups = HashSet::new();
+            let mut failed = false;
+            for item in order.into_iter().map(|i| &items[i]) {
+                let payload = &item.payload;
+                let ordering_key = payload.ordering_key();
+                if failed || ordering_key.is_some_and(|key| held_groups.contains(key)) {
+                    unsent.push(item.id);
+                    continue;
+                }
+                if self.awaits_tenant_key(item) {
+                    held_groups.extend(ordering_key);
+                    unsent.push(item.id);
+                    continue;
+                }
+                if payload.expires_at.is_some_and(|at| at <= Utc::now()) {
+                    self.inner.drops.record_entry(
+                        DeliveryKey::Queued(item.id),
+                        DroppedNotification::new(payload, DropReason::Expired),
+                    );
+                    self.remove_from_postgres(storage, item.id).await;
+                    continue;
+                }
+                if let Some(reset_at) = self.rate_limited_until(payload) {
+                    held_groups.extend(ordering_key);
+                    self.schedule_flush(reset_at);
+                    unsent.push(item.id);
+                    continue;
+                }
+
+                let _ordering = match ordering_key {
+                    Some(key) => Some(self.inner.ordering.lock(key).await),
+                    None => None,
+                };
+                let tenant = Tenant::restore(item.tenant.as_deref(), item.tenant_id.as_deref());
+                let decayed = self.decay_severity(item, Utc::now());
+                let sent = decayed.as_ref().unwrap_or(payload);
+                let result = tenant::scoped(tenant, self.transmit(sent)).await;
+                #[cfg(feature = "journal")]
+                self.journal_result(payload, &result, 2);
+                if result.success {
+                    self.remove_from_postgres(storage, item.id).await;
+                    self.sent_from_queue(item.id, payload, &result);
+                } else {
+                    failed = true;
+                    unsent.push(item.id);
+                }
+            }
+
+            if !unsent.is_empty() {
+                if let Err(e) = storage.release(&unsent).await {
+                    eprintln!("[IronNotify] Warning: could not release Postgres queue claims: {}", e);
+                }
+                return;
+            }
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    async fn remove_from_postgres(&self, storage: &PostgresQueueStorage, id: u64) {
+        if let Err(e) = storage.remove(id).await {
+            eprintln!(
+                "[IronNotify] Warning: could not remove notification {} from the Postgres \
+                 queue: {}",
+                id, e
+            );
+        }
+    }
+
+    /// Publishes a notification sent from a queue and runs the callbacks kept for it.
+    fn sent_from_queue(&self, id: u64, payload: &NotificationPayload, result: &SendResult) {
+        self.publish_local(payload, result);
+        if let Some(callbacks) = self.inner.deliveries.take(&DeliveryKey::Queued(id)) {
+            self.complete_delivery(result, callbacks);
+        }
+    }
+}
+
+/// Runs a future until it finishes or the token is cancelled, dropping it then.
+async fn cancellable<T>(
+    token: CancellationToken,
+    future: impl Future<Output = T>,
+) -> Result<T, Error> {
+    token.run_until_cancelled(future).await.ok_or(Error::Cancelled)
+}
+
+/// Removes snoozed notifications unless the query includes them.
+fn without_snoozed(
+    mut notifications: Vec<Notification>,
+    query: &NotificationQuery,
+) -> Vec<Notification> {
+    if !query.include_snoozed {
+        let now = Utc::now();
+        notifications.retain(|n| !n.is_snoozed_at(now));
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::ChaosScope;
+    use crate::drops::DropReason;
+    use crate::error::TimeoutPhase;
+    use crate::storage::JsonFile;
+    use crate::test_support::{self, MockResponse, MockServer};
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    fn healthy_server() -> MockServer {
+        MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n1" })))
+    }
+
+    fn chaotic_client(server: &MockServer, queue: bool) -> NotifyClient {
+        let mut options = test_support::options(server);
+        options.enable_offline_queue = queue;
+        options.circuit_breaker_threshold = 2;
+        options.chaos = Some(Chaos::with_seed(7));
+        NotifyClient::new(options).unwrap()
+    }
+
+    #[test]
+    fn rejected_payloads_do_not_open_the_circuit() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            for status in [400, 413, 422] {
+                let server = healthy_server();
+                let client = chaotic_client(&server, false);
+                client.chaos().unwrap().force_status(ChaosScope::Sends, status, 3);
+                for _ in 0..3 {
+                    let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+                    assert_eq!(result.status, Some(status));
+                }
+                assert_eq!(client.circuit_state(), CircuitState::Closed);
+
+                let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+                assert!(result.success, "{:?}", result.error);
+                assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+            }
+        });
+    }
+
+    #[test]
+    fn outages_open_the_circuit() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            for status in [500, 503, 429] {
+                let server = healthy_server();
+                let client = chaotic_client(&server, false);
+                client.chaos().unwrap().force_status(ChaosScope::Sends, status, 2);
+                for _ in 0..2 {
+                    let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+                    assert_eq!(result.status, Some(status));
+                }
+                assert_eq!(client.circuit_state(), CircuitState::Open);
+
+                let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+                assert_eq!(result.error.as_deref(), Some("Circuit breaker is open"));
+                assert!(server.requests_to("POST", "/api/v1/notify").is_empty());
+            }
+
+            let server = healthy_server();
+            let client = chaotic_client(&server, false);
+            client.chaos().unwrap().fail_requests(ChaosScope::Sends, 1.0);
+            for _ in 0..2 {
+                let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+                assert!(!result.success);
+            }
+            assert_eq!(client.circuit_state(), CircuitState::Open);
+            assert!(server.requests_to("POST", "/api/v1/notify").is_empty());
+        });
+    }
+
+    #[test]
+    fn failed_sends_are_retried_by_the_next_flush() {
+        let _storage = test_support::TempStorage::new();
+        let server = healthy_server();
+        let client = chaotic_client(&server, true);
+        client.chaos().unwrap().force_status(ChaosScope::Sends, 503, 2);
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(client.send_payload(&NotificationPayload::new("e", "t")).await.queued);
+            client.flush().await;
+            let queued = client.queued_notifications();
+            assert_eq!(queued.len(), 1);
+            assert_eq!(queued[0].attempts, 1);
+
+            client.flush().await;
+        });
+        assert_eq!(client.queue_size(), 0);
+        assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+    }
+
+    #[test]
+    fn a_failed_queue_write_keeps_the_entry_for_the_next_write() {
+        let storage = test_support::TempStorage::new();
+        let server = healthy_server();
+        let client = chaotic_client(&server, true);
+        let chaos = client.chaos().unwrap();
+        let file = storage.path().join("offline_queue.test.json");
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            chaos.fail_persistence(true).force_status(ChaosScope::Sends, 503, 1);
+            assert!(client.send_payload(&NotificationPayload::new("e1", "t")).await.queued);
+            assert_eq!(client.queue_size(), 1);
+            assert!(!file.exists());
+
+            chaos.fail_persistence(false).force_status(ChaosScope::Sends, 503, 1);
+            assert!(client.send_payload(&NotificationPayload::new("e2", "t")).await.queued);
+        });
+        let written = std::fs::read_to_string(&file).unwrap();
+        assert!(written.contains(r#""eventType":"e1""#), "{}", written);
+        assert!(written.contains(r#""eventType":"e2""#), "{}", written);
+    }
+
+    #[test]
+    fn notifications_sent_while_paused_are_delivered_in_order_on_resume() {
+        let _storage = test_support::TempStorage::new();
+        let server = healthy_server();
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            client.pause();
+            for i in 0..3 {
+                let payload = NotificationPayload::new(format!("e{}", i), "t");
+                let result = client.send_payload(&payload).await;
+                assert!(result.queued);
+                assert_eq!(result.queue_reason, Some(QueueReason::Paused));
+            }
+            assert!(server.requests_to("POST", "/api/v1/notify").is_empty());
+
+            client.resume().await;
+            assert_eq!(client.queue_size(), 0);
+            let result = client.send_payload(&NotificationPayload::new("e3", "t")).await;
+            assert!(result.success, "{:?}", result.error);
+        });
+
+        let sent: Vec<_> = server
+            .requests_to("POST", "/api/v1/notify")
+            .iter()
+            .map(|request| request.json()["eventType"].clone())
+            .collect();
+        assert_eq!(sent, [json!("e0"), json!("e1"), json!("e2"), json!("e3")]);
+    }
+
+    #[test]
+    fn notification_history_is_sorted_oldest_first() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(
+                200,
+                json!([
+                    { "type": "read", "timestamp": "2026-10-14T12:05:00Z" },
+                    { "type": "created", "timestamp": "2026-10-14T12:00:00Z" },
+                    { "type": "action_triggered", "action": "view", "actor": "u1",
+                      "timestamp": "2026-10-14T12:05:00Z" },
+                    { "type": "delivered", "timestamp": "2026-10-14T12:01:00Z" },
+                    { "kind": "escalated", "timestamp": "2026-10-14T11:59:59Z" },
+                ]),
+            )
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        let history = runtime.block_on(client.get_notification_history("n1")).unwrap();
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            history,
+            [
+                NotificationEvent::Other {
+                    kind: "escalated".to_string(),
+                    timestamp: at("2026-10-14T11:59:59Z"),
+                },
+                NotificationEvent::Created {
+                    timestamp: at("2026-10-14T12:00:00Z"),
+                },
+                NotificationEvent::Delivered {
+                    timestamp: at("2026-10-14T12:01:00Z"),
+                },
+                // Events at the same time keep the server's order.
+                NotificationEvent::Read {
+                    timestamp: at("2026-10-14T12:05:00Z"),
+                },
+                NotificationEvent::ActionTriggered {
+                    action: "view".to_string(),
+                    actor: Some("u1".to_string()),
+                    timestamp: at("2026-10-14T12:05:00Z"),
+                },
+            ]
+        );
+        assert_eq!(server.requests_to("GET", "/api/v1/notifications/n1/history").len(), 1);
+    }
+
+    #[test]
+    fn snoozing_drops_the_cached_unread_count() {
+        let unread = Arc::new(AtomicUsize::new(2));
+        let server = MockServer::start({
+            let unread = Arc::clone(&unread);
+            move |request| {
+                if request.path.ends_with("/snooze") {
+                    unread.fetch_sub(1, Ordering::SeqCst);
+                    return MockResponse::json(200, json!({}));
+                }
+                let count = unread.load(Ordering::SeqCst);
+                if request.path.contains("group_by") {
+                    MockResponse::json(200, json!({ "counts": { "info": count } }))
+                } else {
+                    MockResponse::json(200, json!({ "count": count }))
+                }
+            }
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let before = client.inbox_summary(None, 0).await.unwrap();
+            assert_eq!(before.unread_count, Some(2));
+
+            client
+                .snooze("n1", Utc::now() + chrono::Duration::hours(1))
+                .await
+                .unwrap();
+            let after = client.inbox_summary(None, 0).await.unwrap();
+            assert_eq!(after.unread_count, Some(1));
+        });
+    }
+
+    #[test]
+    fn flushes_drop_entries_that_run_out_of_attempts() {
+        let _storage = test_support::TempStorage::new();
+        let server = healthy_server();
+        let mut options = test_support::options(&server);
+        options.max_queue_attempts = Some(2);
+        options.chaos = Some(Chaos::with_seed(7));
+        let client = NotifyClient::new(options).unwrap();
+        client.chaos().unwrap().force_status(ChaosScope::Sends, 503, usize::MAX);
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            client.send_payload(&NotificationPayload::new("e", "t")).await;
+            assert_eq!(client.queue_size(), 1);
+            client.flush().await;
+            assert_eq!(client.queue_size(), 1);
+            client.flush().await;
+        });
+        assert_eq!(client.queue_size(), 0);
+        let drops = client.recent_drops(10);
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].reason, DropReason::RetriesExhausted);
+    }
+
+    // async-std's executor is always running, so there is no thread without a runtime.
+    #[cfg(feature = "rt-tokio")]
+    #[test]
+    fn best_effort_emits_outside_a_runtime_write_the_queue_file() {
+        let storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(200, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let budget = client.inner.options.best_effort_budget;
+
+        let emitted = std::thread::spawn(move || {
+            let started = Instant::now();
+            let handed_off = client.emit_best_effort(NotificationPayload::new("shutdown", "t"));
+            (handed_off, started.elapsed())
+        });
+        let (handed_off, elapsed) = emitted.join().unwrap();
+        assert!(handed_off);
+        assert!(elapsed <= budget, "{:?}", elapsed);
+
+        let file = std::fs::read_to_string(storage.path().join("offline_queue.test.json"));
+        assert!(file.unwrap().contains(r#""eventType":"shutdown""#));
+        assert!(server.requests().is_empty());
+    }
+
+    #[cfg(feature = "rt-tokio")]
+    #[test]
+    fn nested_best_effort_emits_are_ignored() {
+        let _storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(200, json!({})));
+        let mut options = test_support::options(&server);
+        options.max_offline_queue_size = 1;
+        let client = NotifyClient::new(options).unwrap();
+        let nested = Arc::new(Mutex::new(Vec::new()));
+        client.on_notification_dropped({
+            let (client, nested) = (client.clone(), Arc::clone(&nested));
+            move |_| {
+                let payload = NotificationPayload::new("from-callback", "t");
+                nested.lock().push(client.emit_best_effort(payload));
+            }
+        });
+
+        std::thread::spawn(move || {
+            assert!(client.emit_best_effort(NotificationPayload::new("first", "t")));
+            // Evicts the first, so the drop callback emits from inside this emit.
+            assert!(client.emit_best_effort(NotificationPayload::new("second", "t")));
+            let queued = client.queued_notifications();
+            assert_eq!(queued.len(), 1);
+            assert_eq!(queued[0].payload.event_type, "second");
+        })
+        .join()
+        .unwrap();
+        assert_eq!(*nested.lock(), [false]);
+    }
+
+    #[test]
+    fn a_locked_queue_file_does_not_block_the_runtime() {
+        let storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(503, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        // Another process holds the queue file's lock.
+        let lock = JsonFile::at(storage.path().join("offline_queue.test.json")).lock();
+
+        let runtime = test_support::current_thread_runtime();
+        let started = Instant::now();
+        let payload = NotificationPayload::new("e", "t");
+        let sent = runtime.block_on(async {
+            tokio::time::timeout(Duration::from_millis(200), client.send_payload(&payload)).await
+        });
+        assert!(sent.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        drop(lock);
+        test_support::wait_until("the payload to be queued", || client.queue_size() == 1);
+    }
+
+    #[test]
+    fn concurrent_rejected_sends_are_not_queued_and_report_one_failure() {
+        let _storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(401, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let failures = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&failures);
+        client.on_auth_failure(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let sends: Vec<_> = (0..20)
+                .map(|i| {
+                    let client = client.clone();
+                    tokio::spawn(async move {
+                        client.send_payload(&NotificationPayload::new(format!("e{}", i), "t")).await
+                    })
+                })
+                .collect();
+            for send in sends {
+                assert!(!send.await.unwrap().success);
+            }
+
+            let attempted = server.requests_to("POST", "/api/v1/notify").len();
+            client.flush().await;
+            assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), attempted);
+        });
+
+        assert_eq!(client.queue_size(), 0);
+        assert_eq!(failures.load(Ordering::SeqCst), 1);
+        assert!(matches!(client.auth_status(), AuthStatus::Failed(_)));
+    }
+
+    /// Cancels a token after a delay.
+    fn cancel_after(delay: Duration) -> CancellationToken {
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            cancelled.cancel();
+        });
+        token
+    }
+
+    #[test]
+    fn a_send_cancelled_in_flight_is_not_queued() {
+        let _storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| {
+            MockResponse::json(503, json!({})).delayed(Duration::from_millis(300))
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let payload = NotificationPayload::new("e", "t");
+            let token = cancel_after(Duration::from_millis(50));
+            let sent = client.send_payload_with_cancel(&payload, token).await;
+            assert!(matches!(sent, Err(Error::Cancelled)));
+            // Let the server answer the abandoned request.
+            tokio::time::sleep(Duration::from_millis(400)).await;
+        });
+
+        assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+        assert_eq!(client.queue_size(), 0);
+    }
+
+    #[test]
+    fn a_send_finishing_before_its_token_fires_is_kept() {
+        let _storage = test_support::TempStorage::new();
+        let server = MockServer::start(|_| {
+            MockResponse::json(503, json!({})).delayed(Duration::from_millis(20))
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let payload = NotificationPayload::new("e", "t");
+            let token = cancel_after(Duration::from_millis(500));
+            let sent = client.send_payload_with_cancel(&payload, token).await.unwrap();
+            assert!(sent.queued);
+        });
+        assert_eq!(client.queue_size(), 1);
+    }
+
+    #[test]
+    fn a_cancelled_flush_keeps_the_unsent_entries() {
+        let _storage = test_support::TempStorage::new();
+        let server = healthy_server();
+        let mut options = test_support::options(&server);
+        options.chaos = Some(Chaos::with_seed(7));
+        let client = NotifyClient::new(options).unwrap();
+        let chaos = client.chaos().unwrap();
+        chaos.fail_requests(ChaosScope::All, 1.0);
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            for i in 0..3 {
+                let payload = NotificationPayload::new(format!("e{}", i), "t");
+                assert!(client.send_payload(&payload).await.queued);
+            }
+            chaos.reset();
+            let latency = Duration::from_millis(200);
+            chaos.inject_latency(ChaosScope::Sends, latency, latency);
+
+            // Cancelled while the first entry is being sent.
+            let flushed = client.flush_with_cancel(cancel_after(Duration::from_millis(100))).await;
+            assert!(matches!(flushed, Err(Error::Cancelled)));
+            assert_eq!(client.queue_size(), 3);
+
+            // Cancelled while the second entry is being sent.
+            let flushed = client.flush_with_cancel(cancel_after(Duration::from_millis(300))).await;
+            assert!(matches!(flushed, Err(Error::Cancelled)));
+            assert_eq!(client.queue_size(), 2);
+
+            client.flush_with_cancel(CancellationToken::new()).await.unwrap();
+        });
+        assert_eq!(client.queue_size(), 0);
+    }
+
+    #[test]
+    fn a_cancelled_query_fails_with_cancelled() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, json!({ "notifications": [] }))
+                .delayed(Duration::from_millis(300))
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let token = cancel_after(Duration::from_millis(50));
+            let queried = client
+                .query_notifications_with_cancel(&NotificationQuery::new(), token)
+                .await;
+            assert!(matches!(queried, Err(Error::Cancelled)));
+        });
+    }
+
+    #[test]
+    fn callbacks_of_identical_queued_sends_follow_their_own_entry() {
+        let _storage = test_support::TempStorage::new();
+        let up = Arc::new(AtomicBool::new(false));
+        let serving = Arc::clone(&up);
+        let server = MockServer::start(move |_| match serving.load(Ordering::SeqCst) {
+            true => MockResponse::json(200, json!({ "notificationId": "n1" })),
+            false => MockResponse::json(503, json!({})),
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let results = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let on_result = |label: &'static str| {
+            let results = Arc::clone(&results);
+            move |result: &SendResult| results.lock().push((label, result.clone()))
+        };
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let kept = client.event("order.created").with_title("Order");
+            assert!(kept.on_result(on_result("kept")).send().await.queued);
+            let expiring = client
+                .event("order.created")
+                .with_title("Order")
+                .expires_in(Duration::from_millis(50));
+            assert!(expiring.on_result(on_result("expiring")).send().await.queued);
+            assert!(results.lock().is_empty());
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            up.store(true, Ordering::SeqCst);
+            client.flush().await;
+        });
+
+        let results = results.lock();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "expiring");
+        assert_eq!(results[0].1.error.as_deref(), Some("Dropped: expired"));
+        assert_eq!(results[1].0, "kept");
+        assert!(results[1].1.success);
+        assert_eq!(results[1].1.notification_id.as_deref(), Some("n1"));
+    }
+
+    #[test]
+    fn an_idle_client_pings_the_api_to_keep_its_connection_warm() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let server =
+                MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n" })));
+            let mut options = test_support::options(&server);
+            options.enable_offline_queue = false;
+            options.keep_connection_warm = Some(Duration::from_millis(50));
+            let client = NotifyClient::new(options).unwrap();
+
+            assert!(client.send_payload(&NotificationPayload::new("e", "t")).await.success);
+            test_support::wait_until("keep-warm pings", || {
+                server.requests_to("HEAD", "/health").len() >= 2
+            });
+            assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+        });
+    }
+
+    #[test]
+    fn keep_warm_pings_stop_when_the_client_is_dropped() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let server =
+                MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n" })));
+            let mut options = test_support::options(&server);
+            options.enable_offline_queue = false;
+            options.keep_connection_warm = Some(Duration::from_millis(20));
+            let client = NotifyClient::new(options).unwrap();
+
+            client.send_payload(&NotificationPayload::new("e", "t")).await;
+            test_support::wait_until("a keep-warm ping", || {
+                !server.requests_to("HEAD", "/health").is_empty()
+            });
+            drop(client);
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            let pings = server.requests_to("HEAD", "/health").len();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert_eq!(server.requests_to("HEAD", "/health").len(), pings);
+        });
+    }
+
+    #[test]
+    fn sends_within_the_pool_idle_timeout_count_as_warm() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let server =
+                MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n" })));
+            let mut options = test_support::options(&server);
+            options.enable_offline_queue = false;
+            options.pool_idle_timeout = Some(Duration::from_millis(100));
+            let client = NotifyClient::new(options).unwrap();
+
+            for _ in 0..2 {
+                client.send_payload(&NotificationPayload::new("e", "t")).await;
+            }
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            client.send_payload(&NotificationPayload::new("e", "t")).await;
+
+            let metrics = client.metrics();
+            assert_eq!(metrics.warm_sends, 1);
+            assert_eq!(metrics.cold_sends, 2);
+        });
+    }
+
+    #[test]
+    fn slow_responses_time_out_in_the_response_phase() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let server = MockServer::start(|_| {
+                MockResponse::json(200, json!({})).delayed(Duration::from_millis(300))
+            });
+            let mut options = test_support::options(&server);
+            options.enable_offline_queue = false;
+            options.request_timeout = Duration::from_millis(100);
+            let client = NotifyClient::new(options).unwrap();
+
+            let error = client.list_event_types(None, None).await.unwrap_err();
+            assert!(matches!(error, Error::Timeout(TimeoutPhase::Response)));
+
+            let result = client.send_payload(&NotificationPayload::new("e", "t")).await;
+            assert!(!result.success);
+            assert_eq!(
+                result.error.as_deref(),
+                Some("Timed out waiting for the server to respond")
+            );
+        });
     }
 }
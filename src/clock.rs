@@ -0,0 +1,76 @@
+//! Clock skew estimation for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::Mutex;
+use reqwest::header::{HeaderMap, DATE};
+
+/// Skew in milliseconds beyond which a warning is logged and, when enabled,
+/// timestamps are adjusted.
+const CLOCK_SKEW_THRESHOLD_MS: f64 = 30_000.0;
+
+/// Weight of each new sample in the smoothed estimate.
+const SMOOTHING: f64 = 0.2;
+
+/// Estimates how far the server clock is ahead of the local clock from `Date` headers.
+///
+/// `Date` has one-second resolution, so samples are smoothed with an exponential
+/// moving average rather than replacing the estimate on each response.
+pub(crate) struct ClockSkew {
+    debug: DebugSwitch,
+    estimate_ms: Mutex<Option<f64>>,
+}
+
+impl ClockSkew {
+    /// Creates an estimator with no samples.
+    pub fn new(debug: DebugSwitch) -> Self {
+        Self {
+            debug,
+            estimate_ms: Mutex::new(None),
+        }
+    }
+
+    /// Adds a sample from a response's `Date` header, if present.
+    pub fn observe(&self, headers: &HeaderMap) {
+        let Some(server_time) = headers
+            .get(DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        else {
+            return;
+        };
+
+        let sample = (server_time.with_timezone(&Utc) - Utc::now()).num_milliseconds() as f64;
+        let mut estimate_ms = self.estimate_ms.lock();
+        let previous = *estimate_ms;
+        let next = match previous {
+            Some(previous) => previous + SMOOTHING * (sample - previous),
+            None => sample,
+        };
+        *estimate_ms = Some(next);
+        drop(estimate_ms);
+
+        let was_skewed = previous.is_some_and(|previous| previous.abs() > CLOCK_SKEW_THRESHOLD_MS);
+        if self.debug.is_enabled() && !was_skewed && next.abs() > CLOCK_SKEW_THRESHOLD_MS {
+            println!(
+                "[IronNotify] Warning: local clock differs from the server by about {}s",
+                (next / 1000.0).round()
+            );
+        }
+    }
+
+    /// Gets the smoothed offset of the server clock from the local clock.
+    ///
+    /// Positive when the server is ahead.
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate_ms
+            .lock()
+            .map(|ms| Duration::milliseconds(ms.round() as i64))
+    }
+
+    /// Gets the offset to add to local timestamps, if the skew exceeds the threshold.
+    pub fn correction(&self) -> Option<Duration> {
+        self.estimate()
+            .filter(|skew| skew.num_milliseconds().abs() as f64 > CLOCK_SKEW_THRESHOLD_MS)
+    }
+}
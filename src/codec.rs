@@ -0,0 +1,77 @@
+//! Wire encodings for IronNotify SDK.
+
+use crate::error::Error;
+use serde_json::Value;
+
+/// Content type of the JSON encoding.
+pub(crate) const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Wire format used for request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+    /// JSON (the default).
+    #[default]
+    Json,
+    /// MessagePack, for gateways that support it. Falls back to JSON on `415`.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl WireFormat {
+    /// Creates the codec for this format.
+    pub(crate) fn codec(self) -> Box<dyn WireCodec> {
+        match self {
+            Self::Json => Box::new(JsonCodec),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => Box::new(MsgPackCodec),
+        }
+    }
+}
+
+/// Encodes request bodies and decodes response bodies.
+pub(crate) trait WireCodec: Send + Sync {
+    /// Gets the content type used for `Content-Type` and `Accept` headers.
+    fn content_type(&self) -> &'static str;
+
+    /// Encodes a value into bytes.
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, Error>;
+
+    /// Decodes bytes into a value.
+    fn decode(&self, bytes: &[u8]) -> Result<Value, Error>;
+}
+
+/// JSON codec.
+pub(crate) struct JsonCodec;
+
+impl WireCodec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        JSON_CONTENT_TYPE
+    }
+
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, Error> {
+        serde_json::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
+
+/// MessagePack codec.
+#[cfg(feature = "msgpack")]
+pub(crate) struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl WireCodec for MsgPackCodec {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        rmp_serde::to_vec_named(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Value, Error> {
+        rmp_serde::from_slice(bytes).map_err(|e| Error::Codec(e.to_string()))
+    }
+}
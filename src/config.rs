@@ -1,31 +1,221 @@
 //! Configuration options for IronNotify SDK.
 
+use crate::auth::TokenProvider;
+#[cfg(any(test, feature = "chaos"))]
+use crate::chaos::Chaos;
+use crate::api_version::ApiVersion;
+use crate::codec::WireFormat;
+use crate::debug::{DebugConfig, DebugLevel};
+use crate::limits::SoftLimits;
+use crate::overflow::OverflowStrategy;
+#[cfg(feature = "tls-pinning")]
+use crate::pinning::PinSet;
+#[cfg(feature = "postgres")]
+use crate::postgres::PostgresQueueStorage;
+use crate::runtime::Runtime;
+use crate::types::SeverityLevel;
+#[cfg(feature = "quiet-hours")]
+use crate::quiet_hours::QuietHours;
+#[cfg(any(feature = "journal", feature = "record"))]
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Configuration options for the IronNotify client.
 #[derive(Debug, Clone)]
 pub struct NotifyOptions {
-    /// API key for authentication (required).
+    /// API key for authentication (required unless `token_provider` is set).
     /// Format: ak_live_xxx or ak_test_xxx
     pub api_key: String,
-    /// Base URL for the IronNotify API.
+    /// Source of short-lived bearer tokens, used instead of `api_key`.
+    pub token_provider: Option<Arc<dyn TokenProvider>>,
+    /// Header carrying the tenant ID of `with_tenant` clients, which then send the
+    /// client's own credentials. Unset, the tenant is an API key sent in its place.
+    pub tenant_header: Option<String>,
+    /// Base URL for the IronNotify API. May include a path prefix for proxied deployments.
     pub api_base_url: String,
+    /// Path prefix for API endpoints, joined onto the base URL.
+    pub api_path_prefix: String,
     /// WebSocket URL for real-time notifications.
     pub ws_url: String,
-    /// Enable debug logging.
+    /// Enable summary debug logging for every component, on top of `debug_config`.
     pub debug: bool,
+    /// Debug log levels for each component.
+    pub debug_config: DebugConfig,
     /// Enable offline notification queuing.
     pub enable_offline_queue: bool,
     /// Maximum number of notifications to queue offline.
     pub max_offline_queue_size: usize,
+    /// Maximum total serialized size of the offline queue in bytes. Unlimited when `None`.
+    pub max_offline_queue_bytes: Option<usize>,
+    /// Failed flush attempts after which a queued notification is dropped. Kept until
+    /// sent when `None`.
+    pub max_queue_attempts: Option<u32>,
+    /// Queue file size in bytes above which the queue is compacted when the client is
+    /// created. Never compacted automatically when `None`.
+    pub offline_queue_compaction_bytes: Option<u64>,
+    /// Severities queued notifications are lowered to once they are at least this old
+    /// when flushed.
+    pub queue_severity_decay: Vec<(Duration, SeverityLevel)>,
+    /// Event type patterns, with `*` and `?` wildcards, whose queued notifications keep
+    /// their severity.
+    pub severity_decay_exempt: Vec<String>,
+    /// Postgres table queuing notifications instead of the local offline queue, shared
+    /// by every replica. The local queue is only used while it is unreachable.
+    #[cfg(feature = "postgres")]
+    pub postgres_queue: Option<Arc<PostgresQueueStorage>>,
     /// Enable automatic WebSocket reconnection.
     pub auto_reconnect: bool,
     /// Maximum number of reconnection attempts.
     pub max_reconnect_attempts: u32,
     /// Base delay between reconnection attempts.
     pub reconnect_delay: Duration,
-    /// HTTP request timeout.
-    pub http_timeout: Duration,
+    /// How often the real-time connection pings the server.
+    pub heartbeat_interval: Duration,
+    /// How long to wait for the pong to a heartbeat ping before closing the
+    /// connection and reconnecting.
+    pub heartbeat_timeout: Duration,
+    /// How long to wait for a connection to the API to be established.
+    pub connect_timeout: Duration,
+    /// Total time allowed for an API request, from connecting to reading the response.
+    pub request_timeout: Duration,
+    /// How long idle pooled connections are kept open. `None` uses the HTTP client's default.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum number of idle pooled connections per host. `None` uses the HTTP client's
+    /// default.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// TCP keepalive interval for API connections. `None` leaves keepalive off.
+    pub tcp_keepalive: Option<Duration>,
+    /// Speak HTTP/2 without negotiating it first, for gateways that support it.
+    pub http2_prior_knowledge: bool,
+    /// Sends `HEAD /health` whenever the client has made no request for this long, so
+    /// pooled connections stay open between sparse sends.
+    pub keep_connection_warm: Option<Duration>,
+    /// Public key pins the API server's certificate must match, on top of normal
+    /// certificate validation.
+    #[cfg(feature = "tls-pinning")]
+    pub certificate_pins: Option<PinSet>,
+    /// Maximum number of concurrent sends when fanning out to multiple users.
+    pub fan_out_concurrency: usize,
+    /// Metadata key patterns (e.g. `*_token`, `email`) redacted from logged payloads.
+    pub redact_metadata_keys: Vec<String>,
+    /// Also redact metadata in the persisted offline queue file.
+    /// Queued payloads restored from disk are then sent with redacted values.
+    pub redact_at_rest: bool,
+    /// Publish successfully sent payloads to the in-process local event bus.
+    pub local_echo: bool,
+    /// Capacity of the local event bus before slow receivers start lagging.
+    pub local_echo_capacity: usize,
+    /// Runtime for background work. Defaults to the one selected by cargo features.
+    pub runtime: Option<Arc<dyn Runtime>>,
+    /// Failure injection for resilience testing. Disabled when `None`.
+    #[cfg(any(test, feature = "chaos"))]
+    pub chaos: Option<Chaos>,
+    /// Wire format for request and response bodies.
+    pub wire_format: WireFormat,
+    /// API version sent in the `X-API-Version` header of every request.
+    pub api_version: ApiVersion,
+    /// Maximum number of response body bytes kept in errors.
+    pub error_body_max_bytes: usize,
+    /// When flushing, send the oldest lower-severity notification after this many
+    /// Error or Critical ones (0 sends all of them first).
+    pub flush_fairness: usize,
+    /// Maximum size of a downloaded attachment.
+    pub max_download_bytes: u64,
+    /// Number of dropped notifications kept for `recent_drops`.
+    pub recent_drops_capacity: usize,
+    /// Serialized payload size above which `metadata_overflow` applies.
+    pub max_payload_bytes: usize,
+    /// How to handle payloads larger than `max_payload_bytes`.
+    pub metadata_overflow: OverflowStrategy,
+    /// Maximum total size of the files attached to one notification.
+    pub max_attachment_bytes: usize,
+    /// Derive a missing title from the event type with `text::humanize_event_type`,
+    /// as `EventBuilder::auto_title` does, instead of failing the build.
+    pub derive_title_from_event_type: bool,
+    /// Maximum number of metadata entries. Unlimited when `None`.
+    pub max_metadata_keys: Option<usize>,
+    /// Maximum number of actions. Unlimited when `None`.
+    pub max_actions: Option<usize>,
+    /// Fraction of each limit at which a limit warning is raised.
+    pub soft_limit_ratio: f64,
+    /// Thresholds overriding the ones derived from `soft_limit_ratio`.
+    pub soft_limits: SoftLimits,
+    /// Minimum time between limit warnings for the same event type and field.
+    pub limit_warning_interval: Duration,
+    /// Timeout for uploading externalized metadata.
+    pub attachment_timeout: Duration,
+    /// Daily window during which non-critical notifications are deferred.
+    #[cfg(feature = "quiet-hours")]
+    pub quiet_hours: Option<QuietHours>,
+    /// Maximum concurrent sends and other writes. Unlimited when `None`.
+    pub max_concurrent_sends: Option<usize>,
+    /// Maximum concurrent reads. Unlimited when `None`.
+    pub max_concurrent_reads: Option<usize>,
+    /// How long a request waits for a concurrency slot before failing with
+    /// `Error::Saturated`. Waits indefinitely when `None`.
+    pub concurrency_timeout: Option<Duration>,
+    /// Longest `emit_best_effort` waits, for the background send or for the offline
+    /// queue's lock.
+    pub best_effort_budget: Duration,
+    /// Shift outgoing `expires_at` timestamps by the estimated server clock offset when
+    /// it exceeds 30 seconds.
+    pub compensate_clock_skew: bool,
+    /// Remaining requests below which Info and Warning sends are queued until the rate
+    /// limit window resets. Disabled when `None`.
+    pub rate_limit_threshold: Option<u64>,
+    /// Use conditional requests (ETag/Last-Modified) for list and count calls.
+    pub http_cache: bool,
+    /// How long list responses are served by `get_notifications_cached` while offline.
+    /// Disabled when `None`.
+    pub read_cache_ttl: Option<Duration>,
+    /// Persist the read cache to disk so it survives restarts.
+    pub read_cache_persist: bool,
+    /// How long `inbox_summary` reuses a complete summary. Zero fetches every time.
+    pub inbox_summary_ttl: Duration,
+    /// Queue mark read, mark unread, archive, and acknowledge calls that fail on a
+    /// network error and replay them on flush.
+    pub write_behind_mutations: bool,
+    /// Consecutive send failures before the circuit breaker opens (0 disables it). Only
+    /// requests that got no response, a 5xx status, or 429 count as failures.
+    pub circuit_breaker_threshold: u32,
+    /// How long the circuit breaker stays open before allowing a probe request.
+    pub circuit_breaker_cooldown: Duration,
+    /// Path of the send journal file. Disabled when `None`.
+    #[cfg(feature = "journal")]
+    pub journal_path: Option<PathBuf>,
+    /// Size at which the send journal file is rotated.
+    #[cfg(feature = "journal")]
+    pub journal_max_bytes: u64,
+    /// Maximum number of rotated send journal files to keep.
+    #[cfg(feature = "journal")]
+    pub journal_max_files: usize,
+    /// Path of the NDJSON file every request and response is recorded to. Disabled
+    /// when `None`.
+    #[cfg(feature = "record")]
+    pub record_traffic: Option<PathBuf>,
+    /// Size at which the traffic recording is rotated.
+    #[cfg(feature = "record")]
+    pub record_max_bytes: u64,
+    /// Maximum number of rotated traffic recordings to keep.
+    #[cfg(feature = "record")]
+    pub record_max_files: usize,
+    /// How long user resolver results are cached.
+    pub user_resolver_ttl: Duration,
+    /// Requests taking at least this long are reported as slow.
+    pub slow_request_threshold: Duration,
+    /// How often notifications sent with `track_read` are checked for being read.
+    pub read_poll_interval: Duration,
+    /// How long notifications sent with `track_read` are checked before giving up.
+    pub read_tracking_timeout: Duration,
+    /// Verify credentials when initializing the global client with `init_async`.
+    pub verify_on_init: bool,
+    /// Reject event types that have no registered metadata schema.
+    /// Only takes effect with the `schema` feature.
+    pub strict_metadata: bool,
+    /// Search by fetching notifications and matching them locally when the server has
+    /// no search endpoint.
+    pub allow_client_side_search: bool,
 }
 
 impl NotifyOptions {
@@ -41,21 +231,379 @@ impl NotifyOptions {
     pub fn builder() -> NotifyOptionsBuilder {
         NotifyOptionsBuilder::default()
     }
+
+    /// Creates options from environment variables.
+    ///
+    /// `IRONNOTIFY_API_KEY` is required. `IRONNOTIFY_API_URL`, `IRONNOTIFY_API_PATH_PREFIX`,
+    /// `IRONNOTIFY_WS_URL`, `IRONNOTIFY_TENANT_HEADER`, `IRONNOTIFY_DEBUG`, and
+    /// `IRONNOTIFY_OFFLINE_QUEUE` override the defaults when set.
+    pub fn from_env() -> Result<Self, OptionsError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let flag = |name: &str| {
+            var(name).map(|value| {
+                matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes")
+            })
+        };
+
+        let mut builder = Self::builder()
+            .api_key(var("IRONNOTIFY_API_KEY").ok_or(
+                OptionsIssue::MissingEnvironmentVariable("IRONNOTIFY_API_KEY"),
+            )?);
+        if let Some(url) = var("IRONNOTIFY_API_URL") {
+            builder = builder.api_base_url(url);
+        }
+        if let Some(prefix) = var("IRONNOTIFY_API_PATH_PREFIX") {
+            builder = builder.api_path_prefix(prefix);
+        }
+        if let Some(url) = var("IRONNOTIFY_WS_URL") {
+            builder = builder.ws_url(url);
+        }
+        if let Some(name) = var("IRONNOTIFY_TENANT_HEADER") {
+            builder = builder.tenant_header(name);
+        }
+        if let Some(debug) = flag("IRONNOTIFY_DEBUG") {
+            builder = builder.debug(debug);
+        }
+        if let Some(enable) = flag("IRONNOTIFY_OFFLINE_QUEUE") {
+            builder = builder.enable_offline_queue(enable);
+        }
+        builder.build()
+    }
+
+    /// Gets the debug log levels in effect: `debug_config`, with every component at
+    /// least at `DebugLevel::Summary` when `debug` is set.
+    pub fn debug_levels(&self) -> DebugConfig {
+        if self.debug {
+            self.debug_config.at_least(DebugLevel::Summary)
+        } else {
+            self.debug_config
+        }
+    }
+
+    /// Clamps `reconnect_delay` to at least `MIN_RECONNECT_DELAY` and
+    /// `max_offline_queue_size` to at most `MAX_OFFLINE_QUEUE_SIZE`.
+    ///
+    /// Called by `NotifyOptionsBuilder::build` and `NotifyClient::new`.
+    pub fn normalize(&mut self) {
+        let debug = self.debug_levels().is_enabled();
+        if self.reconnect_delay < MIN_RECONNECT_DELAY {
+            if debug {
+                println!(
+                    "[IronNotify] Raising reconnect delay from {:?} to {:?}",
+                    self.reconnect_delay, MIN_RECONNECT_DELAY
+                );
+            }
+            self.reconnect_delay = MIN_RECONNECT_DELAY;
+        }
+        if self.max_offline_queue_size > MAX_OFFLINE_QUEUE_SIZE {
+            if debug {
+                println!(
+                    "[IronNotify] Capping offline queue size at {}",
+                    MAX_OFFLINE_QUEUE_SIZE
+                );
+            }
+            self.max_offline_queue_size = MAX_OFFLINE_QUEUE_SIZE;
+        }
+    }
+
+    /// Checks the options, reporting every issue that would make `NotifyClient::new`
+    /// fail.
+    ///
+    /// ```
+    /// use ironnotify::{NotifyOptions, OptionsIssue};
+    /// use std::time::Duration;
+    ///
+    /// let cases: [(fn(&mut NotifyOptions), OptionsIssue); 5] = [
+    ///     (|o| o.api_key.clear(), OptionsIssue::MissingCredentials),
+    ///     (|o| o.max_reconnect_attempts = 0, OptionsIssue::NoReconnectAttempts),
+    ///     (|o| o.max_offline_queue_size = 0, OptionsIssue::EmptyOfflineQueue),
+    ///     (|o| o.request_timeout = Duration::ZERO, OptionsIssue::ZeroTimeout("Request")),
+    ///     (|o| o.soft_limit_ratio = 1.5, OptionsIssue::InvalidSoftLimitRatio),
+    /// ];
+    /// for (change, issue) in cases {
+    ///     let mut options = NotifyOptions::new("ak_test_xxx");
+    ///     change(&mut options);
+    ///     assert_eq!(options.validate().unwrap_err().issues(), [issue]);
+    /// }
+    ///
+    /// // Turning reconnection off makes zero attempts valid.
+    /// let mut options = NotifyOptions::new("ak_test_xxx");
+    /// options.auto_reconnect = false;
+    /// options.max_reconnect_attempts = 0;
+    /// assert!(options.validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        let mut issues = Vec::new();
+
+        match (self.api_key.is_empty(), self.token_provider.is_some()) {
+            (true, false) => issues.push(OptionsIssue::MissingCredentials),
+            (false, true) => issues.push(OptionsIssue::ConflictingCredentials),
+            _ => {}
+        }
+        if reqwest::header::HeaderValue::from_str(&self.api_key).is_err() {
+            issues.push(OptionsIssue::InvalidApiKey);
+        }
+
+        if self.connect_timeout.is_zero() {
+            issues.push(OptionsIssue::ZeroTimeout("Connect"));
+        }
+        if self.request_timeout.is_zero() {
+            issues.push(OptionsIssue::ZeroTimeout("Request"));
+        }
+        if self.pool_idle_timeout.is_some_and(|timeout| timeout.is_zero()) {
+            issues.push(OptionsIssue::ZeroTimeout("Pool idle"));
+        }
+        if self.tcp_keepalive.is_some_and(|interval| interval.is_zero()) {
+            issues.push(OptionsIssue::ZeroTimeout("TCP keepalive"));
+        }
+        if self.keep_connection_warm.is_some_and(|interval| interval.is_zero()) {
+            issues.push(OptionsIssue::ZeroTimeout("Keep connection warm"));
+        }
+        #[cfg(feature = "tls-pinning")]
+        if let Some(ref pins) = self.certificate_pins {
+            if pins.invalid_pin().is_some() {
+                issues.push(OptionsIssue::InvalidCertificatePin);
+            }
+            let secure = |url: &str, scheme: &str| {
+                reqwest::Url::parse(url)
+                    .is_ok_and(|url| url.scheme() == scheme && url.host_str().is_some())
+            };
+            if !secure(&self.api_base_url, "https") || !secure(&self.ws_url, "wss") {
+                issues.push(OptionsIssue::PinningWithoutHttps);
+            }
+        }
+        if reqwest::header::HeaderValue::from_str(self.api_version.as_str()).is_err()
+            || self.api_version.as_str().is_empty()
+        {
+            issues.push(OptionsIssue::InvalidApiVersion);
+        }
+        if self.tenant_header.as_deref().is_some_and(|name| {
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+        }) {
+            issues.push(OptionsIssue::InvalidTenantHeader);
+        }
+        if !(self.soft_limit_ratio > 0.0 && self.soft_limit_ratio <= 1.0) {
+            issues.push(OptionsIssue::InvalidSoftLimitRatio);
+        }
+        if self.auto_reconnect && self.max_reconnect_attempts == 0 {
+            issues.push(OptionsIssue::NoReconnectAttempts);
+        }
+        if self.enable_offline_queue
+            && (self.max_offline_queue_size == 0 || self.max_offline_queue_bytes == Some(0))
+        {
+            issues.push(OptionsIssue::EmptyOfflineQueue);
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(OptionsError { issues })
+        }
+    }
+}
+
+/// Shortest delay between reconnection attempts; shorter ones are raised to it.
+pub const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/// How long `inbox_summary` reuses a summary unless `inbox_summary_ttl` is set.
+pub const DEFAULT_INBOX_SUMMARY_TTL: Duration = Duration::from_secs(5);
+
+/// Largest offline queue size; larger ones are lowered to it.
+pub const MAX_OFFLINE_QUEUE_SIZE: usize = 100_000;
+
+/// A problem that makes client options unusable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionsIssue {
+    /// Neither an API key nor a token provider is set.
+    MissingCredentials,
+    /// Both an API key and a token provider are set.
+    ConflictingCredentials,
+    /// The API key contains characters that cannot be sent in a header.
+    InvalidApiKey,
+    /// An environment variable `from_env` requires is not set.
+    MissingEnvironmentVariable(&'static str),
+    /// A timeout is zero. Holds which one, e.g. `Connect`.
+    ZeroTimeout(&'static str),
+    /// The API version is empty or not a valid header value.
+    InvalidApiVersion,
+    /// `tenant_header` is not a valid header name.
+    InvalidTenantHeader,
+    /// `soft_limit_ratio` is not in (0, 1].
+    InvalidSoftLimitRatio,
+    /// `auto_reconnect` is set but `max_reconnect_attempts` is zero.
+    NoReconnectAttempts,
+    /// The offline queue is enabled but can hold nothing.
+    EmptyOfflineQueue,
+    /// A certificate pin is not a base64 SHA-256 digest.
+    InvalidCertificatePin,
+    /// Certificate pins are set but the API base URL is not an HTTPS URL, or the
+    /// WebSocket URL not a `wss://` URL.
+    PinningWithoutHttps,
+}
+
+impl OptionsIssue {
+    /// Gets a short description of the issue.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::MissingCredentials => "API key is required",
+            Self::ConflictingCredentials => "Set either an API key or a token provider, not both",
+            Self::InvalidApiKey => "API key must be a valid header value",
+            Self::MissingEnvironmentVariable(_) => "A required environment variable is not set",
+            Self::ZeroTimeout(_) => "Timeouts must be greater than zero",
+            Self::InvalidApiVersion => "API version must be a non-empty header value",
+            Self::InvalidTenantHeader => "Tenant header must be a valid header name",
+            Self::InvalidSoftLimitRatio => {
+                "Soft limit ratio must be greater than zero and at most one"
+            }
+            Self::NoReconnectAttempts => "Auto-reconnect needs at least one reconnect attempt",
+            Self::EmptyOfflineQueue => "An enabled offline queue must hold at least one entry",
+            Self::InvalidCertificatePin => "Certificate pins must be base64 SHA-256 digests",
+            Self::PinningWithoutHttps => {
+                "Certificate pinning requires HTTPS API and wss:// WebSocket URLs"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for OptionsIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingEnvironmentVariable(name) => write!(f, "{} is not set", name),
+            Self::ZeroTimeout(timeout) => {
+                write!(f, "{} timeout must be greater than zero", timeout)
+            }
+            other => write!(f, "{}", other.message()),
+        }
+    }
+}
+
+/// The issues found by `NotifyOptions::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionsError {
+    issues: Vec<OptionsIssue>,
+}
+
+impl OptionsError {
+    /// Gets every issue, in the order they were found.
+    pub fn issues(&self) -> &[OptionsIssue] {
+        &self.issues
+    }
+}
+
+impl From<OptionsIssue> for OptionsError {
+    fn from(issue: OptionsIssue) -> Self {
+        Self {
+            issues: vec![issue],
+        }
+    }
 }
 
+impl std::fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
 impl Default for NotifyOptions {
     fn default() -> Self {
         Self {
             api_key: String::new(),
+            token_provider: None,
+            tenant_header: None,
             api_base_url: "https://api.ironnotify.com".to_string(),
+            api_path_prefix: "/api/v1".to_string(),
             ws_url: "wss://ws.ironnotify.com".to_string(),
             debug: false,
+            debug_config: DebugConfig::default(),
             enable_offline_queue: true,
             max_offline_queue_size: 100,
+            max_offline_queue_bytes: None,
+            max_queue_attempts: None,
+            offline_queue_compaction_bytes: Some(10 * 1024 * 1024),
+            queue_severity_decay: Vec::new(),
+            severity_decay_exempt: Vec::new(),
+            #[cfg(feature = "postgres")]
+            postgres_queue: None,
             auto_reconnect: true,
             max_reconnect_attempts: 5,
             reconnect_delay: Duration::from_secs(1),
-            http_timeout: Duration::from_secs(30),
+            heartbeat_interval: Duration::from_secs(30),
+            heartbeat_timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            pool_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+            keep_connection_warm: None,
+            #[cfg(feature = "tls-pinning")]
+            certificate_pins: None,
+            fan_out_concurrency: 8,
+            redact_metadata_keys: Vec::new(),
+            redact_at_rest: false,
+            local_echo: false,
+            local_echo_capacity: 256,
+            runtime: None,
+            #[cfg(any(test, feature = "chaos"))]
+            chaos: None,
+            wire_format: WireFormat::Json,
+            api_version: ApiVersion::V1,
+            error_body_max_bytes: 2 * 1024,
+            flush_fairness: 4,
+            max_download_bytes: 50 * 1024 * 1024,
+            recent_drops_capacity: 100,
+            max_payload_bytes: 256 * 1024,
+            metadata_overflow: OverflowStrategy::Fail,
+            max_attachment_bytes: 1024 * 1024,
+            derive_title_from_event_type: false,
+            max_metadata_keys: None,
+            max_actions: None,
+            soft_limit_ratio: 0.8,
+            soft_limits: SoftLimits::default(),
+            limit_warning_interval: Duration::from_secs(60),
+            attachment_timeout: Duration::from_secs(60),
+            #[cfg(feature = "quiet-hours")]
+            quiet_hours: None,
+            max_concurrent_sends: None,
+            max_concurrent_reads: None,
+            concurrency_timeout: None,
+            best_effort_budget: Duration::from_millis(100),
+            compensate_clock_skew: false,
+            rate_limit_threshold: None,
+            http_cache: true,
+            read_cache_ttl: None,
+            inbox_summary_ttl: DEFAULT_INBOX_SUMMARY_TTL,
+            read_cache_persist: false,
+            write_behind_mutations: false,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_cooldown: Duration::from_secs(30),
+            #[cfg(feature = "journal")]
+            journal_path: None,
+            #[cfg(feature = "journal")]
+            journal_max_bytes: 10 * 1024 * 1024,
+            #[cfg(feature = "journal")]
+            journal_max_files: 5,
+            #[cfg(feature = "record")]
+            record_traffic: None,
+            #[cfg(feature = "record")]
+            record_max_bytes: 10 * 1024 * 1024,
+            #[cfg(feature = "record")]
+            record_max_files: 2,
+            user_resolver_ttl: Duration::from_secs(300),
+            slow_request_threshold: Duration::from_secs(5),
+            read_poll_interval: Duration::from_secs(30),
+            read_tracking_timeout: Duration::from_secs(60 * 60),
+            verify_on_init: false,
+            strict_metadata: false,
+            allow_client_side_search: false,
         }
     }
 }
@@ -73,36 +621,101 @@ impl NotifyOptionsBuilder {
         self
     }
 
+    /// Sets the token provider, for short-lived bearer tokens instead of an API key.
+    pub fn token_provider(mut self, provider: Arc<dyn TokenProvider>) -> Self {
+        self.options.token_provider = Some(provider);
+        self
+    }
+
+    /// Sets the header `with_tenant` clients send their tenant ID in, e.g. `X-Tenant-Id`.
+    pub fn tenant_header(mut self, name: impl Into<String>) -> Self {
+        self.options.tenant_header = Some(name.into());
+        self
+    }
+
     /// Sets the API base URL.
     pub fn api_base_url(mut self, url: impl Into<String>) -> Self {
         self.options.api_base_url = url.into();
         self
     }
 
+    /// Sets the API path prefix.
+    pub fn api_path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.api_path_prefix = prefix.into();
+        self
+    }
+
     /// Sets the WebSocket URL.
     pub fn ws_url(mut self, url: impl Into<String>) -> Self {
         self.options.ws_url = url.into();
         self
     }
 
-    /// Enables or disables debug mode.
+    /// Enables or disables debug mode, logging summaries for every component.
     pub fn debug(mut self, debug: bool) -> Self {
         self.options.debug = debug;
         self
     }
 
+    /// Sets debug log levels for each component. `debug(true)` still raises every
+    /// component to at least `DebugLevel::Summary`.
+    pub fn debug_config(mut self, config: DebugConfig) -> Self {
+        self.options.debug_config = config;
+        self
+    }
+
     /// Enables or disables the offline queue.
     pub fn enable_offline_queue(mut self, enable: bool) -> Self {
         self.options.enable_offline_queue = enable;
         self
     }
 
-    /// Sets the maximum offline queue size.
+    /// Sets the maximum offline queue size, at most `MAX_OFFLINE_QUEUE_SIZE`.
     pub fn max_offline_queue_size(mut self, size: usize) -> Self {
         self.options.max_offline_queue_size = size;
         self
     }
 
+    /// Sets the maximum total size of the offline queue in bytes.
+    pub fn max_offline_queue_bytes(mut self, bytes: usize) -> Self {
+        self.options.max_offline_queue_bytes = Some(bytes);
+        self
+    }
+
+    /// Sets how many failed flush attempts a queued notification survives.
+    pub fn max_queue_attempts(mut self, attempts: u32) -> Self {
+        self.options.max_queue_attempts = Some(attempts);
+        self
+    }
+
+    /// Sets the queue file size above which the queue is compacted on startup, or
+    /// `None` to only compact with `compact_queue`.
+    pub fn offline_queue_compaction_bytes(mut self, bytes: Option<u64>) -> Self {
+        self.options.offline_queue_compaction_bytes = bytes;
+        self
+    }
+
+    /// Lowers the severity of queued notifications that are sent late, e.g.
+    /// `vec![(Duration::from_secs(1800), SeverityLevel::Warning)]` sends a critical alert
+    /// as a warning once it has been queued for half an hour.
+    pub fn queue_severity_decay(mut self, steps: Vec<(Duration, SeverityLevel)>) -> Self {
+        self.options.queue_severity_decay = steps;
+        self
+    }
+
+    /// Sets event type patterns whose queued notifications keep their severity.
+    pub fn severity_decay_exempt(mut self, patterns: Vec<String>) -> Self {
+        self.options.severity_decay_exempt = patterns;
+        self
+    }
+
+    /// Sets the Postgres table to queue notifications in.
+    #[cfg(feature = "postgres")]
+    pub fn postgres_queue(mut self, storage: PostgresQueueStorage) -> Self {
+        self.options.postgres_queue = Some(Arc::new(storage));
+        self
+    }
+
     /// Enables or disables auto-reconnect.
     pub fn auto_reconnect(mut self, enable: bool) -> Self {
         self.options.auto_reconnect = enable;
@@ -115,23 +728,528 @@ impl NotifyOptionsBuilder {
         self
     }
 
-    /// Sets the reconnect delay.
+    /// Sets the reconnect delay, at least `MIN_RECONNECT_DELAY`.
     pub fn reconnect_delay(mut self, delay: Duration) -> Self {
         self.options.reconnect_delay = delay;
         self
     }
 
+    /// Sets how often the real-time connection pings the server.
+    pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.options.heartbeat_interval = interval;
+        self
+    }
+
+    /// Sets how long to wait for a heartbeat pong before reconnecting.
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.options.heartbeat_timeout = timeout;
+        self
+    }
+
+    /// Sets the connect timeout.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.options.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets the total request timeout.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.options.request_timeout = timeout;
+        self
+    }
+
     /// Sets the HTTP timeout.
-    pub fn http_timeout(mut self, timeout: Duration) -> Self {
-        self.options.http_timeout = timeout;
+    #[deprecated(note = "use `request_timeout`")]
+    pub fn http_timeout(self, timeout: Duration) -> Self {
+        self.request_timeout(timeout)
+    }
+
+    /// Sets how long idle pooled connections are kept open.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.options.pool_idle_timeout = Some(timeout);
         self
     }
 
-    /// Builds the NotifyOptions.
-    pub fn build(self) -> Result<NotifyOptions, &'static str> {
-        if self.options.api_key.is_empty() {
-            return Err("API key is required");
-        }
+    /// Sets the maximum number of idle pooled connections per host.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.options.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Sets the TCP keepalive interval for API connections.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.options.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Enables or disables HTTP/2 without negotiation. Only enable it when every
+    /// hop to the API speaks HTTP/2, or requests fail.
+    pub fn http2_prior_knowledge(mut self, enable: bool) -> Self {
+        self.options.http2_prior_knowledge = enable;
+        self
+    }
+
+    /// Sends `HEAD /health` whenever the client has been idle for `interval`, so the
+    /// first send after a quiet period reuses an open connection. Pick an interval
+    /// shorter than the pool idle timeout.
+    pub fn keep_connection_warm(mut self, interval: Duration) -> Self {
+        self.options.keep_connection_warm = Some(interval);
+        self
+    }
+
+    /// Requires the API server's certificate to match one of `pins`, in addition to
+    /// the usual validation. Requests to a server presenting another key fail with
+    /// `Error::PinMismatch`.
+    #[cfg(feature = "tls-pinning")]
+    pub fn pin_server_certificate(mut self, pins: PinSet) -> Self {
+        self.options.certificate_pins = Some(pins);
+        self
+    }
+
+    /// Sets the maximum number of concurrent sends when fanning out to multiple users.
+    pub fn fan_out_concurrency(mut self, concurrency: usize) -> Self {
+        self.options.fan_out_concurrency = concurrency;
+        self
+    }
+
+    /// Sets the metadata key patterns to redact from logged payloads.
+    pub fn redact_metadata_keys(mut self, patterns: Vec<String>) -> Self {
+        self.options.redact_metadata_keys = patterns;
+        self
+    }
+
+    /// Enables or disables redaction of the persisted offline queue.
+    pub fn redact_at_rest(mut self, enable: bool) -> Self {
+        self.options.redact_at_rest = enable;
+        self
+    }
+
+    /// Enables or disables publishing sent payloads to the local event bus.
+    pub fn local_echo(mut self, enable: bool) -> Self {
+        self.options.local_echo = enable;
+        self
+    }
+
+    /// Sets the capacity of the local event bus.
+    pub fn local_echo_capacity(mut self, capacity: usize) -> Self {
+        self.options.local_echo_capacity = capacity;
+        self
+    }
+
+    /// Sets the runtime used for background work.
+    pub fn runtime(mut self, runtime: impl Runtime) -> Self {
+        self.options.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    /// Sets the failure injection handle.
+    #[cfg(any(test, feature = "chaos"))]
+    pub fn chaos(mut self, chaos: Chaos) -> Self {
+        self.options.chaos = Some(chaos);
+        self
+    }
+
+    /// Sets the wire format.
+    pub fn wire_format(mut self, format: WireFormat) -> Self {
+        self.options.wire_format = format;
+        self
+    }
+
+    /// Sets the API version requested from the server.
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.options.api_version = version;
+        self
+    }
+
+    /// Sets the maximum number of response body bytes kept in errors.
+    pub fn error_body_max_bytes(mut self, bytes: usize) -> Self {
+        self.options.error_body_max_bytes = bytes;
+        self
+    }
+
+    /// Sets how many Error or Critical notifications are flushed before an older lower-severity one.
+    pub fn flush_fairness(mut self, sends: usize) -> Self {
+        self.options.flush_fairness = sends;
+        self
+    }
+
+    /// Sets the maximum size of a downloaded attachment.
+    pub fn max_download_bytes(mut self, bytes: u64) -> Self {
+        self.options.max_download_bytes = bytes;
+        self
+    }
+
+    /// Sets the number of dropped notifications kept for `recent_drops`.
+    pub fn recent_drops_capacity(mut self, capacity: usize) -> Self {
+        self.options.recent_drops_capacity = capacity;
+        self
+    }
+
+    /// Sets the payload size above which the overflow strategy applies.
+    pub fn max_payload_bytes(mut self, bytes: usize) -> Self {
+        self.options.max_payload_bytes = bytes;
+        self
+    }
+
+    /// Sets the strategy for oversized metadata.
+    pub fn metadata_overflow(mut self, strategy: OverflowStrategy) -> Self {
+        self.options.metadata_overflow = strategy;
+        self
+    }
+
+    /// Sets the maximum total size of the files attached to one notification.
+    pub fn max_attachment_bytes(mut self, bytes: usize) -> Self {
+        self.options.max_attachment_bytes = bytes;
+        self
+    }
+
+    /// Sets whether notifications without a title get one derived from their event type.
+    pub fn derive_title_from_event_type(mut self, derive: bool) -> Self {
+        self.options.derive_title_from_event_type = derive;
+        self
+    }
+
+    /// Sets the maximum number of metadata entries.
+    pub fn max_metadata_keys(mut self, max: usize) -> Self {
+        self.options.max_metadata_keys = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of actions.
+    pub fn max_actions(mut self, max: usize) -> Self {
+        self.options.max_actions = Some(max);
+        self
+    }
+
+    /// Sets the fraction of each limit at which a limit warning is raised.
+    pub fn soft_limit_ratio(mut self, ratio: f64) -> Self {
+        self.options.soft_limit_ratio = ratio;
+        self
+    }
+
+    /// Sets thresholds overriding the ones derived from `soft_limit_ratio`.
+    pub fn soft_limits(mut self, limits: SoftLimits) -> Self {
+        self.options.soft_limits = limits;
+        self
+    }
+
+    /// Sets the minimum time between limit warnings for the same event type and field.
+    pub fn limit_warning_interval(mut self, interval: Duration) -> Self {
+        self.options.limit_warning_interval = interval;
+        self
+    }
+
+    /// Sets the attachment upload timeout.
+    pub fn attachment_timeout(mut self, timeout: Duration) -> Self {
+        self.options.attachment_timeout = timeout;
+        self
+    }
+
+    /// Sets quiet hours.
+    #[cfg(feature = "quiet-hours")]
+    pub fn quiet_hours(mut self, quiet_hours: QuietHours) -> Self {
+        self.options.quiet_hours = Some(quiet_hours);
+        self
+    }
+
+    /// Sets the maximum number of concurrent sends and other writes.
+    pub fn max_concurrent_sends(mut self, max: usize) -> Self {
+        self.options.max_concurrent_sends = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of concurrent reads.
+    pub fn max_concurrent_reads(mut self, max: usize) -> Self {
+        self.options.max_concurrent_reads = Some(max);
+        self
+    }
+
+    /// Sets how long a request waits for a concurrency slot.
+    pub fn concurrency_timeout(mut self, timeout: Duration) -> Self {
+        self.options.concurrency_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the longest `emit_best_effort` waits.
+    pub fn best_effort_budget(mut self, budget: Duration) -> Self {
+        self.options.best_effort_budget = budget;
+        self
+    }
+
+    /// Enables or disables adjusting outgoing timestamps for clock skew.
+    pub fn compensate_clock_skew(mut self, enable: bool) -> Self {
+        self.options.compensate_clock_skew = enable;
+        self
+    }
+
+    /// Sets the remaining request count below which lower-severity sends are held back.
+    pub fn rate_limit_threshold(mut self, remaining: u64) -> Self {
+        self.options.rate_limit_threshold = Some(remaining);
+        self
+    }
+
+    /// Enables or disables conditional request caching.
+    pub fn http_cache(mut self, enable: bool) -> Self {
+        self.options.http_cache = enable;
+        self
+    }
+
+    /// Enables the offline read cache with the given TTL.
+    pub fn read_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.options.read_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets how long `inbox_summary` reuses a complete summary. Zero fetches every time.
+    pub fn inbox_summary_ttl(mut self, ttl: Duration) -> Self {
+        self.options.inbox_summary_ttl = ttl;
+        self
+    }
+
+    /// Enables or disables persisting the read cache to disk.
+    pub fn read_cache_persist(mut self, enable: bool) -> Self {
+        self.options.read_cache_persist = enable;
+        self
+    }
+
+    /// Enables or disables queueing mutations that fail on a network error.
+    pub fn write_behind_mutations(mut self, enable: bool) -> Self {
+        self.options.write_behind_mutations = enable;
+        self
+    }
+
+    /// Sets the number of consecutive failures that opens the circuit breaker.
+    pub fn circuit_breaker_threshold(mut self, failures: u32) -> Self {
+        self.options.circuit_breaker_threshold = failures;
+        self
+    }
+
+    /// Sets the circuit breaker cool-down period.
+    pub fn circuit_breaker_cooldown(mut self, cooldown: Duration) -> Self {
+        self.options.circuit_breaker_cooldown = cooldown;
+        self
+    }
+
+    /// Enables the send journal at the given path.
+    #[cfg(feature = "journal")]
+    pub fn journal_path(mut self, path: PathBuf) -> Self {
+        self.options.journal_path = Some(path);
+        self
+    }
+
+    /// Sets the size at which the send journal file is rotated.
+    #[cfg(feature = "journal")]
+    pub fn journal_max_bytes(mut self, bytes: u64) -> Self {
+        self.options.journal_max_bytes = bytes;
+        self
+    }
+
+    /// Sets the maximum number of rotated send journal files to keep.
+    #[cfg(feature = "journal")]
+    pub fn journal_max_files(mut self, files: usize) -> Self {
+        self.options.journal_max_files = files;
+        self
+    }
+
+    /// Records every request and response to the given NDJSON file.
+    #[cfg(feature = "record")]
+    pub fn record_traffic(mut self, path: PathBuf) -> Self {
+        self.options.record_traffic = Some(path);
+        self
+    }
+
+    /// Sets the size at which the traffic recording is rotated.
+    #[cfg(feature = "record")]
+    pub fn record_max_bytes(mut self, bytes: u64) -> Self {
+        self.options.record_max_bytes = bytes;
+        self
+    }
+
+    /// Sets the maximum number of rotated traffic recordings to keep.
+    #[cfg(feature = "record")]
+    pub fn record_max_files(mut self, files: usize) -> Self {
+        self.options.record_max_files = files;
+        self
+    }
+
+    /// Sets how long user resolver results are cached.
+    pub fn user_resolver_ttl(mut self, ttl: Duration) -> Self {
+        self.options.user_resolver_ttl = ttl;
+        self
+    }
+
+    /// Sets the threshold above which requests are reported as slow.
+    pub fn slow_request_threshold(mut self, threshold: Duration) -> Self {
+        self.options.slow_request_threshold = threshold;
+        self
+    }
+
+    /// Sets how often notifications sent with `track_read` are checked for being read.
+    pub fn read_poll_interval(mut self, interval: Duration) -> Self {
+        self.options.read_poll_interval = interval;
+        self
+    }
+
+    /// Sets how long notifications sent with `track_read` are checked before giving up.
+    pub fn read_tracking_timeout(mut self, timeout: Duration) -> Self {
+        self.options.read_tracking_timeout = timeout;
+        self
+    }
+
+    /// Enables or disables credential verification on async initialization.
+    pub fn verify_on_init(mut self, verify: bool) -> Self {
+        self.options.verify_on_init = verify;
+        self
+    }
+
+    /// Enables or disables strict metadata validation.
+    pub fn strict_metadata(mut self, strict: bool) -> Self {
+        self.options.strict_metadata = strict;
+        self
+    }
+
+    /// Enables or disables the client-side search fallback.
+    pub fn allow_client_side_search(mut self, allow: bool) -> Self {
+        self.options.allow_client_side_search = allow;
+        self
+    }
+
+    /// Builds the NotifyOptions, normalizing them and checking them with
+    /// `NotifyOptions::validate`.
+    pub fn build(mut self) -> Result<NotifyOptions, OptionsError> {
+        self.options.normalize();
+        self.options.validate()?;
         Ok(self.options)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Change = fn(&mut NotifyOptions);
+
+    #[test]
+    fn invalid_options_report_their_issue() {
+        let cases: [(&str, Change, OptionsIssue); 14] = [
+            ("no key", |o| o.api_key.clear(), OptionsIssue::MissingCredentials),
+            ("key with newline", |o| o.api_key.push('\n'), OptionsIssue::InvalidApiKey),
+            (
+                "zero connect timeout",
+                |o| o.connect_timeout = Duration::ZERO,
+                OptionsIssue::ZeroTimeout("Connect"),
+            ),
+            (
+                "zero request timeout",
+                |o| o.request_timeout = Duration::ZERO,
+                OptionsIssue::ZeroTimeout("Request"),
+            ),
+            (
+                "zero pool idle timeout",
+                |o| o.pool_idle_timeout = Some(Duration::ZERO),
+                OptionsIssue::ZeroTimeout("Pool idle"),
+            ),
+            (
+                "zero keepalive",
+                |o| o.tcp_keepalive = Some(Duration::ZERO),
+                OptionsIssue::ZeroTimeout("TCP keepalive"),
+            ),
+            (
+                "zero warm interval",
+                |o| o.keep_connection_warm = Some(Duration::ZERO),
+                OptionsIssue::ZeroTimeout("Keep connection warm"),
+            ),
+            (
+                "empty API version",
+                |o| o.api_version = ApiVersion::Custom(String::new()),
+                OptionsIssue::InvalidApiVersion,
+            ),
+            (
+                "tenant header with a space",
+                |o| o.tenant_header = Some("x tenant".to_string()),
+                OptionsIssue::InvalidTenantHeader,
+            ),
+            (
+                "soft limit of zero",
+                |o| o.soft_limit_ratio = 0.0,
+                OptionsIssue::InvalidSoftLimitRatio,
+            ),
+            (
+                "soft limit above one",
+                |o| o.soft_limit_ratio = 1.5,
+                OptionsIssue::InvalidSoftLimitRatio,
+            ),
+            (
+                "reconnect without attempts",
+                |o| o.max_reconnect_attempts = 0,
+                OptionsIssue::NoReconnectAttempts,
+            ),
+            (
+                "queue of no entries",
+                |o| o.max_offline_queue_size = 0,
+                OptionsIssue::EmptyOfflineQueue,
+            ),
+            (
+                "queue of no bytes",
+                |o| o.max_offline_queue_bytes = Some(0),
+                OptionsIssue::EmptyOfflineQueue,
+            ),
+        ];
+        for (name, change, issue) in cases {
+            let mut options = NotifyOptions::new("ak_test_xxx");
+            change(&mut options);
+            let error = options.validate().expect_err(name);
+            assert_eq!(error.issues(), [issue], "{}", name);
+        }
+    }
+
+    #[test]
+    fn disabled_features_accept_their_empty_settings() {
+        let cases: [(&str, Change); 2] = [
+            ("reconnect off", |o| {
+                o.auto_reconnect = false;
+                o.max_reconnect_attempts = 0;
+            }),
+            ("queue off", |o| {
+                o.enable_offline_queue = false;
+                o.max_offline_queue_size = 0;
+                o.max_offline_queue_bytes = Some(0);
+            }),
+        ];
+        for (name, change) in cases {
+            let mut options = NotifyOptions::new("ak_test_xxx");
+            change(&mut options);
+            assert!(options.validate().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_issue_is_reported_at_once() {
+        let error = NotifyOptions::builder()
+            .api_key("")
+            .max_reconnect_attempts(0)
+            .max_offline_queue_size(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.issues(),
+            [
+                OptionsIssue::MissingCredentials,
+                OptionsIssue::NoReconnectAttempts,
+                OptionsIssue::EmptyOfflineQueue,
+            ]
+        );
+    }
+
+    #[test]
+    fn build_clamps_the_reconnect_delay_and_queue_size() {
+        let options = NotifyOptions::builder()
+            .api_key("ak_test_xxx")
+            .reconnect_delay(Duration::ZERO)
+            .max_offline_queue_size(MAX_OFFLINE_QUEUE_SIZE + 1)
+            .build()
+            .unwrap();
+        assert_eq!(options.reconnect_delay, MIN_RECONNECT_DELAY);
+        assert_eq!(options.max_offline_queue_size, MAX_OFFLINE_QUEUE_SIZE);
+    }
+}
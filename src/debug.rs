@@ -0,0 +1,207 @@
+//! Debug logging configuration for IronNotify SDK.
+
+use crate::reconfigure::LiveConfig;
+use std::sync::Arc;
+
+/// How much a component logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum DebugLevel {
+    /// Nothing.
+    #[default]
+    Off,
+    /// One line per operation.
+    Summary,
+    /// Summaries plus details such as payload metadata, after redaction.
+    Verbose,
+}
+
+impl DebugLevel {
+    /// Checks whether anything is logged.
+    pub fn is_enabled(self) -> bool {
+        self >= DebugLevel::Summary
+    }
+
+    /// Checks whether details are logged.
+    pub fn is_verbose(self) -> bool {
+        self == DebugLevel::Verbose
+    }
+}
+
+/// Debug log levels for each part of the client.
+///
+/// ```
+/// use ironnotify::{DebugConfig, DebugLevel};
+///
+/// let config = DebugConfig::new().queue(DebugLevel::Verbose);
+/// assert!(config.queue.is_verbose());
+/// assert!(!config.transport.is_enabled());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DebugConfig {
+    /// Requests and responses: sends, reads, auth, rate limits, and the circuit breaker.
+    pub transport: DebugLevel,
+    /// The offline, deferred, and mutation queues, and checkpoints.
+    pub queue: DebugLevel,
+    /// Real-time connection lifecycle and subscriptions.
+    pub realtime: DebugLevel,
+    /// Notification building: validation failures, truncation, and user resolution.
+    pub builder: DebugLevel,
+}
+
+impl DebugConfig {
+    /// Creates a configuration with every component off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a configuration with every component at one level.
+    pub fn all(level: DebugLevel) -> Self {
+        Self {
+            transport: level,
+            queue: level,
+            realtime: level,
+            builder: level,
+        }
+    }
+
+    /// Sets the transport level.
+    pub fn transport(mut self, level: DebugLevel) -> Self {
+        self.transport = level;
+        self
+    }
+
+    /// Sets the queue level.
+    pub fn queue(mut self, level: DebugLevel) -> Self {
+        self.queue = level;
+        self
+    }
+
+    /// Sets the real-time level.
+    pub fn realtime(mut self, level: DebugLevel) -> Self {
+        self.realtime = level;
+        self
+    }
+
+    /// Sets the builder level.
+    pub fn builder(mut self, level: DebugLevel) -> Self {
+        self.builder = level;
+        self
+    }
+
+    /// Checks whether any component logs.
+    pub fn is_enabled(&self) -> bool {
+        [self.transport, self.queue, self.realtime, self.builder]
+            .iter()
+            .any(|level| level.is_enabled())
+    }
+
+    /// Raises every component to at least `level`.
+    pub(crate) fn at_least(self, level: DebugLevel) -> Self {
+        Self {
+            transport: self.transport.max(level),
+            queue: self.queue.max(level),
+            realtime: self.realtime.max(level),
+            builder: self.builder.max(level),
+        }
+    }
+}
+
+/// One component's debug level, following changes made with `reconfigure`.
+#[derive(Clone)]
+pub(crate) struct DebugSwitch {
+    config: Arc<LiveConfig>,
+    component: fn(&DebugConfig) -> DebugLevel,
+}
+
+impl DebugSwitch {
+    /// Follows the transport level.
+    pub fn transport(config: &Arc<LiveConfig>) -> Self {
+        Self {
+            config: Arc::clone(config),
+            component: |levels| levels.transport,
+        }
+    }
+
+    /// Follows the queue level.
+    pub fn queue(config: &Arc<LiveConfig>) -> Self {
+        Self {
+            config: Arc::clone(config),
+            component: |levels| levels.queue,
+        }
+    }
+
+    /// Follows the real-time level.
+    pub fn realtime(config: &Arc<LiveConfig>) -> Self {
+        Self {
+            config: Arc::clone(config),
+            component: |levels| levels.realtime,
+        }
+    }
+
+    /// Gets the level in effect.
+    pub fn level(&self) -> DebugLevel {
+        (self.component)(&self.config.debug_levels())
+    }
+
+    /// Checks whether anything is logged.
+    pub fn is_enabled(&self) -> bool {
+        self.level().is_enabled()
+    }
+
+    /// Checks whether details are logged.
+    pub fn is_verbose(&self) -> bool {
+        self.level().is_verbose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyOptions;
+
+    #[test]
+    fn levels_are_ordered_from_off_to_verbose() {
+        let cases = [
+            (DebugLevel::Off, false, false),
+            (DebugLevel::Summary, true, false),
+            (DebugLevel::Verbose, true, true),
+        ];
+        for (level, enabled, verbose) in cases {
+            assert_eq!(level.is_enabled(), enabled, "{:?}", level);
+            assert_eq!(level.is_verbose(), verbose, "{:?}", level);
+        }
+    }
+
+    #[test]
+    fn raising_a_config_keeps_the_higher_levels() {
+        let config = DebugConfig::new().queue(DebugLevel::Verbose);
+        assert!(config.is_enabled());
+        assert!(!DebugConfig::new().is_enabled());
+        assert_eq!(
+            config.at_least(DebugLevel::Summary),
+            DebugConfig::all(DebugLevel::Summary).queue(DebugLevel::Verbose)
+        );
+    }
+
+    #[test]
+    fn switches_follow_their_component_through_reconfigure() {
+        let mut options = NotifyOptions::new("ak_test_debug");
+        options.debug_config = DebugConfig::new().transport(DebugLevel::Verbose);
+        let config = LiveConfig::new(&options);
+        let transport = DebugSwitch::transport(&config);
+        let queue = DebugSwitch::queue(&config);
+        let realtime = DebugSwitch::realtime(&config);
+        assert!(transport.is_verbose());
+        assert!(!queue.is_enabled());
+
+        let queue_only = DebugConfig::new().queue(DebugLevel::Summary);
+        config.update(|runtime| runtime.debug_config = queue_only);
+        assert!(!transport.is_enabled());
+        assert_eq!(queue.level(), DebugLevel::Summary);
+
+        // `debug` turns on summaries for every component.
+        config.update(|runtime| runtime.debug = true);
+        assert_eq!(realtime.level(), DebugLevel::Summary);
+        assert!(!queue.is_verbose());
+    }
+}
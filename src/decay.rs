@@ -0,0 +1,138 @@
+//! Severity decay of queued notifications for IronNotify SDK.
+
+use crate::redact::glob_match;
+use crate::types::{NotificationPayload, SeverityLevel};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// Metadata key holding the severity a decayed notification was queued with.
+pub const ORIGINAL_SEVERITY_KEY: &str = "original_severity";
+
+/// Metadata key holding how many seconds a decayed notification spent in the queue.
+pub const DELAYED_SECONDS_KEY: &str = "delayed_seconds";
+
+/// Gets how long an entry queued at `enqueued_at` has waited by `now`, or `None` if
+/// it came from an older queue file that did not record when it was queued.
+///
+/// An entry stamped ahead of `now` by a clock change has waited for zero seconds.
+pub(crate) fn queued_for(
+    enqueued_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<Duration> {
+    Some((now - enqueued_at?).to_std().unwrap_or_default())
+}
+
+/// Gets a copy of a queued payload with its severity lowered for its age, or `None`
+/// if it keeps its severity.
+///
+/// The step with the longest threshold that `age` reaches applies. Severities are
+/// only lowered, never raised, and payloads whose event type matches an `exempt`
+/// pattern are left alone.
+pub(crate) fn decayed(
+    payload: &NotificationPayload,
+    age: Duration,
+    steps: &[(Duration, SeverityLevel)],
+    exempt: &[String],
+) -> Option<NotificationPayload> {
+    let (_, target) = steps
+        .iter()
+        .filter(|(threshold, _)| age >= *threshold)
+        .max_by_key(|(threshold, _)| *threshold)?;
+    let severity = payload.severity.unwrap_or_default();
+    if *target >= severity
+        || exempt.iter().any(|pattern| glob_match(pattern, &payload.event_type))
+    {
+        return None;
+    }
+
+    let mut decayed = payload.clone();
+    decayed.severity = Some(*target);
+    let metadata = decayed.metadata.get_or_insert_with(Default::default);
+    metadata.insert(ORIGINAL_SEVERITY_KEY.to_string(), severity.to_string().into());
+    metadata.insert(DELAYED_SECONDS_KEY.to_string(), age.as_secs().into());
+    Some(decayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn queued_at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+    }
+
+    fn steps() -> Vec<(Duration, SeverityLevel)> {
+        vec![(HOUR, SeverityLevel::Warning), (4 * HOUR, SeverityLevel::Info)]
+    }
+
+    fn critical(event_type: &str) -> NotificationPayload {
+        let mut payload = NotificationPayload::new(event_type, "Disk full");
+        payload.severity = Some(SeverityLevel::Critical);
+        payload
+    }
+
+    /// Gets the severity a critical payload is sent with `elapsed` after it was queued.
+    fn severity_after(elapsed: chrono::Duration) -> SeverityLevel {
+        let payload = critical("disk.full");
+        let age = queued_for(Some(queued_at()), queued_at() + elapsed).unwrap();
+        decayed(&payload, age, &steps(), &[])
+            .unwrap_or(payload)
+            .severity
+            .unwrap()
+    }
+
+    #[test]
+    fn steps_apply_from_their_threshold_on() {
+        let cases = [
+            (chrono::Duration::zero(), SeverityLevel::Critical),
+            (chrono::Duration::seconds(3599), SeverityLevel::Critical),
+            (chrono::Duration::seconds(3600), SeverityLevel::Warning),
+            (chrono::Duration::seconds(4 * 3600 - 1), SeverityLevel::Warning),
+            (chrono::Duration::seconds(4 * 3600), SeverityLevel::Info),
+            (chrono::Duration::days(30), SeverityLevel::Info),
+        ];
+        for (elapsed, severity) in cases {
+            assert_eq!(severity_after(elapsed), severity, "after {}", elapsed);
+        }
+    }
+
+    #[test]
+    fn entries_stamped_ahead_of_the_clock_have_not_waited() {
+        let now = queued_at() - chrono::Duration::hours(5);
+        assert_eq!(queued_for(Some(queued_at()), now), Some(Duration::ZERO));
+        assert_eq!(severity_after(chrono::Duration::hours(-5)), SeverityLevel::Critical);
+    }
+
+    #[test]
+    fn entries_of_unknown_age_are_not_decayed() {
+        assert_eq!(queued_for(None, queued_at()), None);
+    }
+
+    #[test]
+    fn severities_are_only_lowered() {
+        let mut payload = critical("disk.full");
+        payload.severity = Some(SeverityLevel::Info);
+        assert!(decayed(&payload, 5 * HOUR, &[(HOUR, SeverityLevel::Warning)], &[]).is_none());
+        payload.severity = Some(SeverityLevel::Warning);
+        assert!(decayed(&payload, 5 * HOUR, &[(HOUR, SeverityLevel::Warning)], &[]).is_none());
+    }
+
+    #[test]
+    fn exempt_event_types_keep_their_severity() {
+        let exempt = vec!["security.*".to_string()];
+        assert!(decayed(&critical("security.breach"), 5 * HOUR, &steps(), &exempt).is_none());
+        assert!(decayed(&critical("disk.full"), 5 * HOUR, &steps(), &exempt).is_some());
+    }
+
+    #[test]
+    fn decayed_payloads_record_what_they_were_queued_with() {
+        let decayed = decayed(&critical("disk.full"), HOUR + Duration::from_secs(5), &steps(), &[])
+            .unwrap();
+        let metadata = decayed.metadata.unwrap();
+        assert_eq!(metadata[ORIGINAL_SEVERITY_KEY], "critical");
+        assert_eq!(metadata[DELAYED_SECONDS_KEY], 3605);
+    }
+}
@@ -0,0 +1,93 @@
+//! Per-notification delivery callbacks for IronNotify SDK.
+
+use crate::types::SendResult;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Callback invoked with the final result of one send.
+pub type SendResultCallback = Arc<dyn Fn(&SendResult) + Send + Sync>;
+
+/// Callback invoked when the recipient reads a tracked notification.
+pub type ReadReceiptCallback = Arc<dyn Fn(&ReadReceipt) + Send + Sync>;
+
+/// A tracked notification that was read.
+#[derive(Debug, Clone)]
+pub struct ReadReceipt {
+    /// ID of the notification.
+    pub notification_id: String,
+    /// When the recipient read it.
+    pub read_at: DateTime<Utc>,
+}
+
+/// Callbacks set on one send with `EventBuilder::on_result` and `on_read`.
+#[derive(Clone, Default)]
+pub(crate) struct DeliveryCallbacks {
+    pub on_result: Option<SendResultCallback>,
+    pub on_read: Option<ReadReceiptCallback>,
+}
+
+impl DeliveryCallbacks {
+    pub fn is_empty(&self) -> bool {
+        self.on_result.is_none() && self.on_read.is_none()
+    }
+}
+
+/// The queued copy of a notification that delivery callbacks wait for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum DeliveryKey {
+    /// An offline or Postgres queue entry, by ID.
+    Queued(u64),
+    /// A notification deferred by quiet hours, by deferred ID.
+    Deferred(String),
+}
+
+/// Callbacks of queued notifications, kept in memory by queue entry until the entry
+/// is sent or dropped.
+///
+/// Keying by entry rather than content hash keeps the callbacks of two identical
+/// payloads apart when only one of them is dropped.
+#[derive(Default)]
+pub(crate) struct PendingDeliveries {
+    pending: Mutex<HashMap<DeliveryKey, DeliveryCallbacks>>,
+}
+
+impl PendingDeliveries {
+    /// Keeps callbacks until the entry is sent or dropped.
+    pub fn register(&self, key: DeliveryKey, callbacks: DeliveryCallbacks) {
+        if !callbacks.is_empty() {
+            self.pending.lock().insert(key, callbacks);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.lock().is_empty()
+    }
+
+    /// Takes the callbacks kept for an entry.
+    pub fn take(&self, key: &DeliveryKey) -> Option<DeliveryCallbacks> {
+        let mut pending = self.pending.lock();
+        if pending.is_empty() {
+            return None;
+        }
+        pending.remove(key)
+    }
+
+    /// Moves the callbacks of an entry to the one it became, e.g. when a deferred
+    /// notification is released into the queue.
+    pub fn rekey(&self, from: &DeliveryKey, to: DeliveryKey) {
+        if let Some(callbacks) = self.take(from) {
+            self.register(to, callbacks);
+        }
+    }
+
+    /// Fails the `on_result` callback of an entry removed without being sent.
+    pub fn fail(&self, key: &DeliveryKey, error: String, content_hash: Option<String>) {
+        if let Some(on_result) = self.take(key).and_then(|callbacks| callbacks.on_result) {
+            let mut result = SendResult::failure(error);
+            result.content_hash = content_hash;
+            on_result(&result);
+        }
+    }
+}
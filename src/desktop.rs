@@ -0,0 +1,129 @@
+//! Native desktop notifications for IronNotify SDK.
+//!
+//! Available with the `desktop` feature.
+
+use crate::error::Error;
+use crate::types::Notification;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::types::SeverityLevel;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// Application name shown by the desktop notification service.
+const APP_NAME: &str = "IronNotify";
+
+/// Shows a notification as a native desktop notification.
+///
+/// On Linux and other XDG desktops, severity maps to urgency and URL actions become
+/// buttons that open the URL. On other platforms only the title and message are shown.
+pub fn show(notification: &Notification) -> Result<(), Error> {
+    let mut desktop = notify_rust::Notification::new();
+    desktop.appname(APP_NAME).summary(&notification.title);
+    if let Some(ref message) = notification.message {
+        desktop.body(message);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        desktop.urgency(match notification.severity {
+            SeverityLevel::Info | SeverityLevel::Success => notify_rust::Urgency::Low,
+            SeverityLevel::Warning => notify_rust::Urgency::Normal,
+            SeverityLevel::Error | SeverityLevel::Critical => notify_rust::Urgency::Critical,
+        });
+
+        let urls: Vec<(String, String)> = notification
+            .actions
+            .iter()
+            .flatten()
+            .filter_map(|action| Some((action.url.clone()?, action.label.clone())))
+            .collect();
+        for (url, label) in &urls {
+            desktop.action(url, label);
+        }
+
+        let handle = desktop.show().map_err(|e| Error::Desktop(e.to_string()))?;
+        if !urls.is_empty() {
+            std::thread::spawn(move || {
+                handle.wait_for_action(|url| {
+                    if url.contains("://") {
+                        let _ = std::process::Command::new("xdg-open").arg(url).spawn();
+                    }
+                });
+            });
+        }
+        Ok(())
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        desktop.show().map_err(|e| Error::Desktop(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Shows notifications on the desktop, skipping repeats and filtered event types.
+///
+/// Repeats are detected by notification ID, so items delivered again after a
+/// reconnect are shown once.
+pub struct DesktopBridge {
+    event_types: Option<HashSet<String>>,
+    window: Duration,
+    shown: Mutex<HashMap<String, Instant>>,
+}
+
+impl DesktopBridge {
+    /// Creates a bridge that shows every event type and suppresses repeats for 5 minutes.
+    pub fn new() -> Self {
+        Self {
+            event_types: None,
+            window: Duration::from_secs(300),
+            shown: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Only shows notifications with the given event types.
+    pub fn event_types<I, T>(mut self, event_types: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.event_types = Some(event_types.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets how long a notification ID is remembered to suppress repeats.
+    pub fn dedup_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Shows a notification unless it is filtered out or was shown recently.
+    ///
+    /// Returns whether it was shown.
+    pub fn forward(&self, notification: &Notification) -> Result<bool, Error> {
+        if let Some(ref event_types) = self.event_types {
+            if !event_types.contains(&notification.event_type) {
+                return Ok(false);
+            }
+        }
+
+        {
+            let mut shown = self.shown.lock();
+            shown.retain(|_, at| at.elapsed() < self.window);
+            if shown.contains_key(&notification.id) {
+                return Ok(false);
+            }
+            shown.insert(notification.id.clone(), Instant::now());
+        }
+
+        show(notification)?;
+        Ok(true)
+    }
+}
+
+impl Default for DesktopBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
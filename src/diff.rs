@@ -0,0 +1,174 @@
+//! Notification list helpers for IronNotify SDK.
+
+use crate::types::Notification;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Changes between two notification lists, matched by ID.
+#[derive(Debug, Clone, Default)]
+pub struct NotificationDiff {
+    /// Notifications in the new list only, in new list order.
+    pub added: Vec<Notification>,
+    /// Notifications in the old list only, in old list order.
+    pub removed: Vec<Notification>,
+    /// New versions of notifications whose read state, snooze, or content changed.
+    pub updated: Vec<Notification>,
+}
+
+impl NotificationDiff {
+    /// Checks whether the lists are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// Compares two notification lists by ID.
+///
+/// When an ID appears more than once in a list, only its first occurrence is used.
+pub fn diff_notifications(old: &[Notification], new: &[Notification]) -> NotificationDiff {
+    let old_by_id = first_by_id(old);
+    let new_by_id = first_by_id(new);
+    let mut diff = NotificationDiff::default();
+
+    let mut seen = HashSet::new();
+    for notification in new {
+        if !seen.insert(notification.id.as_str()) {
+            continue;
+        }
+        match old_by_id.get(notification.id.as_str()) {
+            None => diff.added.push(notification.clone()),
+            Some(previous) if has_changed(previous, notification) => {
+                diff.updated.push(notification.clone())
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for notification in old {
+        if seen.insert(notification.id.as_str()) && !new_by_id.contains_key(notification.id.as_str())
+        {
+            diff.removed.push(notification.clone());
+        }
+    }
+
+    diff
+}
+
+/// Groups notifications by category, keeping their order within each group.
+///
+/// Notifications without a category are grouped under `None`.
+pub fn group_by_category(
+    notifications: &[Notification],
+) -> BTreeMap<Option<String>, Vec<Notification>> {
+    let mut groups: BTreeMap<Option<String>, Vec<Notification>> = BTreeMap::new();
+    for notification in notifications {
+        groups
+            .entry(notification.category.clone())
+            .or_default()
+            .push(notification.clone());
+    }
+    groups
+}
+
+fn first_by_id(notifications: &[Notification]) -> HashMap<&str, &Notification> {
+    let mut by_id = HashMap::with_capacity(notifications.len());
+    for notification in notifications {
+        by_id.entry(notification.id.as_str()).or_insert(notification);
+    }
+    by_id
+}
+
+fn has_changed(old: &Notification, new: &Notification) -> bool {
+    old.read != new.read
+        || old.snoozed_until != new.snoozed_until
+        || old.content_fingerprint() != new.content_fingerprint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use chrono::{Duration, TimeZone, Utc};
+
+    /// Generates reproducible lists of notifications with few distinct IDs, so lists
+    /// share IDs and repeat them.
+    struct Lists(u64);
+
+    impl Lists {
+        fn next(&mut self, below: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % below
+        }
+
+        fn list(&mut self) -> Vec<Notification> {
+            let base = Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap();
+            (0..self.next(8))
+                .map(|_| {
+                    let mut notification = test_support::received_notification(base);
+                    notification.id = format!("n{}", self.next(6));
+                    notification.read = self.next(2) == 0;
+                    notification.title = format!("t{}", self.next(2));
+                    notification.snoozed_until = match self.next(3) {
+                        0 => None,
+                        n => Some(base + Duration::hours(n as i64)),
+                    };
+                    notification
+                })
+                .collect()
+        }
+    }
+
+    /// Applies a diff to the first occurrences of the IDs in `old`, ordered like `new`.
+    fn apply(old: &[Notification], diff: &NotificationDiff, new: &[Notification]) -> Vec<String> {
+        let mut by_id: HashMap<&str, &Notification> = first_by_id(old);
+        for removed in &diff.removed {
+            assert!(by_id.remove(removed.id.as_str()).is_some(), "{} not in old", removed.id);
+        }
+        for updated in &diff.updated {
+            assert!(by_id.insert(&updated.id, updated).is_some(), "{} not in old", updated.id);
+        }
+        for added in &diff.added {
+            assert!(by_id.insert(&added.id, added).is_none(), "{} already in old", added.id);
+        }
+
+        let mut seen = HashSet::new();
+        let order: Vec<&str> = new
+            .iter()
+            .map(|n| n.id.as_str())
+            .filter(|id| seen.insert(*id))
+            .collect();
+        assert_eq!(by_id.len(), order.len());
+        order.into_iter().map(|id| describe(by_id[id])).collect()
+    }
+
+    fn describe(notification: &Notification) -> String {
+        format!(
+            "{} read={} snoozed={:?} {}",
+            notification.id,
+            notification.read,
+            notification.snoozed_until,
+            notification.content_fingerprint()
+        )
+    }
+
+    #[test]
+    fn applying_the_diff_to_the_old_list_gives_the_new_list() {
+        let mut lists = Lists(0x9e37_79b9_7f4a_7c15);
+        for case in 0..500 {
+            let old = lists.list();
+            let new = lists.list();
+            let diff = diff_notifications(&old, &new);
+
+            let mut seen = HashSet::new();
+            let expected: Vec<String> = new
+                .iter()
+                .filter(|n| seen.insert(n.id.as_str()))
+                .map(describe)
+                .collect();
+            assert_eq!(apply(&old, &diff, &new), expected, "case {}", case);
+            assert!(diff_notifications(&new, &new).is_empty(), "case {}", case);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Digest builder for IronNotify SDK.
+
+use crate::client::NotifyClient;
+use crate::types::{NotificationPayload, SendResult, SeverityLevel};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Default number of items included in a digest's metadata.
+const DEFAULT_MAX_ITEMS: usize = 50;
+
+/// Number of item titles listed in the default summary message.
+const SUMMARY_TITLES: usize = 3;
+
+/// Formats a digest's title and message from its items.
+pub type DigestFormatter = Arc<dyn Fn(&[DigestItem]) -> (String, Option<String>) + Send + Sync>;
+
+/// One event summarized by a digest.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestItem {
+    /// The event's title.
+    pub title: String,
+    /// The event's metadata.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+/// Builder for one notification that summarizes many events.
+///
+/// The items are sent in the `items` metadata array, capped at `with_max_items`
+/// and trimmed further to fit `max_payload_bytes`. When any are left out, the
+/// metadata has `truncated: true` and an `overflowCount`.
+#[derive(Clone)]
+pub struct DigestBuilder {
+    client: NotifyClient,
+    event_type: String,
+    items: Vec<DigestItem>,
+    max_items: usize,
+    severity: SeverityLevel,
+    user_id: Option<String>,
+    group_key: Option<String>,
+    formatter: Option<DigestFormatter>,
+}
+
+impl DigestBuilder {
+    /// Creates a new DigestBuilder.
+    pub(crate) fn new(client: NotifyClient, event_type: impl Into<String>) -> Self {
+        Self {
+            client,
+            event_type: event_type.into(),
+            items: Vec::new(),
+            max_items: DEFAULT_MAX_ITEMS,
+            severity: SeverityLevel::Info,
+            user_id: None,
+            group_key: None,
+            formatter: None,
+        }
+    }
+
+    /// Adds an event to the digest.
+    pub fn add_item(
+        mut self,
+        title: impl Into<String>,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Self {
+        self.items.push(DigestItem {
+            title: title.into(),
+            metadata,
+        });
+        self
+    }
+
+    /// Sets the maximum number of items included in the metadata.
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Sets the severity level.
+    pub fn with_severity(mut self, severity: SeverityLevel) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Sets the target user ID.
+    pub fn for_user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Sets the group key. Defaults to the event type.
+    pub fn with_group_key(mut self, group_key: impl Into<String>) -> Self {
+        self.group_key = Some(group_key.into());
+        self
+    }
+
+    /// Sets how the title and message are generated from all added items.
+    pub fn with_summary<F>(mut self, formatter: F) -> Self
+    where
+        F: Fn(&[DigestItem]) -> (String, Option<String>) + Send + Sync + 'static,
+    {
+        self.formatter = Some(Arc::new(formatter));
+        self
+    }
+
+    /// Gets the number of items added.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if no items have been added.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Builds the digest payload.
+    pub fn build(self) -> Result<NotificationPayload, &'static str> {
+        if self.items.is_empty() {
+            return Err("Digest has no items");
+        }
+
+        let (title, message) = match self.formatter {
+            Some(ref formatter) => formatter(&self.items),
+            None => default_summary(&self.items),
+        };
+
+        let mut builder = self
+            .client
+            .event(self.event_type.clone())
+            .with_title(title)
+            .with_severity(self.severity)
+            .with_group_key(self.group_key.unwrap_or_else(|| self.event_type.clone()));
+        if let Some(message) = message {
+            builder = builder.with_message(message);
+        }
+        if let Some(user_id) = self.user_id {
+            builder = builder.for_user(user_id);
+        }
+        let mut payload = builder.build()?;
+
+        let total = self.items.len();
+        let mut included: Vec<DigestItem> = self.items.into_iter().take(self.max_items).collect();
+        let max_bytes = self.client.options().max_payload_bytes;
+        loop {
+            set_items_metadata(&mut payload, &included, total);
+            if included.is_empty() || payload.estimated_wire_size() <= max_bytes {
+                break;
+            }
+            included.pop();
+        }
+        Ok(payload)
+    }
+
+    /// Sends the digest.
+    pub async fn send(self) -> SendResult {
+        let client = self.client.clone();
+        match self.build() {
+            Ok(payload) => client.send_payload(&payload).await,
+            Err(e) => SendResult::failure(e),
+        }
+    }
+}
+
+/// Summarizes items as "N events", listing the first few titles in the message.
+fn default_summary(items: &[DigestItem]) -> (String, Option<String>) {
+    let title = match items.len() {
+        1 => "1 event".to_string(),
+        n => format!("{} events", n),
+    };
+
+    let mut message = items
+        .iter()
+        .take(SUMMARY_TITLES)
+        .map(|item| item.title.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > SUMMARY_TITLES {
+        message.push_str(&format!(" and {} more", items.len() - SUMMARY_TITLES));
+    }
+    (title, Some(message))
+}
+
+fn set_items_metadata(payload: &mut NotificationPayload, included: &[DigestItem], total: usize) {
+    let metadata = payload.metadata.get_or_insert_with(HashMap::new);
+    metadata.insert(
+        "items".to_string(),
+        serde_json::to_value(included).unwrap_or_default(),
+    );
+    metadata.insert("itemCount".to_string(), total.into());
+    if included.len() < total {
+        metadata.insert("truncated".to_string(), true.into());
+        metadata.insert("overflowCount".to_string(), (total - included.len()).into());
+    } else {
+        metadata.remove("truncated");
+        metadata.remove("overflowCount");
+    }
+}
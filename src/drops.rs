@@ -0,0 +1,178 @@
+//! Dropped notification tracking for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use crate::delivery::{DeliveryKey, PendingDeliveries};
+use crate::types::NotificationPayload;
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Callback invoked when a notification is discarded without being sent.
+pub type DroppedNotificationCallback = Arc<dyn Fn(&DroppedNotification) + Send + Sync>;
+
+/// Why a notification was discarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// The offline queue was full and the oldest entry was evicted.
+    QueueFull,
+    /// A persisted queue entry could not be parsed.
+    CorruptEntry,
+    /// The notification expired before it could be sent.
+    Expired,
+    /// The notification was queued under a different key environment than the client's.
+    EnvironmentMismatch,
+    /// The notification is larger than `max_offline_queue_bytes`.
+    TooLarge,
+    /// A queued mutation targets a notification that no longer exists on the server.
+    Conflict,
+    /// The Postgres queue was unreachable and the offline queue could not take the
+    /// notification instead.
+    QueueUnavailable,
+    /// A newer queued notification has the same deduplication key and recipient.
+    Duplicate,
+    /// Sending the queued notification failed `max_queue_attempts` times.
+    RetriesExhausted,
+}
+
+impl std::fmt::Display for DropReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::QueueFull => "queue full",
+            Self::CorruptEntry => "corrupt entry",
+            Self::Expired => "expired",
+            Self::EnvironmentMismatch => "environment mismatch",
+            Self::TooLarge => "too large",
+            Self::Conflict => "conflict",
+            Self::QueueUnavailable => "queue unavailable",
+            Self::Duplicate => "duplicate",
+            Self::RetriesExhausted => "retries exhausted",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A notification that was discarded without being sent.
+#[derive(Debug, Clone)]
+pub struct DroppedNotification {
+    /// Event type, when the payload could be read.
+    pub event_type: Option<String>,
+    /// Title, when the payload could be read.
+    pub title: Option<String>,
+    /// Deduplication key, when set.
+    pub deduplication_key: Option<String>,
+    /// `content_hash` of the payload, when the payload could be read.
+    pub content_hash: Option<String>,
+    /// ID of the notification a dropped mutation targeted.
+    pub notification_id: Option<String>,
+    /// Why the notification was discarded.
+    pub reason: DropReason,
+    /// When the notification was discarded.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl DroppedNotification {
+    /// Creates a record for a discarded payload.
+    pub fn new(payload: &NotificationPayload, reason: DropReason) -> Self {
+        Self {
+            event_type: Some(payload.event_type.clone()),
+            title: Some(payload.title.clone()),
+            deduplication_key: payload.deduplication_key.clone(),
+            content_hash: Some(payload.content_hash()),
+            notification_id: None,
+            reason,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Creates a record for an entry whose payload could not be read.
+    pub fn unreadable(reason: DropReason) -> Self {
+        Self {
+            event_type: None,
+            title: None,
+            deduplication_key: None,
+            content_hash: None,
+            notification_id: None,
+            reason,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Creates a record for a queued mutation of an existing notification.
+    pub fn mutation(notification_id: &str, reason: DropReason) -> Self {
+        Self {
+            notification_id: Some(notification_id.to_string()),
+            ..Self::unreadable(reason)
+        }
+    }
+}
+
+/// Records dropped notifications in a bounded ring buffer and notifies a callback.
+///
+/// Dropped queue entries also fail the `on_result` callbacks waiting for them.
+pub(crate) struct DropLog {
+    capacity: usize,
+    debug: DebugSwitch,
+    recent: Mutex<VecDeque<DroppedNotification>>,
+    callback: RwLock<Option<DroppedNotificationCallback>>,
+    deliveries: Arc<PendingDeliveries>,
+}
+
+impl DropLog {
+    /// Creates a drop log keeping at most `capacity` recent records.
+    pub fn new(capacity: usize, debug: DebugSwitch, deliveries: Arc<PendingDeliveries>) -> Self {
+        Self {
+            capacity,
+            debug,
+            recent: Mutex::new(VecDeque::new()),
+            callback: RwLock::new(None),
+            deliveries,
+        }
+    }
+
+    /// Sets the dropped notification callback.
+    pub fn set_callback(&self, callback: DroppedNotificationCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    /// Records a dropped notification.
+    pub fn record(&self, dropped: DroppedNotification) {
+        if self.debug.is_enabled() {
+            println!(
+                "[IronNotify] Dropped notification ({}): {}",
+                dropped.reason,
+                dropped
+                    .event_type
+                    .as_deref()
+                    .or(dropped.notification_id.as_deref())
+                    .unwrap_or("<unreadable>")
+            );
+        }
+
+        if self.capacity > 0 {
+            let mut recent = self.recent.lock();
+            if recent.len() >= self.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(dropped.clone());
+        }
+
+        let callback = self.callback.read().clone();
+        if let Some(callback) = callback {
+            callback(&dropped);
+        }
+    }
+
+    /// Records a dropped queue entry and fails the `on_result` callback waiting for it.
+    pub fn record_entry(&self, key: DeliveryKey, dropped: DroppedNotification) {
+        let error = format!("Dropped: {}", dropped.reason);
+        let content_hash = dropped.content_hash.clone();
+        self.record(dropped);
+        self.deliveries.fail(&key, error, content_hash);
+    }
+
+    /// Gets up to `limit` of the most recent records, newest first.
+    pub fn recent(&self, limit: usize) -> Vec<DroppedNotification> {
+        self.recent.lock().iter().rev().take(limit).cloned().collect()
+    }
+}
@@ -0,0 +1,168 @@
+//! Error types for IronNotify SDK.
+
+use thiserror::Error;
+
+/// Errors returned by the IronNotify client.
+#[derive(Debug, Clone, Error)]
+pub enum Error {
+    /// The notification metadata does not match the schema registered for its event type.
+    #[error("Metadata validation failed for '{event_type}': {}", .violations.join("; "))]
+    MetadataValidation {
+        event_type: String,
+        violations: Vec<String>,
+    },
+    /// The request to the API failed.
+    #[error("{0}")]
+    Transport(String),
+    /// The request to the API timed out.
+    #[error("{0}")]
+    Timeout(TimeoutPhase),
+    /// The API key was rejected by the server.
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    /// The client options are invalid.
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    /// The event type name does not follow the naming rules.
+    #[error("Invalid event type: {0}")]
+    InvalidEventType(String),
+    /// The payload uses a metadata key reserved by the SDK.
+    #[error("Metadata key '{0}' is reserved")]
+    ReservedMetadataKey(String),
+    /// The global client has already been initialized.
+    #[error("Already initialized")]
+    AlreadyInitialized,
+    /// The global client has not been initialized.
+    #[error("Not initialized. Call init() first.")]
+    NotInitialized,
+    /// A request or response body could not be encoded or decoded.
+    #[error("Encoding error: {0}")]
+    Codec(String),
+    /// The API responded with a non-success status code.
+    #[error("{0}")]
+    Http(ResponseError),
+    /// The attachment's signed URL has expired; refresh the notification and retry.
+    #[error("Attachment link expired")]
+    AttachmentExpired,
+    /// The attachment is larger than `max_download_bytes`.
+    #[error("Attachment exceeds {limit} bytes")]
+    DownloadTooLarge { limit: u64 },
+    /// A payload's attachments are larger than `max_attachment_bytes` in total.
+    #[error("Attachments are {size} bytes, exceeding the {limit} byte limit")]
+    AttachmentsTooLarge { size: usize, limit: usize },
+    /// The circuit breaker is open after repeated failures.
+    #[error("Circuit breaker is open")]
+    CircuitOpen,
+    /// The server does not accept the requested `api_version`.
+    #[error("Server does not support API version {0}")]
+    UnsupportedApiVersion(String),
+    /// The server does not support the requested operation.
+    #[error("Not supported by the server: {0}")]
+    Unsupported(String),
+    /// A bulk operation has no filters and was not confirmed with `confirm_delete_all`.
+    #[error("Bulk operation has no filters; call confirm_delete_all() to affect every notification")]
+    UnscopedBulkOperation,
+    /// A desktop notification could not be shown.
+    #[error("Desktop notification failed: {0}")]
+    Desktop(String),
+    /// No request slot became free within `concurrency_timeout`.
+    #[error("Too many concurrent {0}")]
+    Saturated(&'static str),
+    /// A payload is larger than `max_offline_queue_bytes` and cannot be queued.
+    #[error("Payload is {size} bytes, exceeding the {limit} byte offline queue limit")]
+    QueueItemTooLarge { size: usize, limit: usize },
+    /// The offline queue is full and every entry that would have to be evicted is
+    /// awaiting confirmation.
+    #[error("Offline queue is full of notifications awaiting confirmation")]
+    QueueFull,
+    /// A metadata schema could not be compiled.
+    #[error("Invalid metadata schema: {0}")]
+    InvalidSchema(String),
+    /// A consumer checkpoint could not be loaded or saved.
+    #[error("Checkpoint storage failed: {0}")]
+    Checkpoint(String),
+    /// The Postgres offline queue could not be read or written.
+    #[error("Queue storage failed: {0}")]
+    QueueStorage(String),
+    /// A notification search was given no text.
+    #[error("Search text must not be empty")]
+    EmptySearch,
+    /// The operation was cancelled through its `CancellationToken`.
+    #[error("Operation was cancelled")]
+    Cancelled,
+    /// A `consume_notifications` handler failed.
+    #[error("Notification handler failed: {0}")]
+    Handler(String),
+    /// A `Pager` stopped because the endpoint repeated a cursor or returned too many
+    /// pages.
+    #[error("Pagination stopped: {0}")]
+    Pagination(String),
+    /// The API server presented a certificate whose key is not in the pin set. Holds
+    /// the pin of the presented key.
+    #[error("Server certificate does not match the pinned keys (presented {0})")]
+    PinMismatch(String),
+    /// A catalog entry or the parameters passed to it are invalid.
+    #[error("Invalid catalog parameters for '{event_type}': {}", .problems.join("; "))]
+    CatalogParams {
+        event_type: String,
+        problems: Vec<String>,
+    },
+}
+
+impl From<crate::config::OptionsError> for Error {
+    fn from(e: crate::config::OptionsError) -> Self {
+        Self::Config(e.to_string())
+    }
+}
+
+/// The phase of an API request that timed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutPhase {
+    /// No connection was established within `connect_timeout`.
+    Connect,
+    /// The server did not finish responding within `request_timeout`.
+    Response,
+}
+
+impl std::fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Connect => "Timed out connecting to the server",
+            Self::Response => "Timed out waiting for the server to respond",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Details of a non-success API response.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ResponseError {
+    /// HTTP status code.
+    pub status: u16,
+    /// Error message from a JSON error body.
+    pub message: Option<String>,
+    /// Raw response body, truncated, when it is not a JSON error.
+    pub body: Option<String>,
+    /// Value of the `x-request-id` response header.
+    pub request_id: Option<String>,
+    /// Value of the `server` response header.
+    pub server: Option<String>,
+}
+
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match reqwest::StatusCode::from_u16(self.status) {
+            Ok(status) => write!(f, "HTTP {}", status)?,
+            Err(_) => write!(f, "HTTP {}", self.status)?,
+        }
+        if let Some(detail) = self.message.as_ref().or(self.body.as_ref()) {
+            write!(f, ": {}", detail)?;
+        }
+        match (&self.request_id, &self.server) {
+            (Some(request_id), Some(server)) => write!(f, " (request {}, server {})", request_id, server),
+            (Some(request_id), None) => write!(f, " (request {})", request_id),
+            (None, Some(server)) => write!(f, " (server {})", server),
+            (None, None) => Ok(()),
+        }
+    }
+}
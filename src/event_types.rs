@@ -0,0 +1,70 @@
+//! Event type definitions for IronNotify SDK.
+
+use crate::types::SeverityLevel;
+use serde::{Deserialize, Serialize};
+
+/// Maximum length of an event type name.
+const MAX_EVENT_TYPE_LENGTH: usize = 128;
+
+/// Checks that an event type name is non-empty, at most 128 characters, and
+/// contains only letters, digits, `.`, `_`, `-`, and `:`.
+pub(crate) fn validate_event_type(event_type: &str) -> Result<(), &'static str> {
+    if event_type.is_empty() {
+        return Err("Event type is required");
+    }
+    if event_type.chars().count() > MAX_EVENT_TYPE_LENGTH {
+        return Err("Event type must be at most 128 characters");
+    }
+    if !event_type
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | ':'))
+    {
+        return Err("Event type may only contain letters, digits, '.', '_', '-', and ':'");
+    }
+    Ok(())
+}
+
+/// A registered event type, shared between producers and the dashboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTypeDefinition {
+    pub name: String,
+    pub display_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_severity: Option<SeverityLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl EventTypeDefinition {
+    /// Creates a new event type definition.
+    pub fn new(name: impl Into<String>, display_name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            display_name: display_name.into(),
+            description: None,
+            default_severity: None,
+            icon: None,
+        }
+    }
+
+    /// Sets the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the default severity.
+    pub fn with_default_severity(mut self, severity: SeverityLevel) -> Self {
+        self.default_severity = Some(severity);
+        self
+    }
+
+    /// Sets the default icon.
+    pub fn with_icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+}
@@ -0,0 +1,143 @@
+//! Process-wide client for IronNotify SDK.
+
+use crate::builder::EventBuilder;
+use crate::client::NotifyClient;
+use crate::config::NotifyOptions;
+use crate::error::Error;
+use crate::metrics::MetricsSnapshot;
+use crate::types::{ConnectionState, Notification, SendResult};
+use once_cell::sync::OnceCell;
+
+static GLOBAL_CLIENT: OnceCell<NotifyClient> = OnceCell::new();
+
+/// Initializes the global client with an API key.
+pub fn init(api_key: impl Into<String>) -> Result<(), Error> {
+    init_with_options(NotifyOptions::new(api_key))
+}
+
+/// Initializes the global client with options.
+pub fn init_with_options(options: NotifyOptions) -> Result<(), Error> {
+    let client = NotifyClient::new(options)?;
+    GLOBAL_CLIENT
+        .set(client)
+        .map_err(|_| Error::AlreadyInitialized)
+}
+
+/// Initializes the global client with options, verifying credentials first
+/// when `verify_on_init` is set.
+///
+/// A rejected API key fails with `Error::InvalidApiKey`.
+pub async fn init_async(options: NotifyOptions) -> Result<(), Error> {
+    let verify = options.verify_on_init;
+    let client = NotifyClient::new(options)?;
+
+    if verify {
+        client.verify_credentials().await?;
+    }
+
+    GLOBAL_CLIENT
+        .set(client)
+        .map_err(|_| Error::AlreadyInitialized)
+}
+
+/// Gets the global client.
+pub fn get_client() -> Result<&'static NotifyClient, Error> {
+    GLOBAL_CLIENT.get().ok_or(Error::NotInitialized)
+}
+
+/// Sends a notification using the global client.
+pub async fn notify(
+    event_type: impl Into<String>,
+    title: impl Into<String>,
+) -> Result<SendResult, Error> {
+    let client = get_client()?;
+    Ok(client.notify(event_type, title).await)
+}
+
+/// Sends a notification if the global client has been initialized.
+///
+/// Returns `Ok(None)` instead of an error when it has not, for libraries that only
+/// notify when the host application sets up IronNotify.
+pub async fn try_notify(
+    event_type: impl Into<String>,
+    title: impl Into<String>,
+) -> Result<Option<SendResult>, Error> {
+    match GLOBAL_CLIENT.get() {
+        Some(client) => Ok(Some(client.notify(event_type, title).await)),
+        None => Ok(None),
+    }
+}
+
+/// Creates an event builder using the global client.
+pub fn event(event_type: impl Into<String>) -> Result<EventBuilder, Error> {
+    let client = get_client()?;
+    Ok(client.event(event_type))
+}
+
+/// Gets notifications using the global client.
+pub async fn get_notifications(
+    limit: Option<i32>,
+    offset: Option<i32>,
+    unread_only: bool,
+) -> Result<Vec<Notification>, Error> {
+    let client = get_client()?;
+    client
+        .get_notifications(limit, offset, unread_only)
+        .await
+        .map_err(Error::Transport)
+}
+
+/// Gets the unread count using the global client.
+pub async fn get_unread_count() -> Result<i32, Error> {
+    let client = get_client()?;
+    client.get_unread_count().await.map_err(Error::Transport)
+}
+
+/// Marks a notification as read using the global client.
+pub async fn mark_as_read(notification_id: &str) -> Result<bool, Error> {
+    let client = get_client()?;
+    client
+        .mark_as_read(notification_id)
+        .await
+        .map_err(Error::Transport)
+}
+
+/// Marks all notifications as read using the global client.
+pub async fn mark_all_as_read() -> Result<bool, Error> {
+    let client = get_client()?;
+    client.mark_all_as_read().await.map_err(Error::Transport)
+}
+
+/// Flushes the offline queue using the global client.
+pub async fn flush() -> Result<(), Error> {
+    let client = get_client()?;
+    client.flush().await;
+    Ok(())
+}
+
+/// Gets the connection state of the global client.
+pub fn connection_state() -> Result<ConnectionState, Error> {
+    Ok(get_client()?.connection_state())
+}
+
+/// Connects the global client to real-time notifications.
+pub fn connect() -> Result<(), Error> {
+    get_client()?.connect();
+    Ok(())
+}
+
+/// Disconnects the global client from real-time notifications.
+pub fn disconnect() -> Result<(), Error> {
+    get_client()?.disconnect();
+    Ok(())
+}
+
+/// Gets the number of notifications in the global client's offline queue.
+pub fn queue_size() -> Result<usize, Error> {
+    Ok(get_client()?.queue_size())
+}
+
+/// Gets a snapshot of the global client's request metrics.
+pub fn metrics() -> Result<MetricsSnapshot, Error> {
+    Ok(get_client()?.metrics())
+}
@@ -0,0 +1,105 @@
+//! Conditional request cache for IronNotify SDK.
+
+use crate::types::Notification;
+use parking_lot::Mutex;
+use reqwest::header::{HeaderMap, HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use std::collections::{HashMap, VecDeque};
+
+/// A parsed response body that can be returned on `304 Not Modified`.
+#[derive(Clone)]
+pub(crate) enum CachedValue {
+    Notifications(Vec<Notification>),
+    Count(i32),
+}
+
+struct CacheEntry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    value: CachedValue,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CacheEntry>,
+    order: VecDeque<String>,
+}
+
+/// Bounded cache of validators and parsed responses, keyed by request URL.
+pub(crate) struct HttpCache {
+    max_entries: usize,
+    state: Mutex<CacheState>,
+}
+
+impl HttpCache {
+    /// Creates a cache holding at most `max_entries` distinct URLs.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Adds conditional request headers for a previously cached URL.
+    pub fn apply(&self, key: &str, mut request: RequestBuilder) -> RequestBuilder {
+        let state = self.state.lock();
+        if let Some(entry) = state.entries.get(key) {
+            if let Some(ref etag) = entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(ref last_modified) = entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        request
+    }
+
+    /// Gets the cached value for a URL.
+    pub fn get(&self, key: &str) -> Option<CachedValue> {
+        self.state.lock().entries.get(key).map(|entry| entry.value.clone())
+    }
+
+    /// Stores a parsed response if it carries validators, evicting the oldest entry when full.
+    pub fn store(&self, key: &str, headers: &HeaderMap, value: CachedValue) {
+        let header = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        if etag.is_none() && last_modified.is_none() {
+            return;
+        }
+
+        let mut state = self.state.lock();
+        if !state.entries.contains_key(key) {
+            while state.entries.len() >= self.max_entries {
+                match state.order.pop_front() {
+                    Some(oldest) => {
+                        state.entries.remove(&oldest);
+                    }
+                    None => break,
+                }
+            }
+            state.order.push_back(key.to_string());
+        }
+        state.entries.insert(
+            key.to_string(),
+            CacheEntry {
+                etag,
+                last_modified,
+                value,
+            },
+        );
+    }
+
+    /// Removes all cached entries.
+    pub fn clear(&self) {
+        let mut state = self.state.lock();
+        state.entries.clear();
+        state.order.clear();
+    }
+}
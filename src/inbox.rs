@@ -0,0 +1,164 @@
+//! Inbox badge summaries for IronNotify SDK.
+
+use crate::error::Error;
+use crate::types::{ConnectionState, Notification, SeverityLevel};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Maximum number of users and preview sizes kept in the summary cache.
+const INBOX_CACHE_MAX_ENTRIES: usize = 64;
+
+/// What an inbox badge shows, from `inbox_summary`.
+#[derive(Debug, Clone)]
+pub struct InboxSummary {
+    /// Number of unread notifications, or `None` if it could not be fetched.
+    pub unread_count: Option<i32>,
+    /// The newest unread notifications, up to the requested preview count.
+    pub preview: Vec<Notification>,
+    /// Highest severity among the unread notifications. When the server cannot group
+    /// counts by severity, only the preview is considered.
+    pub highest_unread_severity: Option<SeverityLevel>,
+    /// State of the real-time connection when the summary was returned.
+    pub connection_state: ConnectionState,
+    /// When the summary was fetched from the server.
+    pub fetched_at: DateTime<Utc>,
+    /// Whether a request failed, leaving some fields empty.
+    pub partial: bool,
+    /// The first error of a partial summary.
+    pub error: Option<Error>,
+}
+
+type CacheKey = (Option<String>, usize);
+
+/// Complete summaries, keyed by user and preview count, kept for a short time.
+pub(crate) struct InboxCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, InboxSummary>>,
+}
+
+impl InboxCache {
+    /// Creates a cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gets the cached summary for a user and preview count if it has not expired.
+    pub fn get(
+        &self,
+        user_id: Option<&str>,
+        preview_count: usize,
+        now: DateTime<Utc>,
+    ) -> Option<InboxSummary> {
+        let key = (user_id.map(str::to_string), preview_count);
+        let entries = self.entries.lock();
+        let summary = entries.get(&key)?;
+        let age = (now - summary.fetched_at).to_std().unwrap_or_default();
+        (age <= self.ttl).then(|| summary.clone())
+    }
+
+    /// Stores a complete summary, dropping expired entries, and the oldest one when
+    /// full.
+    pub fn store(&self, user_id: Option<&str>, preview_count: usize, summary: &InboxSummary) {
+        let now = summary.fetched_at;
+        let mut entries = self.entries.lock();
+        entries.retain(|_, entry| {
+            (now - entry.fetched_at).to_std().unwrap_or_default() <= self.ttl
+        });
+        let key = (user_id.map(str::to_string), preview_count);
+        if !entries.contains_key(&key) && entries.len() >= INBOX_CACHE_MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, summary.clone());
+    }
+
+    /// Drops every cached summary, e.g. after notifications were marked as read.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+/// Gets the highest severity with unread notifications from counts grouped by
+/// severity, or `None` if no group names a severity.
+pub(crate) fn highest_severity(counts: &HashMap<String, i32>) -> Option<SeverityLevel> {
+    counts
+        .iter()
+        .filter(|(_, count)| **count > 0)
+        .filter_map(|(severity, _)| severity.parse::<SeverityLevel>().ok())
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, second).unwrap()
+    }
+
+    fn summary(unread: i32, fetched_at: DateTime<Utc>) -> InboxSummary {
+        InboxSummary {
+            unread_count: Some(unread),
+            preview: Vec::new(),
+            highest_unread_severity: None,
+            connection_state: ConnectionState::Disconnected,
+            fetched_at,
+            partial: false,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn summaries_are_cached_per_user_and_preview_count_until_they_expire() {
+        let cache = InboxCache::new(Duration::from_secs(10));
+        cache.store(Some("alice"), 3, &summary(4, at(0)));
+        assert_eq!(cache.get(Some("alice"), 3, at(10)).unwrap().unread_count, Some(4));
+        assert!(cache.get(Some("alice"), 3, at(11)).is_none());
+        assert!(cache.get(Some("alice"), 5, at(1)).is_none());
+        assert!(cache.get(None, 3, at(1)).is_none());
+
+        cache.clear();
+        assert!(cache.get(Some("alice"), 3, at(1)).is_none());
+    }
+
+    #[test]
+    fn a_full_cache_drops_its_oldest_summary() {
+        let cache = InboxCache::new(Duration::from_secs(600));
+        let later = |seconds: usize| at(0) + chrono::Duration::seconds(seconds as i64);
+        for user in 0..INBOX_CACHE_MAX_ENTRIES {
+            cache.store(Some(user.to_string().as_str()), 1, &summary(1, later(user)));
+        }
+        let now = later(INBOX_CACHE_MAX_ENTRIES);
+        cache.store(Some("new"), 1, &summary(1, now));
+        assert!(cache.get(Some("0"), 1, now).is_none());
+        assert!(cache.get(Some("1"), 1, now).is_some());
+        assert!(cache.get(Some("new"), 1, now).is_some());
+
+        // Storing a cached key again replaces it without evicting another.
+        cache.store(Some("new"), 1, &summary(2, now));
+        assert!(cache.get(Some("1"), 1, now).is_some());
+        assert_eq!(cache.get(Some("new"), 1, now).unwrap().unread_count, Some(2));
+    }
+
+    #[test]
+    fn the_highest_severity_comes_from_groups_with_unread_notifications() {
+        let counts = |groups: &[(&str, i32)]| -> HashMap<String, i32> {
+            groups.iter().map(|&(name, count)| (name.to_string(), count)).collect()
+        };
+        let grouped = counts(&[("info", 3), ("Error", 1), ("critical", 0), ("other", 9)]);
+        assert_eq!(highest_severity(&grouped), Some(SeverityLevel::Error));
+        assert_eq!(highest_severity(&counts(&[("total", 4)])), None);
+        assert_eq!(highest_severity(&HashMap::new()), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Persistent send journal for IronNotify SDK.
+
+use crate::rotation::{read_lines, RotatingFile};
+use crate::types::{NotificationPayload, SendResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Outcome of a journaled send attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalOutcome {
+    Sent,
+    Queued,
+    Failed,
+}
+
+/// A single journaled send attempt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub event_type: String,
+    #[serde(rename = "dedup_key", skip_serializing_if = "Option::is_none", default)]
+    pub deduplication_key: Option<String>,
+    pub outcome: JournalOutcome,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub notification_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+    /// Delivery attempt number. Retries from the offline queue are counted from 2.
+    pub attempt: u32,
+}
+
+impl JournalEntry {
+    /// Creates an entry describing the outcome of sending a payload.
+    pub fn from_result(payload: &NotificationPayload, result: &SendResult, attempt: u32) -> Self {
+        let outcome = if result.success {
+            JournalOutcome::Sent
+        } else if result.queued {
+            JournalOutcome::Queued
+        } else {
+            JournalOutcome::Failed
+        };
+
+        Self {
+            timestamp: Utc::now(),
+            event_type: payload.event_type.clone(),
+            deduplication_key: payload.deduplication_key.clone(),
+            outcome,
+            notification_id: result.notification_id.clone(),
+            error: result.error.clone(),
+            attempt,
+        }
+    }
+}
+
+/// Append-only journal of send attempts, written by a background thread.
+///
+/// Entries still in flight when the process crashes may be lost, but each line is
+/// written whole so the file is never left with interleaved records. Partially
+/// written trailing lines are skipped when reading.
+pub struct SendJournal {
+    sender: mpsc::UnboundedSender<JournalEntry>,
+    path: PathBuf,
+    max_files: usize,
+}
+
+impl SendJournal {
+    /// Opens a journal at `path`, rotating once the file reaches `max_bytes`
+    /// and keeping at most `max_files` rotated files.
+    pub fn open(path: PathBuf, max_bytes: u64, max_files: usize) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<JournalEntry>();
+
+        let mut writer = RotatingFile::new(path.clone(), max_bytes, max_files);
+
+        std::thread::Builder::new()
+            .name("ironnotify-journal".to_string())
+            .spawn(move || {
+                while let Some(entry) = receiver.blocking_recv() {
+                    if let Ok(mut line) = serde_json::to_string(&entry) {
+                        line.push('\n');
+                        writer.write_line(&line);
+                    }
+                }
+            })
+            .expect("Failed to spawn journal writer thread");
+
+        Self {
+            sender,
+            path,
+            max_files,
+        }
+    }
+
+    /// Records an entry without blocking the caller.
+    pub fn record(&self, entry: JournalEntry) {
+        let _ = self.sender.send(entry);
+    }
+
+    /// Reads entries at or after `since`, oldest first.
+    pub fn entries_since(&self, since: DateTime<Utc>) -> Vec<JournalEntry> {
+        read_lines(&self.path, self.max_files)
+            .iter()
+            .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+            .filter(|entry| entry.timestamp >= since)
+            .collect()
+    }
+}
@@ -2,6 +2,10 @@
 //!
 //! Event notifications and alerts SDK for Rust applications.
 //!
+//! The global client (`init`, `notify`, and the other free functions) is
+//! available with the default `global-client` feature. Build with
+//! `default-features = false` to use only [`NotifyClient`] instances.
+//!
 //! # Quick Start
 //!
 //! ```rust,no_run
@@ -40,7 +44,7 @@
 //!         .with_metadata("order_id", "1234")
 //!         .with_url_action("Retry Payment", "/orders/1234/retry")
 //!         .for_user("user-123")
-//!         .expires_in_std(Duration::from_secs(86400))
+//!         .expires_in(Duration::from_secs(86400))
 //!         .send()
 //!         .await;
 //!
@@ -50,88 +54,211 @@
 //! }
 //! ```
 
+mod api_version;
+mod auth;
 mod builder;
+mod catalog;
+#[cfg(any(test, feature = "chaos"))]
+mod chaos;
+mod checkpoint;
+mod circuit;
 mod client;
+mod clock;
+mod codec;
 mod config;
+mod debug;
+mod decay;
+#[cfg(feature = "desktop")]
+pub mod desktop;
+mod delivery;
+mod diff;
+mod digest;
+mod drops;
+mod error;
+mod event_types;
+#[cfg(feature = "global-client")]
+mod global;
+mod hash;
+mod http_cache;
+mod inbox;
+#[cfg(feature = "journal")]
+mod journal;
+mod limiter;
+mod limits;
+mod metrics;
+mod multipart;
+mod mutations;
+mod ordering;
+mod overflow;
+mod pager;
+mod pinning;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod process;
+mod query;
 mod queue;
+#[cfg(feature = "quiet-hours")]
+mod quiet_hours;
+mod rate_limit;
+mod read_cache;
+mod realtime;
+mod reconfigure;
+#[cfg(feature = "record")]
+mod record;
+mod redact;
+#[cfg(feature = "record")]
+pub mod replay;
+#[cfg(feature = "cli-render")]
+mod render;
+mod resolver;
+pub mod rollout;
+#[cfg(any(feature = "journal", feature = "record"))]
+mod rotation;
+mod runtime;
+#[cfg(feature = "schema")]
+mod schema;
+#[cfg(feature = "tower")]
+mod service;
+mod search;
+mod snapshot;
+mod state;
+mod storage;
+mod store;
+mod tenant;
+#[cfg(test)]
+mod test_support;
+pub mod text;
+#[cfg(feature = "otel")]
+mod trace_context;
+#[cfg(feature = "tracing-layer")]
+mod tracing_layer;
 mod transport;
 mod types;
+mod websocket;
 
-pub use builder::EventBuilder;
+pub use api_version::ApiVersion;
+pub use auth::{AuthFailure, AuthFailureCallback, AuthStatus, AuthToken, TokenProvider};
+pub use builder::{EventBuilder, PayloadBuilder, ValidationIssue};
+pub use catalog::{CatalogEntry, NotificationCatalog, ParamType};
+#[cfg(any(test, feature = "chaos"))]
+pub use chaos::{Chaos, ChaosScope};
+pub use checkpoint::{CheckpointBackend, CheckpointStore, FileCheckpoint, NotificationCursor};
+pub use circuit::CircuitState;
 pub use client::NotifyClient;
-pub use config::{NotifyOptions, NotifyOptionsBuilder};
+pub use codec::WireFormat;
+pub use config::{
+    NotifyOptions, NotifyOptionsBuilder, OptionsError, OptionsIssue, DEFAULT_INBOX_SUMMARY_TTL,
+    MAX_OFFLINE_QUEUE_SIZE, MIN_RECONNECT_DELAY,
+};
+pub use debug::{DebugConfig, DebugLevel};
+pub use decay::{DELAYED_SECONDS_KEY, ORIGINAL_SEVERITY_KEY};
+pub use delivery::{ReadReceipt, ReadReceiptCallback, SendResultCallback};
+pub use diff::{diff_notifications, group_by_category, NotificationDiff};
+pub use digest::{DigestBuilder, DigestFormatter, DigestItem};
+pub use drops::{DropReason, DroppedNotification, DroppedNotificationCallback};
+pub use error::{Error, ResponseError, TimeoutPhase};
+pub use event_types::EventTypeDefinition;
+#[cfg(feature = "global-client")]
+pub use global::{
+    connect, connection_state, disconnect, event, flush, get_client, get_notifications,
+    get_unread_count, init, init_async, init_with_options, mark_all_as_read, mark_as_read,
+    metrics, notify, queue_size, try_notify,
+};
+pub use inbox::InboxSummary;
+#[cfg(feature = "journal")]
+pub use journal::{JournalEntry, JournalOutcome, SendJournal};
+pub use limits::{LimitField, LimitWarning, LimitWarningCallback, SoftLimits};
+pub use metrics::{LatencyHistogram, MetricsSnapshot, SlowRequest, TenantMetrics};
+pub use mutations::{Mutation, PendingMutation};
+pub use overflow::{OverflowStrategy, ATTACHMENT_KEY};
+pub use pager::{Cursor, Pager, DEFAULT_MAX_PAGES};
+pub use pinning::PinSet;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresQueueStorage;
+pub use process::{ProcessErrorPolicy, ProcessOptions, ProcessSummary};
+pub use query::{CountGroupBy, NotificationQuery};
+pub use queue::{
+    CompactionReport, DeferredNotification, EnqueueReceipt, QueueStats, QueuedNotification,
+};
+#[cfg(feature = "quiet-hours")]
+pub use quiet_hours::QuietHours;
+pub use rate_limit::{RateLimitCallback, RateLimitStatus};
+pub use read_cache::CachedResult;
+pub use realtime::{GapCallback, RealtimeCallback, RealtimeMessage, REALTIME_PROTOCOL_VERSION};
+pub use reconfigure::{ReconfiguredCallback, RuntimeConfig};
+#[cfg(feature = "record")]
+pub use record::RecordedExchange;
+pub use redact::{Redactor, REDACTED};
+#[cfg(feature = "cli-render")]
+pub use render::{relative_time, render_list, render_notification, RenderOptions, RenderStyle};
+pub use resolver::{UserContext, RECIPIENT_METADATA_KEY};
+#[cfg(feature = "rt-async-std")]
+pub use runtime::AsyncStdRuntime;
+#[cfg(feature = "rt-tokio")]
+pub use runtime::TokioRuntime;
+pub use runtime::{BoxFuture, Interval, Runtime};
+#[cfg(feature = "schema")]
+pub use schema::MetadataSchemas;
+#[cfg(feature = "tower")]
+pub use service::{NotifyService, OfflineQueueLayer, OfflineQueueService};
+pub use search::SearchHit;
+pub use snapshot::{NotificationSnapshot, SNAPSHOT_VERSION};
+pub use store::{
+    GroupReadState, NotificationStore, StoreChange, StoreChangeKind, DEFAULT_TOMBSTONE_CAPACITY,
+};
+pub use tenant::TenantScopedClient;
+#[cfg(feature = "otel")]
+pub use trace_context::TRACING_METADATA_KEY;
+#[cfg(feature = "tracing-layer")]
+pub use tracing_layer::IronNotifyLayer;
 pub use types::{
-    ConnectionState, Notification, NotificationAction, NotificationPayload, SendResult,
-    SeverityLevel,
+    Attachment, ConnectionState, DeliveryChannels, DownloadInfo, ExpiresIn, GroupBehavior, KeyEnvironment,
+    LocalEvent, Notification, NotificationAction, NotificationEvent, NotificationPayload,
+    NotificationRef, ProjectInfo, QueueReason, SendResult, SeverityLevel,
 };
 
-use once_cell::sync::OnceCell;
-use std::sync::Arc;
-
-static GLOBAL_CLIENT: OnceCell<Arc<NotifyClient>> = OnceCell::new();
-
-/// Initializes the global client with an API key.
-pub fn init(api_key: impl Into<String>) -> Result<(), &'static str> {
-    init_with_options(NotifyOptions::new(api_key))
-}
-
-/// Initializes the global client with options.
-pub fn init_with_options(options: NotifyOptions) -> Result<(), &'static str> {
-    let client = NotifyClient::new(options)?;
-    GLOBAL_CLIENT.set(client).map_err(|_| "Already initialized")
-}
-
-/// Gets the global client.
-pub fn get_client() -> Result<&'static Arc<NotifyClient>, &'static str> {
-    GLOBAL_CLIENT.get().ok_or("Not initialized. Call init() first.")
-}
-
-/// Sends a notification using the global client.
-pub async fn notify(
-    event_type: impl Into<String>,
-    title: impl Into<String>,
-) -> Result<SendResult, &'static str> {
-    let client = get_client()?;
-    Ok(client.notify(event_type, title).await)
-}
-
-/// Creates an event builder using the global client.
-pub fn event(event_type: impl Into<String>) -> Result<EventBuilder, &'static str> {
-    let client = get_client()?;
-    Ok(client.event(event_type))
-}
+/// Token for cancelling the `*_with_cancel` methods, re-exported from `tokio-util`.
+pub use tokio_util::sync::CancellationToken;
 
-/// Gets notifications using the global client.
-pub async fn get_notifications(
-    limit: Option<i32>,
-    offset: Option<i32>,
-    unread_only: bool,
-) -> Result<Vec<Notification>, String> {
-    let client = get_client().map_err(|e| e.to_string())?;
-    client.get_notifications(limit, offset, unread_only).await
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, MockResponse, MockServer};
+    use serde_json::json;
 
-/// Gets the unread count using the global client.
-pub async fn get_unread_count() -> Result<i32, String> {
-    let client = get_client().map_err(|e| e.to_string())?;
-    client.get_unread_count().await
-}
+    fn server() -> MockServer {
+        MockServer::start(|_| MockResponse::json(200, json!({ "notificationId": "n1" })))
+    }
 
-/// Marks a notification as read using the global client.
-pub async fn mark_as_read(notification_id: &str) -> Result<bool, String> {
-    let client = get_client().map_err(|e| e.to_string())?;
-    client.mark_as_read(notification_id).await
-}
+    #[cfg(feature = "global-client")]
+    #[test]
+    fn the_global_client_sends_once_initialized() {
+        let _storage = test_support::TempStorage::new();
+        let server = server();
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(matches!(notify("e", "t").await, Err(Error::NotInitialized)));
+            assert!(try_notify("e", "t").await.unwrap().is_none());
 
-/// Marks all notifications as read using the global client.
-pub async fn mark_all_as_read() -> Result<bool, String> {
-    let client = get_client().map_err(|e| e.to_string())?;
-    client.mark_all_as_read().await
-}
+            init_with_options(test_support::options(&server)).unwrap();
+            assert!(notify("e", "t").await.unwrap().success);
+            assert!(matches!(
+                init_with_options(test_support::options(&server)),
+                Err(Error::AlreadyInitialized)
+            ));
+        });
+        assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+    }
 
-/// Flushes the offline queue using the global client.
-pub async fn flush() -> Result<(), &'static str> {
-    let client = get_client()?;
-    client.flush().await;
-    Ok(())
+    #[cfg(not(feature = "global-client"))]
+    #[test]
+    fn instances_send_without_the_global_client() {
+        let _storage = test_support::TempStorage::new();
+        let server = server();
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let runtime = test_support::runtime();
+        let result = runtime.block_on(client.event("e").with_title("t").send());
+        assert!(result.success);
+        assert_eq!(server.requests_to("POST", "/api/v1/notify").len(), 1);
+    }
 }
@@ -0,0 +1,158 @@
+//! Request concurrency limits for IronNotify SDK.
+
+use crate::error::Error;
+use crate::runtime::{timeout, Runtime};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Caps the number of concurrent requests in one endpoint category.
+///
+/// Requests over the limit wait for a permit. With an acquisition timeout, waiting
+/// longer than it fails with `Error::Saturated`.
+pub(crate) struct ConcurrencyLimit {
+    category: &'static str,
+    semaphore: Option<Semaphore>,
+    timeout: Option<Duration>,
+    runtime: Arc<dyn Runtime>,
+    in_flight: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// A held request slot, released on drop.
+pub(crate) struct Permit<'a> {
+    _permit: Option<SemaphorePermit<'a>>,
+    in_flight: &'a AtomicUsize,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ConcurrencyLimit {
+    /// Creates a limit of `max` concurrent requests. `None` only tracks in-flight counts.
+    pub fn new(
+        category: &'static str,
+        max: Option<usize>,
+        timeout: Option<Duration>,
+        runtime: Arc<dyn Runtime>,
+    ) -> Self {
+        Self {
+            category,
+            semaphore: max.map(|max| Semaphore::new(max.max(1))),
+            timeout,
+            runtime,
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Waits for a request slot.
+    pub async fn acquire(&self) -> Result<Permit<'_>, Error> {
+        let permit = match self.semaphore {
+            Some(ref semaphore) => {
+                let acquired = match self.timeout {
+                    Some(duration) => timeout(&*self.runtime, duration, semaphore.acquire())
+                        .await
+                        .ok_or(Error::Saturated(self.category))?,
+                    None => semaphore.acquire().await,
+                };
+                Some(acquired.map_err(|_| Error::Saturated(self.category))?)
+            }
+            None => None,
+        };
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(in_flight, Ordering::SeqCst);
+        Ok(Permit {
+            _permit: permit,
+            in_flight: &self.in_flight,
+        })
+    }
+
+    /// Gets the number of requests currently holding a slot.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Gets the highest number of concurrent requests seen.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::auth::{AuthToken, TokenProvider};
+    use crate::runtime::BoxFuture;
+    use crate::test_support::{self, MockResponse, MockServer};
+    use crate::{NotificationPayload, NotifyClient};
+    use chrono::Utc;
+    use parking_lot::Mutex;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// Mints `tok-0`, `tok-1`, and so on.
+    #[derive(Debug, Default)]
+    struct CountingTokens(AtomicUsize);
+
+    impl TokenProvider for CountingTokens {
+        fn token(&self) -> BoxFuture<Result<AuthToken, String>> {
+            let token = format!("tok-{}", self.0.fetch_add(1, Ordering::SeqCst));
+            let expires_at = Utc::now() + chrono::Duration::hours(1);
+            Box::pin(async move { Ok(AuthToken::new(token, expires_at)) })
+        }
+    }
+
+    #[test]
+    fn the_send_cap_holds_across_a_retried_request() {
+        const LATENCY: Duration = Duration::from_millis(100);
+
+        let arrivals = Arc::new(Mutex::new(Vec::new()));
+        let server = MockServer::start({
+            let arrivals = Arc::clone(&arrivals);
+            move |request| {
+                arrivals.lock().push(Instant::now());
+                // The first token is rejected, so the first send is retried.
+                let status = match request.headers.get("authorization") {
+                    Some(token) if token == "Bearer tok-0" => 401,
+                    _ => 200,
+                };
+                MockResponse::json(status, json!({ "notificationId": "n1" })).delayed(LATENCY)
+            }
+        });
+        let mut options = test_support::options(&server);
+        options.api_key = String::new();
+        options.enable_offline_queue = false;
+        options.max_concurrent_sends = Some(1);
+        options.token_provider = Some(Arc::new(CountingTokens::default()));
+        let client = NotifyClient::new(options).unwrap();
+
+        let runtime = test_support::runtime();
+        let sent = runtime.block_on(async {
+            let payload = NotificationPayload::new("e", "t");
+            tokio::join!(
+                client.send_payload(&payload),
+                client.send_payload(&payload),
+                client.send_payload(&payload),
+            )
+        });
+        for result in [sent.0, sent.1, sent.2] {
+            assert!(result.success, "{:?}", result.error);
+        }
+
+        // With one send at a time, each request starts after the previous one was
+        // answered, the retry included.
+        let arrivals = arrivals.lock();
+        assert_eq!(arrivals.len(), 4);
+        for pair in arrivals.windows(2) {
+            assert!(pair[1] - pair[0] >= LATENCY, "{:?}", pair[1] - pair[0]);
+        }
+        assert_eq!(client.metrics().peak_in_flight_sends, 1);
+    }
+}
@@ -0,0 +1,227 @@
+//! Soft limit warnings for IronNotify SDK.
+
+use crate::config::NotifyOptions;
+use crate::types::NotificationPayload;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Callback invoked when a payload comes close to a configured limit.
+pub type LimitWarningCallback = Arc<dyn Fn(&LimitWarning) + Send + Sync>;
+
+/// A limit a payload can approach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitField {
+    /// The serialized size, limited by `max_payload_bytes`.
+    PayloadBytes,
+    /// The number of metadata entries, limited by `max_metadata_keys`.
+    MetadataKeys,
+    /// The number of actions, limited by `max_actions`.
+    Actions,
+}
+
+impl std::fmt::Display for LimitField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::PayloadBytes => "payload bytes",
+            Self::MetadataKeys => "metadata keys",
+            Self::Actions => "actions",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A payload that reached the soft limit of a field. It is still sent.
+#[derive(Debug, Clone)]
+pub struct LimitWarning {
+    /// Event type of the payload.
+    pub event_type: String,
+    /// The field that is close to its limit.
+    pub field: LimitField,
+    /// The payload's value for the field.
+    pub current: usize,
+    /// The hard limit of the field.
+    pub limit: usize,
+}
+
+/// Thresholds at which limit warnings are raised, overriding the ones derived from
+/// the hard limits with `soft_limit_ratio`.
+#[derive(Debug, Clone, Default)]
+pub struct SoftLimits {
+    /// Payload size in bytes.
+    pub payload_bytes: Option<usize>,
+    /// Number of metadata entries.
+    pub metadata_keys: Option<usize>,
+    /// Number of actions.
+    pub actions: Option<usize>,
+}
+
+/// Checks payloads against the soft limits, warning at most once per interval for
+/// each event type and field.
+pub(crate) struct LimitMonitor {
+    thresholds: Vec<(LimitField, usize, usize)>,
+    interval: Duration,
+    warned: Mutex<HashMap<(String, LimitField), Instant>>,
+    callback: RwLock<Option<LimitWarningCallback>>,
+}
+
+impl LimitMonitor {
+    /// Creates a monitor for the limits configured in the options.
+    pub fn new(options: &NotifyOptions) -> Self {
+        let soft = &options.soft_limits;
+        let limits = [
+            (LimitField::PayloadBytes, Some(options.max_payload_bytes), soft.payload_bytes),
+            (LimitField::MetadataKeys, options.max_metadata_keys, soft.metadata_keys),
+            (LimitField::Actions, options.max_actions, soft.actions),
+        ];
+        let thresholds = limits
+            .into_iter()
+            .filter_map(|(field, limit, threshold)| {
+                let limit = limit?;
+                let threshold = threshold.unwrap_or_else(|| {
+                    (limit as f64 * options.soft_limit_ratio).ceil() as usize
+                });
+                Some((field, threshold, limit))
+            })
+            .collect();
+
+        Self {
+            thresholds,
+            interval: options.limit_warning_interval,
+            warned: Mutex::new(HashMap::new()),
+            callback: RwLock::new(None),
+        }
+    }
+
+    /// Sets the limit warning callback.
+    pub fn set_callback(&self, callback: LimitWarningCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    /// Warns about every field of the payload at or above its soft limit.
+    pub fn check(&self, payload: &NotificationPayload) {
+        for &(field, threshold, limit) in &self.thresholds {
+            let current = match field {
+                LimitField::PayloadBytes => payload.estimated_wire_size(),
+                LimitField::MetadataKeys => payload.metadata.as_ref().map_or(0, |m| m.len()),
+                LimitField::Actions => payload.actions.as_ref().map_or(0, |a| a.len()),
+            };
+            if current < threshold || !self.should_warn(&payload.event_type, field) {
+                continue;
+            }
+
+            eprintln!(
+                "[IronNotify] Warning: {} notification has {} {}, close to the limit of {}",
+                payload.event_type, current, field, limit
+            );
+            let callback = self.callback.read().clone();
+            if let Some(callback) = callback {
+                callback(&LimitWarning {
+                    event_type: payload.event_type.clone(),
+                    field,
+                    current,
+                    limit,
+                });
+            }
+        }
+    }
+
+    /// Checks whether the interval has passed since the last warning for the event
+    /// type and field, recording the warning if so.
+    fn should_warn(&self, event_type: &str, field: LimitField) -> bool {
+        let now = Instant::now();
+        let mut warned = self.warned.lock();
+        let key = (event_type.to_string(), field);
+        if warned
+            .get(&key)
+            .is_some_and(|last| now.duration_since(*last) < self.interval)
+        {
+            return false;
+        }
+        warned.insert(key, now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::NotificationAction;
+
+    type Warnings = Arc<Mutex<Vec<LimitWarning>>>;
+
+    fn monitored(change: impl FnOnce(&mut NotifyOptions)) -> (LimitMonitor, Warnings) {
+        let mut options = NotifyOptions::new("ak_test_limits");
+        options.max_metadata_keys = Some(10);
+        options.max_actions = Some(4);
+        options.soft_limit_ratio = 0.8;
+        change(&mut options);
+        let monitor = LimitMonitor::new(&options);
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&warnings);
+        monitor.set_callback(Arc::new(move |warning: &LimitWarning| {
+            recorded.lock().push(warning.clone());
+        }));
+        (monitor, warnings)
+    }
+
+    fn with_metadata(event_type: &str, keys: usize) -> NotificationPayload {
+        let mut payload = NotificationPayload::new(event_type, "Title");
+        payload.metadata = Some((0..keys).map(|i| (format!("k{}", i), i.into())).collect());
+        payload
+    }
+
+    fn fields(warnings: &Mutex<Vec<LimitWarning>>) -> Vec<(LimitField, usize, usize)> {
+        warnings
+            .lock()
+            .iter()
+            .map(|warning| (warning.field, warning.current, warning.limit))
+            .collect()
+    }
+
+    #[test]
+    fn warnings_start_at_the_ratio_of_the_hard_limit() {
+        let (monitor, warnings) = monitored(|_| {});
+        monitor.check(&with_metadata("a", 7));
+        assert!(warnings.lock().is_empty());
+        monitor.check(&with_metadata("b", 8));
+        assert_eq!(fields(&warnings), [(LimitField::MetadataKeys, 8, 10)]);
+    }
+
+    #[test]
+    fn soft_limits_override_the_ratio() {
+        let (monitor, warnings) = monitored(|options| {
+            options.soft_limits.metadata_keys = Some(3);
+            options.soft_limits.actions = Some(4);
+        });
+        let mut payload = with_metadata("a", 3);
+        payload.actions = Some(vec![NotificationAction::new("Open"); 3]);
+        monitor.check(&payload);
+        assert_eq!(fields(&warnings), [(LimitField::MetadataKeys, 3, 10)]);
+    }
+
+    #[test]
+    fn fields_without_a_hard_limit_are_not_checked() {
+        let (monitor, warnings) = monitored(|options| options.max_metadata_keys = None);
+        monitor.check(&with_metadata("a", 100));
+        assert!(warnings.lock().is_empty());
+    }
+
+    #[test]
+    fn each_event_type_warns_once_per_interval() {
+        let (monitor, warnings) = monitored(|_| {});
+        monitor.check(&with_metadata("a", 9));
+        monitor.check(&with_metadata("a", 10));
+        monitor.check(&with_metadata("b", 9));
+        let event_types: Vec<String> =
+            warnings.lock().iter().map(|warning| warning.event_type.clone()).collect();
+        assert_eq!(event_types, ["a", "b"]);
+
+        let (monitor, warnings) =
+            monitored(|options| options.limit_warning_interval = Duration::ZERO);
+        monitor.check(&with_metadata("a", 9));
+        monitor.check(&with_metadata("a", 9));
+        assert_eq!(warnings.lock().len(), 2);
+    }
+}
@@ -0,0 +1,308 @@
+//! Request metrics for IronNotify SDK.
+
+use crate::circuit::CircuitState;
+use crate::debug::DebugSwitch;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+const BUCKET_BOUNDS_MS: [u64; 12] = [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000];
+
+/// Callback invoked when a request exceeds the slow request threshold.
+pub type SlowRequestCallback = Arc<dyn Fn(&SlowRequest) + Send + Sync>;
+
+/// Details about a request that exceeded the slow request threshold.
+#[derive(Debug, Clone)]
+pub struct SlowRequest {
+    /// The API endpoint path.
+    pub endpoint: String,
+    /// HTTP status code, or `None` if the request failed before a response arrived.
+    pub status: Option<u16>,
+    /// Total elapsed time of the request.
+    pub elapsed: Duration,
+}
+
+/// Fixed-bucket latency histogram.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Records a single observation.
+    pub fn record(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        let index = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// Gets the number of recorded observations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Gets the largest recorded observation.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Gets the mean of all observations.
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(Duration::from_nanos(
+                (self.total.as_nanos() / self.count as u128) as u64,
+            ))
+        }
+    }
+
+    /// Gets the buckets as `(upper_bound, count)` pairs. The last bucket has no upper bound.
+    pub fn buckets(&self) -> Vec<(Option<Duration>, u64)> {
+        BUCKET_BOUNDS_MS
+            .iter()
+            .map(|&ms| Some(Duration::from_millis(ms)))
+            .chain(std::iter::once(None))
+            .zip(self.buckets.iter().copied())
+            .collect()
+    }
+
+    /// Estimates the given percentile (0.0-1.0) as the upper bound of the bucket containing it.
+    ///
+    /// Observations in the overflow bucket are reported as the maximum recorded latency.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let target = ((self.count as f64) * quantile.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bound, count) in self.buckets() {
+            seen += count;
+            if seen >= target {
+                return Some(bound.unwrap_or(self.max).min(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Point-in-time view of the client's request metrics.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    /// Total number of HTTP requests made.
+    pub requests: u64,
+    /// Requests that failed with a transport error or non-success status.
+    pub failures: u64,
+    /// Requests that exceeded the slow request threshold.
+    pub slow_requests: u64,
+    /// Conditional requests answered from the HTTP cache.
+    pub cache_hits: u64,
+    /// Cacheable requests that returned a fresh response.
+    pub cache_misses: u64,
+    /// Latency across all endpoints.
+    pub latency: LatencyHistogram,
+    /// Latency per endpoint path.
+    pub endpoints: HashMap<String, LatencyHistogram>,
+    /// Current state of the send circuit breaker.
+    pub circuit_state: CircuitState,
+    /// Number of times the circuit breaker has opened.
+    pub circuit_opened: u64,
+    /// Sends and other writes currently in flight.
+    pub in_flight_sends: usize,
+    /// Highest number of concurrent sends and other writes.
+    pub peak_in_flight_sends: usize,
+    /// Reads currently in flight.
+    pub in_flight_reads: usize,
+    /// Highest number of concurrent reads.
+    pub peak_in_flight_reads: usize,
+    /// User resolver calls that failed, so notifications were sent without recipient details.
+    pub resolver_failures: u64,
+    /// Sends made soon enough after another request that they likely reused its
+    /// pooled connection.
+    pub warm_sends: u64,
+    /// Sends made after the pool idle timeout, which likely opened a new connection.
+    pub cold_sends: u64,
+    /// Round-trip time of answered real-time heartbeats.
+    pub heartbeat_rtt: LatencyHistogram,
+    /// Real-time heartbeats left unanswered within `heartbeat_timeout`, each of which
+    /// closed the connection.
+    pub missed_heartbeats: u64,
+}
+
+/// Request metrics of one tenant.
+#[derive(Debug, Clone, Default)]
+pub struct TenantMetrics {
+    /// Number of HTTP requests made for the tenant.
+    pub requests: u64,
+    /// Requests that failed with a transport error or non-success status.
+    pub failures: u64,
+    /// Latency of the tenant's requests.
+    pub latency: LatencyHistogram,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    requests: u64,
+    failures: u64,
+    slow_requests: u64,
+    cache_hits: u64,
+    cache_misses: u64,
+    resolver_failures: u64,
+    warm_sends: u64,
+    cold_sends: u64,
+    missed_heartbeats: u64,
+    heartbeat_rtt: LatencyHistogram,
+    latency: LatencyHistogram,
+    endpoints: HashMap<&'static str, LatencyHistogram>,
+    tenants: HashMap<Arc<str>, TenantMetrics>,
+}
+
+/// Shared metrics recorder.
+pub(crate) struct Metrics {
+    slow_threshold: Duration,
+    debug: DebugSwitch,
+    state: Mutex<MetricsState>,
+    on_slow_request: RwLock<Option<SlowRequestCallback>>,
+}
+
+impl Metrics {
+    /// Creates a new metrics recorder.
+    pub fn new(slow_threshold: Duration, debug: DebugSwitch) -> Self {
+        Self {
+            slow_threshold,
+            debug,
+            state: Mutex::new(MetricsState::default()),
+            on_slow_request: RwLock::new(None),
+        }
+    }
+
+    /// Sets the slow request callback.
+    pub fn set_on_slow_request(&self, callback: SlowRequestCallback) {
+        *self.on_slow_request.write() = Some(callback);
+    }
+
+    /// Records a completed request.
+    pub fn record_request(&self, endpoint: &'static str, status: Option<u16>, elapsed: Duration) {
+        let failed = !matches!(status, Some(code) if (200..300).contains(&code));
+        let slow = elapsed >= self.slow_threshold;
+
+        {
+            let mut state = self.state.lock();
+            state.requests += 1;
+            if failed {
+                state.failures += 1;
+            }
+            if slow {
+                state.slow_requests += 1;
+            }
+            state.latency.record(elapsed);
+            state.endpoints.entry(endpoint).or_default().record(elapsed);
+            if let Some(tenant) = crate::tenant::current() {
+                let tenant = state.tenants.entry(tenant.name).or_default();
+                tenant.requests += 1;
+                if failed {
+                    tenant.failures += 1;
+                }
+                tenant.latency.record(elapsed);
+            }
+        }
+
+        if slow {
+            let info = SlowRequest {
+                endpoint: endpoint.to_string(),
+                status,
+                elapsed,
+            };
+
+            if self.debug.is_enabled() {
+                println!(
+                    "[IronNotify] Warning: slow request to {} (status: {}, elapsed: {:?})",
+                    info.endpoint,
+                    info.status.map_or_else(|| "none".to_string(), |s| s.to_string()),
+                    info.elapsed
+                );
+            }
+
+            let callback = self.on_slow_request.read().clone();
+            if let Some(callback) = callback {
+                callback(&info);
+            }
+        }
+    }
+
+    /// Records an HTTP cache lookup.
+    pub fn record_cache(&self, hit: bool) {
+        let mut state = self.state.lock();
+        if hit {
+            state.cache_hits += 1;
+        } else {
+            state.cache_misses += 1;
+        }
+    }
+
+    /// Records whether a send likely reused a pooled connection.
+    pub fn record_send_connection(&self, warm: bool) {
+        let mut state = self.state.lock();
+        if warm {
+            state.warm_sends += 1;
+        } else {
+            state.cold_sends += 1;
+        }
+    }
+
+    /// Records a failed user resolver call.
+    pub fn record_resolver_failure(&self) {
+        self.state.lock().resolver_failures += 1;
+    }
+
+    /// Records the round-trip time of an answered real-time heartbeat.
+    pub fn record_heartbeat(&self, rtt: Duration) {
+        self.state.lock().heartbeat_rtt.record(rtt);
+    }
+
+    /// Records a real-time heartbeat left unanswered.
+    pub fn record_missed_heartbeat(&self) {
+        self.state.lock().missed_heartbeats += 1;
+    }
+
+    /// Gets the metrics of requests made for a tenant.
+    pub fn tenant(&self, tenant: &str) -> TenantMetrics {
+        self.state.lock().tenants.get(tenant).cloned().unwrap_or_default()
+    }
+
+    /// Takes a snapshot of the current metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let state = self.state.lock();
+        MetricsSnapshot {
+            requests: state.requests,
+            failures: state.failures,
+            slow_requests: state.slow_requests,
+            cache_hits: state.cache_hits,
+            cache_misses: state.cache_misses,
+            resolver_failures: state.resolver_failures,
+            warm_sends: state.warm_sends,
+            cold_sends: state.cold_sends,
+            heartbeat_rtt: state.heartbeat_rtt.clone(),
+            missed_heartbeats: state.missed_heartbeats,
+            latency: state.latency.clone(),
+            endpoints: state
+                .endpoints
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Multipart request bodies for IronNotify SDK.
+
+use crate::hash::sha256_hex;
+use crate::types::Attachment;
+
+/// Name of the part holding the notification body.
+const PAYLOAD_PART: &str = "payload";
+
+/// Name of each part holding an attachment.
+const ATTACHMENT_PART: &str = "attachments";
+
+/// Builds a `multipart/form-data` body with the encoded payload and one part per
+/// attachment, returning its content type and bytes.
+pub(crate) fn form_data(
+    payload_type: &str,
+    payload: &[u8],
+    attachments: &[Attachment],
+) -> (String, Vec<u8>) {
+    let boundary = boundary(payload, attachments);
+    let size = payload.len()
+        + attachments
+            .iter()
+            .map(|attachment| attachment.content.len() + 128)
+            .sum::<usize>();
+    let mut body = Vec::with_capacity(size + 256);
+
+    part_header(&mut body, &boundary, PAYLOAD_PART, None, payload_type);
+    body.extend_from_slice(payload);
+    for attachment in attachments {
+        body.extend_from_slice(b"\r\n");
+        part_header(
+            &mut body,
+            &boundary,
+            ATTACHMENT_PART,
+            Some(&attachment.name),
+            &attachment.content_type,
+        );
+        body.extend_from_slice(&attachment.content);
+    }
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+/// Picks a boundary that does not occur in any part.
+fn boundary(payload: &[u8], attachments: &[Attachment]) -> String {
+    let mut seed = sha256_hex(payload);
+    loop {
+        let boundary = format!("ironnotify-{}", &seed[..32]);
+        let occurs = std::iter::once(payload)
+            .chain(attachments.iter().map(|attachment| attachment.content.as_slice()))
+            .any(|part| contains(part, boundary.as_bytes()));
+        if !occurs {
+            return boundary;
+        }
+        seed = sha256_hex(seed.as_bytes());
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn part_header(
+    body: &mut Vec<u8>,
+    boundary: &str,
+    name: &str,
+    filename: Option<&str>,
+    content_type: &str,
+) {
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"", name).as_bytes());
+    if let Some(filename) = filename {
+        body.extend_from_slice(format!("; filename=\"{}\"", escape(filename)).as_bytes());
+    }
+    body.extend_from_slice(format!("\r\nContent-Type: {}\r\n\r\n", content_type).as_bytes());
+}
+
+/// Percent-encodes the characters that would end a quoted header parameter, as
+/// browsers do for file names.
+fn escape(filename: &str) -> String {
+    filename
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
@@ -0,0 +1,327 @@
+//! Write-behind queue for notification mutations in IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use crate::drops::{DropLog, DropReason, DroppedNotification};
+use crate::queue::merge_saved;
+use crate::storage::JsonFile;
+use crate::types::{KeyEnvironment, Notification};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// A change to one notification's state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Mutation {
+    MarkRead,
+    MarkUnread,
+    Archive,
+    Acknowledge { note: Option<String> },
+}
+
+impl Mutation {
+    /// Gets the path segment of the mutation's endpoint under `notifications/{id}/`.
+    pub(crate) fn action(&self) -> &'static str {
+        match self {
+            Self::MarkRead => "read",
+            Self::MarkUnread => "unread",
+            Self::Archive => "archive",
+            Self::Acknowledge { .. } => "ack",
+        }
+    }
+
+    /// Gets the route recorded in metrics for the mutation's endpoint.
+    pub(crate) fn endpoint(&self) -> &'static str {
+        match self {
+            Self::MarkRead => "/api/v1/notifications/{id}/read",
+            Self::MarkUnread => "/api/v1/notifications/{id}/unread",
+            Self::Archive => "/api/v1/notifications/{id}/archive",
+            Self::Acknowledge { .. } => "/api/v1/notifications/{id}/ack",
+        }
+    }
+
+    /// Applies the mutation to the copy of a notification in a local list.
+    ///
+    /// Archived notifications are removed from the list.
+    pub(crate) fn apply(
+        &self,
+        notification_id: &str,
+        list: &mut Vec<Notification>,
+        now: DateTime<Utc>,
+    ) {
+        if *self == Self::Archive {
+            list.retain(|n| n.id != notification_id);
+            return;
+        }
+        for notification in list.iter_mut().filter(|n| n.id == notification_id) {
+            match self {
+                Self::MarkRead => notification.read = true,
+                Self::MarkUnread => notification.read = false,
+                Self::Acknowledge { .. } => {
+                    notification.acknowledged_at.get_or_insert(now);
+                }
+                Self::Archive => {}
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::MarkRead => "mark read",
+            Self::MarkUnread => "mark unread",
+            Self::Archive => "archive",
+            Self::Acknowledge { .. } => "acknowledge",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A mutation that failed on a network error and waits to be replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingMutation {
+    /// Identifies the entry across processes sharing the mutation file.
+    pub id: u64,
+    pub notification_id: String,
+    #[serde(flatten)]
+    pub mutation: Mutation,
+    pub queued_at: DateTime<Utc>,
+    /// Tenant the mutation was made for with `NotifyClient::with_tenant`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// ID of the tenant the mutation was made for, when its API key was registered
+    /// with `NotifyClient::register_tenant_key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+/// Persisted mutations waiting to be replayed, in the order they were made.
+///
+/// Shares the offline queue's storage and locking, in a separate file per key
+/// environment.
+pub(crate) struct MutationQueue {
+    debug: DebugSwitch,
+    next_id: AtomicU64,
+    pending: Mutex<Vec<PendingMutation>>,
+    storage: JsonFile,
+    saved: AtomicBool,
+    drops: Arc<DropLog>,
+}
+
+impl MutationQueue {
+    /// Creates a queue, loading the mutations saved for the key environment.
+    pub fn new(environment: KeyEnvironment, debug: DebugSwitch, drops: Arc<DropLog>) -> Self {
+        let storage = JsonFile::new(&format!("mutations.{}.json", environment));
+        let pending = storage
+            .read()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+
+        Self {
+            debug,
+            next_id: AtomicU64::new(RandomState::new().build_hasher().finish()),
+            pending: Mutex::new(pending),
+            storage,
+            saved: AtomicBool::new(true),
+            drops,
+        }
+    }
+
+    /// Queues a mutation, unless the notification's last pending mutation is the same.
+    pub fn push(&self, notification_id: &str, mutation: Mutation) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.update(|pending| {
+            let last = pending
+                .iter()
+                .rev()
+                .find(|item| item.notification_id == notification_id);
+            if last.is_some_and(|item| item.mutation == mutation) {
+                return;
+            }
+            let (tenant, tenant_id) = crate::tenant::persisted();
+            pending.push(PendingMutation {
+                id,
+                notification_id: notification_id.to_string(),
+                mutation,
+                queued_at: Utc::now(),
+                tenant,
+                tenant_id,
+            });
+        });
+    }
+
+    /// Checks whether a notification has mutations waiting to be replayed.
+    pub fn has_pending(&self, notification_id: &str) -> bool {
+        self.update(|pending| {
+            pending
+                .iter()
+                .any(|item| item.notification_id == notification_id)
+        })
+    }
+
+    /// Gets the pending mutations, oldest first, including those other processes queued.
+    pub fn pending(&self) -> Vec<PendingMutation> {
+        self.update(|pending| pending.clone())
+    }
+
+    /// Removes a mutation after it was replayed.
+    pub fn remove(&self, id: u64) {
+        self.update(|pending| pending.retain(|item| item.id != id));
+    }
+
+    /// Removes a mutation the server rejected because its notification no longer
+    /// exists, reporting it to the drop callback.
+    pub fn drop_conflict(&self, item: &PendingMutation) {
+        if self.debug.is_enabled() {
+            println!(
+                "[IronNotify] Dropping {} for notification {}: it no longer exists",
+                item.mutation, item.notification_id
+            );
+        }
+        self.remove(item.id);
+        self.drops.record(DroppedNotification::mutation(
+            &item.notification_id,
+            DropReason::Conflict,
+        ));
+    }
+
+    /// Applies the pending mutations to a list fetched from the server, so it shows
+    /// the state the server will have once they are replayed.
+    pub fn overlay(&self, list: &mut Vec<Notification>) {
+        let pending = self.pending.lock();
+        let now = Utc::now();
+        for item in pending.iter() {
+            item.mutation.apply(&item.notification_id, list, now);
+        }
+    }
+
+    /// Runs `f` on the pending mutations while holding the file's lock, after merging
+    /// in changes saved by other processes, then saves the result if it changed.
+    fn update<R>(&self, f: impl FnOnce(&mut Vec<PendingMutation>) -> R) -> R {
+        let lock = self.storage.lock();
+        let mut pending = self.pending.lock();
+        if let Some(lock) = &lock {
+            lock.touch();
+        }
+        let current = self.storage.read();
+        if self.saved.load(Ordering::Relaxed) {
+            if let Some(saved) = current
+                .as_deref()
+                .and_then(|data| serde_json::from_str(data).ok())
+            {
+                merge_saved(&mut pending, saved, |item| item.id);
+            }
+        }
+        let result = f(&mut pending);
+        let saved = self.storage.write_changed(&*pending, current.as_deref());
+        self.saved.store(saved, Ordering::Relaxed);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyOptions;
+    use crate::delivery::PendingDeliveries;
+    use crate::reconfigure::LiveConfig;
+    use crate::test_support::{notification_json, TempStorage};
+
+    fn queue(drops: &Arc<DropLog>) -> MutationQueue {
+        let config = LiveConfig::new(&NotifyOptions::new("ak_test_mutations"));
+        MutationQueue::new(KeyEnvironment::Test, DebugSwitch::queue(&config), Arc::clone(drops))
+    }
+
+    fn drops() -> Arc<DropLog> {
+        let config = LiveConfig::new(&NotifyOptions::new("ak_test_mutations"));
+        let deliveries = Arc::new(PendingDeliveries::default());
+        Arc::new(DropLog::new(10, DebugSwitch::queue(&config), deliveries))
+    }
+
+    fn pending(queue: &MutationQueue) -> Vec<(String, Mutation)> {
+        queue
+            .pending()
+            .into_iter()
+            .map(|item| (item.notification_id, item.mutation))
+            .collect()
+    }
+
+    #[test]
+    fn repeating_a_notifications_last_mutation_queues_nothing() {
+        let _storage = TempStorage::new();
+        let queue = queue(&drops());
+        queue.push("n1", Mutation::MarkRead);
+        queue.push("n2", Mutation::MarkRead);
+        queue.push("n1", Mutation::MarkRead);
+        queue.push("n1", Mutation::MarkUnread);
+        queue.push("n1", Mutation::MarkRead);
+        assert_eq!(
+            pending(&queue),
+            [
+                ("n1".to_string(), Mutation::MarkRead),
+                ("n2".to_string(), Mutation::MarkRead),
+                ("n1".to_string(), Mutation::MarkUnread),
+                ("n1".to_string(), Mutation::MarkRead),
+            ]
+        );
+        assert!(queue.has_pending("n2"));
+        assert!(!queue.has_pending("n3"));
+    }
+
+    #[test]
+    fn mutations_are_shared_through_the_file() {
+        let _storage = TempStorage::new();
+        let drops = drops();
+        let first = queue(&drops);
+        first.push("n1", Mutation::Archive);
+        let second = queue(&drops);
+        assert_eq!(pending(&second), [("n1".to_string(), Mutation::Archive)]);
+
+        second.push("n2", Mutation::Acknowledge { note: Some("on it".to_string()) });
+        first.remove(first.pending()[0].id);
+        assert_eq!(
+            pending(&second),
+            [("n2".to_string(), Mutation::Acknowledge { note: Some("on it".to_string()) })]
+        );
+    }
+
+    #[test]
+    fn conflicts_are_removed_and_reported() {
+        let _storage = TempStorage::new();
+        let drops = drops();
+        let queue = queue(&drops);
+        queue.push("n1", Mutation::MarkRead);
+        queue.drop_conflict(&queue.pending()[0]);
+        assert!(queue.pending().is_empty());
+        let dropped = drops.recent(10);
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].notification_id.as_deref(), Some("n1"));
+        assert_eq!(dropped[0].reason, DropReason::Conflict);
+    }
+
+    #[test]
+    fn the_overlay_shows_the_state_after_replay() {
+        let _storage = TempStorage::new();
+        let queue = queue(&drops());
+        let mut list: Vec<Notification> = ["n1", "n2", "n3"]
+            .iter()
+            .map(|id| serde_json::from_value(notification_json(id, Utc::now())).unwrap())
+            .collect();
+        queue.push("n1", Mutation::MarkRead);
+        queue.push("n2", Mutation::Archive);
+        queue.push("n3", Mutation::Acknowledge { note: None });
+        queue.overlay(&mut list);
+
+        let state: Vec<(&str, bool, bool)> = list
+            .iter()
+            .map(|n| (n.id.as_str(), n.read, n.acknowledged_at.is_some()))
+            .collect();
+        assert_eq!(state, [("n1", true, false), ("n3", false, true)]);
+    }
+}
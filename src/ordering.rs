@@ -0,0 +1,131 @@
+//! Per-group send ordering for IronNotify SDK.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Async locks keyed by group key, created on demand and removed once unused.
+///
+/// Waiters acquire a key's lock in FIFO order.
+#[derive(Default)]
+pub(crate) struct KeyedLocks {
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl KeyedLocks {
+    /// Waits until no earlier holder of `key` remains.
+    pub async fn lock(&self, key: &str) -> KeyedGuard<'_> {
+        let lock = Arc::clone(self.locks.lock().entry(key.to_string()).or_default());
+        let guard = lock.lock_owned().await;
+        KeyedGuard {
+            locks: self,
+            key: key.to_string(),
+            guard: Some(guard),
+        }
+    }
+}
+
+/// Holds a group key's lock until dropped.
+pub(crate) struct KeyedGuard<'a> {
+    locks: &'a KeyedLocks,
+    key: String,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Drop for KeyedGuard<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+        let mut locks = self.locks.locks.lock();
+        if locks.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{self, MockResponse, MockServer};
+    use crate::types::QueueReason;
+    use crate::{NotificationPayload, NotifyClient, PayloadBuilder};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    const SLOW_HEAD: Duration = Duration::from_millis(300);
+
+    fn ordered(group: &str, title: &str) -> NotificationPayload {
+        PayloadBuilder::new("order.updated")
+            .with_title(title)
+            .with_group_key(group)
+            .ordered()
+            .build()
+            .unwrap()
+    }
+
+    fn sent_titles(server: &MockServer) -> Vec<String> {
+        server
+            .requests_to("POST", "/api/v1/notify")
+            .iter()
+            .map(|request| request.json()["title"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn groups_stay_in_order_without_waiting_for_each_other() {
+        let _storage = test_support::TempStorage::new();
+        let healthy = Arc::new(AtomicBool::new(false));
+        let server = MockServer::start({
+            let healthy = Arc::clone(&healthy);
+            move |request| {
+                let ok = MockResponse::json(200, json!({ "notificationId": "n" }));
+                match request.json()["title"].as_str() {
+                    Some("a1") => ok.delayed(SLOW_HEAD),
+                    Some("b1") if !healthy.load(Ordering::SeqCst) => {
+                        MockResponse::json(503, json!({}))
+                    }
+                    _ => ok,
+                }
+            }
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let started = Instant::now();
+            let timed = |payload: NotificationPayload| {
+                let client = client.clone();
+                async move {
+                    let result = client.send_payload(&payload).await;
+                    (result, started.elapsed())
+                }
+            };
+            let ((a1, a1_at), (a2, _), (b1, _), (b2, b2_at)) = tokio::join!(
+                timed(ordered("a", "a1")),
+                timed(ordered("a", "a2")),
+                timed(ordered("b", "b1")),
+                timed(ordered("b", "b2")),
+            );
+
+            assert!(a1.success && a2.success);
+            // The failed head of group b is queued, and b2 held behind it, while the
+            // slow head of group a is still in flight.
+            assert_eq!(b1.queue_reason, Some(QueueReason::Offline));
+            assert_eq!(b2.queue_reason, Some(QueueReason::GroupOrder));
+            assert!(b2_at < a1_at, "{:?} {:?}", b2_at, a1_at);
+            assert!(a1_at >= SLOW_HEAD);
+
+            healthy.store(true, Ordering::SeqCst);
+            client.flush().await;
+        });
+
+        let titles = sent_titles(&server);
+        let group = |prefix: &str| -> Vec<String> {
+            titles.iter().filter(|title| title.starts_with(prefix)).cloned().collect()
+        };
+        assert_eq!(group("a"), ["a1", "a2"]);
+        assert_eq!(group("b"), ["b1", "b1", "b2"]);
+        assert_eq!(client.queue_size(), 0);
+    }
+}
@@ -0,0 +1,90 @@
+//! Oversized metadata handling for IronNotify SDK.
+
+use crate::error::Error;
+use crate::transport::Transport;
+use crate::types::NotificationPayload;
+use serde_json::Value;
+
+/// Key identifying an externalized metadata reference.
+pub const ATTACHMENT_KEY: &str = "$attachment";
+
+/// Suffix appended to truncated metadata values.
+const TRUNCATED_SUFFIX: &str = "...[truncated]";
+
+/// What to do when a payload exceeds `max_payload_bytes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowStrategy {
+    /// Send the payload unchanged and let the server reject it.
+    #[default]
+    Fail,
+    /// Truncate the largest metadata values until the payload fits.
+    Truncate,
+    /// Upload the largest metadata values as attachments and send references instead.
+    Externalize,
+}
+
+/// Gets the serialized size of a value in bytes.
+fn value_size(value: &Value) -> usize {
+    serde_json::to_vec(value).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+/// Gets metadata keys ordered from largest to smallest value.
+fn keys_by_size(payload: &NotificationPayload) -> Vec<(String, usize)> {
+    let mut keys: Vec<(String, usize)> = payload
+        .metadata
+        .iter()
+        .flatten()
+        .map(|(key, value)| (key.clone(), value_size(value)))
+        .collect();
+    keys.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+    keys
+}
+
+/// Truncates the largest metadata values until the payload fits within `max_bytes`.
+pub(crate) fn truncate(payload: &NotificationPayload, max_bytes: usize) -> NotificationPayload {
+    let mut payload = payload.clone();
+
+    for (key, _) in keys_by_size(&payload) {
+        let excess = payload.estimated_wire_size().saturating_sub(max_bytes);
+        if excess == 0 {
+            break;
+        }
+
+        if let Some(value) = payload.metadata.as_mut().and_then(|m| m.get_mut(&key)) {
+            let text = match *value {
+                Value::String(ref mut s) => std::mem::take(s),
+                ref other => other.to_string(),
+            };
+            let keep = text.len().saturating_sub(excess + TRUNCATED_SUFFIX.len());
+            let mut end = keep;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            *value = Value::String(format!("{}{}", &text[..end], TRUNCATED_SUFFIX));
+        }
+    }
+
+    payload
+}
+
+/// Uploads the largest metadata values as attachments until the payload fits within `max_bytes`.
+pub(crate) async fn externalize(
+    transport: &Transport,
+    payload: &NotificationPayload,
+    max_bytes: usize,
+) -> Result<NotificationPayload, Error> {
+    let mut payload = payload.clone();
+
+    for (key, size) in keys_by_size(&payload) {
+        if payload.estimated_wire_size() <= max_bytes {
+            break;
+        }
+
+        if let Some(value) = payload.metadata.as_mut().and_then(|m| m.get_mut(&key)) {
+            let url = transport.upload_attachment(value).await?;
+            *value = serde_json::json!({ ATTACHMENT_KEY: url, "size": size });
+        }
+    }
+
+    Ok(payload)
+}
@@ -0,0 +1,328 @@
+//! Page-by-page iteration over listing endpoints for IronNotify SDK.
+
+use crate::error::Error;
+use futures_core::Stream;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Most pages a `Pager` fetches unless `max_pages` sets another cap.
+pub const DEFAULT_MAX_PAGES: usize = 1_000;
+
+/// Position of a page in a listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Cursor {
+    /// Number of items before the page, for endpoints paged by limit and offset.
+    Offset(i32),
+    /// Opaque token returned by the endpoint.
+    Token(String),
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cursor::Offset(offset) => write!(f, "offset {}", offset),
+            Cursor::Token(token) => write!(f, "token '{}'", token),
+        }
+    }
+}
+
+type PageResult<T> = Result<(Vec<T>, Option<Cursor>), Error>;
+type PageFuture<T> = Pin<Box<dyn Future<Output = PageResult<T>> + Send>>;
+type PageFetch<T> = Box<dyn FnMut(Option<Cursor>) -> PageFuture<T> + Send>;
+
+/// Fetches a listing one page at a time.
+///
+/// The fetch closure gets the cursor of the page to fetch, `None` for the first, and
+/// returns the page's items with the cursor of the next page, or `None` after the
+/// last. Fetching stops with `Error::Pagination` if a cursor comes back a second
+/// time or more than `max_pages` pages are fetched.
+///
+/// ```
+/// use ironnotify::Pager;
+///
+/// let items: Vec<i32> = (0..250).collect();
+/// let pager = Pager::by_offset(100, 0, move |offset, limit| {
+///     let page = items.iter().copied().skip(offset as usize).take(limit as usize).collect();
+///     async move { Ok(page) }
+/// });
+///
+/// let first = tokio_test::block_on(pager.try_collect(Some(150))).unwrap();
+/// assert_eq!(first.len(), 150);
+/// ```
+pub struct Pager<T> {
+    fetch: PageFetch<T>,
+    next: Option<Cursor>,
+    seen: HashSet<Cursor>,
+    pages: usize,
+    max_pages: usize,
+    done: bool,
+}
+
+impl<T> Pager<T> {
+    /// Creates a pager from a page-fetch closure.
+    pub fn new<F, Fut>(mut fetch: F) -> Self
+    where
+        F: FnMut(Option<Cursor>) -> Fut + Send + 'static,
+        Fut: Future<Output = PageResult<T>> + Send + 'static,
+    {
+        Self {
+            fetch: Box::new(move |cursor| Box::pin(fetch(cursor))),
+            next: None,
+            seen: HashSet::new(),
+            pages: 0,
+            max_pages: DEFAULT_MAX_PAGES,
+            done: false,
+        }
+    }
+
+    /// Creates a pager for an endpoint paged by limit and offset, starting at `start`.
+    ///
+    /// `fetch` gets the offset and limit of each page. A page shorter than
+    /// `page_size` is the last.
+    pub fn by_offset<F, Fut>(page_size: i32, start: i32, mut fetch: F) -> Self
+    where
+        F: FnMut(i32, i32) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<T>, Error>> + Send + 'static,
+    {
+        let page_size = page_size.max(1);
+        let start = start.max(0);
+        Self::new(move |cursor| {
+            let offset = match cursor {
+                Some(Cursor::Offset(offset)) => offset,
+                _ => start,
+            };
+            let page = fetch(offset, page_size);
+            async move {
+                let page = page.await?;
+                let next = if page.len() >= page_size as usize {
+                    offset.checked_add(page_size).map(Cursor::Offset)
+                } else {
+                    None
+                };
+                Ok((page, next))
+            }
+        })
+    }
+
+    /// Sets the cursor of the first page to fetch.
+    pub fn starting_at(mut self, cursor: Cursor) -> Self {
+        self.next = Some(cursor);
+        self
+    }
+
+    /// Sets the most pages to fetch before failing with `Error::Pagination`.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Gets the number of pages fetched so far.
+    pub fn pages_fetched(&self) -> usize {
+        self.pages
+    }
+
+    /// Fetches the next page, or returns `None` after the last.
+    ///
+    /// After an error the pager is finished and returns `None`.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+        // Stop now so callers retrying on error do not fetch the same page forever.
+        self.done = true;
+        if self.pages >= self.max_pages {
+            return Err(Error::Pagination(format!("more than {} pages", self.max_pages)));
+        }
+
+        let cursor = self.next.take();
+        if let Some(ref cursor) = cursor {
+            self.seen.insert(cursor.clone());
+        }
+        let (items, next) = (self.fetch)(cursor).await?;
+        self.pages += 1;
+
+        match next {
+            Some(next) if self.seen.contains(&next) => {
+                return Err(Error::Pagination(format!("{} was returned twice", next)));
+            }
+            Some(next) => {
+                self.next = Some(next);
+                self.done = false;
+            }
+            None => {}
+        }
+        Ok(Some(items))
+    }
+
+    /// Fetches pages until the last, or until at least `limit` items are collected,
+    /// and returns up to `limit` items.
+    pub async fn try_collect(mut self, limit: Option<usize>) -> Result<Vec<T>, Error> {
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut items = Vec::new();
+        while items.len() < limit {
+            match self.next_page().await? {
+                Some(page) => items.extend(page),
+                None => break,
+            }
+        }
+        items.truncate(limit);
+        Ok(items)
+    }
+}
+
+impl<T: Send + 'static> Pager<T> {
+    /// Turns the pager into a stream of items, fetching each page when the previous
+    /// one is used up.
+    ///
+    /// An error ends the stream after it is yielded.
+    pub fn into_stream(self) -> impl Stream<Item = Result<T, Error>> + Send {
+        PagerStream {
+            pager: Some(self),
+            buffered: VecDeque::new(),
+            fetching: None,
+        }
+    }
+}
+
+type NextPage<T> =
+    Pin<Box<dyn Future<Output = (Pager<T>, Result<Option<Vec<T>>, Error>)> + Send>>;
+
+struct PagerStream<T> {
+    pager: Option<Pager<T>>,
+    buffered: VecDeque<T>,
+    fetching: Option<NextPage<T>>,
+}
+
+// Nothing is pinned in place: the page fetch is boxed and the items are only moved out.
+impl<T> Unpin for PagerStream<T> {}
+
+impl<T: Send + 'static> Stream for PagerStream<T> {
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(item) = this.buffered.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if this.fetching.is_none() {
+                let Some(mut pager) = this.pager.take() else {
+                    return Poll::Ready(None);
+                };
+                this.fetching = Some(Box::pin(async move {
+                    let page = pager.next_page().await;
+                    (pager, page)
+                }));
+            }
+
+            let fetching = this.fetching.as_mut().expect("page fetch in progress");
+            let (pager, page) = ready!(fetching.as_mut().poll(cx));
+            this.fetching = None;
+            match page {
+                Ok(Some(items)) => {
+                    this.buffered.extend(items);
+                    this.pager = Some(pager);
+                }
+                Ok(None) => return Poll::Ready(None),
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    type Fetches = Arc<Mutex<Vec<(i32, i32)>>>;
+
+    /// Pages through `len` numbers, recording the offset and limit of each fetch.
+    fn numbers(len: i32, page_size: i32) -> (Pager<i32>, Fetches) {
+        let fetches = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&fetches);
+        let pager = Pager::by_offset(page_size, 0, move |offset, limit| {
+            recorded.lock().push((offset, limit));
+            let page: Vec<i32> = (offset..len.min(offset + limit)).collect();
+            async move { Ok(page) }
+        });
+        (pager, fetches)
+    }
+
+    /// Pages through the given token cursors, one item per page.
+    fn tokens(next: &'static [Option<&'static str>]) -> Pager<usize> {
+        let mut page = 0;
+        Pager::new(move |_| {
+            let cursor = next[page].map(|token| Cursor::Token(token.to_string()));
+            page += 1;
+            let items = vec![page];
+            async move { Ok((items, cursor)) }
+        })
+    }
+
+    #[test]
+    fn offset_pages_end_with_a_short_page() {
+        let runtime = test_support::runtime();
+        let (pager, fetches) = numbers(250, 100);
+        let items = runtime.block_on(pager.try_collect(None)).unwrap();
+        assert_eq!(items, (0..250).collect::<Vec<_>>());
+        assert_eq!(*fetches.lock(), [(0, 100), (100, 100), (200, 100)]);
+
+        // A full last page takes one more, empty, fetch to find the end.
+        let (pager, fetches) = numbers(200, 100);
+        assert_eq!(runtime.block_on(pager.try_collect(None)).unwrap().len(), 200);
+        assert_eq!(fetches.lock().len(), 3);
+    }
+
+    #[test]
+    fn collecting_stops_once_the_limit_is_reached() {
+        let runtime = test_support::runtime();
+        let (pager, fetches) = numbers(1_000, 100);
+        let items = runtime.block_on(pager.try_collect(Some(150))).unwrap();
+        assert_eq!(items, (0..150).collect::<Vec<_>>());
+        assert_eq!(fetches.lock().len(), 2);
+    }
+
+    #[test]
+    fn a_repeated_cursor_stops_the_pager() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let mut pager = tokens(&[Some("a"), Some("b"), Some("a")]);
+            assert_eq!(pager.next_page().await.unwrap(), Some(vec![1]));
+            assert_eq!(pager.next_page().await.unwrap(), Some(vec![2]));
+            let repeated = pager.next_page().await;
+            assert!(matches!(repeated, Err(Error::Pagination(ref e)) if e.contains("token 'a'")));
+            assert_eq!(pager.next_page().await.unwrap(), None);
+            assert_eq!(pager.pages_fetched(), 3);
+        });
+    }
+
+    #[test]
+    fn pages_past_the_cap_are_not_fetched() {
+        let runtime = test_support::runtime();
+        let (pager, fetches) = numbers(1_000, 10);
+        let collected = runtime.block_on(pager.max_pages(3).try_collect(None));
+        assert!(matches!(collected, Err(Error::Pagination(_))));
+        assert_eq!(fetches.lock().len(), 3);
+    }
+
+    #[test]
+    fn streams_yield_every_item_and_end_after_an_error() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let mut stream = Box::pin(tokens(&[Some("a"), Some("b"), Some("a")]).into_stream());
+            let mut items = Vec::new();
+            while let Some(item) = std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+                items.push(item.map_err(|e| e.to_string()));
+            }
+            assert_eq!(items.len(), 3);
+            assert_eq!(items[..2], [Ok(1), Ok(2)]);
+            assert!(items[2].is_err());
+        });
+    }
+}
@@ -0,0 +1,343 @@
+//! Certificate pinning for IronNotify SDK.
+
+use crate::error::Error;
+use crate::hash::sha256;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Prefix OkHttp and HPKP style pins carry, accepted and ignored by `PinSet`.
+const PIN_PREFIX: &str = "sha256/";
+
+/// DER tag of a SEQUENCE.
+const SEQUENCE: u8 = 0x30;
+
+/// DER tag of the explicit `[0]` version field of a certificate.
+const VERSION: u8 = 0xa0;
+
+/// SHA-256 pins of the public keys the API server may present.
+///
+/// A pin is the base64 SHA-256 digest of a certificate's DER-encoded
+/// SubjectPublicKeyInfo, as computed by `pin_from_pem`, or by piping the certificate
+/// through `openssl x509 -pubkey -noout`, `openssl pkey -pubin -outform der`, and
+/// `openssl dgst -sha256 -binary | base64`.
+/// Add a backup pin for the key you will rotate to, so the switch does not lock
+/// clients out.
+///
+/// ```
+/// use ironnotify::PinSet;
+///
+/// let pins = PinSet::new("MgQvdH5MplnqMMxb2Cv8irb/ubasYTTWpM6nT/JgmEE=")
+///     .with_backup("sha256/47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=");
+/// assert_eq!(pins.pins().count(), 2);
+/// assert!(pins.contains("47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU="));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinSet {
+    pins: Vec<String>,
+}
+
+impl PinSet {
+    /// Creates a pin set with the pin of the key currently in use.
+    pub fn new(pin: impl Into<String>) -> Self {
+        Self {
+            pins: vec![normalize(pin.into())],
+        }
+    }
+
+    /// Adds a backup pin, e.g. for the key the server rotates to next.
+    pub fn with_backup(mut self, pin: impl Into<String>) -> Self {
+        let pin = normalize(pin.into());
+        if !self.pins.contains(&pin) {
+            self.pins.push(pin);
+        }
+        self
+    }
+
+    /// Gets every pin, the primary first.
+    pub fn pins(&self) -> impl Iterator<Item = &str> {
+        self.pins.iter().map(String::as_str)
+    }
+
+    /// Checks whether a pin is in the set.
+    pub fn contains(&self, pin: &str) -> bool {
+        let pin = pin.strip_prefix(PIN_PREFIX).unwrap_or(pin);
+        self.pins.iter().any(|candidate| candidate == pin)
+    }
+
+    /// Gets the first pin that is not the base64 encoding of a SHA-256 digest.
+    #[cfg(feature = "tls-pinning")]
+    pub(crate) fn invalid_pin(&self) -> Option<&str> {
+        self.pins()
+            .find(|pin| !matches!(STANDARD.decode(pin), Ok(digest) if digest.len() == 32))
+    }
+
+    /// Computes the pin of the first certificate in a PEM document.
+    ///
+    /// ```
+    /// use ironnotify::PinSet;
+    ///
+    /// let pem = "-----BEGIN CERTIFICATE-----
+    /// MIIBkjCCATmgAwIBAgIUD+AkdGcbDANffXGm46DXMmBf0pQwCgYIKoZIzj0EAwIw
+    /// HjEcMBoGA1UEAwwTYXBpLmlyb25ub3RpZnkudGVzdDAgFw0yNjEwMTQwNTU2Mjha
+    /// GA8yMTI2MDkyMDA1NTYyOFowHjEcMBoGA1UEAwwTYXBpLmlyb25ub3RpZnkudGVz
+    /// dDBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABNBhIK4TPb9Kp2q1PaERCx1qkqan
+    /// YLC/7ZPGh3aisjOQRILfhiU8VI2GreYrT8iVDILrTdvEE9iSJtW7r6353uKjUzBR
+    /// MB0GA1UdDgQWBBR8nxYXGszhLH6mh7DrQpX2TUh+eTAfBgNVHSMEGDAWgBR8nxYX
+    /// GszhLH6mh7DrQpX2TUh+eTAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0cA
+    /// MEQCIA+f19goZj+lgD/jQBcQ9hQd+x7dCYOePZk6UdAVZ42MAiASR2WS82hF0dnQ
+    /// zbhHl6c1Tazv7jmlHIoUIyq7meeabQ==
+    /// -----END CERTIFICATE-----";
+    ///
+    /// let pin = PinSet::pin_from_pem(pem).unwrap();
+    /// assert_eq!(pin, "MgQvdH5MplnqMMxb2Cv8irb/ubasYTTWpM6nT/JgmEE=");
+    /// ```
+    pub fn pin_from_pem(pem: &str) -> Result<String, Error> {
+        let body = pem
+            .split("-----BEGIN CERTIFICATE-----")
+            .nth(1)
+            .and_then(|rest| rest.split("-----END CERTIFICATE-----").next())
+            .ok_or_else(|| Error::Codec("No PEM certificate found".to_string()))?;
+        let base64: String = body.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        let der = STANDARD
+            .decode(base64)
+            .map_err(|e| Error::Codec(format!("Invalid PEM certificate: {}", e)))?;
+        spki_pin(&der).ok_or_else(|| Error::Codec("Invalid DER certificate".to_string()))
+    }
+}
+
+/// Strips the optional `sha256/` prefix from a pin.
+fn normalize(pin: String) -> String {
+    match pin.strip_prefix(PIN_PREFIX) {
+        Some(pin) => pin.to_string(),
+        None => pin,
+    }
+}
+
+/// Computes the pin of a DER certificate, or `None` if it cannot be parsed.
+pub(crate) fn spki_pin(certificate: &[u8]) -> Option<String> {
+    subject_public_key_info(certificate).map(|spki| STANDARD.encode(sha256(spki)))
+}
+
+/// A DER element.
+struct Element<'a> {
+    tag: u8,
+    /// The whole element, tag and length included.
+    encoded: &'a [u8],
+    contents: &'a [u8],
+    /// What follows the element.
+    rest: &'a [u8],
+}
+
+/// Reads the DER element at the start of `der`.
+fn read_element(der: &[u8]) -> Option<Element<'_>> {
+    let (&tag, after_tag) = der.split_first()?;
+    let (&first, mut after_length) = after_tag.split_first()?;
+    let length = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || after_length.len() < count {
+            return None;
+        }
+        let (bytes, rest) = after_length.split_at(count);
+        after_length = rest;
+        bytes.iter().fold(0, |length, &byte| (length << 8) | byte as usize)
+    };
+    if after_length.len() < length {
+        return None;
+    }
+    let header = der.len() - after_length.len();
+    Some(Element {
+        tag,
+        encoded: &der[..header + length],
+        contents: &after_length[..length],
+        rest: &after_length[length..],
+    })
+}
+
+/// Finds the SubjectPublicKeyInfo of a DER certificate.
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let certificate = read_element(certificate).filter(|e| e.tag == SEQUENCE)?;
+    let tbs = read_element(certificate.contents).filter(|e| e.tag == SEQUENCE)?;
+    let mut fields = tbs.contents;
+    if fields.first() == Some(&VERSION) {
+        fields = read_element(fields)?.rest;
+    }
+    // Serial number, signature algorithm, issuer, validity, and subject.
+    for _ in 0..5 {
+        fields = read_element(fields)?.rest;
+    }
+    read_element(fields)
+        .filter(|e| e.tag == SEQUENCE)
+        .map(|e| e.encoded)
+}
+
+#[cfg(feature = "tls-pinning")]
+pub(crate) use verifier::{client_config, PinMismatches};
+#[cfg(all(test, feature = "tls-pinning"))]
+pub(crate) use verifier::TEST_ROOT;
+
+#[cfg(feature = "tls-pinning")]
+mod verifier {
+    use super::{spki_pin, PinSet};
+    use parking_lot::Mutex;
+    use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+    use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    #[cfg(test)]
+    thread_local! {
+        /// DER certificate that pinned connections opened on this thread also trust,
+        /// e.g. the CA of a test server.
+        pub(crate) static TEST_ROOT: std::cell::RefCell<Option<Vec<u8>>> =
+            const { std::cell::RefCell::new(None) };
+    }
+
+    /// The latest certificate rejected for not matching the pins.
+    #[derive(Default)]
+    pub struct PinMismatches {
+        latest: Mutex<Option<String>>,
+    }
+
+    impl PinMismatches {
+        /// Takes the pin of the latest rejected certificate, if one was rejected
+        /// since the last call.
+        pub fn take(&self) -> Option<String> {
+            self.latest.lock().take()
+        }
+    }
+
+    /// Validates certificates against the web PKI roots, then requires the API
+    /// host's certificate to match a pin. Other hosts, e.g. attachment storage, are
+    /// only validated.
+    struct PinningVerifier {
+        inner: WebPkiVerifier,
+        host: String,
+        pins: PinSet,
+        mismatches: Arc<PinMismatches>,
+    }
+
+    impl ServerCertVerifier for PinningVerifier {
+        fn verify_server_cert(
+            &self,
+            end_entity: &Certificate,
+            intermediates: &[Certificate],
+            server_name: &ServerName,
+            scts: &mut dyn Iterator<Item = &[u8]>,
+            ocsp_response: &[u8],
+            now: SystemTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            let verified = self.inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                scts,
+                ocsp_response,
+                now,
+            )?;
+            let pinned = match server_name {
+                ServerName::DnsName(name) => name.as_ref().eq_ignore_ascii_case(&self.host),
+                _ => false,
+            };
+            if !pinned {
+                return Ok(verified);
+            }
+
+            // Only the server's own certificate is pinned: intermediates are not
+            // checked to be on the validated path, so they could be padded in.
+            let pin = spki_pin(&end_entity.0).unwrap_or_default();
+            if self.pins.contains(&pin) {
+                return Ok(verified);
+            }
+            eprintln!(
+                "[IronNotify] Warning: Certificate for {} has pin {}, which is not pinned",
+                self.host, pin
+            );
+            *self.mismatches.latest.lock() = Some(pin);
+            Err(rustls::Error::General("Certificate pin mismatch".to_string()))
+        }
+    }
+
+    /// Builds a TLS configuration pinning `host` to `pins`.
+    pub fn client_config(
+        host: &str,
+        pins: &PinSet,
+        mismatches: Arc<PinMismatches>,
+    ) -> ClientConfig {
+        let mut roots = RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        #[cfg(test)]
+        if let Some(root) = TEST_ROOT.with(|root| root.borrow().clone()) {
+            let _ = roots.add(&Certificate(root));
+        }
+        let verifier = PinningVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            host: host.to_string(),
+            pins: pins.clone(),
+            mismatches,
+        };
+
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        config
+    }
+}
+
+#[cfg(all(test, feature = "tls-pinning"))]
+mod tests {
+    use super::PinSet;
+    use crate::error::Error;
+    use crate::query::CountGroupBy;
+    use crate::test_support::{self, MockResponse, MockServer, TrustedTestCa};
+    use crate::test_support::{TLS_CA_PEM, TLS_CA_PIN, TLS_CERT_PEM, TLS_CERT_PIN};
+    use crate::NotifyClient;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn unread_counts(pins: PinSet) -> Result<HashMap<String, i32>, Error> {
+        let server = MockServer::start_tls(|_| {
+            MockResponse::json(200, json!({ "counts": { "info": 3 } }))
+        });
+        let _trusted = TrustedTestCa::new();
+        let mut options = test_support::options(&server);
+        options.enable_offline_queue = false;
+        options.certificate_pins = Some(pins);
+        let client = NotifyClient::new(options).unwrap();
+        test_support::runtime().block_on(client.get_unread_counts(CountGroupBy::Severity, None))
+    }
+
+    #[test]
+    fn pins_are_read_from_pem_certificates() {
+        assert_eq!(PinSet::pin_from_pem(TLS_CERT_PEM).unwrap(), TLS_CERT_PIN);
+        assert_eq!(PinSet::pin_from_pem(TLS_CA_PEM).unwrap(), TLS_CA_PIN);
+    }
+
+    #[test]
+    fn requests_to_a_server_with_a_pinned_key_succeed() {
+        let pin = PinSet::pin_from_pem(TLS_CERT_PEM).unwrap();
+        let counts = unread_counts(PinSet::new(pin)).unwrap();
+        assert_eq!(counts, HashMap::from([("info".to_string(), 3)]));
+    }
+
+    #[test]
+    fn a_backup_pin_matches_when_the_primary_does_not() {
+        let pins = PinSet::new(TLS_CA_PIN).with_backup(TLS_CERT_PIN);
+        assert!(unread_counts(pins).is_ok());
+    }
+
+    #[test]
+    fn requests_to_a_server_with_another_key_fail_with_its_pin() {
+        let error = unread_counts(PinSet::new(TLS_CA_PIN)).unwrap_err();
+        assert!(matches!(error, Error::PinMismatch(ref pin) if pin == TLS_CERT_PIN), "{}", error);
+    }
+}
@@ -0,0 +1,397 @@
+//! Postgres offline queue for IronNotify SDK.
+
+use crate::error::Error;
+use crate::queue::QueuedNotification;
+use crate::types::{KeyEnvironment, NotificationPayload};
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::types::Json;
+use sqlx::{Executor, Postgres, Row, Transaction};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Name of the table used unless `with_table` sets another.
+const DEFAULT_TABLE: &str = "ironnotify_queue";
+
+/// How long a claimed notification is reserved for the replica that claimed it.
+const DEFAULT_CLAIM_LEASE: Duration = Duration::from_secs(300);
+
+/// How long to wait for a pooled connection before the queue counts as unavailable.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Columns read back into a `QueuedNotification`.
+const COLUMNS: &str = "id, payload, enqueued_at, environment, tenant, tenant_id, confirmed";
+
+/// An offline queue kept in a Postgres table, shared by every replica using the same
+/// database.
+///
+/// Set with `NotifyOptions::postgres_queue`. The table is created on first use with
+/// the columns `id`, `payload` (jsonb), `enqueued_at`, `attempts`, `tenant`, `tenant_id`,
+/// `environment`, `claimed_until`, and `confirmed`, which is false for entries added
+/// with `enqueue_only` until they are confirmed. Replicas flushing at the same time claim
+/// disjoint batches with `FOR UPDATE SKIP LOCKED`, so a notification is sent by only
+/// one of them; a claim that is neither removed nor released expires after the
+/// claim lease, e.g. when the replica holding it crashed.
+#[derive(Debug)]
+pub struct PostgresQueueStorage {
+    pool: PgPool,
+    table: String,
+    claim_lease: Duration,
+    next_id: AtomicU64,
+    migrated: OnceCell<()>,
+}
+
+impl PostgresQueueStorage {
+    /// Creates a queue using an existing connection pool.
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            table: DEFAULT_TABLE.to_string(),
+            claim_lease: DEFAULT_CLAIM_LEASE,
+            next_id: AtomicU64::new(RandomState::new().build_hasher().finish()),
+            migrated: OnceCell::new(),
+        }
+    }
+
+    /// Creates a queue connecting to a database URL. Connections are opened when first
+    /// needed, so this does not fail when the database is down.
+    pub fn connect(url: &str) -> Result<Self, Error> {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(ACQUIRE_TIMEOUT)
+            .connect_lazy(url)
+            .map_err(|e| Error::QueueStorage(e.to_string()))?;
+        Ok(Self::new(pool))
+    }
+
+    /// Sets the table name. It is used verbatim in queries, so it must be a valid
+    /// identifier, optionally schema-qualified.
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Sets how long claimed notifications stay reserved before other replicas may
+    /// claim them again.
+    pub fn with_claim_lease(mut self, lease: Duration) -> Self {
+        self.claim_lease = lease;
+        self
+    }
+
+    /// Creates the table and its index if they do not exist.
+    ///
+    /// Called before the first operation, so it only needs to be called directly to
+    /// check the database at startup.
+    pub async fn migrate(&self) -> Result<(), Error> {
+        self.migrated
+            .get_or_try_init(|| async {
+                let table = &self.table;
+                let statements = [
+                    format!(
+                        "CREATE TABLE IF NOT EXISTS {} (\
+                             id BIGINT PRIMARY KEY, \
+                             payload JSONB NOT NULL, \
+                             enqueued_at TIMESTAMPTZ NOT NULL, \
+                             attempts INTEGER NOT NULL DEFAULT 0, \
+                             tenant TEXT, \
+                             environment TEXT, \
+                             claimed_until TIMESTAMPTZ\
+                         )",
+                        table
+                    ),
+                    format!(
+                        "CREATE INDEX IF NOT EXISTS {}_enqueued_at ON {} (enqueued_at, id)",
+                        index_prefix(table),
+                        table
+                    ),
+                    // Added after the first release; existing entries count as confirmed.
+                    format!(
+                        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS confirmed BOOLEAN NOT NULL \
+                         DEFAULT TRUE",
+                        table
+                    ),
+                    // Added with `register_tenant_key`.
+                    format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS tenant_id TEXT", table),
+                ];
+                for statement in &statements {
+                    sqlx::query(statement).execute(&self.pool).await.map_err(storage_error)?;
+                }
+                Ok::<(), Error>(())
+            })
+            .await
+            .map(|_| ())
+    }
+
+    /// Gets every queued notification, oldest first, including claimed ones.
+    pub async fn load(&self) -> Result<Vec<QueuedNotification>, Error> {
+        self.migrate().await?;
+        let sql = format!("SELECT {} FROM {} ORDER BY enqueued_at, id", COLUMNS, self.table);
+        let rows = sqlx::query(&sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        rows.iter().map(decode).collect()
+    }
+
+    /// Adds a notification to the queue. Adding an ID that is already queued does
+    /// nothing.
+    ///
+    /// Entries with `awaiting_confirmation` set are not claimed until `confirm`.
+    pub async fn append(&self, item: &QueuedNotification) -> Result<(), Error> {
+        self.migrate().await?;
+        self.insert(&self.pool, item).await
+    }
+
+    /// Adds a notification as part of the caller's transaction, so it is only queued
+    /// if the transaction commits.
+    pub async fn append_in(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        item: &QueuedNotification,
+    ) -> Result<(), Error> {
+        self.migrate().await?;
+        self.insert(&mut **transaction, item).await
+    }
+
+    async fn insert<'e>(
+        &self,
+        executor: impl Executor<'e, Database = Postgres>,
+        item: &QueuedNotification,
+    ) -> Result<(), Error> {
+        let sql = format!(
+            "INSERT INTO {} (id, payload, enqueued_at, environment, tenant, tenant_id, \
+             confirmed) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (id) DO NOTHING",
+            self.table
+        );
+        sqlx::query(&sql)
+            .bind(item.id as i64)
+            .bind(Json(&item.payload))
+            // Entries moved over from older queue files are stamped when inserted.
+            .bind(item.enqueued_at.unwrap_or_else(Utc::now))
+            .bind(item.environment.map(|environment| environment.to_string()))
+            .bind(item.tenant.as_deref())
+            .bind(item.tenant_id.as_deref())
+            .bind(!item.awaiting_confirmation)
+            .execute(executor)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Lets replicas claim a notification appended unconfirmed. Returns false if it is
+    /// not waiting for confirmation, e.g. because it was aborted.
+    pub async fn confirm(&self, id: u64) -> Result<bool, Error> {
+        self.migrate().await?;
+        let sql = format!(
+            "UPDATE {} SET confirmed = TRUE WHERE id = $1 AND NOT confirmed",
+            self.table
+        );
+        let result = sqlx::query(&sql)
+            .bind(id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes a notification appended unconfirmed. Returns false if it is not waiting
+    /// for confirmation, e.g. because it was already confirmed.
+    pub async fn abort(&self, id: u64) -> Result<bool, Error> {
+        self.migrate().await?;
+        let sql = format!("DELETE FROM {} WHERE id = $1 AND NOT confirmed", self.table);
+        let result = sqlx::query(&sql)
+            .bind(id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes a notification from the queue, e.g. once it was sent.
+    pub async fn remove(&self, id: u64) -> Result<(), Error> {
+        self.migrate().await?;
+        let sql = format!("DELETE FROM {} WHERE id = $1", self.table);
+        sqlx::query(&sql)
+            .bind(id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Claims up to `n` of the oldest unclaimed, confirmed notifications.
+    ///
+    /// Claimed notifications are skipped by other replicas until they are removed,
+    /// released, or the claim lease expires. Each claim counts as an attempt.
+    pub async fn claim_batch(&self, n: usize) -> Result<Vec<QueuedNotification>, Error> {
+        self.claim(n, None).await
+    }
+
+    /// Claims notifications like `claim_batch`, only taking those queued under a key
+    /// from `environment`.
+    pub(crate) async fn claim_for(
+        &self,
+        n: usize,
+        environment: KeyEnvironment,
+    ) -> Result<Vec<QueuedNotification>, Error> {
+        self.claim(n, Some(environment)).await
+    }
+
+    async fn claim(
+        &self,
+        n: usize,
+        environment: Option<KeyEnvironment>,
+    ) -> Result<Vec<QueuedNotification>, Error> {
+        self.migrate().await?;
+        let table = &self.table;
+        let sql = format!(
+            "UPDATE {table} \
+             SET claimed_until = now() + make_interval(secs => $3), attempts = attempts + 1 \
+             WHERE id IN (\
+                 SELECT id FROM {table} \
+                 WHERE ($1::TEXT IS NULL OR environment = $1) \
+                   AND confirmed \
+                   AND (claimed_until IS NULL OR claimed_until < now()) \
+                 ORDER BY enqueued_at, id \
+                 LIMIT $2 \
+                 FOR UPDATE SKIP LOCKED\
+             ) \
+             RETURNING {COLUMNS}"
+        );
+        let rows = sqlx::query(&sql)
+            .bind(environment.map(|environment| environment.to_string()))
+            .bind(i64::try_from(n).unwrap_or(i64::MAX))
+            .bind(self.claim_lease.as_secs_f64())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(storage_error)?;
+
+        let mut items = rows.iter().map(decode).collect::<Result<Vec<_>, _>>()?;
+        items.sort_by_key(|item| (item.enqueued_at, item.id));
+        Ok(items)
+    }
+
+    /// Releases claimed notifications that were not sent, so any replica may claim
+    /// them again.
+    pub async fn release(&self, ids: &[u64]) -> Result<(), Error> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.migrate().await?;
+        let sql = format!("UPDATE {} SET claimed_until = NULL WHERE id = ANY($1)", self.table);
+        let ids: Vec<i64> = ids.iter().map(|&id| id as i64).collect();
+        sqlx::query(&sql)
+            .bind(ids)
+            .execute(&self.pool)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    /// Creates the queue entry for a payload, for the tenant of the current request.
+    ///
+    /// IDs count up from a random start, so entries added by different replicas do
+    /// not collide.
+    pub(crate) fn entry(
+        &self,
+        payload: NotificationPayload,
+        environment: KeyEnvironment,
+    ) -> QueuedNotification {
+        let id = match self.next_id.fetch_add(1, Ordering::Relaxed) {
+            0 => self.next_id.fetch_add(1, Ordering::Relaxed),
+            id => id,
+        };
+        let (tenant, tenant_id) = crate::tenant::persisted();
+        QueuedNotification {
+            id,
+            size: payload.estimated_wire_size(),
+            payload,
+            enqueued_at: Some(Utc::now()),
+            environment: Some(environment),
+            tenant,
+            tenant_id,
+            awaiting_confirmation: false,
+            attempts: 0,
+        }
+    }
+}
+
+/// Reads a queue entry from a row.
+fn decode(row: &PgRow) -> Result<QueuedNotification, Error> {
+    let id: i64 = row.try_get("id").map_err(storage_error)?;
+    let Json(payload): Json<NotificationPayload> = row.try_get("payload").map_err(storage_error)?;
+    let enqueued_at: DateTime<Utc> = row.try_get("enqueued_at").map_err(storage_error)?;
+    let environment: Option<String> = row.try_get("environment").map_err(storage_error)?;
+    let tenant: Option<String> = row.try_get("tenant").map_err(storage_error)?;
+    let tenant_id: Option<String> = row.try_get("tenant_id").map_err(storage_error)?;
+    let confirmed: bool = row.try_get("confirmed").map_err(storage_error)?;
+
+    Ok(QueuedNotification {
+        id: id as u64,
+        size: payload.estimated_wire_size(),
+        payload,
+        enqueued_at: Some(enqueued_at),
+        environment: environment.as_deref().and_then(parse_environment),
+        tenant,
+        tenant_id,
+        awaiting_confirmation: !confirmed,
+        attempts: 0,
+    })
+}
+
+fn parse_environment(environment: &str) -> Option<KeyEnvironment> {
+    match environment {
+        "live" => Some(KeyEnvironment::Live),
+        "test" => Some(KeyEnvironment::Test),
+        "other" => Some(KeyEnvironment::Other),
+        _ => None,
+    }
+}
+
+/// Gets a prefix for index names from a possibly schema-qualified table name.
+fn index_prefix(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
+
+fn storage_error(e: sqlx::Error) -> Error {
+    Error::QueueStorage(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn stored_environments_parse_back() {
+        for environment in [KeyEnvironment::Live, KeyEnvironment::Test, KeyEnvironment::Other] {
+            assert_eq!(parse_environment(&environment.to_string()), Some(environment));
+        }
+        assert_eq!(parse_environment("staging"), None);
+    }
+
+    #[test]
+    fn index_names_leave_out_the_schema() {
+        assert_eq!(index_prefix("ironnotify_queue"), "ironnotify_queue");
+        assert_eq!(index_prefix("notify.queue"), "queue");
+    }
+
+    #[test]
+    fn entries_get_distinct_nonzero_ids() {
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            let storage = PostgresQueueStorage::connect("postgres://localhost/ironnotify").unwrap();
+            storage.next_id.store(u64::MAX, Ordering::Relaxed);
+
+            let first = storage.entry(NotificationPayload::new("e", "t"), KeyEnvironment::Live);
+            let second = storage.entry(NotificationPayload::new("e", "t"), KeyEnvironment::Live);
+            assert_eq!(first.id, u64::MAX);
+            assert_eq!(second.id, 1);
+            assert!(first.enqueued_at.is_some());
+            assert_eq!(first.environment, Some(KeyEnvironment::Live));
+            assert!(!first.awaiting_confirmation);
+        });
+    }
+}
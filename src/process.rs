@@ -0,0 +1,224 @@
+//! Unread notification processing for IronNotify SDK.
+
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// What `process_unread` does after a handler fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessErrorPolicy {
+    /// Stop handing out notifications; handlers already running finish.
+    #[default]
+    Stop,
+    /// Keep processing the remaining notifications.
+    Continue,
+}
+
+/// Options for `process_unread_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessOptions {
+    /// Maximum number of handlers running at once.
+    pub concurrency: usize,
+    /// What to do after a handler fails.
+    pub on_error: ProcessErrorPolicy,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            on_error: ProcessErrorPolicy::Stop,
+        }
+    }
+}
+
+impl ProcessOptions {
+    /// Creates options running one handler at a time and stopping at the first error.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of handlers running at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets what to do after a handler fails.
+    pub fn on_error(mut self, policy: ProcessErrorPolicy) -> Self {
+        self.on_error = policy;
+        self
+    }
+}
+
+/// Outcome of `process_unread`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProcessSummary {
+    /// Notifications handled and marked as read.
+    pub processed: usize,
+    /// Notifications whose handler failed, or that could not be marked as read after
+    /// it succeeded. They stay unread.
+    pub failed: usize,
+    /// Notifications left unhandled because another `process_unread` call was
+    /// handling them, or because processing stopped after an error.
+    pub skipped: usize,
+}
+
+/// IDs of the notifications being handled by `process_unread` calls on one client.
+#[derive(Default)]
+pub(crate) struct InFlight {
+    ids: Mutex<HashSet<String>>,
+}
+
+impl InFlight {
+    /// Claims a notification ID, or returns `None` if another call holds it.
+    pub fn claim(self: &Arc<Self>, id: &str) -> Option<InFlightGuard> {
+        if !self.ids.lock().insert(id.to_string()) {
+            return None;
+        }
+        Some(InFlightGuard {
+            in_flight: Arc::clone(self),
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Releases a claimed notification ID when dropped.
+pub(crate) struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    id: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.ids.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::NotifyClient;
+    use crate::query::NotificationQuery;
+    use crate::test_support::{self, notification_json, MockResponse, MockServer, TempStorage};
+    use chrono::Utc;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// A server listing `ids` as unread and accepting every mutation.
+    fn listing(ids: &'static [&'static str]) -> MockServer {
+        MockServer::start(move |request| {
+            if request.method == "GET" && request.path.starts_with("/api/v1/notifications") {
+                let listed: Vec<_> =
+                    ids.iter().map(|id| notification_json(id, Utc::now())).collect();
+                return MockResponse::json(200, json!(listed));
+            }
+            MockResponse::json(200, json!({}))
+        })
+    }
+
+    fn marked_read(server: &MockServer) -> Vec<String> {
+        let mut marked: Vec<String> = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == "POST" && request.path.ends_with("/read"))
+            .map(|request| request.path)
+            .collect();
+        marked.sort();
+        marked
+    }
+
+    #[test]
+    fn claimed_ids_are_released_when_the_guard_drops() {
+        let in_flight = Arc::new(InFlight::default());
+        let guard = in_flight.claim("n1").unwrap();
+        assert!(in_flight.claim("n1").is_none());
+        assert!(in_flight.claim("n2").is_some());
+        drop(guard);
+        assert!(in_flight.claim("n1").is_some());
+    }
+
+    #[test]
+    fn failed_notifications_stay_unread() {
+        let _storage = TempStorage::new();
+        let server = listing(&["n1", "n2", "n3"]);
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let options = ProcessOptions::new().on_error(ProcessErrorPolicy::Continue);
+
+        let query = NotificationQuery::new();
+        let runtime = test_support::runtime();
+        let summary = runtime
+            .block_on(client.process_unread_with(&query, options, |n| async move {
+                if n.id == "n2" {
+                    Err("rejected")
+                } else {
+                    Ok(())
+                }
+            }))
+            .unwrap();
+        let expected = ProcessSummary {
+            processed: 2,
+            failed: 1,
+            skipped: 0,
+        };
+        assert_eq!(summary, expected);
+        assert_eq!(
+            marked_read(&server),
+            ["/api/v1/notifications/n1/read", "/api/v1/notifications/n3/read"]
+        );
+    }
+
+    #[test]
+    fn processing_stops_after_the_first_failure_by_default() {
+        let _storage = TempStorage::new();
+        let server = listing(&["n1", "n2", "n3"]);
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        let summary = runtime
+            .block_on(client.process_unread(&NotificationQuery::new(), |n| async move {
+                if n.id == "n2" {
+                    Err("rejected")
+                } else {
+                    Ok(())
+                }
+            }))
+            .unwrap();
+        let expected = ProcessSummary {
+            processed: 1,
+            failed: 1,
+            skipped: 1,
+        };
+        assert_eq!(summary, expected);
+        assert_eq!(marked_read(&server), ["/api/v1/notifications/n1/read"]);
+    }
+
+    #[test]
+    fn handlers_run_up_to_the_concurrency_limit() {
+        let _storage = TempStorage::new();
+        let server = listing(&["n1", "n2", "n3", "n4", "n5", "n6"]);
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (counted, seen) = (Arc::clone(&running), Arc::clone(&most));
+
+        let query = NotificationQuery::new();
+        let runtime = test_support::runtime();
+        let options = ProcessOptions::new().concurrency(2);
+        let summary = runtime
+            .block_on(client.process_unread_with(&query, options, move |_| {
+                let (running, most) = (Arc::clone(&counted), Arc::clone(&seen));
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok::<(), String>(())
+                }
+            }))
+            .unwrap();
+        assert_eq!(summary.processed, 6);
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+}
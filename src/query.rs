@@ -0,0 +1,241 @@
+//! Notification list queries for IronNotify SDK.
+
+use crate::types::SeverityLevel;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Field to group unread counts by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountGroupBy {
+    EventType,
+    GroupKey,
+    Severity,
+    Category,
+}
+
+impl CountGroupBy {
+    /// Gets the query parameter value for this grouping.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EventType => "event_type",
+            Self::GroupKey => "group_key",
+            Self::Severity => "severity",
+            Self::Category => "category",
+        }
+    }
+}
+
+impl std::fmt::Display for CountGroupBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Filters and pagination for listing notifications.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationQuery {
+    /// Maximum number of notifications to return.
+    pub limit: Option<i32>,
+    /// Number of notifications to skip.
+    pub offset: Option<i32>,
+    /// Only return unread notifications.
+    pub unread_only: bool,
+    /// Include notifications that are currently snoozed.
+    pub include_snoozed: bool,
+    /// Only return notifications for this user.
+    pub user_id: Option<String>,
+    /// Only return notifications targeting this device.
+    pub device_id: Option<String>,
+    /// Only return notifications targeting this session.
+    pub session_id: Option<String>,
+    /// Only return notifications carrying all of these tags.
+    pub tags: Vec<String>,
+    /// Only return notifications in this category.
+    pub category: Option<String>,
+    /// Only return notifications created after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only return notifications at or above this severity.
+    pub min_severity: Option<SeverityLevel>,
+    /// Only return notifications that require acknowledgement and have not been acknowledged.
+    pub unacknowledged: bool,
+    /// Allow bulk operations to affect every notification when no filters are set.
+    pub confirm_all: bool,
+    /// Have bulk operations report the affected count without changing anything.
+    pub dry_run: bool,
+}
+
+impl NotificationQuery {
+    /// Creates an empty query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of notifications to return.
+    pub fn limit(mut self, limit: i32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Sets the number of notifications to skip.
+    pub fn offset(mut self, offset: i32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Only returns unread notifications.
+    pub fn unread_only(mut self, unread_only: bool) -> Self {
+        self.unread_only = unread_only;
+        self
+    }
+
+    /// Includes snoozed notifications, which are excluded by default.
+    pub fn include_snoozed(mut self, include: bool) -> Self {
+        self.include_snoozed = include;
+        self
+    }
+
+    /// Only returns notifications for the given user.
+    pub fn user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Only returns notifications targeting the given device.
+    pub fn device(mut self, device_id: impl Into<String>) -> Self {
+        self.device_id = Some(device_id.into());
+        self
+    }
+
+    /// Only returns notifications targeting the given session.
+    pub fn session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Only returns notifications carrying the given tag. Multiple tags are combined with AND.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Only returns notifications in the given category.
+    pub fn category(mut self, category: impl Into<String>) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Only returns notifications created after the given time, e.g. the newest one already seen.
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only returns notifications at or above the given severity.
+    pub fn min_severity(mut self, severity: SeverityLevel) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Only returns notifications that require acknowledgement and have not been acknowledged.
+    ///
+    /// Combine with `min_severity(SeverityLevel::Critical)` for unacknowledged critical items.
+    pub fn unacknowledged(mut self) -> Self {
+        self.unacknowledged = true;
+        self
+    }
+
+    /// Allows `archive_matching` and `delete_matching` to run without filters,
+    /// affecting every notification.
+    pub fn confirm_delete_all(mut self) -> Self {
+        self.confirm_all = true;
+        self
+    }
+
+    /// Makes bulk operations report the would-be-affected count without changing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Checks whether any filter narrows the matched notifications.
+    pub fn has_filters(&self) -> bool {
+        self.unread_only
+            || self.user_id.is_some()
+            || self.device_id.is_some()
+            || self.session_id.is_some()
+            || !self.tags.is_empty()
+            || self.category.is_some()
+            || self.since.is_some()
+            || self.min_severity.is_some()
+            || self.unacknowledged
+    }
+
+    /// Builds the query string parameters.
+    pub(crate) fn to_params(&self) -> Vec<String> {
+        let mut params = page_params(self.limit, self.offset);
+
+        if self.unread_only {
+            params.push("unread_only=true".to_string());
+        }
+        if self.include_snoozed {
+            params.push("include_snoozed=true".to_string());
+        }
+        if let Some(ref u) = self.user_id {
+            params.push(format!("user_id={}", encode_query_value(u)));
+        }
+        if let Some(ref d) = self.device_id {
+            params.push(format!("device_id={}", encode_query_value(d)));
+        }
+        if let Some(ref s) = self.session_id {
+            params.push(format!("session_id={}", encode_query_value(s)));
+        }
+        for tag in &self.tags {
+            params.push(format!("tag={}", encode_query_value(tag)));
+        }
+        if let Some(ref category) = self.category {
+            params.push(format!("category={}", encode_query_value(category)));
+        }
+        if let Some(since) = self.since {
+            let since = since.to_rfc3339_opts(SecondsFormat::Millis, true);
+            params.push(format!("since={}", encode_query_value(&since)));
+        }
+        if let Some(severity) = self.min_severity {
+            params.push(format!("min_severity={}", severity));
+        }
+        if self.unacknowledged {
+            params.push("unacknowledged=true".to_string());
+        }
+        if self.dry_run {
+            params.push("dry_run=true".to_string());
+        }
+
+        params
+    }
+}
+
+/// Builds the pagination query string parameters shared by list endpoints.
+pub(crate) fn page_params(limit: Option<i32>, offset: Option<i32>) -> Vec<String> {
+    let mut params = Vec::new();
+
+    if let Some(l) = limit {
+        params.push(format!("limit={}", l));
+    }
+    if let Some(o) = offset {
+        params.push(format!("offset={}", o));
+    }
+
+    params
+}
+
+/// Percent-encodes a query string value.
+pub(crate) fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -1,79 +1,409 @@
 //! Offline queue for IronNotify SDK.
 
-use crate::types::NotificationPayload;
-use parking_lot::Mutex;
-use std::fs;
-use std::path::PathBuf;
+#[cfg(any(test, feature = "chaos"))]
+use crate::chaos::Chaos;
+use crate::debug::DebugSwitch;
+use crate::delivery::{DeliveryCallbacks, DeliveryKey, PendingDeliveries};
+use crate::drops::{DropLog, DropReason, DroppedNotification};
+use crate::error::Error;
+use crate::redact::Redactor;
+use crate::storage::JsonFile;
+use crate::types::{KeyEnvironment, NotificationPayload, SeverityLevel};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+#[cfg(feature = "quiet-hours")]
+static NEXT_DEFERRED_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A notification held back during quiet hours.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeferredNotification {
+    pub id: String,
+    pub payload: NotificationPayload,
+    pub deferred_at: DateTime<Utc>,
+    pub release_at: DateTime<Utc>,
+    /// Environment of the key the notification was deferred under.
+    #[serde(default)]
+    pub environment: Option<KeyEnvironment>,
+}
+
+/// A payload waiting in the offline queue.
+///
+/// Persisted with the payload fields at the top level, so queue files written
+/// before `enqueuedAt` existed still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedNotification {
+    /// Identifies the entry across processes sharing the queue file. Assigned on load
+    /// for older files.
+    #[serde(default, rename = "queueId")]
+    pub id: u64,
+    #[serde(flatten)]
+    pub payload: NotificationPayload,
+    /// When the payload was queued. Unset in older files, whose entries sort as the
+    /// oldest and keep their severity under `queue_severity_decay`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enqueued_at: Option<DateTime<Utc>>,
+    /// Environment of the key the payload was queued under. Unset in older files.
+    #[serde(default)]
+    pub environment: Option<KeyEnvironment>,
+    /// Serialized size of the payload in bytes. Computed on load for older files.
+    #[serde(default)]
+    pub size: usize,
+    /// Tenant the payload was sent for with `NotifyClient::with_tenant`, whose API key
+    /// or ID is used when it is flushed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// ID of the tenant the payload was sent for, when its API key was registered
+    /// with `NotifyClient::register_tenant_key`. Flushes wait until it is registered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Added with `enqueue_only` and not confirmed yet, so flushes skip it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub awaiting_confirmation: bool,
+    /// Number of flushes that failed to send the payload.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempts: u32,
+}
+
+fn is_zero(attempts: &u32) -> bool {
+    *attempts == 0
+}
+
+/// Identifies a notification added with `NotifyClient::enqueue_only`, to `confirm` or
+/// `abort` it.
+///
+/// Receipts serialize, so they can be stored alongside the transaction that queued
+/// the notification and used by another process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnqueueReceipt {
+    id: u64,
+    storage: ReceiptStorage,
+}
+
+/// Which queue an `EnqueueReceipt` refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ReceiptStorage {
+    Local,
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    Postgres,
+}
+
+impl EnqueueReceipt {
+    pub(crate) fn new(id: u64, storage: ReceiptStorage) -> Self {
+        Self { id, storage }
+    }
+
+    /// Gets the ID of the queue entry, stable across restarts.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub(crate) fn storage(&self) -> ReceiptStorage {
+        self.storage
+    }
+}
+
+/// Size of the offline queue.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QueueStats {
+    /// Number of queued notifications.
+    pub count: usize,
+    /// Total serialized size of the queued payloads in bytes.
+    pub bytes: usize,
+    /// Size of the queue file on disk in bytes, or `None` if it has not been written.
+    pub file_bytes: Option<u64>,
+    /// When the longest-waiting notification was queued, leaving out entries from
+    /// older files that did not record it.
+    pub oldest_enqueued_at: Option<DateTime<Utc>>,
+    /// Number of queued notifications at each severity. Severities with none queued
+    /// are left out.
+    pub by_severity: BTreeMap<SeverityLevel, usize>,
+}
+
+/// What `NotifyClient::compact_queue` removed from the offline queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompactionReport {
+    /// Number of queued notifications before compacting.
+    pub before_items: usize,
+    /// Number of queued notifications after compacting.
+    pub after_items: usize,
+    /// Size of the queue file before compacting, 0 if there was none.
+    pub before_bytes: u64,
+    /// Size of the queue file after compacting, 0 if there is none.
+    pub after_bytes: u64,
+}
 
 /// Offline queue for storing notifications when offline.
+///
+/// Processes sharing a home directory share the queue files. Every change takes a
+/// lock on the file and first merges in what other processes saved, so concurrent
+/// writers never lose each other's entries.
 pub struct OfflineQueue {
     max_size: usize,
-    debug: bool,
-    queue: Mutex<Vec<NotificationPayload>>,
-    storage_path: PathBuf,
+    max_bytes: Option<usize>,
+    debug: DebugSwitch,
+    environment: KeyEnvironment,
+    next_id: AtomicU64,
+    queue: Mutex<Vec<QueuedNotification>>,
+    deferred: Mutex<Vec<DeferredNotification>>,
+    storage: JsonFile,
+    deferred_storage: JsonFile,
+    queue_saved: AtomicBool,
+    deferred_saved: AtomicBool,
+    at_rest_redactor: Option<Redactor>,
+    drops: Arc<DropLog>,
+    deliveries: Arc<PendingDeliveries>,
+    #[cfg(any(test, feature = "chaos"))]
+    chaos: Option<Chaos>,
 }
 
 impl OfflineQueue {
     /// Creates a new OfflineQueue.
-    pub fn new(max_size: usize, debug: bool) -> Self {
-        let storage_path = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".ironnotify")
-            .join("offline_queue.json");
-
+    ///
+    /// Queue files are kept separately for each key environment, so test notifications
+    /// are never loaded by a client using a live key.
+    ///
+    /// When `at_rest_redactor` is set, payloads are redacted before being written to disk.
+    /// Discarded payloads are reported to `drops`. Callbacks of queued sends are kept
+    /// in `deliveries` by entry.
+    pub fn new(
+        max_size: usize,
+        max_bytes: Option<usize>,
+        debug: DebugSwitch,
+        environment: KeyEnvironment,
+        at_rest_redactor: Option<Redactor>,
+        drops: Arc<DropLog>,
+        deliveries: Arc<PendingDeliveries>,
+    ) -> Self {
         let queue = Self {
             max_size,
+            max_bytes,
             debug,
+            environment,
+            next_id: AtomicU64::new(RandomState::new().build_hasher().finish()),
             queue: Mutex::new(Vec::new()),
-            storage_path,
+            deferred: Mutex::new(Vec::new()),
+            storage: JsonFile::new(&format!("offline_queue.{}.json", environment)),
+            deferred_storage: JsonFile::new(&format!("deferred_queue.{}.json", environment)),
+            queue_saved: AtomicBool::new(true),
+            deferred_saved: AtomicBool::new(true),
+            at_rest_redactor,
+            drops,
+            deliveries,
+            #[cfg(any(test, feature = "chaos"))]
+            chaos: None,
         };
 
         queue.load_from_storage();
         queue
     }
 
-    /// Adds a notification to the queue.
-    pub fn add(&self, payload: NotificationPayload) {
-        let mut queue = self.queue.lock();
+    /// Sets the handle whose `fail_persistence` rule makes queue writes fail.
+    #[cfg(any(test, feature = "chaos"))]
+    pub fn with_chaos(mut self, chaos: Option<Chaos>) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Adds a notification to the queue, evicting entries until it fits; see
+    /// `evict_to_fit`. `callbacks` are kept until the entry is sent or dropped.
+    ///
+    /// Fails if the payload alone exceeds `max_offline_queue_bytes`, or if it only fits
+    /// by evicting entries awaiting confirmation.
+    pub fn add(
+        &self,
+        payload: NotificationPayload,
+        callbacks: DeliveryCallbacks,
+    ) -> Result<(), Error> {
+        let id = self.next_id();
+        self.push(id, payload, Some(self.environment), None, false, callbacks)
+            .map(|_| ())
+    }
+
+    /// Adds a notification like `add`, giving up if the queue is still locked by
+    /// another thread or process at `deadline`. Returns whether it was added.
+    pub fn add_by(&self, payload: NotificationPayload, deadline: Instant) -> Result<bool, Error> {
+        let id = self.next_id();
+        let callbacks = DeliveryCallbacks::default();
+        self.push(id, payload, Some(self.environment), Some(deadline), false, callbacks)
+            .map(|added| added.is_some())
+    }
+
+    /// Adds a notification like `add` that is not flushed until it is confirmed,
+    /// returning its ID.
+    pub fn add_unconfirmed(&self, payload: NotificationPayload) -> Result<u64, Error> {
+        let id = self.next_id();
+        let callbacks = DeliveryCallbacks::default();
+        self.push(id, payload, Some(self.environment), None, true, callbacks)
+            .map(|added| added.expect("queue update without a deadline completes"))
+    }
+
+    /// Lets flushes send an entry added with `add_unconfirmed`. Returns false if it is
+    /// not waiting for confirmation, e.g. because it was aborted.
+    pub fn confirm(&self, id: u64) -> bool {
+        self.update_queue(|queue| {
+            let item = queue
+                .iter_mut()
+                .find(|item| item.id == id && item.awaiting_confirmation);
+            match item {
+                Some(item) => {
+                    item.awaiting_confirmation = false;
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    /// Removes an entry added with `add_unconfirmed`. Returns false if it is not
+    /// waiting for confirmation, e.g. because it was already confirmed.
+    pub fn abort(&self, id: u64) -> bool {
+        let aborted = self.update_queue(|queue| {
+            let count = queue.len();
+            queue.retain(|item| item.id != id || !item.awaiting_confirmation);
+            queue.len() < count
+        });
+        if aborted {
+            let key = DeliveryKey::Queued(id);
+            self.deliveries.fail(&key, "Dropped: aborted".to_string(), None);
+        }
+        aborted
+    }
 
-        if queue.len() >= self.max_size {
-            queue.remove(0);
-            if self.debug {
-                println!("[IronNotify] Offline queue full, dropping oldest notification");
+    /// Adds a payload as entry `id`, keeping `callbacks` for it. Returns `None` if the
+    /// locks were not free by `deadline`.
+    ///
+    /// The callbacks are kept before the entry is saved, so a flush in another task
+    /// cannot send it before they are in place. Fails without keeping them if the
+    /// payload cannot fit.
+    fn push(
+        &self,
+        id: u64,
+        payload: NotificationPayload,
+        environment: Option<KeyEnvironment>,
+        deadline: Option<Instant>,
+        awaiting_confirmation: bool,
+        callbacks: DeliveryCallbacks,
+    ) -> Result<Option<u64>, Error> {
+        let size = payload.estimated_wire_size();
+        if let Some(limit) = self.max_bytes {
+            if size > limit {
+                return Err(Error::QueueItemTooLarge { size, limit });
             }
         }
 
-        if self.debug {
+        if self.debug.is_enabled() {
             println!(
                 "[IronNotify] Notification queued for later: {}",
                 payload.event_type
             );
         }
 
-        queue.push(payload);
-        drop(queue);
-        self.save_to_storage();
+        let (tenant, tenant_id) = crate::tenant::persisted();
+        let item = QueuedNotification {
+            id,
+            payload,
+            enqueued_at: Some(Utc::now()),
+            environment,
+            size,
+            tenant,
+            tenant_id,
+            awaiting_confirmation,
+            attempts: 0,
+        };
+        let evicted = self.update_queue_until(deadline, |queue| {
+            let evicted = evict_to_fit(queue, self.max_size, self.max_bytes, size)?;
+            self.deliveries.register(DeliveryKey::Queued(id), callbacks);
+            queue.push(item);
+            Some(evicted)
+        });
+        let Some(evicted) = evicted else {
+            return Ok(None);
+        };
+        let Some(evicted) = evicted else {
+            return Err(Error::QueueFull);
+        };
+
+        for evicted in evicted {
+            self.drop_entry(&evicted, DropReason::QueueFull);
+        }
+        Ok(Some(id))
+    }
+
+    /// Removes notifications that expired at or before `now`.
+    pub fn purge_expired(&self, now: DateTime<Utc>) {
+        let expired = self.update_queue(|queue| {
+            let (expired, live): (Vec<_>, Vec<_>) = queue
+                .drain(..)
+                .partition(|item| item.payload.expires_at.is_some_and(|at| at <= now));
+            *queue = live;
+            expired
+        });
+
+        for item in expired {
+            self.drop_entry(&item, DropReason::Expired);
+        }
+    }
+
+    /// Removes notifications queued under a different key environment, returning how many
+    /// were removed.
+    pub fn purge_foreign(&self) -> usize {
+        let environment = Some(self.environment);
+        let foreign = self.update_queue(|queue| {
+            let (foreign, own): (Vec<_>, Vec<_>) = queue
+                .drain(..)
+                .partition(|item| item.environment != environment);
+            *queue = own;
+            foreign
+        });
+
+        let count = foreign.len();
+        for item in foreign {
+            self.drop_entry(&item, DropReason::EnvironmentMismatch);
+        }
+        count
     }
 
-    /// Gets all queued notifications.
-    pub fn get_all(&self) -> Vec<NotificationPayload> {
+    /// Gets all queued notifications, oldest first.
+    pub fn get_all(&self) -> Vec<QueuedNotification> {
         self.queue.lock().clone()
     }
 
-    /// Removes a notification at the given index.
-    pub fn remove(&self, index: usize) {
-        let mut queue = self.queue.lock();
-        if index < queue.len() {
-            queue.remove(index);
-            drop(queue);
-            self.save_to_storage();
+    /// Removes the queued notification with the given ID.
+    pub fn remove(&self, id: u64) {
+        self.update_queue(|queue| queue.retain(|item| item.id != id));
+    }
+
+    /// Clears the queue, returning how many notifications were removed.
+    pub fn clear(&self) -> usize {
+        let cleared = self.update_queue(std::mem::take);
+        if !self.deliveries.is_empty() {
+            for item in &cleared {
+                let error = "Dropped: queue cleared".to_string();
+                let key = DeliveryKey::Queued(item.id);
+                self.deliveries.fail(&key, error, Some(item.payload.content_hash()));
+            }
         }
+        cleared.len()
     }
 
-    /// Clears the queue.
-    pub fn clear(&self) {
-        self.queue.lock().clear();
-        self.save_to_storage();
+    /// Checks if an ordered payload with the given group key is waiting in the queue.
+    pub fn has_ordered(&self, key: &str) -> bool {
+        self.queue
+            .lock()
+            .iter()
+            .any(|item| item.payload.ordering_key() == Some(key))
     }
 
     /// Gets the queue size.
@@ -81,25 +411,943 @@ impl OfflineQueue {
         self.queue.lock().len()
     }
 
-    /// Checks if the queue is empty.
-    pub fn is_empty(&self) -> bool {
-        self.queue.lock().is_empty()
+    /// Gets the number, total size, age, and severities of the queued notifications.
+    pub fn stats(&self) -> QueueStats {
+        let queue = self.queue.lock();
+        let mut by_severity = BTreeMap::new();
+        for item in queue.iter() {
+            *by_severity
+                .entry(item.payload.severity.unwrap_or_default())
+                .or_insert(0) += 1;
+        }
+        QueueStats {
+            count: queue.len(),
+            bytes: queue.iter().map(|item| item.size).sum(),
+            file_bytes: self.storage.size(),
+            oldest_enqueued_at: queue.iter().filter_map(|item| item.enqueued_at).min(),
+            by_severity,
+        }
+    }
+
+    /// Gets the size of the queue file on disk, if it exists.
+    pub fn file_size(&self) -> Option<u64> {
+        self.storage.size()
+    }
+
+    /// Counts a failed attempt to send a queued notification, removing it once it has
+    /// failed `max_attempts` times.
+    pub fn record_attempt(&self, id: u64, max_attempts: Option<u32>) {
+        let exhausted = self.update_queue(|queue| {
+            let index = queue.iter().position(|item| item.id == id)?;
+            queue[index].attempts += 1;
+            let attempts = queue[index].attempts;
+            max_attempts
+                .is_some_and(|max| attempts >= max)
+                .then(|| queue.remove(index))
+        });
+        if let Some(item) = exhausted {
+            self.drop_entry(&item, DropReason::RetriesExhausted);
+        }
+    }
+
+    /// Shrinks the queue: keeps only the newest of the notifications sharing a
+    /// deduplication key and recipient, and removes expired ones and ones that failed
+    /// to send `max_attempts` times. The file is then rewritten in one step.
+    ///
+    /// Entries awaiting confirmation are left alone. Removed entries are reported to
+    /// the drop log.
+    pub fn compact(&self, now: DateTime<Utc>, max_attempts: Option<u32>) -> CompactionReport {
+        let before_bytes = self.storage.size().unwrap_or(0);
+        let (before_items, removed) = self.update_queue(|queue| {
+            let before_items = queue.len();
+            let mut removed = Vec::new();
+            let mut seen_ids = HashSet::new();
+            let mut seen_keys = HashSet::new();
+            let mut kept = Vec::with_capacity(queue.len());
+
+            // Walk newest first, so the first entry seen for a key is the one kept.
+            for item in std::mem::take(queue).into_iter().rev() {
+                if !seen_ids.insert(item.id) {
+                    continue;
+                }
+                if item.awaiting_confirmation {
+                    kept.push(item);
+                    continue;
+                }
+                let reason = if item.payload.expires_at.is_some_and(|at| at <= now) {
+                    Some(DropReason::Expired)
+                } else if max_attempts.is_some_and(|max| item.attempts >= max) {
+                    Some(DropReason::RetriesExhausted)
+                } else if dedup_key(&item).is_some_and(|key| !seen_keys.insert(key)) {
+                    Some(DropReason::Duplicate)
+                } else {
+                    None
+                };
+                match reason {
+                    Some(reason) => removed.push((item, reason)),
+                    None => kept.push(item),
+                }
+            }
+            kept.reverse();
+            *queue = kept;
+            (before_items, removed)
+        });
+
+        for (item, reason) in &removed {
+            self.drop_entry(item, *reason);
+        }
+        let report = CompactionReport {
+            before_items,
+            after_items: before_items - removed.len(),
+            before_bytes,
+            after_bytes: self.storage.size().unwrap_or(0),
+        };
+        if self.debug.is_enabled() {
+            println!(
+                "[IronNotify] Compacted offline queue from {} to {} notifications ({} to {} bytes)",
+                report.before_items, report.after_items, report.before_bytes, report.after_bytes
+            );
+        }
+        report
+    }
+
+    /// Holds a notification until `release_at`, returning its deferred ID.
+    #[cfg(feature = "quiet-hours")]
+    pub fn defer(
+        &self,
+        payload: NotificationPayload,
+        release_at: DateTime<Utc>,
+        callbacks: DeliveryCallbacks,
+    ) -> String {
+        let now = Utc::now();
+        let id = format!(
+            "{:x}-{:x}-{:x}",
+            now.timestamp_millis(),
+            std::process::id(),
+            NEXT_DEFERRED_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        if self.debug.is_enabled() {
+            println!(
+                "[IronNotify] Notification deferred until {}: {}",
+                release_at, payload.event_type
+            );
+        }
+
+        self.deliveries.register(DeliveryKey::Deferred(id.clone()), callbacks);
+        let item = DeferredNotification {
+            id: id.clone(),
+            payload,
+            deferred_at: now,
+            release_at,
+            environment: Some(self.environment),
+        };
+        self.update_deferred(|deferred| deferred.push(item));
+        id
+    }
+
+    /// Gets all deferred notifications.
+    pub fn deferred(&self) -> Vec<DeferredNotification> {
+        self.deferred.lock().clone()
+    }
+
+    /// Cancels a deferred notification so that it is never sent.
+    pub fn cancel_deferred(&self, id: &str) -> bool {
+        let cancelled = self.update_deferred(|deferred| {
+            let before = deferred.len();
+            deferred.retain(|item| item.id != id);
+            deferred.len() != before
+        });
+        if cancelled {
+            let key = DeliveryKey::Deferred(id.to_string());
+            self.deliveries.fail(&key, "Dropped: cancelled".to_string(), None);
+        }
+        cancelled
+    }
+
+    /// Moves deferred notifications due at `now` into the queue, returning how many moved.
+    pub fn release_due(&self, now: DateTime<Utc>) -> usize {
+        let due = self.update_deferred(|deferred| {
+            let (due, pending): (Vec<_>, Vec<_>) =
+                deferred.drain(..).partition(|item| item.release_at <= now);
+            *deferred = pending;
+            due
+        });
+
+        let count = due.len();
+        for item in due {
+            let id = self.next_id();
+            let key = DeliveryKey::Queued(id);
+            self.deliveries.rekey(&DeliveryKey::Deferred(item.id), key.clone());
+            let mut dropped = DroppedNotification::new(&item.payload, DropReason::TooLarge);
+            let callbacks = DeliveryCallbacks::default();
+            match self.push(id, item.payload, item.environment, None, false, callbacks) {
+                Err(Error::QueueFull) => {
+                    dropped.reason = DropReason::QueueFull;
+                    self.drops.record_entry(key, dropped);
+                }
+                Err(_) => self.drops.record_entry(key, dropped),
+                Ok(_) => {}
+            }
+        }
+        count
+    }
+
+    /// Gets the earliest release time of the deferred notifications.
+    pub fn next_release(&self) -> Option<DateTime<Utc>> {
+        self.deferred.lock().iter().map(|item| item.release_at).min()
     }
 
     fn load_from_storage(&self) {
-        if let Ok(data) = fs::read_to_string(&self.storage_path) {
-            if let Ok(queue) = serde_json::from_str::<Vec<NotificationPayload>>(&data) {
-                *self.queue.lock() = queue;
+        if let Some(data) = self.storage.read() {
+            *self.queue.lock() = self.parse_queue(&data);
+        }
+        if let Some(data) = self.deferred_storage.read() {
+            *self.deferred.lock() = self.parse_entries(&data);
+        }
+        self.migrate_legacy_files();
+    }
+
+    /// Moves entries from the queue files used before they were split by key environment
+    /// into this queue, then deletes the old files.
+    ///
+    /// The old files do not record which key queued each entry, so the first client to
+    /// start after upgrading adopts them under its own environment.
+    fn migrate_legacy_files(&self) {
+        let legacy = JsonFile::new("offline_queue.json");
+        let lock = legacy.lock();
+        if let Some(data) = legacy.read() {
+            let mut entries = self.parse_queue(&data);
+            if self.debug.is_enabled() && !entries.is_empty() {
+                println!(
+                    "[IronNotify] Migrating {} queued notifications to the {} queue",
+                    entries.len(),
+                    self.environment
+                );
+            }
+            for item in entries.iter_mut() {
+                item.environment.get_or_insert(self.environment);
+                item.size = item.payload.estimated_wire_size();
+            }
+            self.update_queue(|queue| queue.extend(entries));
+            legacy.remove();
+        }
+        drop(lock);
+
+        let legacy_deferred = JsonFile::new("deferred_queue.json");
+        let _lock = legacy_deferred.lock();
+        if let Some(data) = legacy_deferred.read() {
+            let mut entries: Vec<DeferredNotification> = self.parse_entries(&data);
+            for item in entries.iter_mut() {
+                item.environment.get_or_insert(self.environment);
+            }
+            self.update_deferred(|deferred| deferred.extend(entries));
+            legacy_deferred.remove();
+        }
+    }
+
+    /// Parses persisted queue entries, assigning IDs and sizes missing from older files.
+    fn parse_queue(&self, data: &str) -> Vec<QueuedNotification> {
+        let mut entries: Vec<QueuedNotification> = self.parse_entries(data);
+        for item in entries.iter_mut() {
+            if item.id == 0 {
+                item.id = self.next_id();
+            }
+            if item.size == 0 {
+                item.size = item.payload.estimated_wire_size();
+            }
+        }
+        entries
+    }
+
+    /// Reports a removed entry to the drop log, failing the callbacks kept for it.
+    fn drop_entry(&self, item: &QueuedNotification, reason: DropReason) {
+        let dropped = DroppedNotification::new(&item.payload, reason);
+        self.drops.record_entry(DeliveryKey::Queued(item.id), dropped);
+    }
+
+    /// Gets a new queue entry ID.
+    ///
+    /// IDs count up from a random start, so entries queued by different processes
+    /// do not collide.
+    fn next_id(&self) -> u64 {
+        match self.next_id.fetch_add(1, Ordering::Relaxed) {
+            0 => self.next_id.fetch_add(1, Ordering::Relaxed),
+            id => id,
+        }
+    }
+
+    /// Runs `f` on the queue while holding the queue file's lock, after merging in
+    /// changes saved by other processes, then saves the result if it changed.
+    fn update_queue<R>(&self, f: impl FnOnce(&mut Vec<QueuedNotification>) -> R) -> R {
+        let lock = self.storage.lock();
+        let queue = self.queue.lock();
+        // Waiting for the queue may have taken long enough for the lock to look stale.
+        if let Some(lock) = &lock {
+            lock.touch();
+        }
+        self.apply_update(queue, f)
+    }
+
+    /// Runs `f` like `update_queue`, giving up with `None` if the locks are not free
+    /// by `deadline`.
+    fn update_queue_until<R>(
+        &self,
+        deadline: Option<Instant>,
+        f: impl FnOnce(&mut Vec<QueuedNotification>) -> R,
+    ) -> Option<R> {
+        let Some(deadline) = deadline else {
+            return Some(self.update_queue(f));
+        };
+        let lock = self.storage.lock_until(Some(deadline)).ok()?;
+        let queue = self.queue.try_lock_until(deadline)?;
+        if let Some(lock) = &lock {
+            lock.touch();
+        }
+        Some(self.apply_update(queue, f))
+    }
+
+    /// Merges in saved changes, runs `f`, and saves, with both locks already held.
+    fn apply_update<R>(
+        &self,
+        mut queue: MutexGuard<'_, Vec<QueuedNotification>>,
+        f: impl FnOnce(&mut Vec<QueuedNotification>) -> R,
+    ) -> R {
+        let current = self.storage.read();
+        if self.queue_saved.load(Ordering::Relaxed) {
+            if let Some(ref data) = current {
+                let saved = self.parse_queue(data);
+                // Another process may have confirmed an entry this one still holds.
+                let confirmed: HashSet<u64> = saved
+                    .iter()
+                    .filter(|item| !item.awaiting_confirmation)
+                    .map(|item| item.id)
+                    .collect();
+                merge_saved(&mut queue, saved, |item| item.id);
+                for item in queue.iter_mut() {
+                    if confirmed.contains(&item.id) {
+                        item.awaiting_confirmation = false;
+                    }
+                }
             }
         }
+        let result = f(&mut queue);
+        let saved = self.save_to_storage(&queue, current.as_deref());
+        self.queue_saved.store(saved, Ordering::Relaxed);
+        result
     }
 
-    fn save_to_storage(&self) {
-        if let Some(parent) = self.storage_path.parent() {
-            let _ = fs::create_dir_all(parent);
+    /// Runs `f` on the deferred notifications while holding the deferred file's lock,
+    /// after merging in changes saved by other processes, then saves the result if it changed.
+    fn update_deferred<R>(&self, f: impl FnOnce(&mut Vec<DeferredNotification>) -> R) -> R {
+        let lock = self.deferred_storage.lock();
+        let mut deferred = self.deferred.lock();
+        if let Some(lock) = &lock {
+            lock.touch();
         }
-        if let Ok(json) = serde_json::to_string(&*self.queue.lock()) {
-            let _ = fs::write(&self.storage_path, json);
+        let current = self.deferred_storage.read();
+        if self.deferred_saved.load(Ordering::Relaxed) {
+            if let Some(ref data) = current {
+                let saved = self.parse_entries(data);
+                merge_saved(&mut deferred, saved, |item: &DeferredNotification| {
+                    item.id.clone()
+                });
+            }
         }
+        let result = f(&mut deferred);
+        let saved = self.save_deferred(&deferred, current.as_deref());
+        self.deferred_saved.store(saved, Ordering::Relaxed);
+        result
+    }
+
+    /// Parses persisted entries one at a time, reporting those that cannot be read.
+    fn parse_entries<T: serde::de::DeserializeOwned>(&self, data: &str) -> Vec<T> {
+        let values = match serde_json::from_str::<Vec<serde_json::Value>>(data) {
+            Ok(values) => values,
+            Err(_) => {
+                if !data.trim().is_empty() {
+                    self.drops
+                        .record(DroppedNotification::unreadable(DropReason::CorruptEntry));
+                }
+                return Vec::new();
+            }
+        };
+
+        values
+            .into_iter()
+            .filter_map(|value| match serde_json::from_value::<T>(value.clone()) {
+                Ok(entry) => Some(entry),
+                Err(_) => {
+                    let dropped = match serde_json::from_value::<NotificationPayload>(value) {
+                        Ok(payload) => DroppedNotification::new(&payload, DropReason::CorruptEntry),
+                        Err(_) => DroppedNotification::unreadable(DropReason::CorruptEntry),
+                    };
+                    self.drops.record(dropped);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Checks whether a chaos rule is failing queue writes.
+    fn persistence_fails(&self) -> bool {
+        #[cfg(any(test, feature = "chaos"))]
+        if self.chaos.as_ref().is_some_and(Chaos::persistence_fails) {
+            if self.debug.is_enabled() {
+                println!("[IronNotify] Chaos: failing queue write");
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Writes the deferred notifications to disk unless they match `current`, returning
+    /// whether the file is up to date.
+    fn save_deferred(&self, deferred: &[DeferredNotification], current: Option<&str>) -> bool {
+        if self.persistence_fails() {
+            return false;
+        }
+        match self.at_rest_redactor {
+            Some(ref redactor) => {
+                let redacted: Vec<DeferredNotification> = deferred
+                    .iter()
+                    .map(|item| DeferredNotification {
+                        payload: redactor.redact_payload(&item.payload),
+                        ..item.clone()
+                    })
+                    .collect();
+                self.deferred_storage.write_changed(&redacted, current)
+            }
+            None => self.deferred_storage.write_changed(deferred, current),
+        }
+    }
+
+    /// Writes the queue to disk unless it matches `current`, returning whether the file
+    /// is up to date.
+    fn save_to_storage(&self, queue: &[QueuedNotification], current: Option<&str>) -> bool {
+        if self.persistence_fails() {
+            return false;
+        }
+        match self.at_rest_redactor {
+            Some(ref redactor) => {
+                let redacted: Vec<QueuedNotification> = queue
+                    .iter()
+                    .map(|item| QueuedNotification {
+                        payload: redactor.redact_payload(&item.payload),
+                        ..item.clone()
+                    })
+                    .collect();
+                self.storage.write_changed(&redacted, current)
+            }
+            None => self.storage.write_changed(queue, current),
+        }
+    }
+}
+
+/// Gets what makes queued notifications duplicates of each other: the same
+/// deduplication key for the same recipient, tenant, and key environment.
+fn dedup_key(item: &QueuedNotification) -> Option<DedupKey> {
+    let payload = &item.payload;
+    let key = payload.deduplication_key.clone()?;
+    Some((
+        key,
+        payload.user_id.clone(),
+        payload.device_id.clone(),
+        payload.session_id.clone(),
+        item.tenant.clone(),
+        item.tenant_id.clone(),
+        item.environment,
+    ))
+}
+
+type DedupKey = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<KeyEnvironment>,
+);
+
+/// Replaces `held` with the `saved` entries, keeping the in-memory copy of each entry
+/// this process already holds.
+///
+/// Entries missing from `saved` were removed by another process. In-memory copies are
+/// kept because saved payloads may be redacted.
+pub(crate) fn merge_saved<T, K: Eq + Hash>(held: &mut Vec<T>, saved: Vec<T>, key: impl Fn(&T) -> K) {
+    let mut own: HashMap<K, T> = held.drain(..).map(|item| (key(&item), item)).collect();
+    *held = saved
+        .into_iter()
+        .map(|item| own.remove(&key(&item)).unwrap_or(item))
+        .collect();
+}
+
+/// Evicts entries until an item of `size` bytes fits, returning them in the order
+/// they were evicted.
+///
+/// While the queue holds `max_size` items, the oldest is evicted. While it is over
+/// `max_bytes`, the least severe is, oldest first among equally severe ones, so a
+/// burst of large Info payloads cannot push out queued Critical ones.
+///
+/// Entries awaiting confirmation are never evicted. Returns `None`, leaving the
+/// queue unchanged, if the item cannot fit without evicting one.
+fn evict_to_fit(
+    queue: &mut Vec<QueuedNotification>,
+    max_size: usize,
+    max_bytes: Option<usize>,
+    size: usize,
+) -> Option<Vec<QueuedNotification>> {
+    let mut evictable: Vec<usize> =
+        (0..queue.len()).filter(|&i| !queue[i].awaiting_confirmation).collect();
+    let mut count = queue.len();
+    let mut bytes: usize = queue.iter().map(|item| item.size).sum();
+    let mut chosen = Vec::new();
+    loop {
+        let position = if count >= max_size {
+            0
+        } else if max_bytes.is_some_and(|limit| bytes + size > limit) {
+            (0..evictable.len())
+                .min_by_key(|&i| {
+                    let item = &queue[evictable[i]];
+                    (item.payload.severity.unwrap_or_default(), item.enqueued_at)
+                })
+                .unwrap_or(0)
+        } else {
+            break;
+        };
+        if position >= evictable.len() {
+            return None;
+        }
+        let index = evictable.remove(position);
+        count -= 1;
+        bytes -= queue[index].size;
+        chosen.push(index);
+    }
+
+    let mut slots: Vec<Option<QueuedNotification>> = queue.drain(..).map(Some).collect();
+    let evicted = chosen.into_iter().filter_map(|index| slots[index].take()).collect();
+    *queue = slots.into_iter().flatten().collect();
+    Some(evicted)
+}
+
+/// Checks if a payload is drained ahead of the rest and sent despite a low rate limit.
+pub(crate) fn is_high_priority(payload: &NotificationPayload) -> bool {
+    payload.severity.unwrap_or_default() >= SeverityLevel::Error
+}
+
+/// Gets the order in which queued notifications are sent, as indices into `items`.
+///
+/// Notifications are ordered by severity, most severe first, then by age, oldest
+/// first. After every `fairness` Error or Critical sends, the oldest remaining
+/// lower-severity notification is sent so it cannot starve. A `fairness` of 0
+/// disables interleaving. Entries from older files, which did not record when they
+/// were queued, count as the oldest.
+///
+/// Ordered notifications sharing a group key keep their queue order: they take the
+/// same slots, filled oldest first.
+pub fn drain_order(items: &[QueuedNotification], fairness: usize) -> Vec<usize> {
+    let mut sorted: Vec<usize> = (0..items.len()).collect();
+    sorted.sort_by(|&a, &b| {
+        let (a, b) = (&items[a], &items[b]);
+        b.payload
+            .severity
+            .unwrap_or_default()
+            .cmp(&a.payload.severity.unwrap_or_default())
+            .then(a.enqueued_at.cmp(&b.enqueued_at))
+    });
+
+    let (high, mut low): (Vec<usize>, Vec<usize>) =
+        sorted.into_iter().partition(|&i| is_high_priority(&items[i].payload));
+
+    let mut order = Vec::with_capacity(items.len());
+    for (sent, index) in high.into_iter().enumerate() {
+        order.push(index);
+        if fairness > 0 && (sent + 1) % fairness == 0 && !low.is_empty() {
+            let oldest = (0..low.len())
+                .min_by_key(|&i| (items[low[i]].enqueued_at, low[i]))
+                .unwrap_or(0);
+            order.push(low.remove(oldest));
+        }
+    }
+    order.extend(low);
+    keep_group_order(items, &mut order);
+    order
+}
+
+/// Reassigns the slots held by each ordered group so its items go out oldest first.
+fn keep_group_order(items: &[QueuedNotification], order: &mut [usize]) {
+    let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (slot, &index) in order.iter().enumerate() {
+        if let Some(key) = items[index].payload.ordering_key() {
+            groups.entry(key).or_default().push(slot);
+        }
+    }
+
+    for slots in groups.into_values() {
+        let mut members: Vec<usize> = slots.iter().map(|&slot| order[slot]).collect();
+        members.sort_by_key(|&index| (items[index].enqueued_at, items[index].id));
+        for (slot, index) in slots.into_iter().zip(members) {
+            order[slot] = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyOptions;
+    use crate::delivery::PendingDeliveries;
+    use crate::reconfigure::LiveConfig;
+    use crate::test_support::TempStorage;
+    use crate::types::SendResult;
+    use chrono::TimeZone;
+    use std::time::{Duration, SystemTime};
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, minute, 0).unwrap()
+    }
+
+    fn item(id: u64, severity: SeverityLevel, minute: u32) -> QueuedNotification {
+        let mut payload = NotificationPayload::new(format!("event.{}", id), "Title");
+        payload.severity = Some(severity);
+        QueuedNotification {
+            id,
+            size: payload.estimated_wire_size(),
+            payload,
+            enqueued_at: Some(at(minute)),
+            environment: Some(KeyEnvironment::Test),
+            tenant: None,
+            tenant_id: None,
+            awaiting_confirmation: false,
+            attempts: 0,
+        }
+    }
+
+    fn ids(items: &[QueuedNotification], order: Vec<usize>) -> Vec<u64> {
+        order.into_iter().map(|i| items[i].id).collect()
+    }
+
+    fn mixed_queue() -> Vec<QueuedNotification> {
+        use SeverityLevel::*;
+        vec![
+            item(1, Info, 0),
+            item(2, Critical, 5),
+            item(3, Error, 1),
+            item(4, Warning, 2),
+            item(5, Error, 3),
+            item(6, Critical, 4),
+            item(7, Info, 6),
+            item(8, Error, 7),
+            item(9, Success, 8),
+        ]
+    }
+
+    #[test]
+    fn drain_order_is_by_severity_then_age() {
+        let items = mixed_queue();
+        assert_eq!(ids(&items, drain_order(&items, 0)), [6, 2, 3, 5, 8, 4, 9, 1, 7]);
+    }
+
+    #[test]
+    fn drain_order_interleaves_the_oldest_low_severity_item() {
+        let items = mixed_queue();
+        assert_eq!(ids(&items, drain_order(&items, 2)), [6, 2, 1, 3, 5, 4, 8, 9, 7]);
+        assert_eq!(ids(&items, drain_order(&items, 1)), [6, 1, 2, 4, 3, 7, 5, 9, 8]);
+    }
+
+    #[test]
+    fn drain_order_is_deterministic_for_equal_items() {
+        let items: Vec<_> = (1..=6).map(|id| item(id, SeverityLevel::Error, 0)).collect();
+        let first = drain_order(&items, 2);
+        for _ in 0..10 {
+            assert_eq!(drain_order(&items, 2), first);
+        }
+        assert_eq!(ids(&items, first), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn drain_order_keeps_ordered_groups_in_queue_order() {
+        let mut items = mixed_queue();
+        for index in [0, 1] {
+            items[index].payload.group_key = Some("thread".to_string());
+            items[index].payload.ordered = true;
+        }
+        // The Critical item 2 was queued after the Info item 1 in the same group, so
+        // item 1 takes the group's first slot.
+        assert_eq!(ids(&items, drain_order(&items, 0)), [6, 1, 3, 5, 8, 4, 9, 2, 7]);
+    }
+
+    /// Drop log and delivery callbacks shared by the queues of one test.
+    struct Logs {
+        drops: Arc<DropLog>,
+        deliveries: Arc<PendingDeliveries>,
+    }
+
+    impl Logs {
+        fn new() -> Self {
+            let deliveries = Arc::new(PendingDeliveries::default());
+            let config = LiveConfig::new(&NotifyOptions::new("ak_test_queue"));
+            let drops = DropLog::new(100, DebugSwitch::queue(&config), Arc::clone(&deliveries));
+            Self {
+                drops: Arc::new(drops),
+                deliveries,
+            }
+        }
+
+        fn queue(&self, max_size: usize) -> OfflineQueue {
+            let config = LiveConfig::new(&NotifyOptions::new("ak_test_queue"));
+            OfflineQueue::new(
+                max_size,
+                None,
+                DebugSwitch::queue(&config),
+                KeyEnvironment::Test,
+                None,
+                Arc::clone(&self.drops),
+                Arc::clone(&self.deliveries),
+            )
+        }
+    }
+
+    #[test]
+    fn queues_on_one_path_in_separate_threads_keep_every_entry() {
+        let storage = TempStorage::new();
+        let logs = Logs::new();
+        let queues: Vec<OfflineQueue> = (0..4).map(|_| logs.queue(1000)).collect();
+        // A crashed process left its lock behind.
+        let lock = storage.path().join("offline_queue.test.json.lock");
+        std::fs::write(&lock, "1:0").unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&lock).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+
+        std::thread::scope(|scope| {
+            for (thread, queue) in queues.iter().enumerate() {
+                scope.spawn(move || {
+                    for i in 0..25 {
+                        let payload = NotificationPayload::new(format!("e{}.{}", thread, i), "t");
+                        queue.add(payload, DeliveryCallbacks::default()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let reloaded = logs.queue(1000);
+        assert_eq!(reloaded.size(), 100);
+        let ids: HashSet<u64> = reloaded.get_all().iter().map(|item| item.id).collect();
+        assert_eq!(ids.len(), 100);
+        assert!(logs.drops.recent(100).is_empty());
+        assert!(!lock.exists());
+    }
+
+    #[test]
+    fn entries_are_dropped_once_their_attempts_run_out() {
+        let _storage = TempStorage::new();
+        let logs = Logs::new();
+        let queue = logs.queue(10);
+        queue
+            .add(NotificationPayload::new("e", "t"), DeliveryCallbacks::default())
+            .unwrap();
+        let id = queue.get_all()[0].id;
+
+        queue.record_attempt(id, Some(2));
+        assert_eq!(queue.get_all()[0].attempts, 1);
+        queue.record_attempt(id, Some(2));
+        assert!(queue.get_all().is_empty());
+        let drops = logs.drops.recent(10);
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].reason, DropReason::RetriesExhausted);
+        assert_eq!(drops[0].event_type.as_deref(), Some("e"));
+    }
+
+    #[test]
+    fn corrupt_entries_in_a_loaded_queue_are_reported_and_the_rest_kept() {
+        let storage = TempStorage::new();
+        let logs = Logs::new();
+        let entries = serde_json::json!([
+            { "eventType": "kept", "title": "Kept" },
+            { "eventType": "corrupt", "title": "Corrupt", "queueId": "not a number" },
+        ]);
+        std::fs::write(storage.path().join("offline_queue.test.json"), entries.to_string())
+            .unwrap();
+
+        let queue = logs.queue(10);
+        let kept: Vec<String> =
+            queue.get_all().into_iter().map(|item| item.payload.event_type).collect();
+        assert_eq!(kept, ["kept"]);
+        let drops = logs.drops.recent(10);
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].reason, DropReason::CorruptEntry);
+        assert_eq!(drops[0].event_type.as_deref(), Some("corrupt"));
+    }
+
+    type Results = Arc<Mutex<Vec<(&'static str, SendResult)>>>;
+
+    fn recording(results: &Results, label: &'static str) -> DeliveryCallbacks {
+        let results = Arc::clone(results);
+        DeliveryCallbacks {
+            on_result: Some(Arc::new(move |result: &SendResult| {
+                results.lock().push((label, result.clone()));
+            })),
+            on_read: None,
+        }
+    }
+
+    #[test]
+    fn every_drop_path_fails_the_callbacks_of_its_own_entry() {
+        let _storage = TempStorage::new();
+        let logs = Logs::new();
+        let queue = logs.queue(2);
+        let results = Results::default();
+        // Identical content, so only the entry ID tells the callbacks apart.
+        let payload = || NotificationPayload::new("order.created", "Order");
+        let add = |label| queue.add(payload(), recording(&results, label)).unwrap();
+
+        add("evicted");
+        let mut expired = payload();
+        expired.expires_at = Some(Utc::now() - chrono::Duration::minutes(1));
+        queue.add(expired, recording(&results, "expired")).unwrap();
+        add("exhausted");
+        queue.purge_expired(Utc::now());
+        let exhausted = queue.get_all()[0].id;
+        queue.record_attempt(exhausted, None);
+        queue.compact(Utc::now(), Some(1));
+
+        let aborted = queue.add_unconfirmed(payload()).unwrap();
+        logs.deliveries
+            .register(DeliveryKey::Queued(aborted), recording(&results, "aborted"));
+        queue.abort(aborted);
+        add("cleared");
+        queue.clear();
+
+        let results = results.lock();
+        let outcomes: Vec<(&str, Option<&str>)> = results
+            .iter()
+            .map(|(label, result)| (*label, result.error.as_deref()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("evicted", Some("Dropped: queue full")),
+                ("expired", Some("Dropped: expired")),
+                ("exhausted", Some("Dropped: retries exhausted")),
+                ("aborted", Some("Dropped: aborted")),
+                ("cleared", Some("Dropped: queue cleared")),
+            ]
+        );
+        assert!(results.iter().all(|(_, result)| !result.success));
+        assert!(logs.deliveries.is_empty());
+    }
+
+    fn sized(mut items: Vec<QueuedNotification>, size: usize) -> Vec<QueuedNotification> {
+        for item in &mut items {
+            item.size = size;
+        }
+        items
+    }
+
+    #[test]
+    fn byte_cap_evicts_the_least_severe_first_and_oldest_within_a_severity() {
+        let mut queue = sized(mixed_queue(), 100);
+        let evicted = evict_to_fit(&mut queue, 100, Some(700), 300).unwrap();
+        let evicted: Vec<u64> = evicted.iter().map(|item| item.id).collect();
+        // Infos 1 and 7 go oldest first, then the only Success, before any Warning.
+        assert_eq!(evicted, [1, 7, 9, 4, 3]);
+        assert_eq!(queue.iter().map(|item| item.id).collect::<Vec<_>>(), [2, 5, 6, 8]);
+    }
+
+    #[test]
+    fn count_cap_still_evicts_the_oldest() {
+        let mut queue = sized(mixed_queue(), 100);
+        let evicted = evict_to_fit(&mut queue, 8, None, 100).unwrap();
+        assert_eq!(evicted.iter().map(|item| item.id).collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn byte_cap_eviction_keeps_the_priority_drain_of_what_remains() {
+        let mut queue = sized(mixed_queue(), 100);
+        let evicted = evict_to_fit(&mut queue, 100, Some(900), 200).unwrap();
+        assert_eq!(evicted.iter().map(|item| item.id).collect::<Vec<_>>(), [1, 7]);
+        assert_eq!(ids(&queue, drain_order(&queue, 0)), [6, 2, 3, 5, 8, 4, 9]);
+        // With both Infos gone the Warning is the oldest low-severity item to interleave.
+        assert_eq!(ids(&queue, drain_order(&queue, 2)), [6, 2, 4, 3, 5, 9, 8]);
+    }
+
+    #[test]
+    fn eviction_skips_entries_awaiting_confirmation() {
+        let unconfirmed = || {
+            let mut queue = sized(mixed_queue(), 100);
+            queue[0].awaiting_confirmation = true;
+            queue[1].awaiting_confirmation = true;
+            queue
+        };
+        let mut queue = unconfirmed();
+        let evicted = evict_to_fit(&mut queue, 8, None, 100).unwrap();
+        assert_eq!(evicted.iter().map(|item| item.id).collect::<Vec<_>>(), [3, 4]);
+
+        let mut queue = unconfirmed();
+        let evicted = evict_to_fit(&mut queue, 100, Some(700), 300).unwrap();
+        assert_eq!(evicted.iter().map(|item| item.id).collect::<Vec<_>>(), [7, 9, 4, 3, 5]);
+        assert_eq!(queue.iter().map(|item| item.id).collect::<Vec<_>>(), [1, 2, 6, 8]);
+    }
+
+    #[test]
+    fn a_queue_of_unconfirmed_entries_is_left_alone_when_nothing_fits() {
+        let mut queue = sized(mixed_queue(), 100);
+        for item in &mut queue {
+            item.awaiting_confirmation = item.id != 9;
+        }
+        assert!(evict_to_fit(&mut queue, 8, None, 100).is_none());
+        assert!(evict_to_fit(&mut queue, 100, Some(800), 200).is_none());
+        assert_eq!(queue.len(), 9);
+    }
+
+    #[test]
+    fn adding_to_a_queue_full_of_unconfirmed_entries_fails() {
+        let _storage = TempStorage::new();
+        let logs = Logs::new();
+        let queue = logs.queue(2);
+        let results = Results::default();
+        let payload = || NotificationPayload::new("order.created", "Order");
+
+        let first = queue.add_unconfirmed(payload()).unwrap();
+        queue.add_unconfirmed(payload()).unwrap();
+        let added = queue.add(payload(), recording(&results, "rejected"));
+        assert!(matches!(added, Err(Error::QueueFull)));
+        assert!(logs.deliveries.is_empty());
+        assert_eq!(queue.get_all().len(), 2);
+
+        assert!(queue.confirm(first));
+        queue.add(payload(), recording(&results, "added")).unwrap();
+        let ids: Vec<u64> = queue.get_all().iter().map(|item| item.id).collect();
+        assert!(!ids.contains(&first));
+        assert!(results.lock().is_empty());
+    }
+
+    #[test]
+    fn entries_from_older_files_still_load() {
+        let old = serde_json::json!({
+            "eventType": "order.created",
+            "title": "Order",
+            "severity": "error"
+        });
+        let item: QueuedNotification = serde_json::from_value(old).unwrap();
+        assert_eq!(item.payload.event_type, "order.created");
+        assert_eq!(item.enqueued_at, None);
+        assert_eq!(item.environment, None);
+        assert!(!item.awaiting_confirmation);
+    }
+
+    #[test]
+    fn entries_of_unknown_age_drain_as_the_oldest() {
+        let mut legacy = item(3, SeverityLevel::Error, 0);
+        legacy.enqueued_at = None;
+        let items = vec![
+            item(1, SeverityLevel::Error, 5),
+            item(2, SeverityLevel::Error, 1),
+            legacy,
+        ];
+        assert_eq!(ids(&items, drain_order(&items, 0)), [3, 2, 1]);
+        let saved = serde_json::to_value(&items[2]).unwrap();
+        assert!(saved.get("enqueuedAt").is_none());
     }
 }
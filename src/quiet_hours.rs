@@ -0,0 +1,59 @@
+//! Client-side quiet hours for IronNotify SDK.
+
+use crate::types::SeverityLevel;
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// A daily window during which non-critical notifications are deferred.
+///
+/// Windows where `end` is earlier than `start` cross midnight, e.g. 22:00–07:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    /// Local time at which the window starts.
+    pub start: NaiveTime,
+    /// Local time at which the window ends.
+    pub end: NaiveTime,
+    /// Timezone the window is evaluated in.
+    pub timezone: Tz,
+    /// Notifications at or above this severity are sent immediately.
+    pub min_severity_to_bypass: SeverityLevel,
+}
+
+impl QuietHours {
+    /// Checks if `at` falls inside the window.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let time = at.with_timezone(&self.timezone).time();
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Checks if a notification with the given severity is sent during the window.
+    pub fn bypasses(&self, severity: SeverityLevel) -> bool {
+        severity >= self.min_severity_to_bypass
+    }
+
+    /// Gets the first end of the window strictly after `at`.
+    pub fn next_end(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let local = at.with_timezone(&self.timezone);
+        let mut date = local.date_naive();
+        if local.time() >= self.end {
+            date += Duration::days(1);
+        }
+
+        let naive = date.and_time(self.end);
+        self.timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            // The end falls in a DST gap; release once the clocks have moved forward.
+            .or_else(|| {
+                self.timezone
+                    .from_local_datetime(&(naive + Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|end| end.with_timezone(&Utc))
+            .unwrap_or_else(|| at + Duration::days(1))
+    }
+}
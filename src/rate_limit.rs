@@ -0,0 +1,188 @@
+//! Rate limit tracking for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use crate::reconfigure::LiveConfig;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use parking_lot::RwLock;
+use reqwest::header::HeaderMap;
+use std::sync::Arc;
+
+/// Reset values at or above this are Unix timestamps; smaller values are seconds from now.
+const EPOCH_RESET_THRESHOLD: i64 = 1_000_000_000;
+
+/// Callback invoked when the remaining request budget drops below `rate_limit_threshold`.
+pub type RateLimitCallback = Arc<dyn Fn(&RateLimitStatus) + Send + Sync>;
+
+/// The API's rate limit as of the latest response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Requests allowed per window, from `X-RateLimit-Limit`.
+    pub limit: u64,
+    /// Requests left in the current window, from `X-RateLimit-Remaining`.
+    pub remaining: u64,
+    /// When the window resets, from `X-RateLimit-Reset`.
+    pub reset_at: Option<DateTime<Utc>>,
+    /// When the headers were received.
+    pub observed_at: DateTime<Utc>,
+}
+
+impl RateLimitStatus {
+    /// Parses the rate limit headers of a response.
+    ///
+    /// `X-RateLimit-Reset` may be a Unix timestamp or a number of seconds from now.
+    pub fn from_headers(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Self> {
+        let number = |name: &str| -> Option<i64> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        };
+
+        let limit = number("x-ratelimit-limit")?;
+        let remaining = number("x-ratelimit-remaining")?;
+        let reset_at = number("x-ratelimit-reset").and_then(|reset| {
+            if reset >= EPOCH_RESET_THRESHOLD {
+                Utc.timestamp_opt(reset, 0).single()
+            } else {
+                now.checked_add_signed(Duration::seconds(reset.max(0)))
+            }
+        });
+
+        Some(Self {
+            limit: limit.max(0) as u64,
+            remaining: remaining.max(0) as u64,
+            reset_at,
+            observed_at: now,
+        })
+    }
+
+    /// Checks whether fewer than `threshold` requests remain in a window that has not reset yet.
+    pub fn is_below(&self, threshold: u64, now: DateTime<Utc>) -> bool {
+        self.remaining < threshold && self.reset_at.is_some_and(|reset_at| reset_at > now)
+    }
+}
+
+/// Keeps the latest rate limit status and reports when it runs low.
+pub(crate) struct RateLimits {
+    config: Arc<LiveConfig>,
+    debug: DebugSwitch,
+    latest: RwLock<Option<RateLimitStatus>>,
+    callback: RwLock<Option<RateLimitCallback>>,
+}
+
+impl RateLimits {
+    /// Creates a tracker. The low callback fires when fewer requests remain than the
+    /// `rate_limit_threshold` in effect.
+    pub fn new(config: &Arc<LiveConfig>) -> Self {
+        Self {
+            config: Arc::clone(config),
+            debug: DebugSwitch::transport(config),
+            latest: RwLock::new(None),
+            callback: RwLock::new(None),
+        }
+    }
+
+    /// Sets the low rate limit callback.
+    pub fn set_callback(&self, callback: RateLimitCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    /// Records the rate limit headers of a response, if present.
+    pub fn observe(&self, headers: &HeaderMap) {
+        let now = Utc::now();
+        let Some(status) = RateLimitStatus::from_headers(headers, now) else {
+            return;
+        };
+
+        let was_low = self.is_throttled(now);
+        *self.latest.write() = Some(status.clone());
+
+        let Some(threshold) = self.config.rate_limit_threshold() else {
+            return;
+        };
+        if was_low || !status.is_below(threshold, now) {
+            return;
+        }
+
+        if self.debug.is_enabled() {
+            println!(
+                "[IronNotify] Warning: rate limit low ({} of {} remaining)",
+                status.remaining, status.limit
+            );
+        }
+        let callback = self.callback.read().clone();
+        if let Some(callback) = callback {
+            callback(&status);
+        }
+    }
+
+    /// Gets the latest status.
+    pub fn latest(&self) -> Option<RateLimitStatus> {
+        self.latest.read().clone()
+    }
+
+    /// Gets the reset time if lower-priority sends should wait for it.
+    pub fn throttled_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let threshold = self.config.rate_limit_threshold()?;
+        let latest = self.latest.read();
+        let status = latest.as_ref()?;
+        if status.is_below(threshold, now) {
+            status.reset_at
+        } else {
+            None
+        }
+    }
+
+    fn is_throttled(&self, now: DateTime<Utc>) -> bool {
+        self.throttled_until(now).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn status(reset: &str, now: DateTime<Utc>) -> Option<RateLimitStatus> {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("100"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("7"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_str(reset).unwrap());
+        RateLimitStatus::from_headers(&headers, now)
+    }
+
+    fn reset_at(reset: i64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        status(&reset.to_string(), now).unwrap().reset_at
+    }
+
+    #[test]
+    fn small_reset_values_are_seconds_from_now() {
+        let now = Utc.timestamp_opt(1_800_000_000, 0).unwrap();
+        assert_eq!(reset_at(0, now), Some(now));
+        assert_eq!(reset_at(60, now), Some(now + Duration::seconds(60)));
+        let latest = EPOCH_RESET_THRESHOLD - 1;
+        assert_eq!(reset_at(latest, now), Some(now + Duration::seconds(latest)));
+        // A negative delta is a window that has already reset.
+        assert_eq!(reset_at(-5, now), Some(now));
+    }
+
+    #[test]
+    fn large_reset_values_are_unix_timestamps() {
+        let now = Utc.timestamp_opt(1_800_000_000, 0).unwrap();
+        assert_eq!(
+            reset_at(EPOCH_RESET_THRESHOLD, now),
+            Utc.timestamp_opt(EPOCH_RESET_THRESHOLD, 0).single()
+        );
+        assert_eq!(reset_at(1_800_000_060, now), Some(now + Duration::seconds(60)));
+        // Timestamps are absolute, so a past one stays in the past.
+        assert_eq!(reset_at(1_799_999_940, now), Some(now - Duration::seconds(60)));
+    }
+
+    #[test]
+    fn counts_and_unreadable_resets_are_parsed_leniently() {
+        let now = Utc.timestamp_opt(1_800_000_000, 0).unwrap();
+        let parsed = status(" 30 ", now).unwrap();
+        assert_eq!((parsed.limit, parsed.remaining), (100, 7));
+        assert_eq!(parsed.reset_at, Some(now + Duration::seconds(30)));
+        assert_eq!(parsed.observed_at, now);
+        assert_eq!(status("soon", now).unwrap().reset_at, None);
+        assert!(RateLimitStatus::from_headers(&HeaderMap::new(), now).is_none());
+    }
+}
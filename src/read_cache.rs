@@ -0,0 +1,164 @@
+//! Read-through cache for notification lists in IronNotify SDK.
+
+use crate::mutations::Mutation;
+use crate::redact::Redactor;
+use crate::storage::JsonFile;
+use crate::types::Notification;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Maximum number of distinct queries kept in the read cache.
+const READ_CACHE_MAX_ENTRIES: usize = 32;
+
+/// Notifications returned by `get_notifications_cached`.
+#[derive(Debug, Clone)]
+pub struct CachedResult {
+    /// The notifications.
+    pub items: Vec<Notification>,
+    /// When the notifications were fetched from the server.
+    pub fetched_at: DateTime<Utc>,
+    /// Whether the server was unreachable and the items come from the cache.
+    pub stale: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    items: Vec<Notification>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Cache of successful list responses, keyed by query, optionally persisted to disk.
+pub(crate) struct ReadCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    storage: Option<JsonFile>,
+    at_rest_redactor: Option<Redactor>,
+}
+
+impl ReadCache {
+    /// Creates a cache whose entries expire after `ttl`.
+    ///
+    /// When `persist` is set, entries are also written to disk and survive restarts.
+    pub fn new(ttl: Duration, persist: bool, at_rest_redactor: Option<Redactor>) -> Self {
+        let storage = if persist {
+            Some(JsonFile::new("read_cache.json"))
+        } else {
+            None
+        };
+
+        let entries = storage
+            .as_ref()
+            .and_then(|storage| storage.read())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+
+        Self {
+            ttl,
+            entries: Mutex::new(entries),
+            storage,
+            at_rest_redactor,
+        }
+    }
+
+    /// Gets the cached items for a query if they have not expired.
+    pub fn get(&self, key: &str, now: DateTime<Utc>) -> Option<CachedResult> {
+        let entries = self.entries.lock();
+        let entry = entries.get(key)?;
+        let age = (now - entry.fetched_at).to_std().unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+
+        Some(CachedResult {
+            items: entry.items.clone(),
+            fetched_at: entry.fetched_at,
+            stale: true,
+        })
+    }
+
+    /// Stores the items returned for a query, evicting the oldest entry when full.
+    pub fn store(&self, key: &str, items: Vec<Notification>, fetched_at: DateTime<Utc>) {
+        let mut entries = self.entries.lock();
+        if !entries.contains_key(key) && entries.len() >= READ_CACHE_MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.to_string(), CacheEntry { items, fetched_at });
+        drop(entries);
+        self.save();
+    }
+
+    /// Drops cached queries that contain the given notification.
+    pub fn invalidate_notification(&self, notification_id: &str) {
+        let mut entries = self.entries.lock();
+        let before = entries.len();
+        entries.retain(|_, entry| !entry.items.iter().any(|n| n.id == notification_id));
+        let changed = entries.len() != before;
+        drop(entries);
+        if changed {
+            self.save();
+        }
+    }
+
+    /// Applies a mutation to the cached copies of a notification.
+    pub fn apply_mutation(&self, notification_id: &str, mutation: &Mutation) {
+        let now = Utc::now();
+        let mut entries = self.entries.lock();
+        for entry in entries.values_mut() {
+            mutation.apply(notification_id, &mut entry.items, now);
+        }
+        drop(entries);
+        self.save();
+    }
+
+    /// Drops all cached queries.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(ref storage) = self.storage else {
+            return;
+        };
+
+        let entries = self.entries.lock();
+        match self.at_rest_redactor {
+            Some(ref redactor) => {
+                let redacted: HashMap<&String, CacheEntry> = entries
+                    .iter()
+                    .map(|(key, entry)| {
+                        let items = entry
+                            .items
+                            .iter()
+                            .map(|n| Notification {
+                                metadata: n.metadata.as_ref().map(|m| redactor.redact_metadata(m)),
+                                ..n.clone()
+                            })
+                            .collect();
+                        (
+                            key,
+                            CacheEntry {
+                                items,
+                                fetched_at: entry.fetched_at,
+                            },
+                        )
+                    })
+                    .collect();
+                storage.write(&redacted);
+            }
+            None => {
+                storage.write(&*entries);
+            }
+        }
+    }
+}
@@ -0,0 +1,1207 @@
+//! Real-time message protocol for IronNotify SDK.
+
+use crate::debug::DebugSwitch;
+use crate::metrics::Metrics;
+use crate::query::NotificationQuery;
+use crate::runtime::{BoxFuture, Runtime};
+use crate::state::WatchedState;
+use crate::types::{ConnectionState, Notification};
+use crate::websocket::{Opcode, TlsSettings, WebSocket};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{mpsc as std_mpsc, Arc};
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+/// Version of the real-time protocol spoken by this SDK.
+pub const REALTIME_PROTOCOL_VERSION: u32 = 1;
+
+/// Query parameter carrying the protocol version on connect.
+const PROTOCOL_PARAM: &str = "protocol";
+
+/// Longest the connection thread blocks on a read before checking for queued
+/// frames and `disconnect`.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest delay between reconnection attempts, however many have failed.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Number of delivered notification IDs remembered to drop replayed duplicates.
+const DELIVERED_ID_CAPACITY: usize = 1_000;
+
+/// Topic of app-wide notifications.
+const APP_TOPIC: &str = "app";
+
+/// Prefix of a user's topic, followed by the user ID.
+const USER_TOPIC_PREFIX: &str = "user:";
+
+/// Callback invoked for each message received over the real-time connection.
+pub type RealtimeCallback = Arc<dyn Fn(&RealtimeMessage) + Send + Sync>;
+
+/// Callback invoked with the number of missed notifications replayed after a reconnect.
+pub type GapCallback = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// A message exchanged over the real-time connection.
+///
+/// Frames use a `{"type": "...", "data": {...}}` envelope. Types this SDK version
+/// does not know are returned as `Unknown` instead of failing.
+#[derive(Debug, Clone)]
+pub enum RealtimeMessage {
+    /// A new notification was created for a subscribed topic.
+    NotificationNew(Box<Notification>),
+    /// A notification was marked as read.
+    NotificationRead { id: String },
+    /// A notification was deleted, possibly from another device.
+    NotificationDeleted { id: String },
+    /// The unread count changed.
+    UnreadCount { count: i64 },
+    Ping,
+    Pong,
+    /// Asks the server for a topic's messages, e.g. `user:<id>` or `app`.
+    Subscribe { topic: String },
+    /// A subscription was accepted.
+    SubscriptionAck { topic: String },
+    /// The server reported an error.
+    Error { code: String, message: String },
+    /// A message type this SDK version does not know.
+    Unknown { r#type: String, raw: Value },
+}
+
+impl RealtimeMessage {
+    /// Gets the envelope type name.
+    pub fn message_type(&self) -> &str {
+        match self {
+            Self::NotificationNew(_) => "notification_new",
+            Self::NotificationRead { .. } => "notification_read",
+            Self::NotificationDeleted { .. } => "notification_deleted",
+            Self::UnreadCount { .. } => "unread_count",
+            Self::Ping => "ping",
+            Self::Pong => "pong",
+            Self::Subscribe { .. } => "subscribe",
+            Self::SubscriptionAck { .. } => "subscription_ack",
+            Self::Error { .. } => "error",
+            Self::Unknown { r#type, .. } => r#type,
+        }
+    }
+
+    fn data(&self) -> Result<Value, serde_json::Error> {
+        Ok(match self {
+            Self::NotificationNew(notification) => serde_json::to_value(notification)?,
+            Self::NotificationRead { id } | Self::NotificationDeleted { id } => {
+                serde_json::json!({ "id": id })
+            }
+            Self::UnreadCount { count } => serde_json::json!({ "count": count }),
+            Self::Ping | Self::Pong => Value::Null,
+            Self::Subscribe { topic } | Self::SubscriptionAck { topic } => {
+                serde_json::json!({ "topic": topic })
+            }
+            Self::Error { code, message } => serde_json::json!({ "code": code, "message": message }),
+            Self::Unknown { raw, .. } => raw.clone(),
+        })
+    }
+}
+
+/// Wire representation of an outgoing real-time frame.
+#[derive(Serialize)]
+struct Envelope<'a> {
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    data: Value,
+}
+
+/// Wire representation of an incoming real-time frame.
+#[derive(Deserialize)]
+struct RawEnvelope {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Deserialize)]
+struct IdData {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct CountData {
+    count: i64,
+}
+
+#[derive(Deserialize)]
+struct TopicData {
+    topic: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorData {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl Serialize for RealtimeMessage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.data().map_err(serde::ser::Error::custom)?;
+        Envelope {
+            kind: self.message_type(),
+            data,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RealtimeMessage {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let envelope = RawEnvelope::deserialize(deserializer)?;
+        let data = envelope.data;
+        Ok(match envelope.kind.as_str() {
+            "notification_new" => {
+                Self::NotificationNew(serde_json::from_value(data).map_err(D::Error::custom)?)
+            }
+            "notification_read" => {
+                let data: IdData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::NotificationRead { id: data.id }
+            }
+            "notification_deleted" => {
+                let data: IdData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::NotificationDeleted { id: data.id }
+            }
+            "unread_count" => {
+                let data: CountData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::UnreadCount { count: data.count }
+            }
+            "ping" => Self::Ping,
+            "pong" => Self::Pong,
+            "subscribe" => {
+                let data: TopicData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::Subscribe { topic: data.topic }
+            }
+            "subscription_ack" => {
+                let data: TopicData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::SubscriptionAck { topic: data.topic }
+            }
+            "error" => {
+                let data: ErrorData = serde_json::from_value(data).map_err(D::Error::custom)?;
+                Self::Error {
+                    code: data.code,
+                    message: data.message,
+                }
+            }
+            _ => Self::Unknown {
+                r#type: envelope.kind,
+                raw: data,
+            },
+        })
+    }
+}
+
+/// Gets the WebSocket URL with the protocol version appended.
+pub(crate) fn connect_url(ws_url: &str) -> String {
+    let separator = if ws_url.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}{}={}",
+        ws_url, separator, PROTOCOL_PARAM, REALTIME_PROTOCOL_VERSION
+    )
+}
+
+/// Gets the topic of a user's notifications.
+pub(crate) fn user_topic(user_id: &str) -> String {
+    format!("{}{}", USER_TOPIC_PREFIX, user_id)
+}
+
+/// Gets the topic of app-wide notifications.
+pub(crate) fn app_topic() -> String {
+    APP_TOPIC.to_string()
+}
+
+/// Something that happened on the real-time connection, handed over in order.
+pub(crate) enum ConnectionEvent {
+    /// A connection was opened; `resumed` is set when it replaces a lost one.
+    Connected { resumed: bool },
+    /// A message arrived.
+    Message(RealtimeMessage),
+}
+
+/// Tracks delivered notifications, to replay the ones missed while disconnected
+/// without delivering any twice.
+#[derive(Debug, Default)]
+pub(crate) struct Replay {
+    /// Creation time of the newest delivered notification.
+    cursor: Option<DateTime<Utc>>,
+    /// Most recently delivered IDs, oldest first.
+    delivered: VecDeque<String>,
+    delivered_ids: HashSet<String>,
+}
+
+impl Replay {
+    /// Gets the creation time of the newest delivered notification.
+    pub fn cursor(&self) -> Option<DateTime<Utc>> {
+        self.cursor
+    }
+
+    /// Records a live message, returning `false` if it is a notification that was
+    /// delivered already.
+    pub fn live(&mut self, message: &RealtimeMessage) -> bool {
+        match message {
+            RealtimeMessage::NotificationNew(notification) => self.record(notification),
+            _ => true,
+        }
+    }
+
+    /// Gets the fetched notifications that were not delivered yet, oldest first,
+    /// and records them as delivered.
+    pub fn gap(&mut self, mut fetched: Vec<Notification>) -> Vec<Notification> {
+        fetched.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        fetched.retain(|notification| self.record(notification));
+        fetched
+    }
+
+    fn record(&mut self, notification: &Notification) -> bool {
+        if !self.delivered_ids.insert(notification.id.clone()) {
+            return false;
+        }
+        self.delivered.push_back(notification.id.clone());
+        if self.delivered.len() > DELIVERED_ID_CAPACITY {
+            if let Some(oldest) = self.delivered.pop_front() {
+                self.delivered_ids.remove(&oldest);
+            }
+        }
+        self.cursor = Some(match self.cursor {
+            Some(cursor) => cursor.max(notification.created_at),
+            None => notification.created_at,
+        });
+        true
+    }
+}
+
+/// Settings of a real-time connection, taken from the client's options on connect.
+#[derive(Clone)]
+pub(crate) struct ConnectSettings {
+    /// WebSocket URL, with the protocol version.
+    pub url: String,
+    /// Resolves the `Authorization` header of each handshake.
+    pub authorizer: Authorizer,
+    /// How `wss://` connections are secured.
+    pub tls: TlsSettings,
+    /// Timeout of connecting and of the handshake.
+    pub connect_timeout: Duration,
+    pub heartbeat_interval: Duration,
+    pub heartbeat_timeout: Duration,
+    pub auto_reconnect: bool,
+    pub max_reconnect_attempts: u32,
+    pub reconnect_delay: Duration,
+}
+
+/// Reply to a request for the handshake's `Authorization` header.
+type AuthorizationReply = std_mpsc::SyncSender<Result<Option<String>, String>>;
+
+/// Resolves the handshake's `Authorization` header on the client's runtime for the
+/// connection thread, so every connect and reconnect uses a current token or key.
+#[derive(Clone)]
+pub(crate) struct Authorizer {
+    requests: mpsc::UnboundedSender<AuthorizationReply>,
+}
+
+impl Authorizer {
+    /// Starts the task answering requests with `resolve`, which runs until the
+    /// authorizer is dropped. Must be called from within the runtime.
+    pub fn spawn<F>(runtime: &dyn Runtime, resolve: F) -> Self
+    where
+        F: Fn() -> BoxFuture<Result<Option<String>, String>> + Send + Sync + 'static,
+    {
+        let (requests, mut receiver) = mpsc::unbounded_channel::<AuthorizationReply>();
+        runtime.spawn(Box::pin(async move {
+            while let Some(reply) = receiver.recv().await {
+                let _ = reply.send(resolve().await);
+            }
+        }));
+        Self { requests }
+    }
+
+    /// Gets the header value, waiting up to `timeout` for it.
+    fn get(&self, timeout: Duration) -> io::Result<Option<String>> {
+        let (reply, receiver) = std_mpsc::sync_channel(1);
+        if self.requests.send(reply).is_err() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "Client was dropped"));
+        }
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(authorization)) => Ok(authorization),
+            Ok(Err(error)) => Err(io::Error::new(io::ErrorKind::PermissionDenied, error)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Timed out getting an auth token",
+            )),
+        }
+    }
+}
+
+/// The real-time connection: a background thread keeping a WebSocket open.
+///
+/// The thread pings the server every `heartbeat_interval` and closes the socket
+/// when no pong arrives within `heartbeat_timeout`, so half-open connections are
+/// noticed and replaced like dropped ones. Received messages and reconnects are
+/// handed over on a channel, in order.
+pub(crate) struct Realtime {
+    shared: Arc<Shared>,
+    session: Mutex<Option<Arc<Session>>>,
+    callback: RwLock<Option<RealtimeCallback>>,
+    gap_callback: RwLock<Option<GapCallback>>,
+}
+
+/// State shared with the connection thread.
+struct Shared {
+    state: WatchedState<ConnectionState>,
+    /// Subscribed topics, sent again on every reconnect.
+    topics: Mutex<Vec<String>>,
+    last_heartbeat: Mutex<Option<DateTime<Utc>>>,
+    metrics: Arc<Metrics>,
+    debug: DebugSwitch,
+}
+
+/// One `connect` call, until `disconnect`.
+struct Session {
+    /// Set by `disconnect`. State changes are made under this lock, so the thread
+    /// never overwrites the `Disconnected` state.
+    stopped: Mutex<bool>,
+    /// Frames queued by other threads, sent by the connection thread.
+    outgoing: Mutex<Vec<String>>,
+    /// The open socket, shut down to unblock the thread on `disconnect`.
+    socket: Mutex<Option<TcpStream>>,
+    thread: Mutex<Option<Thread>>,
+}
+
+/// Why a connection ended.
+enum Closed {
+    Stopped,
+    HeartbeatMissed,
+    Lost(io::Error),
+}
+
+impl Realtime {
+    pub fn new(metrics: Arc<Metrics>, debug: DebugSwitch) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                state: WatchedState::new(ConnectionState::Disconnected),
+                topics: Mutex::new(Vec::new()),
+                last_heartbeat: Mutex::new(None),
+                metrics,
+                debug,
+            }),
+            session: Mutex::new(None),
+            callback: RwLock::new(None),
+            gap_callback: RwLock::new(None),
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.shared.state.get()
+    }
+
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.shared.state.subscribe()
+    }
+
+    /// Gets when the server last answered a heartbeat.
+    pub fn last_heartbeat_at(&self) -> Option<DateTime<Utc>> {
+        *self.shared.last_heartbeat.lock()
+    }
+
+    pub fn set_callback(&self, callback: RealtimeCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    /// Invokes the message callback.
+    pub fn dispatch(&self, message: &RealtimeMessage) {
+        let callback = self.callback.read().clone();
+        if let Some(callback) = callback {
+            callback(message);
+        }
+    }
+
+    pub fn set_gap_callback(&self, callback: GapCallback) {
+        *self.gap_callback.write() = Some(callback);
+    }
+
+    /// Invokes the gap callback.
+    pub fn gap_detected(&self, count: usize) {
+        let callback = self.gap_callback.read().clone();
+        if let Some(callback) = callback {
+            callback(count);
+        }
+    }
+
+    /// Gets the queries listing the subscribed topics' notifications created after
+    /// `since`.
+    pub fn replay_queries(&self, since: DateTime<Utc>) -> Vec<NotificationQuery> {
+        let topics = self.shared.topics.lock();
+        if topics.iter().any(|topic| topic == APP_TOPIC) {
+            return vec![NotificationQuery::new().since(since)];
+        }
+        topics
+            .iter()
+            .filter_map(|topic| topic.strip_prefix(USER_TOPIC_PREFIX))
+            .map(|user_id| NotificationQuery::new().user(user_id).since(since))
+            .collect()
+    }
+
+    /// Starts the connection thread, returning the channel of connection events, or
+    /// `None` if a connection is already running.
+    pub fn connect(
+        &self,
+        settings: ConnectSettings,
+    ) -> io::Result<Option<mpsc::UnboundedReceiver<ConnectionEvent>>> {
+        let mut current = self.session.lock();
+        if current.is_some() {
+            return Ok(None);
+        }
+
+        let session = Arc::new(Session {
+            stopped: Mutex::new(false),
+            outgoing: Mutex::new(Vec::new()),
+            socket: Mutex::new(None),
+            thread: Mutex::new(None),
+        });
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.shared.state.set(ConnectionState::Connecting);
+
+        let shared = Arc::clone(&self.shared);
+        let thread_session = Arc::clone(&session);
+        let handle = std::thread::Builder::new()
+            .name("ironnotify-realtime".to_string())
+            .spawn(move || shared.run(&thread_session, &settings, &sender));
+        let handle = match handle {
+            Ok(handle) => handle,
+            Err(error) => {
+                self.shared.state.set(ConnectionState::Disconnected);
+                return Err(error);
+            }
+        };
+        *session.thread.lock() = Some(handle.thread().clone());
+        *current = Some(session);
+        Ok(Some(receiver))
+    }
+
+    /// Stops the connection thread, closing the socket.
+    pub fn disconnect(&self) {
+        let Some(session) = self.session.lock().take() else {
+            return;
+        };
+        {
+            let mut stopped = session.stopped.lock();
+            *stopped = true;
+            self.shared.state.set(ConnectionState::Disconnected);
+        }
+        if let Some(socket) = session.socket.lock().take() {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+        let thread = session.thread.lock().take();
+        if let Some(thread) = thread {
+            thread.unpark();
+        }
+    }
+
+    /// Subscribes to a topic, now if connected and on every reconnect.
+    pub fn subscribe(&self, topic: String) {
+        let mut topics = self.shared.topics.lock();
+        if topics.contains(&topic) {
+            return;
+        }
+        if let Some(session) = self.session.lock().as_ref() {
+            session.outgoing.lock().push(subscribe_frame(&topic));
+        }
+        topics.push(topic);
+    }
+}
+
+impl Drop for Realtime {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+impl Shared {
+    /// Body of the connection thread: connects, and reconnects until stopped or out
+    /// of attempts.
+    fn run(
+        &self,
+        session: &Session,
+        settings: &ConnectSettings,
+        events: &mpsc::UnboundedSender<ConnectionEvent>,
+    ) {
+        let mut attempts = 0;
+        let mut resumed = false;
+        loop {
+            let connected = settings
+                .authorizer
+                .get(settings.connect_timeout)
+                .and_then(|authorization| {
+                    WebSocket::connect(
+                        &settings.url,
+                        authorization.as_deref(),
+                        settings.connect_timeout,
+                        &settings.tls,
+                    )
+                });
+            match connected {
+                Ok(mut socket) => {
+                    if let Ok(handle) = socket.shutdown_handle() {
+                        *session.socket.lock() = Some(handle);
+                    }
+                    if !self.set_state(session, ConnectionState::Connected) {
+                        socket.close();
+                        return;
+                    }
+                    attempts = 0;
+                    if self.debug.is_enabled() {
+                        println!("[IronNotify] Connected to {}", settings.url);
+                    }
+                    if events.send(ConnectionEvent::Connected { resumed }).is_err() {
+                        socket.close();
+                        return;
+                    }
+                    resumed = true;
+
+                    let closed = self.drive(&mut socket, session, settings, events);
+                    session.socket.lock().take();
+                    match closed {
+                        Closed::Stopped => return,
+                        Closed::HeartbeatMissed => eprintln!(
+                            "[IronNotify] Warning: No heartbeat reply within {:?}, reconnecting",
+                            settings.heartbeat_timeout
+                        ),
+                        Closed::Lost(error) => {
+                            if self.debug.is_enabled() {
+                                println!("[IronNotify] Real-time connection lost: {}", error);
+                            }
+                        }
+                    }
+                }
+                Err(error) => {
+                    if self.debug.is_enabled() {
+                        println!(
+                            "[IronNotify] Failed to connect to {}: {}",
+                            settings.url, error
+                        );
+                    }
+                }
+            }
+
+            attempts += 1;
+            if !settings.auto_reconnect || attempts > settings.max_reconnect_attempts {
+                if self.set_state(session, ConnectionState::Disconnected) {
+                    eprintln!(
+                        "[IronNotify] Warning: Real-time connection closed after {} \
+                         failed attempts",
+                        attempts
+                    );
+                }
+                return;
+            }
+            if !self.set_state(session, ConnectionState::Reconnecting) {
+                return;
+            }
+            let backoff = 1u32 << (attempts - 1).min(16);
+            let delay = settings
+                .reconnect_delay
+                .saturating_mul(backoff)
+                .min(MAX_RECONNECT_DELAY);
+            if !session.sleep(delay) {
+                return;
+            }
+        }
+    }
+
+    /// Sets the connection state unless the session was stopped, returning whether
+    /// it is still running.
+    fn set_state(&self, session: &Session, state: ConnectionState) -> bool {
+        let stopped = session.stopped.lock();
+        if !*stopped {
+            self.state.set(state);
+        }
+        !*stopped
+    }
+
+    /// Exchanges frames on an open socket until it closes, the heartbeat is missed,
+    /// or the session is stopped.
+    fn drive(
+        &self,
+        socket: &mut WebSocket,
+        session: &Session,
+        settings: &ConnectSettings,
+        events: &mpsc::UnboundedSender<ConnectionEvent>,
+    ) -> Closed {
+        // Subscriptions queued before the socket opened are covered by sending every
+        // topic. Taking both locks keeps a concurrent `subscribe` from being sent twice.
+        let subscriptions: Vec<String> = {
+            let topics = self.topics.lock();
+            session.outgoing.lock().clear();
+            topics.iter().map(|topic| subscribe_frame(topic)).collect()
+        };
+        for frame in subscriptions {
+            if let Err(error) = socket.send_text(&frame) {
+                return Closed::Lost(error);
+            }
+        }
+
+        let interval = settings.heartbeat_interval.max(Duration::from_millis(1));
+        let mut next_ping = Instant::now() + interval;
+        // Sequence number and send time of the ping awaiting its pong.
+        let mut pending: Option<(u64, Instant)> = None;
+        let mut sequence = 0u64;
+        loop {
+            if *session.stopped.lock() {
+                socket.close();
+                return Closed::Stopped;
+            }
+            let outgoing = std::mem::take(&mut *session.outgoing.lock());
+            for frame in outgoing {
+                if let Err(error) = socket.send_text(&frame) {
+                    return Closed::Lost(error);
+                }
+            }
+
+            let now = Instant::now();
+            let deadline = match pending {
+                Some((_, sent)) if now >= sent + settings.heartbeat_timeout => {
+                    self.metrics.record_missed_heartbeat();
+                    socket.close();
+                    return Closed::HeartbeatMissed;
+                }
+                Some((_, sent)) => sent + settings.heartbeat_timeout,
+                None if now >= next_ping => {
+                    sequence += 1;
+                    if let Err(error) = socket.send(Opcode::Ping, &sequence.to_be_bytes()) {
+                        return Closed::Lost(error);
+                    }
+                    pending = Some((sequence, now));
+                    now + settings.heartbeat_timeout
+                }
+                None => next_ping,
+            };
+
+            let wait = deadline.saturating_duration_since(now).min(POLL_INTERVAL);
+            let frame = match socket.read(wait) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => continue,
+                Err(error) => return Closed::Lost(error),
+            };
+            let sent = match frame.opcode {
+                Opcode::Ping => socket.send(Opcode::Pong, &frame.payload),
+                Opcode::Pong => {
+                    if let Some((expected, sent)) = pending {
+                        if frame.payload == expected.to_be_bytes() {
+                            self.metrics.record_heartbeat(sent.elapsed());
+                            *self.last_heartbeat.lock() = Some(Utc::now());
+                            pending = None;
+                            next_ping = Instant::now() + interval;
+                        }
+                    }
+                    Ok(())
+                }
+                Opcode::Text => match serde_json::from_slice::<RealtimeMessage>(&frame.payload) {
+                    Ok(RealtimeMessage::Ping) => socket.send_text(r#"{"type":"pong"}"#),
+                    Ok(message) => {
+                        if events.send(ConnectionEvent::Message(message)).is_err() {
+                            socket.close();
+                            return Closed::Stopped;
+                        }
+                        Ok(())
+                    }
+                    Err(error) => {
+                        if self.debug.is_enabled() {
+                            println!(
+                                "[IronNotify] Ignoring malformed real-time message: {}",
+                                error
+                            );
+                        }
+                        Ok(())
+                    }
+                },
+                Opcode::Close => {
+                    socket.close();
+                    return Closed::Lost(io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        "closed by the server",
+                    ));
+                }
+                Opcode::Binary | Opcode::Continuation => Ok(()),
+            };
+            if let Err(error) = sent {
+                return Closed::Lost(error);
+            }
+        }
+    }
+}
+
+impl Session {
+    /// Sleeps unless stopped meanwhile, returning whether it is still running.
+    fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if *self.stopped.lock() {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            std::thread::park_timeout(deadline - now);
+        }
+    }
+}
+
+fn subscribe_frame(topic: &str) -> String {
+    let message = RealtimeMessage::Subscribe {
+        topic: topic.to_string(),
+    };
+    serde_json::to_string(&message).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{AuthToken, TokenProvider};
+    use crate::client::NotifyClient;
+    use crate::test_support::{
+        notification_json, options, runtime, wait_until, MockResponse, MockServer,
+    };
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Mints `tok-0`, `tok-1`, and so on.
+    #[derive(Debug, Default)]
+    struct CountingTokens(AtomicUsize);
+
+    impl TokenProvider for CountingTokens {
+        fn token(&self) -> BoxFuture<Result<AuthToken, String>> {
+            let token = format!("tok-{}", self.0.fetch_add(1, Ordering::SeqCst));
+            let expires_at = Utc::now() + chrono::Duration::hours(1);
+            Box::pin(async move { Ok(AuthToken::new(token, expires_at)) })
+        }
+    }
+
+    /// Gets the `Authorization` headers of the WebSocket handshakes so far.
+    fn handshake_authorizations(server: &MockServer) -> Vec<String> {
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.headers.contains_key("sec-websocket-key"))
+            .map(|request| request.headers.get("authorization").cloned().unwrap_or_default())
+            .collect()
+    }
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 9, minute, 0).unwrap()
+    }
+
+    fn notification(id: &str, minute: u32) -> Notification {
+        serde_json::from_value(notification_json(id, at(minute))).unwrap()
+    }
+
+    fn new_message(id: &str, minute: u32) -> String {
+        json!({ "type": "notification_new", "data": notification_json(id, at(minute)) })
+            .to_string()
+    }
+
+    fn ids(notifications: &[Notification]) -> Vec<&str> {
+        notifications.iter().map(|notification| notification.id.as_str()).collect()
+    }
+
+    #[test]
+    fn gap_skips_delivered_notifications_and_sorts_oldest_first() {
+        let mut replay = Replay::default();
+        assert_eq!(replay.cursor(), None);
+        let live = RealtimeMessage::NotificationNew(Box::new(notification("a", 1)));
+        assert!(replay.live(&live));
+        assert!(!replay.live(&live));
+        assert!(replay.live(&RealtimeMessage::UnreadCount { count: 1 }));
+        assert_eq!(replay.cursor(), Some(at(1)));
+
+        let fetched = vec![
+            notification("d", 4),
+            notification("c", 2),
+            notification("a", 1),
+            notification("b", 2),
+            notification("d", 4),
+        ];
+        assert_eq!(ids(&replay.gap(fetched)), ["b", "c", "d"]);
+        assert_eq!(replay.cursor(), Some(at(4)));
+        assert!(replay.gap(vec![notification("c", 2)]).is_empty());
+    }
+
+    #[test]
+    fn delivered_ids_are_bounded() {
+        let mut replay = Replay::default();
+        let fetched = (0..=DELIVERED_ID_CAPACITY)
+            .map(|i| notification(&i.to_string(), 0))
+            .collect();
+        assert_eq!(replay.gap(fetched).len(), DELIVERED_ID_CAPACITY + 1);
+
+        // The oldest ID was forgotten; the newer ones are still known.
+        assert!(replay.gap(vec![notification("1", 0)]).is_empty());
+        assert_eq!(ids(&replay.gap(vec![notification("0", 0)])), ["0"]);
+    }
+
+    #[test]
+    fn missed_notifications_are_replayed_in_order_after_a_reconnect() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        let server = MockServer::start(move |request| {
+            if request.path.starts_with("/api/v1/notifications") {
+                // Newest first, overlapping what was and will be delivered live.
+                let listed = [("n3", 3), ("n2", 2), ("n1", 1)];
+                let listed: Vec<Value> = listed
+                    .iter()
+                    .map(|&(id, minute)| notification_json(id, at(minute)))
+                    .collect();
+                return MockResponse::json(200, json!(listed));
+            }
+            let connection = counter.fetch_add(1, Ordering::SeqCst);
+            MockResponse::websocket(move |socket| {
+                if connection == 0 {
+                    // Drops the connection after the first notification.
+                    socket.send_text(&new_message("n1", 1)).unwrap();
+                    let _ = socket.read(Duration::from_millis(200));
+                    return;
+                }
+                // Sent while the client is still replaying the gap.
+                socket.send_text(&new_message("n3", 3)).unwrap();
+                socket.send_text(&new_message("n4", 4)).unwrap();
+                while let Ok(frame) = socket.read(Duration::from_secs(10)) {
+                    if matches!(frame, Some(ref frame) if frame.opcode == Opcode::Close) {
+                        return;
+                    }
+                }
+            })
+        });
+
+        let mut options = options(&server);
+        options.reconnect_delay = Duration::from_millis(100);
+        let client = NotifyClient::new(options).unwrap();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&delivered);
+        client.on_realtime_message(move |message| {
+            if let RealtimeMessage::NotificationNew(notification) = message {
+                seen.lock().push(notification.id.clone());
+            }
+        });
+        let gaps = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&gaps);
+        client.on_gap_detected(move |count| seen.lock().push(count));
+        let runtime = runtime();
+        let _guard = runtime.enter();
+
+        client.subscribe_to_user("u1");
+        client.connect();
+        wait_until("four notifications", || delivered.lock().len() >= 4);
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(*delivered.lock(), ["n1", "n2", "n3", "n4"]);
+        assert_eq!(*gaps.lock(), [2]);
+        let fetches = server.requests_to("GET", "/api/v1/notifications");
+        assert_eq!(fetches.len(), 1);
+        assert!(fetches[0].path.contains("since="));
+        assert!(fetches[0].path.contains("u1"));
+        client.disconnect();
+    }
+
+    #[test]
+    fn unanswered_heartbeats_reconnect() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        let server = MockServer::start(move |_| {
+            let connection = counter.fetch_add(1, Ordering::SeqCst);
+            MockResponse::websocket(move |socket| {
+                // The first connection answers one ping and then goes silent, like a
+                // half-open connection; later ones keep answering.
+                let mut answered = 0;
+                while let Ok(frame) = socket.read(Duration::from_secs(10)) {
+                    let Some(frame) = frame else { continue };
+                    match frame.opcode {
+                        Opcode::Ping if connection > 0 || answered == 0 => {
+                            answered += 1;
+                            let _ = socket.send(Opcode::Pong, &frame.payload);
+                        }
+                        Opcode::Close => return,
+                        _ => {}
+                    }
+                }
+            })
+        });
+
+        let mut options = options(&server);
+        options.heartbeat_interval = Duration::from_millis(50);
+        options.heartbeat_timeout = Duration::from_millis(200);
+        options.reconnect_delay = Duration::from_millis(100);
+        let client = NotifyClient::new(options).unwrap();
+        let runtime = runtime();
+        let _guard = runtime.enter();
+
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&states);
+        let mut changes = client.watch_connection_state();
+        runtime.spawn(async move {
+            while changes.changed().await.is_ok() {
+                let state = *changes.borrow_and_update();
+                seen.lock().push(state);
+            }
+        });
+
+        client.connect();
+        wait_until("a heartbeat", || client.last_heartbeat_at().is_some());
+        wait_until("a reconnect", || {
+            connections.load(Ordering::SeqCst) == 2
+                && client.connection_state() == ConnectionState::Connected
+        });
+        wait_until("heartbeats on the new connection", || {
+            client.metrics().heartbeat_rtt.count() >= 3
+        });
+
+        assert_eq!(client.metrics().missed_heartbeats, 1);
+        let states = states.lock().clone();
+        let reconnecting = states
+            .iter()
+            .position(|&state| state == ConnectionState::Reconnecting)
+            .expect("never reconnecting");
+        assert!(states[..reconnecting].contains(&ConnectionState::Connected));
+        assert_eq!(states.last(), Some(&ConnectionState::Connected));
+
+        client.disconnect();
+        assert_eq!(client.connection_state(), ConnectionState::Disconnected);
+    }
+
+    #[test]
+    fn server_pings_are_answered_and_messages_delivered() {
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&frames);
+        let server = MockServer::start(move |_| {
+            let recorded = Arc::clone(&recorded);
+            MockResponse::websocket(move |socket| {
+                socket.send(Opcode::Ping, b"server").unwrap();
+                socket.send_text(r#"{"type":"ping"}"#).unwrap();
+                socket
+                    .send_text(r#"{"type":"notification_read","data":{"id":"n1"}}"#)
+                    .unwrap();
+                while let Ok(frame) = socket.read(Duration::from_secs(10)) {
+                    let Some(frame) = frame else { continue };
+                    let payload = String::from_utf8(frame.payload).unwrap();
+                    match frame.opcode {
+                        Opcode::Pong => recorded.lock().push(format!("pong:{}", payload)),
+                        Opcode::Text => recorded.lock().push(payload),
+                        Opcode::Close => return,
+                        _ => {}
+                    }
+                }
+            })
+        });
+
+        let client = NotifyClient::new(options(&server)).unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&received);
+        client.on_realtime_message(move |message| seen.lock().push(message.clone()));
+        let runtime = runtime();
+        let _guard = runtime.enter();
+
+        client.subscribe_to_user("u1");
+        client.connect();
+        wait_until("three replies", || frames.lock().len() == 3);
+        wait_until("the message", || !received.lock().is_empty());
+
+        let frames = frames.lock().clone();
+        let subscribe: Value = serde_json::from_str(&frames[0]).unwrap();
+        assert_eq!(
+            subscribe,
+            serde_json::json!({ "type": "subscribe", "data": { "topic": "user:u1" } })
+        );
+        assert_eq!(frames[1], "pong:server");
+        assert_eq!(frames[2], r#"{"type":"pong"}"#);
+
+        let received = received.lock().clone();
+        assert_eq!(received.len(), 1);
+        assert!(matches!(&received[0], RealtimeMessage::NotificationRead { id } if id == "n1"));
+        client.disconnect();
+    }
+
+    #[test]
+    fn handshakes_carry_the_token_provider_token() {
+        let server = MockServer::start(|_| {
+            MockResponse::websocket(|socket| {
+                while let Ok(frame) = socket.read(Duration::from_secs(10)) {
+                    if matches!(frame, Some(ref frame) if frame.opcode == Opcode::Close) {
+                        return;
+                    }
+                }
+            })
+        });
+        let mut options = options(&server);
+        options.api_key = String::new();
+        options.token_provider = Some(Arc::new(CountingTokens::default()));
+        let client = NotifyClient::new(options).unwrap();
+        let runtime = runtime();
+        let _guard = runtime.enter();
+
+        client.connect();
+        wait_until("a connection", || client.connection_state() == ConnectionState::Connected);
+
+        assert_eq!(handshake_authorizations(&server), ["Bearer tok-0"]);
+        client.disconnect();
+    }
+
+    #[test]
+    fn reconnects_use_the_updated_api_key() {
+        let key_updated = Arc::new(AtomicBool::new(false));
+        let updated = Arc::clone(&key_updated);
+        let server = MockServer::start(move |_| {
+            let updated = Arc::clone(&updated);
+            MockResponse::websocket(move |socket| {
+                // The first connection is dropped once the key was replaced.
+                while !updated.load(Ordering::SeqCst) {
+                    if let Ok(Some(frame)) = socket.read(Duration::from_millis(10)) {
+                        if frame.opcode == Opcode::Close {
+                            return;
+                        }
+                    }
+                }
+            })
+        });
+        let mut options = options(&server);
+        options.reconnect_delay = Duration::from_millis(20);
+        let client = NotifyClient::new(options).unwrap();
+        let runtime = runtime();
+        let _guard = runtime.enter();
+
+        client.connect();
+        wait_until("a connection", || client.connection_state() == ConnectionState::Connected);
+        client.update_api_key("ak_test_rotated").unwrap();
+        key_updated.store(true, Ordering::SeqCst);
+        wait_until("a reconnect", || handshake_authorizations(&server).len() >= 2);
+
+        assert_eq!(
+            handshake_authorizations(&server)[..2],
+            ["Bearer ak_test_mock", "Bearer ak_test_rotated"]
+        );
+        client.disconnect();
+    }
+
+    /// Frames as the server sends them.
+    const SERVER_FRAMES: [&str; 10] = [
+        r#"{"type":"notification_new","data":{"id":"n1","eventType":"order.created",
+            "title":"New order","message":"Order #1234","severity":"warning",
+            "metadata":{"orderId":1234},"read":false,"createdAt":"2026-10-14T12:00:00Z",
+            "workflowId":"wf_42"}}"#,
+        r#"{"type":"notification_read","data":{"id":"n1"}}"#,
+        r#"{"type":"notification_deleted","data":{"id":"n1","deletedBy":"u2"}}"#,
+        r#"{"type":"unread_count","data":{"count":3}}"#,
+        r#"{"type":"ping"}"#,
+        r#"{"type":"pong","data":null}"#,
+        r#"{"type":"subscription_ack","data":{"topic":"user:u1"}}"#,
+        r#"{"type":"error","data":{"code":"forbidden","message":"Not allowed to subscribe"}}"#,
+        r#"{"type":"error","data":{}}"#,
+        r#"{"type":"presence","data":{"online":["u1"]}}"#,
+    ];
+
+    #[test]
+    fn server_frames_decode_to_their_messages() {
+        let messages: Vec<RealtimeMessage> = SERVER_FRAMES
+            .iter()
+            .map(|frame| serde_json::from_str(frame).unwrap())
+            .collect();
+        let types: Vec<&str> = messages.iter().map(RealtimeMessage::message_type).collect();
+        assert_eq!(
+            types,
+            [
+                "notification_new",
+                "notification_read",
+                "notification_deleted",
+                "unread_count",
+                "ping",
+                "pong",
+                "subscription_ack",
+                "error",
+                "error",
+                "presence",
+            ]
+        );
+
+        let RealtimeMessage::NotificationNew(ref notification) = messages[0] else {
+            panic!("{:?}", messages[0]);
+        };
+        assert_eq!(notification.id, "n1");
+        assert_eq!(notification.title, "New order");
+        assert_eq!(notification.extra["workflowId"], "wf_42");
+        assert!(matches!(messages[1], RealtimeMessage::NotificationRead { ref id } if id == "n1"));
+        assert!(
+            matches!(messages[2], RealtimeMessage::NotificationDeleted { ref id } if id == "n1")
+        );
+        assert!(matches!(messages[3], RealtimeMessage::UnreadCount { count: 3 }));
+        assert!(matches!(
+            messages[6],
+            RealtimeMessage::SubscriptionAck { ref topic } if topic == "user:u1"
+        ));
+        assert!(matches!(
+            messages[7],
+            RealtimeMessage::Error { ref code, ref message }
+                if code == "forbidden" && message == "Not allowed to subscribe"
+        ));
+        assert!(matches!(
+            messages[8],
+            RealtimeMessage::Error { ref code, ref message }
+                if code.is_empty() && message.is_empty()
+        ));
+        assert!(matches!(
+            messages[9],
+            RealtimeMessage::Unknown { ref raw, .. } if *raw == json!({ "online": ["u1"] })
+        ));
+    }
+
+    #[test]
+    fn messages_encode_to_the_frames_the_server_expects() {
+        let frames = [
+            (
+                RealtimeMessage::Subscribe {
+                    topic: "user:u1".to_string(),
+                },
+                r#"{"type":"subscribe","data":{"topic":"user:u1"}}"#,
+            ),
+            (RealtimeMessage::Ping, r#"{"type":"ping"}"#),
+            (RealtimeMessage::Pong, r#"{"type":"pong"}"#),
+            (
+                RealtimeMessage::UnreadCount { count: 3 },
+                r#"{"type":"unread_count","data":{"count":3}}"#,
+            ),
+        ];
+        for (message, frame) in frames {
+            assert_eq!(serde_json::to_string(&message).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn server_frames_survive_a_round_trip() {
+        for frame in &SERVER_FRAMES[1..] {
+            let message: RealtimeMessage = serde_json::from_str(frame).unwrap();
+            let encoded: RealtimeMessage =
+                serde_json::from_value(serde_json::to_value(&message).unwrap()).unwrap();
+            assert_eq!(format!("{:?}", encoded), format!("{:?}", message), "{}", frame);
+        }
+
+        let frame = SERVER_FRAMES[0];
+        let message: RealtimeMessage = serde_json::from_str(frame).unwrap();
+        let encoded = serde_json::to_value(&message).unwrap();
+        let captured: Value = serde_json::from_str(frame).unwrap();
+        for (field, value) in captured["data"].as_object().unwrap() {
+            assert_eq!(&encoded["data"][field], value, "{}", field);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Runtime reconfiguration for IronNotify SDK.
+
+use crate::config::NotifyOptions;
+use crate::debug::{DebugConfig, DebugLevel};
+#[cfg(feature = "quiet-hours")]
+use crate::quiet_hours::QuietHours;
+use parking_lot::{Mutex, RwLock};
+use std::sync::Arc;
+
+/// Callback invoked with the settings in effect after `reconfigure`.
+pub type ReconfiguredCallback = Arc<dyn Fn(&RuntimeConfig) + Send + Sync>;
+
+/// The options that can be changed on a running client with `NotifyClient::reconfigure`.
+///
+/// Base URLs, credentials, and queue storage are not included: they keep the values
+/// the client was created with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeConfig {
+    /// Enable summary debug logging for every component, on top of `debug_config`.
+    pub debug: bool,
+    /// Debug log levels for each component.
+    pub debug_config: DebugConfig,
+    /// Remaining requests below which Info and Warning sends are queued until the rate
+    /// limit window resets. Disabled when `None`.
+    pub rate_limit_threshold: Option<u64>,
+    /// When flushing, send the oldest lower-severity notification after this many
+    /// Error or Critical ones (0 sends all of them first).
+    pub flush_fairness: usize,
+    /// Daily window during which non-critical notifications are deferred. Its
+    /// `min_severity_to_bypass` is the lowest severity sent during the window.
+    #[cfg(feature = "quiet-hours")]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl RuntimeConfig {
+    /// Gets the runtime settings of client options.
+    pub(crate) fn from_options(options: &NotifyOptions) -> Self {
+        Self {
+            debug: options.debug,
+            debug_config: options.debug_config,
+            rate_limit_threshold: options.rate_limit_threshold,
+            flush_fairness: options.flush_fairness,
+            #[cfg(feature = "quiet-hours")]
+            quiet_hours: options.quiet_hours,
+        }
+    }
+
+    /// Gets the debug log levels in effect, like `NotifyOptions::debug_levels`.
+    pub fn debug_levels(&self) -> DebugConfig {
+        if self.debug {
+            self.debug_config.at_least(DebugLevel::Summary)
+        } else {
+            self.debug_config
+        }
+    }
+
+    /// Gets the names of the settings that differ from `other`.
+    pub fn changes(&self, other: &RuntimeConfig) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+        if self.debug != other.debug {
+            changes.push("debug");
+        }
+        if self.debug_config != other.debug_config {
+            changes.push("debug_config");
+        }
+        if self.rate_limit_threshold != other.rate_limit_threshold {
+            changes.push("rate_limit_threshold");
+        }
+        if self.flush_fairness != other.flush_fairness {
+            changes.push("flush_fairness");
+        }
+        #[cfg(feature = "quiet-hours")]
+        if self.quiet_hours != other.quiet_hours {
+            changes.push("quiet_hours");
+        }
+        changes
+    }
+}
+
+/// The runtime settings shared by the client and its components, swapped as a whole
+/// by `reconfigure`.
+pub(crate) struct LiveConfig {
+    current: RwLock<RuntimeConfig>,
+    /// Held while a change is applied, so concurrent changes do not overwrite each other.
+    updating: Mutex<()>,
+    callback: RwLock<Option<ReconfiguredCallback>>,
+}
+
+impl LiveConfig {
+    /// Creates the shared settings from client options.
+    pub fn new(options: &NotifyOptions) -> Arc<Self> {
+        Arc::new(Self {
+            current: RwLock::new(RuntimeConfig::from_options(options)),
+            updating: Mutex::new(()),
+            callback: RwLock::new(None),
+        })
+    }
+
+    /// Gets a copy of the settings in effect.
+    pub fn get(&self) -> RuntimeConfig {
+        self.current.read().clone()
+    }
+
+    /// Gets the debug log levels in effect.
+    pub fn debug_levels(&self) -> DebugConfig {
+        self.current.read().debug_levels()
+    }
+
+    /// Gets the rate limit threshold in effect.
+    pub fn rate_limit_threshold(&self) -> Option<u64> {
+        self.current.read().rate_limit_threshold
+    }
+
+    /// Gets the flush fairness in effect.
+    pub fn flush_fairness(&self) -> usize {
+        self.current.read().flush_fairness
+    }
+
+    /// Gets the quiet hours in effect.
+    #[cfg(feature = "quiet-hours")]
+    pub fn quiet_hours(&self) -> Option<QuietHours> {
+        self.current.read().quiet_hours
+    }
+
+    /// Sets the reconfiguration callback.
+    pub fn set_callback(&self, callback: ReconfiguredCallback) {
+        *self.callback.write() = Some(callback);
+    }
+
+    /// Applies `change` to a copy of the settings, then replaces them in one step.
+    ///
+    /// Gets the names of the settings that changed.
+    pub fn update(&self, change: impl FnOnce(&mut RuntimeConfig)) -> Vec<&'static str> {
+        let (changes, applied, debug) = {
+            let _updating = self.updating.lock();
+            // The change runs without the settings locked, so it may read them.
+            let current = self.get();
+            let mut next = current.clone();
+            change(&mut next);
+            let changes = next.changes(&current);
+            let debug = current.debug_levels().is_enabled() || next.debug_levels().is_enabled();
+            *self.current.write() = next.clone();
+            (changes, next, debug)
+        };
+
+        if debug {
+            if changes.is_empty() {
+                println!("[IronNotify] Reconfigured with no changes");
+            } else {
+                println!("[IronNotify] Reconfigured {}: {:?}", changes.join(", "), applied);
+            }
+        }
+        let callback = self.callback.read().clone();
+        if let Some(callback) = callback {
+            callback(&applied);
+        }
+        changes
+    }
+}
@@ -0,0 +1,240 @@
+//! Wire-level traffic recording for IronNotify SDK.
+
+use crate::redact::{Redactor, REDACTED};
+use crate::rotation::{read_lines, rotated_files, RotatingFile};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Response, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Headers that are never written to a recording.
+const OMITTED_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Query parameters that are never written to a recording.
+const OMITTED_PARAMS: [&str; 3] = ["apikey", "api_key", "token"];
+
+/// One recorded request and its response.
+///
+/// Credentials are left out, and headers, body fields, and query parameters matching
+/// `redact_metadata_keys` are replaced with `[REDACTED]`. Bodies that are not text,
+/// e.g. MessagePack, are not recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedExchange {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub request_headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    /// HTTP status, or `None` if the request failed before a response arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<String>,
+    /// Why the request failed, when it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+impl RecordedExchange {
+    /// Reads the exchanges recorded at `path` and in its rotated files, oldest first.
+    ///
+    /// Lines that cannot be parsed, e.g. one cut short by a crash, are skipped.
+    pub fn read_all(path: impl AsRef<Path>) -> Vec<Self> {
+        let path = path.as_ref();
+        read_lines(path, rotated_files(path))
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+}
+
+/// Writes recorded exchanges as NDJSON from a background thread.
+pub(crate) struct TrafficRecorder {
+    sender: mpsc::UnboundedSender<RecordedExchange>,
+    redactor: Redactor,
+    api_key: String,
+}
+
+impl TrafficRecorder {
+    /// Opens a recording at `path`, rotating once the file reaches `max_bytes` and
+    /// keeping at most `max_files` rotated files.
+    pub fn open(
+        path: PathBuf,
+        max_bytes: u64,
+        max_files: usize,
+        redactor: Redactor,
+        api_key: String,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<RecordedExchange>();
+        let mut writer = RotatingFile::new(path, max_bytes, max_files);
+
+        std::thread::Builder::new()
+            .name("ironnotify-recorder".to_string())
+            .spawn(move || {
+                while let Some(exchange) = receiver.blocking_recv() {
+                    if let Ok(mut line) = serde_json::to_string(&exchange) {
+                        line.push('\n');
+                        writer.write_line(&line);
+                    }
+                }
+            })
+            .expect("Failed to spawn traffic recorder thread");
+
+        Self {
+            sender,
+            redactor,
+            api_key,
+        }
+    }
+
+    /// Captures a request about to be sent.
+    pub fn request(&self, request: &reqwest::Request) -> RecordedExchange {
+        RecordedExchange {
+            timestamp: Utc::now(),
+            method: request.method().to_string(),
+            url: self.redact_url(request.url()),
+            request_headers: self.redact_headers(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|bytes| self.redact_body(bytes)),
+            status: None,
+            response_headers: BTreeMap::new(),
+            response_body: None,
+            error: None,
+            elapsed_ms: 0,
+        }
+    }
+
+    /// Completes and writes an exchange, returning the response unchanged.
+    ///
+    /// Text responses are read to record their body and handed back from memory;
+    /// others, such as attachment downloads, are passed through without their body.
+    pub async fn finish(
+        &self,
+        mut exchange: RecordedExchange,
+        result: reqwest::Result<Response>,
+        elapsed: Duration,
+    ) -> reqwest::Result<Response> {
+        exchange.elapsed_ms = elapsed.as_millis() as u64;
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                exchange.error = Some(e.to_string());
+                let _ = self.sender.send(exchange);
+                return Err(e);
+            }
+        };
+
+        exchange.status = Some(response.status().as_u16());
+        exchange.response_headers = self.redact_headers(response.headers());
+        if !is_text(response.headers()) {
+            let _ = self.sender.send(exchange);
+            return Ok(response);
+        }
+
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let bytes = match response.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                exchange.error = Some(e.to_string());
+                let _ = self.sender.send(exchange);
+                return Err(e);
+            }
+        };
+        exchange.response_body = self.redact_body(&bytes);
+        let _ = self.sender.send(exchange);
+
+        let mut rebuilt = http::Response::new(bytes);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        Ok(Response::from(rebuilt))
+    }
+
+    /// Removes credentials from a URL's query and redacts matching parameters.
+    fn redact_url(&self, url: &Url) -> String {
+        if url.query().is_none() {
+            return url.to_string();
+        }
+
+        let params: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, value)| {
+                let is_key = !self.api_key.is_empty() && value == &self.api_key;
+                !is_key && !OMITTED_PARAMS.contains(&key.to_lowercase().as_str())
+            })
+            .map(|(key, value)| {
+                let value = if self.redactor.matches(&key) {
+                    REDACTED.to_string()
+                } else {
+                    value.into_owned()
+                };
+                (key.into_owned(), value)
+            })
+            .collect();
+
+        let mut url = url.clone();
+        if params.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(params);
+        }
+        url.to_string()
+    }
+
+    fn redact_headers(&self, headers: &HeaderMap) -> BTreeMap<String, String> {
+        headers
+            .iter()
+            .filter(|(name, _)| !OMITTED_HEADERS.contains(&name.as_str()))
+            .map(|(name, value)| {
+                let value = if self.redactor.matches(name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Gets a body as text, with matching JSON fields redacted. Empty and binary
+    /// bodies are not recorded.
+    fn redact_body(&self, bytes: &[u8]) -> Option<String> {
+        if bytes.is_empty() {
+            return None;
+        }
+        if let Ok(value) = serde_json::from_slice::<serde_json::Value>(bytes) {
+            return serde_json::to_string(&self.redactor.redact_value(&value)).ok();
+        }
+        std::str::from_utf8(bytes).ok().map(str::to_string)
+    }
+}
+
+/// Checks whether a response has a JSON or text body, which is recorded.
+fn is_text(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| {
+            content_type.contains("json") || content_type.starts_with("text/")
+        })
+}
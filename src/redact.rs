@@ -0,0 +1,190 @@
+//! Metadata redaction for IronNotify SDK.
+
+use crate::types::NotificationPayload;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Replacement value for redacted metadata entries.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Redacts metadata values whose keys match configured glob patterns.
+///
+/// Patterns support `*` (any sequence) and `?` (any single character) and are
+/// matched case-insensitively against keys at every nesting level.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<String>,
+}
+
+impl Redactor {
+    /// Creates a redactor for the given key patterns.
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(|p| p.to_lowercase()).collect(),
+        }
+    }
+
+    /// Checks whether no patterns are configured.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Checks whether a key matches any pattern.
+    pub fn matches(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.patterns.iter().any(|pattern| glob_match(pattern, &key))
+    }
+
+    /// Returns a copy of the value with matching keys redacted, traversing nested objects and arrays.
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| {
+                        let v = if self.matches(k) {
+                            Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact_value(v)
+                        };
+                        (k.clone(), v)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.redact_value(v)).collect()),
+            other => other.clone(),
+        }
+    }
+
+    /// Returns a copy of the payload with its metadata redacted.
+    pub fn redact_payload(&self, payload: &NotificationPayload) -> NotificationPayload {
+        let mut redacted = payload.clone();
+        if self.is_empty() {
+            return redacted;
+        }
+
+        if let Some(ref metadata) = redacted.metadata {
+            redacted.metadata = Some(self.redact_metadata(metadata));
+        }
+        redacted
+    }
+
+    /// Returns a copy of a metadata map with matching keys redacted.
+    pub fn redact_metadata(&self, metadata: &HashMap<String, Value>) -> HashMap<String, Value> {
+        metadata
+            .iter()
+            .map(|(key, value)| {
+                let value = if self.matches(key) {
+                    Value::String(REDACTED.to_string())
+                } else {
+                    self.redact_value(value)
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redactor() -> Redactor {
+        Redactor::new(vec!["*_token".to_string(), "email".to_string()])
+    }
+
+    #[test]
+    fn glob_patterns_match_case_insensitively() {
+        let redactor = redactor();
+        assert!(redactor.matches("access_token"));
+        assert!(redactor.matches("Refresh_TOKEN"));
+        assert!(redactor.matches("EMAIL"));
+        assert!(!redactor.matches("email_verified"));
+        assert!(!redactor.matches("token"));
+        assert!(glob_match("user_?d", "user_id"));
+        assert!(!glob_match("user_?d", "user_uuid"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn nested_objects_and_arrays_are_traversed() {
+        let value = json!({
+            "order": {
+                "id": 12,
+                "customer": { "email": "a@example.com", "name": "Ada" },
+                "payments": [
+                    { "card_token": "tok_1", "amount": 5 },
+                    { "card_token": "tok_2", "amount": 7 }
+                ]
+            },
+            "email": { "nested": "whole value is replaced" }
+        });
+
+        assert_eq!(
+            redactor().redact_value(&value),
+            json!({
+                "order": {
+                    "id": 12,
+                    "customer": { "email": REDACTED, "name": "Ada" },
+                    "payments": [
+                        { "card_token": REDACTED, "amount": 5 },
+                        { "card_token": REDACTED, "amount": 7 }
+                    ]
+                },
+                "email": REDACTED
+            })
+        );
+    }
+
+    #[test]
+    fn payload_copies_are_redacted_and_the_original_is_kept() {
+        let mut payload = NotificationPayload::new("order.created", "Order");
+        payload.metadata = Some(HashMap::from([
+            ("session_token".to_string(), json!("secret")),
+            ("details".to_string(), json!({ "email": "a@example.com", "items": 3 })),
+        ]));
+
+        let redacted = redactor().redact_payload(&payload);
+        let metadata = redacted.metadata.unwrap();
+        assert_eq!(metadata["session_token"], json!(REDACTED));
+        assert_eq!(metadata["details"], json!({ "email": REDACTED, "items": 3 }));
+
+        let original = payload.metadata.unwrap();
+        assert_eq!(original["session_token"], json!("secret"));
+        assert_eq!(original["details"]["email"], json!("a@example.com"));
+    }
+
+    #[test]
+    fn no_patterns_leave_payloads_unchanged() {
+        let mut payload = NotificationPayload::new("e", "t");
+        payload.metadata = Some(HashMap::from([("email".to_string(), json!("a@example.com"))]));
+        let redacted = Redactor::default().redact_payload(&payload);
+        assert_eq!(redacted.metadata, payload.metadata);
+    }
+}
@@ -0,0 +1,314 @@
+//! Terminal rendering of notifications for IronNotify SDK.
+
+use crate::types::{Notification, SeverityLevel};
+use chrono::{DateTime, Utc};
+
+/// Width of the severity column in `render_list`.
+const SEVERITY_WIDTH: usize = 10;
+
+/// Width of the age column in `render_list`.
+const AGE_WIDTH: usize = 8;
+
+/// How much detail a rendered notification includes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderStyle {
+    /// A single line with severity, title, and age.
+    #[default]
+    Compact,
+    /// Several lines including the message, event type, read state, and actions.
+    Detailed,
+}
+
+/// Options for `render_list`.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// How much detail each notification includes.
+    pub style: RenderStyle,
+    /// Color severity prefixes with ANSI escapes.
+    pub color: bool,
+    /// Maximum line width in characters. Lines are not truncated when `None`.
+    pub width: Option<usize>,
+    /// Time that ages are relative to.
+    pub now: DateTime<Utc>,
+}
+
+impl Default for RenderOptions {
+    /// Colors unless `NO_COLOR` is set and uses `COLUMNS` as the width.
+    fn default() -> Self {
+        Self {
+            style: RenderStyle::Compact,
+            color: std::env::var_os("NO_COLOR").is_none(),
+            width: std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok()),
+            now: Utc::now(),
+        }
+    }
+}
+
+/// Renders a notification for the terminal.
+///
+/// Colors unless `NO_COLOR` is set. Nothing is printed; the caller decides where the text goes.
+pub fn render_notification(notification: &Notification, style: RenderStyle) -> String {
+    let options = RenderOptions {
+        style,
+        width: None,
+        ..Default::default()
+    };
+    render(notification, &options)
+}
+
+/// Renders notifications one per entry with aligned columns.
+///
+/// In the compact style, titles are padded to a common width and lines are
+/// truncated to `width`.
+pub fn render_list(notifications: &[Notification], options: &RenderOptions) -> String {
+    if options.style == RenderStyle::Detailed {
+        return notifications
+            .iter()
+            .map(|n| render(n, options))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+
+    let title_width = notifications
+        .iter()
+        .map(|n| n.title.chars().count())
+        .max()
+        .unwrap_or(0);
+    let title_width = match options.width {
+        Some(width) => title_width.min(width.saturating_sub(SEVERITY_WIDTH + AGE_WIDTH + 2)),
+        None => title_width,
+    };
+
+    notifications
+        .iter()
+        .map(|n| {
+            let title = pad(&truncate(&n.title, title_width), title_width);
+            let age = relative_time(n.created_at, options.now);
+            let line = format!(
+                "{} {} {:>width$}",
+                pad(&severity_tag(n.severity), SEVERITY_WIDTH),
+                title,
+                age,
+                width = AGE_WIDTH
+            );
+            let line = match options.width {
+                Some(width) => truncate(&line, width),
+                None => line,
+            };
+            colorize_prefix(line, n.severity, options.color)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render(notification: &Notification, options: &RenderOptions) -> String {
+    let age = relative_time(notification.created_at, options.now);
+    let tag = severity_tag(notification.severity);
+
+    let text = match options.style {
+        RenderStyle::Compact => {
+            let mut line = format!("{} {}", tag, notification.title);
+            if let Some(ref message) = notification.message {
+                line.push_str(": ");
+                line.push_str(message);
+            }
+            line.push_str(&format!(" ({})", age));
+            match options.width {
+                Some(width) => truncate(&line, width),
+                None => line,
+            }
+        }
+        RenderStyle::Detailed => {
+            let mut lines = vec![format!("{} {}", tag, notification.title)];
+            if let Some(ref message) = notification.message {
+                lines.push(format!("  {}", message));
+            }
+            lines.push(format!(
+                "  {} · {} · {}",
+                notification.event_type,
+                age,
+                if notification.read { "read" } else { "unread" }
+            ));
+            if let Some(ref actions) = notification.actions {
+                if !actions.is_empty() {
+                    let labels: Vec<&str> = actions.iter().map(|a| a.label.as_str()).collect();
+                    lines.push(format!("  Actions: {}", labels.join(", ")));
+                }
+            }
+            lines.join("\n")
+        }
+    };
+
+    colorize_prefix(text, notification.severity, options.color)
+}
+
+/// Formats `then` relative to `now`, e.g. `3h ago` or `in 5m`.
+pub fn relative_time(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - then).num_seconds();
+    let magnitude = seconds.unsigned_abs();
+    let amount = match magnitude {
+        0..=59 => return "just now".to_string(),
+        60..=3_599 => format!("{}m", magnitude / 60),
+        3_600..=86_399 => format!("{}h", magnitude / 3_600),
+        86_400..=2_591_999 => format!("{}d", magnitude / 86_400),
+        _ => format!("{}mo", magnitude / 2_592_000),
+    };
+    if seconds >= 0 {
+        format!("{} ago", amount)
+    } else {
+        format!("in {}", amount)
+    }
+}
+
+fn severity_tag(severity: SeverityLevel) -> String {
+    format!("[{}]", severity.to_string().to_uppercase())
+}
+
+fn ansi_color(severity: SeverityLevel) -> &'static str {
+    match severity {
+        SeverityLevel::Info => "\x1b[36m",
+        SeverityLevel::Success => "\x1b[32m",
+        SeverityLevel::Warning => "\x1b[33m",
+        SeverityLevel::Error => "\x1b[31m",
+        SeverityLevel::Critical => "\x1b[1;31m",
+    }
+}
+
+/// Colors the leading severity tag of the first line.
+fn colorize_prefix(text: String, severity: SeverityLevel, color: bool) -> String {
+    if !color {
+        return text;
+    }
+    match text.find(']') {
+        Some(end) => format!(
+            "{}{}\x1b[0m{}",
+            ansi_color(severity),
+            &text[..=end],
+            &text[end + 1..]
+        ),
+        None => text,
+    }
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(len)))
+}
+
+/// Truncates text to at most `max` characters, ending with `…` when shortened.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    fn notifications(now: DateTime<Utc>) -> Vec<Notification> {
+        let disk = json!({
+            "id": "n1", "eventType": "host.disk", "title": "Disk almost full",
+            "message": "92% used on /var", "severity": "warning", "read": false,
+            "createdAt": now - Duration::minutes(181),
+            "actions": [{ "label": "View" }, { "label": "Dismiss" }]
+        });
+        let backup = json!({
+            "id": "n2", "eventType": "host.backup", "title": "Backup done",
+            "severity": "info", "read": true, "createdAt": now - Duration::seconds(30)
+        });
+        vec![
+            serde_json::from_value(disk).unwrap(),
+            serde_json::from_value(backup).unwrap(),
+        ]
+    }
+
+    fn list(style: RenderStyle, color: bool, width: Option<usize>) -> String {
+        let now = "2026-10-14T12:00:00Z".parse().unwrap();
+        let options = RenderOptions {
+            style,
+            color,
+            width,
+            now,
+        };
+        render_list(&notifications(now), &options)
+    }
+
+    /// Colors the severity tag at the start of `text` the way `render_notification`
+    /// does unless `NO_COLOR` is set.
+    fn default_color(text: &str, color: &str) -> String {
+        match std::env::var_os("NO_COLOR") {
+            Some(_) => text.to_string(),
+            None => text.replacen('[', &format!("{}[", color), 1).replacen(']', "]\x1b[0m", 1),
+        }
+    }
+
+    #[test]
+    fn notifications_render_in_each_style() {
+        let disk = &notifications(Utc::now())[0];
+        assert_eq!(
+            render_notification(disk, RenderStyle::Compact),
+            default_color("[WARNING] Disk almost full: 92% used on /var (3h ago)", "\x1b[33m")
+        );
+        assert_eq!(
+            render_notification(disk, RenderStyle::Detailed),
+            default_color(
+                "[WARNING] Disk almost full\n\
+                 \x20 92% used on /var\n\
+                 \x20 host.disk · 3h ago · unread\n\
+                 \x20 Actions: View, Dismiss",
+                "\x1b[33m"
+            )
+        );
+    }
+
+    #[test]
+    fn compact_lists_align_their_columns() {
+        assert_eq!(
+            list(RenderStyle::Compact, false, None),
+            "[WARNING]  Disk almost full   3h ago\n\
+             [INFO]     Backup done      just now"
+        );
+        assert_eq!(
+            list(RenderStyle::Compact, true, None),
+            "\x1b[33m[WARNING]\x1b[0m  Disk almost full   3h ago\n\
+             \x1b[36m[INFO]\x1b[0m     Backup done      just now"
+        );
+    }
+
+    #[test]
+    fn compact_lists_truncate_titles_to_the_width() {
+        assert_eq!(
+            list(RenderStyle::Compact, false, Some(30)),
+            "[WARNING]  Disk almo…   3h ago\n\
+             [INFO]     Backup do… just now"
+        );
+        assert_eq!(
+            list(RenderStyle::Compact, false, Some(12)),
+            "[WARNING]  …\n\
+             [INFO]     …"
+        );
+    }
+
+    #[test]
+    fn detailed_lists_separate_notifications_with_a_blank_line() {
+        assert_eq!(
+            list(RenderStyle::Detailed, false, None),
+            "[WARNING] Disk almost full\n\
+             \x20 92% used on /var\n\
+             \x20 host.disk · 3h ago · unread\n\
+             \x20 Actions: View, Dismiss\n\
+             \n\
+             [INFO] Backup done\n\
+             \x20 host.backup · just now · read"
+        );
+    }
+}
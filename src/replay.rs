@@ -0,0 +1,137 @@
+//! Replaying recorded traffic for IronNotify SDK.
+//!
+//! Available with the `record` feature.
+//!
+//! Requests recorded with `NotifyOptionsBuilder::record_traffic` can be sent again to
+//! another server, e.g. a staging deployment or a mock server, to reproduce a bug:
+//!
+//! ```rust,no_run
+//! use ironnotify::replay::Replayer;
+//! use ironnotify::RecordedExchange;
+//!
+//! # async fn example() -> Result<(), ironnotify::Error> {
+//! let exchanges = RecordedExchange::read_all("ironnotify-traffic.ndjson");
+//! let replayer = Replayer::new("http://localhost:8080")?.api_key("ak_test_xxxxx");
+//! for exchange in &exchanges {
+//!     let replayed = replayer.replay(exchange).await?;
+//!     if Some(replayed.status) != exchange.status {
+//!         println!("{} {}: now {}", exchange.method, exchange.url, replayed.status);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Error;
+use crate::record::RecordedExchange;
+use crate::redact::REDACTED;
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::{Client, Method, Url};
+use std::time::{Duration, Instant};
+
+/// Headers that are set by the HTTP client rather than copied from the recording.
+const CLIENT_HEADERS: [&str; 3] = ["host", "content-length", "transfer-encoding"];
+
+/// The response to a replayed request.
+#[derive(Debug, Clone)]
+pub struct ReplayedResponse {
+    pub status: u16,
+    pub body: String,
+    pub elapsed: Duration,
+}
+
+/// Sends recorded requests to another base URL.
+///
+/// Recordings contain no credentials, so set `api_key` to authenticate against the
+/// target. Redacted values are sent as `[REDACTED]`.
+pub struct Replayer {
+    base_url: Url,
+    api_key: Option<String>,
+    client: Client,
+}
+
+impl Replayer {
+    /// Creates a replayer sending to `base_url`, which replaces the scheme, host,
+    /// and port of recorded URLs and is prepended to their paths.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let base_url = Url::parse(base_url).map_err(|e| Error::Config(e.to_string()))?;
+        Ok(Self {
+            base_url,
+            api_key: None,
+            client: Client::new(),
+        })
+    }
+
+    /// Sets the API key sent with replayed requests.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Gets the URL a recorded request is replayed to.
+    pub fn target_url(&self, exchange: &RecordedExchange) -> Result<Url, Error> {
+        let recorded = Url::parse(&exchange.url).map_err(|e| Error::Config(e.to_string()))?;
+        let mut url = self.base_url.clone();
+        url.set_path(&format!(
+            "{}{}",
+            self.base_url.path().trim_end_matches('/'),
+            recorded.path()
+        ));
+        url.set_query(recorded.query());
+        Ok(url)
+    }
+
+    /// Sends a recorded request again and returns the response.
+    pub async fn replay(&self, exchange: &RecordedExchange) -> Result<ReplayedResponse, Error> {
+        let method = Method::from_bytes(exchange.method.as_bytes())
+            .map_err(|e| Error::Config(e.to_string()))?;
+        let mut request = self.client.request(method, self.target_url(exchange)?);
+
+        for (name, value) in &exchange.request_headers {
+            if CLIENT_HEADERS.contains(&name.as_str()) || value == REDACTED {
+                continue;
+            }
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                request = request.header(name, value);
+            }
+        }
+        if let Some(ref api_key) = self.api_key {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", api_key));
+        }
+        if let Some(ref body) = exchange.request_body {
+            request = request.body(body.clone());
+        }
+
+        let started = Instant::now();
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?;
+        let status = response.status().as_u16();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?;
+
+        Ok(ReplayedResponse {
+            status,
+            body,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Replays recorded requests one after another, in order.
+    pub async fn replay_all(
+        &self,
+        exchanges: &[RecordedExchange],
+    ) -> Vec<Result<ReplayedResponse, Error>> {
+        let mut results = Vec::with_capacity(exchanges.len());
+        for exchange in exchanges {
+            results.push(self.replay(exchange).await);
+        }
+        results
+    }
+}
@@ -0,0 +1,85 @@
+//! Recipient enrichment for IronNotify SDK.
+
+use crate::runtime::BoxFuture;
+use crate::types::NotificationPayload;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Metadata key holding the resolved recipient details.
+pub const RECIPIENT_METADATA_KEY: &str = "recipient";
+
+/// Details about a recipient, looked up by the user resolver.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// BCP 47 language tag, e.g. `de-CH`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// IANA time zone name, e.g. `Europe/Zurich`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+type ResolveFn = Arc<dyn Fn(String) -> BoxFuture<Result<UserContext, String>> + Send + Sync>;
+
+/// Looks up recipients with a user-supplied callback, caching results for a TTL.
+pub(crate) struct UserResolver {
+    resolve: ResolveFn,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (UserContext, Instant)>>,
+}
+
+impl UserResolver {
+    /// Creates a resolver from a callback.
+    pub fn new<F, Fut, E>(ttl: Duration, resolve: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<UserContext, E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let resolve: ResolveFn = Arc::new(move |user_id| {
+            let future = resolve(user_id);
+            Box::pin(async move { future.await.map_err(|e| e.to_string()) })
+        });
+        Self {
+            resolve,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gets the context for a user, calling the callback on a cache miss.
+    pub async fn resolve(&self, user_id: &str) -> Result<UserContext, String> {
+        if let Some((context, resolved_at)) = self.cache.lock().get(user_id) {
+            if resolved_at.elapsed() < self.ttl {
+                return Ok(context.clone());
+            }
+        }
+
+        let context = (self.resolve)(user_id.to_string()).await?;
+        let mut cache = self.cache.lock();
+        cache.retain(|_, (_, resolved_at)| resolved_at.elapsed() < self.ttl);
+        cache.insert(user_id.to_string(), (context.clone(), Instant::now()));
+        Ok(context)
+    }
+}
+
+/// Returns a copy of the payload with the context under the `recipient` metadata key.
+///
+/// A `recipient` entry set by the caller is kept.
+pub(crate) fn enrich(payload: &NotificationPayload, context: &UserContext) -> NotificationPayload {
+    let mut enriched = payload.clone();
+    let metadata = enriched.metadata.get_or_insert_with(HashMap::new);
+    if !metadata.contains_key(RECIPIENT_METADATA_KEY) {
+        if let Ok(value) = serde_json::to_value(context) {
+            metadata.insert(RECIPIENT_METADATA_KEY.to_string(), value);
+        }
+    }
+    enriched
+}
@@ -0,0 +1,123 @@
+//! Percentage rollouts for IronNotify SDK.
+//!
+//! `EventBuilder::rollout_percentage` lets the server pick which part of the audience
+//! receives a notification. When the recipients are chosen in the application
+//! instead, `in_bucket` splits users the same way every time: a user's bucket only
+//! depends on their ID and the salt, so a cohort stays the same across notifications
+//! that share a salt, and grows without reshuffling as the percentage is raised.
+
+use crate::hash::sha256_hex;
+
+/// Number of buckets users are split into, so percentages have two decimals of
+/// precision.
+pub const BUCKETS: u32 = 10_000;
+
+/// Gets the bucket of a user, from 0 to `BUCKETS - 1`.
+///
+/// The bucket is the first 32 bits of the SHA-256 of `salt:user_id`, modulo
+/// `BUCKETS`. It does not change across runs, platforms, or crate versions.
+///
+/// ```
+/// use ironnotify::rollout::bucket;
+///
+/// assert_eq!(bucket("user-1", "spring-launch"), 2322);
+/// assert_eq!(bucket("user-2", "spring-launch"), 2565);
+/// assert_eq!(bucket("user-1", "summer-launch"), 9081);
+/// ```
+pub fn bucket(user_id: &str, salt: &str) -> u32 {
+    let digest = sha256_hex(format!("{}:{}", salt, user_id).as_bytes());
+    let value = u32::from_str_radix(&digest[..8], 16).unwrap_or_default();
+    value % BUCKETS
+}
+
+/// Checks whether a user is in a rollout to `percentage` percent of users.
+///
+/// Users in a rollout stay in it when the percentage is raised. Percentages outside
+/// (0, 100] include nobody.
+///
+/// ```
+/// use ironnotify::rollout::in_bucket;
+///
+/// // user-1 is in bucket 2322 and user-2 in bucket 2565.
+/// assert!(in_bucket("user-1", 25.0, "spring-launch"));
+/// assert!(!in_bucket("user-2", 25.0, "spring-launch"));
+/// assert!(in_bucket("user-2", 30.0, "spring-launch"));
+/// assert!(in_bucket("user-1", 100.0, "spring-launch"));
+/// assert!(!in_bucket("user-1", 0.0, "spring-launch"));
+/// ```
+pub fn in_bucket(user_id: &str, percentage: f32, salt: &str) -> bool {
+    if !is_valid_percentage(percentage) {
+        return false;
+    }
+    let threshold = (f64::from(percentage) * f64::from(BUCKETS) / 100.0).round() as u32;
+    bucket(user_id, salt) < threshold
+}
+
+/// Checks that a rollout percentage is in (0, 100].
+pub(crate) fn is_valid_percentage(percentage: f32) -> bool {
+    percentage > 0.0 && percentage <= 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Buckets recorded when rollouts were added. A change here moves users between
+    /// cohorts of running rollouts, so it needs a release note, not a new value.
+    const BUCKETS_BY_USER: [(&str, &str, u32); 9] = [
+        ("user-1", "spring-launch", 2322),
+        ("user-2", "spring-launch", 2565),
+        ("user-3", "spring-launch", 5395),
+        ("user-4", "spring-launch", 9621),
+        ("user-5", "spring-launch", 31),
+        ("alice", "spring-launch", 5463),
+        ("", "spring-launch", 2724),
+        ("user-1", "", 4549),
+        ("bob", "", 8498),
+    ];
+
+    #[test]
+    fn buckets_are_fixed() {
+        for (user_id, salt, expected) in BUCKETS_BY_USER {
+            assert_eq!(bucket(user_id, salt), expected, "{:?} with salt {:?}", user_id, salt);
+        }
+    }
+
+    #[test]
+    fn ten_percent_cohort_is_fixed() {
+        let cohort: Vec<String> = (1..=50)
+            .map(|i| format!("user-{}", i))
+            .filter(|user_id| in_bucket(user_id, 10.0, "spring-launch"))
+            .collect();
+        assert_eq!(cohort, ["user-5", "user-16", "user-25", "user-40", "user-42", "user-47"]);
+    }
+
+    #[test]
+    fn thresholds_round_to_the_nearest_bucket() {
+        // user-5 is in bucket 31.
+        assert!(!in_bucket("user-5", 0.31, "spring-launch"));
+        assert!(in_bucket("user-5", 0.32, "spring-launch"));
+    }
+
+    #[test]
+    fn raising_the_percentage_only_adds_users() {
+        let users: Vec<String> = (1..=200).map(|i| format!("user-{}", i)).collect();
+        let mut previous: Vec<&String> = Vec::new();
+        for percentage in [1.0, 5.0, 10.0, 25.0, 50.0, 100.0] {
+            let cohort: Vec<&String> = users
+                .iter()
+                .filter(|user_id| in_bucket(user_id, percentage, "spring-launch"))
+                .collect();
+            assert!(previous.iter().all(|user_id| cohort.contains(user_id)));
+            previous = cohort;
+        }
+        assert_eq!(previous.len(), users.len());
+    }
+
+    #[test]
+    fn percentages_outside_the_range_include_nobody() {
+        for percentage in [0.0, -5.0, 100.5, f32::NAN, f32::INFINITY] {
+            assert!(!in_bucket("user-5", percentage, "spring-launch"));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Size-capped, rotated line files for IronNotify SDK.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Appends lines to a file, rotating it once it reaches `max_bytes` and keeping at
+/// most `max_files` rotated files as `path.1` (newest) to `path.{max_files}`.
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> Self {
+        Self {
+            path,
+            max_bytes,
+            max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    /// Appends one line, which must end with a newline, in a single write.
+    pub fn write_line(&mut self, line: &str) {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate();
+        }
+
+        if self.file.is_none() {
+            self.open();
+        }
+
+        if let Some(ref mut file) = self.file {
+            if file.write_all(line.as_bytes()).is_ok() {
+                self.size += line.len() as u64;
+            } else {
+                self.file = None;
+            }
+        }
+    }
+
+    fn open(&mut self) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        if let Ok(file) = OpenOptions::new().create(true).append(true).open(&self.path) {
+            self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+            self.file = Some(file);
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        self.size = 0;
+
+        if self.max_files == 0 {
+            let _ = fs::remove_file(&self.path);
+            return;
+        }
+
+        let _ = fs::remove_file(rotated_path(&self.path, self.max_files));
+        for n in (1..self.max_files).rev() {
+            let _ = fs::rename(rotated_path(&self.path, n), rotated_path(&self.path, n + 1));
+        }
+        let _ = fs::rename(&self.path, rotated_path(&self.path, 1));
+    }
+}
+
+/// Reads the lines of a file and up to `max_files` of its rotated files, oldest first.
+///
+/// Missing files are skipped.
+pub(crate) fn read_lines(path: &Path, max_files: usize) -> Vec<String> {
+    let mut paths: Vec<PathBuf> = (1..=max_files)
+        .rev()
+        .map(|n| rotated_path(path, n))
+        .collect();
+    paths.push(path.to_path_buf());
+
+    let mut lines = Vec::new();
+    for path in paths {
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        lines.extend(BufReader::new(file).lines().map_while(Result::ok));
+    }
+    lines
+}
+
+/// Counts the rotated files that exist next to `path`.
+pub(crate) fn rotated_files(path: &Path) -> usize {
+    (1..).take_while(|&n| rotated_path(path, n).exists()).count()
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
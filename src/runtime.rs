@@ -0,0 +1,160 @@
+//! Async runtime abstraction for IronNotify SDK.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+#[cfg(not(any(feature = "rt-tokio", feature = "rt-async-std")))]
+compile_error!("ironnotify requires either the `rt-tokio` or the `rt-async-std` feature");
+
+/// A boxed, sendable future.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Spawns and times the SDK's background work.
+///
+/// Implement this to run the client on an executor other than the built-in ones.
+pub trait Runtime: Debug + Send + Sync + 'static {
+    /// Runs a future in the background.
+    fn spawn(&self, future: BoxFuture<()>);
+
+    /// Waits for the given duration.
+    fn sleep(&self, duration: Duration) -> BoxFuture<()>;
+
+    /// Runs work that may block, such as waiting for the offline queue's file lock,
+    /// off the threads driving futures.
+    ///
+    /// Defaults to running it on the calling thread.
+    fn spawn_blocking(&self, work: Box<dyn FnOnce() + Send>) {
+        work();
+    }
+
+    /// Checks whether `spawn` can be called from the current thread.
+    ///
+    /// Defaults to `true`. Used by `emit_best_effort` to fall back to writing the
+    /// offline queue directly, e.g. from a `Drop` outside the runtime.
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+/// Ticks at a fixed period using a [`Runtime`]'s timer.
+///
+/// Missed ticks are skipped rather than fired in a burst.
+#[derive(Debug, Clone)]
+pub struct Interval {
+    runtime: Arc<dyn Runtime>,
+    period: Duration,
+    next: Instant,
+}
+
+impl Interval {
+    /// Creates an interval whose first tick completes immediately.
+    pub fn new(runtime: Arc<dyn Runtime>, period: Duration) -> Self {
+        Self {
+            runtime,
+            period,
+            next: Instant::now(),
+        }
+    }
+
+    /// Waits until the next tick.
+    pub async fn tick(&mut self) {
+        let now = Instant::now();
+        if self.next > now {
+            self.runtime.sleep(self.next - now).await;
+        }
+        let now = Instant::now();
+        while self.next <= now {
+            self.next += self.period.max(Duration::from_millis(1));
+        }
+    }
+}
+
+/// Runtime backed by tokio.
+#[cfg(feature = "rt-tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "rt-tokio")]
+impl Runtime for TokioRuntime {
+    fn spawn(&self, future: BoxFuture<()>) {
+        tokio::spawn(future);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn_blocking(&self, work: Box<dyn FnOnce() + Send>) {
+        tokio::task::spawn_blocking(work);
+    }
+
+    fn is_available(&self) -> bool {
+        tokio::runtime::Handle::try_current().is_ok()
+    }
+}
+
+/// Runtime backed by async-std, which also works alongside smol.
+#[cfg(feature = "rt-async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "rt-async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn spawn(&self, future: BoxFuture<()>) {
+        async_std::task::spawn(future);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn_blocking(&self, work: Box<dyn FnOnce() + Send>) {
+        async_std::task::spawn_blocking(work);
+    }
+}
+
+/// Runs a future, returning `None` if it does not finish within `duration`.
+pub(crate) async fn timeout<F: Future>(
+    runtime: &dyn Runtime,
+    duration: Duration,
+    future: F,
+) -> Option<F::Output> {
+    let mut future = std::pin::pin!(future);
+    let mut sleep = runtime.sleep(duration);
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        if sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Runs `work` with `Runtime::spawn_blocking` and waits for its result, returning
+/// `None` if it panicked.
+pub(crate) async fn blocking<T: Send + 'static>(
+    runtime: &dyn Runtime,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    runtime.spawn_blocking(Box::new(move || {
+        let _ = sender.send(work());
+    }));
+    receiver.await.ok()
+}
+
+/// Gets the runtime selected by cargo features, preferring tokio when both are enabled.
+pub(crate) fn default_runtime() -> Arc<dyn Runtime> {
+    #[cfg(feature = "rt-tokio")]
+    return Arc::new(TokioRuntime);
+
+    #[cfg(all(feature = "rt-async-std", not(feature = "rt-tokio")))]
+    return Arc::new(AsyncStdRuntime);
+}
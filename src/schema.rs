@@ -0,0 +1,79 @@
+//! Metadata schema validation for IronNotify SDK.
+
+use crate::error::Error;
+use crate::types::NotificationPayload;
+use jsonschema::JSONSchema;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+/// Registry of JSON Schemas that notification metadata must satisfy, keyed by event type.
+#[derive(Default)]
+pub struct MetadataSchemas {
+    schemas: RwLock<HashMap<String, JSONSchema>>,
+}
+
+impl MetadataSchemas {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compiles and registers a schema for an event type, replacing any previous one.
+    pub fn register(&self, event_type: impl Into<String>, schema: &serde_json::Value) -> Result<(), Error> {
+        let compiled = JSONSchema::compile(schema).map_err(|e| Error::InvalidSchema(e.to_string()))?;
+        self.schemas.write().insert(event_type.into(), compiled);
+        Ok(())
+    }
+
+    /// Removes the schema registered for an event type.
+    pub fn unregister(&self, event_type: &str) -> bool {
+        self.schemas.write().remove(event_type).is_some()
+    }
+
+    /// Validates a payload's metadata against the schema for its event type.
+    ///
+    /// When `strict` is set, event types without a registered schema are rejected.
+    pub fn validate(&self, payload: &NotificationPayload, strict: bool) -> Result<(), Error> {
+        let schemas = self.schemas.read();
+
+        let schema = match schemas.get(&payload.event_type) {
+            Some(schema) => schema,
+            None if strict => {
+                return Err(Error::MetadataValidation {
+                    event_type: payload.event_type.clone(),
+                    violations: vec!["no metadata schema registered for event type".to_string()],
+                });
+            }
+            None => return Ok(()),
+        };
+
+        let instance = serde_json::Value::Object(
+            payload
+                .metadata
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        );
+
+        let result = schema.validate(&instance);
+        if let Err(errors) = result {
+            let violations = errors
+                .map(|e| {
+                    let path = e.instance_path.to_string();
+                    if path.is_empty() {
+                        e.to_string()
+                    } else {
+                        format!("{}: {}", path, e)
+                    }
+                })
+                .collect();
+            return Err(Error::MetadataValidation {
+                event_type: payload.event_type.clone(),
+                violations,
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Free-text notification search for IronNotify SDK.
+
+use crate::api_version::ApiVersion;
+use crate::error::Error;
+use crate::types::Notification;
+use serde_json::Value;
+
+/// A notification found by `search_notifications`.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// The matching notification.
+    pub notification: Notification,
+    /// Relevance of the match, higher being more relevant. Set by the server when it
+    /// ranks results; `None` for client-side matches.
+    pub score: Option<f64>,
+}
+
+impl SearchHit {
+    /// Creates a hit without a score.
+    pub(crate) fn unscored(notification: Notification) -> Self {
+        Self {
+            notification,
+            score: None,
+        }
+    }
+}
+
+/// Decodes a search response: a list of notifications, each with an optional `score`.
+pub(crate) fn decode_hits(api_version: &ApiVersion, value: Value) -> Result<Vec<SearchHit>, Error> {
+    let Value::Array(items) = value else {
+        return Err(Error::Codec("Search response is not a list".to_string()));
+    };
+
+    let mut scores = Vec::with_capacity(items.len());
+    let items: Vec<Value> = items
+        .into_iter()
+        .map(|mut item| {
+            let score = item
+                .as_object_mut()
+                .and_then(|item| item.remove("score"))
+                .and_then(|score| score.as_f64());
+            scores.push(score);
+            item
+        })
+        .collect();
+
+    let notifications = api_version.decode_notifications(Value::Array(items))?;
+    Ok(notifications
+        .into_iter()
+        .zip(scores)
+        .map(|(notification, score)| SearchHit {
+            notification,
+            score,
+        })
+        .collect())
+}
+
+/// Checks whether a notification's title, message, or metadata values contain the
+/// text, ignoring case.
+pub(crate) fn matches(notification: &Notification, text: &str) -> bool {
+    let text = text.to_lowercase();
+    let contains = |value: &str| value.to_lowercase().contains(&text);
+
+    contains(&notification.title)
+        || notification.message.as_deref().is_some_and(contains)
+        || notification
+            .metadata
+            .iter()
+            .flat_map(|metadata| metadata.values())
+            .any(|value| match value {
+                Value::String(value) => contains(value),
+                Value::Null => false,
+                other => contains(&other.to_string()),
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::notification_json;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn notification(message: Option<&str>, metadata: Value) -> Notification {
+        let mut value = notification_json("n1", Utc::now());
+        value["title"] = json!("Order shipped");
+        value["message"] = json!(message);
+        value["metadata"] = metadata;
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn hits_keep_the_score_of_each_item() {
+        let mut scored = notification_json("n1", Utc::now());
+        scored["score"] = json!(0.75);
+        let unscored = notification_json("n2", Utc::now());
+        let hits = decode_hits(&ApiVersion::V1, json!([scored, unscored])).unwrap();
+        let hits: Vec<(&str, Option<f64>)> = hits
+            .iter()
+            .map(|hit| (hit.notification.id.as_str(), hit.score))
+            .collect();
+        assert_eq!(hits, [("n1", Some(0.75)), ("n2", None)]);
+    }
+
+    #[test]
+    fn hits_are_decoded_in_the_api_version_shape() {
+        let mut item = notification_json("n1", Utc::now());
+        item["threadId"] = json!("orders");
+        item["score"] = json!(2);
+        let hits = decode_hits(&ApiVersion::V2, json!([item])).unwrap();
+        assert_eq!(hits[0].notification.group_key.as_deref(), Some("orders"));
+        assert_eq!(hits[0].score, Some(2.0));
+    }
+
+    #[test]
+    fn responses_that_are_not_lists_fail() {
+        let decoded = decode_hits(&ApiVersion::V1, json!({ "notifications": [] }));
+        assert!(matches!(decoded, Err(Error::Codec(_))));
+    }
+
+    #[test]
+    fn text_matches_the_title_message_and_metadata_ignoring_case() {
+        let found = notification(Some("Tracking: ZX-42"), json!({ "carrier": "DHL", "boxes": 3 }));
+        for text in ["SHIPPED", "zx-42", "dhl", "3"] {
+            assert!(matches(&found, text), "{}", text);
+        }
+        for text in ["carrier", "returned", "null"] {
+            assert!(!matches(&found, text), "{}", text);
+        }
+        assert!(!matches(&notification(None, json!({ "note": null })), "null"));
+    }
+}
@@ -0,0 +1,108 @@
+//! tower integration for IronNotify SDK.
+
+use crate::client::NotifyClient;
+use crate::delivery::DeliveryCallbacks;
+use crate::error::Error;
+use crate::types::{NotificationPayload, SendResult};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// A `tower::Service` that sends notification payloads.
+///
+/// Failed sends are returned as errors so that middleware such as retries and
+/// circuit breakers can observe them. Wrap the service in [`OfflineQueueLayer`]
+/// to fall back to the offline queue.
+#[derive(Clone)]
+pub struct NotifyService {
+    client: NotifyClient,
+}
+
+impl NotifyService {
+    /// Creates a new NotifyService backed by the given client.
+    pub fn new(client: NotifyClient) -> Self {
+        Self { client }
+    }
+}
+
+impl Service<NotificationPayload> for NotifyService {
+    type Response = SendResult;
+    type Error = Error;
+    type Future = BoxFuture<Result<SendResult, Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, payload: NotificationPayload) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move { client.try_send_payload(&payload).await })
+    }
+}
+
+/// Layer that adds offline queue fallback to a notification service.
+#[derive(Clone)]
+pub struct OfflineQueueLayer {
+    client: NotifyClient,
+}
+
+impl OfflineQueueLayer {
+    /// Creates a new OfflineQueueLayer that queues into the given client's offline queue.
+    pub fn new(client: NotifyClient) -> Self {
+        Self { client }
+    }
+}
+
+impl<S> Layer<S> for OfflineQueueLayer {
+    type Service = OfflineQueueService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OfflineQueueService {
+            inner,
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// Service that queues payloads offline when the inner service fails to send them.
+#[derive(Clone)]
+pub struct OfflineQueueService<S> {
+    inner: S,
+    client: NotifyClient,
+}
+
+impl<S> Service<NotificationPayload> for OfflineQueueService<S>
+where
+    S: Service<NotificationPayload, Response = SendResult, Error = Error>,
+    S::Future: Send + 'static,
+{
+    type Response = SendResult;
+    type Error = Error;
+    type Future = BoxFuture<Result<SendResult, Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, payload: NotificationPayload) -> Self::Future {
+        let client = self.client.clone();
+        let fallback = payload.clone();
+        let future = self.inner.call(payload);
+
+        Box::pin(async move {
+            match future.await {
+                Err(Error::Transport(error)) => {
+                    match client.enqueue(fallback, DeliveryCallbacks::default()).await {
+                        Ok(true) => Ok(SendResult::queued(error)),
+                        Ok(false) => Err(Error::Transport(error)),
+                        Err(e) => Err(e),
+                    }
+                }
+                other => other,
+            }
+        })
+    }
+}
@@ -0,0 +1,212 @@
+//! Inbox snapshots for IronNotify SDK.
+
+use crate::error::Error;
+use crate::redact::Redactor;
+use crate::types::Notification;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Format version written by this release.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A serializable copy of an inbox, for server-side rendering and handing an inbox
+/// to another process.
+///
+/// Readers ignore fields they do not know, so snapshots written by newer releases
+/// still load; check `version` to tell them apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSnapshot {
+    /// Format version, `SNAPSHOT_VERSION` when written by this release.
+    pub version: u32,
+    /// When the notifications were fetched.
+    pub taken_at: DateTime<Utc>,
+    /// Unread count reported by the server when the snapshot was taken.
+    pub unread_count: i32,
+    /// The notifications, newest first.
+    pub notifications: Vec<Notification>,
+}
+
+impl NotificationSnapshot {
+    /// Creates a snapshot of the given notifications, taken now.
+    ///
+    /// Metadata is kept as is; use `redacted` before sending the snapshot to browsers.
+    pub fn from_notifications(notifications: &[Notification], unread_count: i32) -> Self {
+        let mut snapshot = Self {
+            version: SNAPSHOT_VERSION,
+            taken_at: Utc::now(),
+            unread_count,
+            notifications: Vec::new(),
+        };
+        snapshot.merge_notifications(notifications);
+        snapshot
+    }
+
+    /// Redacts the metadata keys matching the redactor's patterns.
+    pub fn redacted(mut self, redactor: &Redactor) -> Self {
+        if redactor.is_empty() {
+            return self;
+        }
+        for notification in &mut self.notifications {
+            if let Some(ref metadata) = notification.metadata {
+                notification.metadata = Some(redactor.redact_metadata(metadata));
+            }
+        }
+        self
+    }
+
+    /// Serializes the snapshot to JSON.
+    ///
+    /// `<`, `>`, and `&` are escaped, so the output can be embedded in a `<script>` tag.
+    pub fn to_json(&self) -> Result<String, Error> {
+        let json = serde_json::to_string(self).map_err(|e| Error::Codec(e.to_string()))?;
+        Ok(json
+            .replace('<', "\\u003c")
+            .replace('>', "\\u003e")
+            .replace('&', "\\u0026"))
+    }
+
+    /// Parses a snapshot written by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    /// Merges a newer snapshot into this one.
+    ///
+    /// Notifications are combined as by `merge_notifications`, and the unread count and
+    /// `taken_at` come from whichever snapshot was taken later.
+    pub fn merge(&mut self, newer: NotificationSnapshot) {
+        if newer.taken_at >= self.taken_at {
+            self.taken_at = newer.taken_at;
+            self.unread_count = newer.unread_count;
+        }
+        self.merge_notifications(&newer.notifications);
+    }
+
+    /// Merges notifications into the snapshot, keeping one copy of each ID.
+    ///
+    /// Of two copies, the one with the later `created_at` is kept, or the merged one
+    /// when they are equal. The result is sorted newest first.
+    pub fn merge_notifications(&mut self, notifications: &[Notification]) {
+        let mut by_id: HashMap<String, Notification> = self
+            .notifications
+            .drain(..)
+            .map(|n| (n.id.clone(), n))
+            .collect();
+        for notification in notifications {
+            let older = by_id
+                .get(&notification.id)
+                .is_some_and(|existing| notification.created_at < existing.created_at);
+            if !older {
+                by_id.insert(notification.id.clone(), notification.clone());
+            }
+        }
+
+        self.notifications = by_id.into_values().collect();
+        self.notifications
+            .sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.id.cmp(&b.id)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::notification_json;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, minute, 0).unwrap()
+    }
+
+    fn notification(id: &str, minute: u32, title: &str) -> Notification {
+        let mut value = notification_json(id, at(minute));
+        value["title"] = json!(title);
+        value["metadata"] = json!({ "token": "secret", "order": "1234" });
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn ids(snapshot: &NotificationSnapshot) -> Vec<(&str, &str)> {
+        snapshot
+            .notifications
+            .iter()
+            .map(|n| (n.id.as_str(), n.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn json_can_be_embedded_in_a_script_tag_and_read_back() {
+        let title = "</script><script>alert(1) & more";
+        let snapshot = NotificationSnapshot::from_notifications(&[notification("n1", 0, title)], 1);
+        let json = snapshot.to_json().unwrap();
+        assert!(!json.contains('<') && !json.contains('>') && !json.contains('&'));
+
+        let read = NotificationSnapshot::from_json(&json).unwrap();
+        assert_eq!(read.version, SNAPSHOT_VERSION);
+        assert_eq!(read.unread_count, 1);
+        assert_eq!(read.notifications[0].title, title);
+    }
+
+    #[test]
+    fn snapshots_from_newer_releases_still_load() {
+        let value = json!({
+            "version": 2,
+            "takenAt": at(0),
+            "unreadCount": 0,
+            "notifications": [notification_json("n1", at(0))],
+            "cursor": "abc"
+        });
+        let read = NotificationSnapshot::from_json(&value.to_string()).unwrap();
+        assert_eq!(read.version, 2);
+        assert_eq!(read.notifications.len(), 1);
+    }
+
+    #[test]
+    fn merging_keeps_the_newest_copy_of_each_notification() {
+        let mut snapshot = NotificationSnapshot::from_notifications(
+            &[notification("a", 1, "old a"), notification("b", 3, "b")],
+            2,
+        );
+        snapshot.taken_at = at(10);
+        let mut newer = NotificationSnapshot::from_notifications(
+            &[notification("a", 2, "new a"), notification("b", 0, "stale b")],
+            5,
+        );
+        newer.taken_at = at(20);
+        snapshot.merge(newer);
+        assert_eq!(ids(&snapshot), [("b", "b"), ("a", "new a")]);
+        assert_eq!((snapshot.taken_at, snapshot.unread_count), (at(20), 5));
+
+        // An older snapshot adds notifications but keeps the count.
+        let mut older = NotificationSnapshot::from_notifications(&[notification("c", 4, "c")], 9);
+        older.taken_at = at(5);
+        snapshot.merge(older);
+        assert_eq!(ids(&snapshot), [("c", "c"), ("b", "b"), ("a", "new a")]);
+        assert_eq!((snapshot.taken_at, snapshot.unread_count), (at(20), 5));
+    }
+
+    #[test]
+    fn equal_copies_are_replaced_and_ties_sort_by_id() {
+        let mut snapshot = NotificationSnapshot::from_notifications(
+            &[notification("b", 1, "first"), notification("a", 1, "a")],
+            0,
+        );
+        snapshot.merge_notifications(&[notification("b", 1, "second")]);
+        assert_eq!(ids(&snapshot), [("a", "a"), ("b", "second")]);
+    }
+
+    #[test]
+    fn redaction_covers_every_notification() {
+        let snapshot = NotificationSnapshot::from_notifications(
+            &[notification("a", 1, "a"), notification("b", 2, "b")],
+            0,
+        );
+        let redacted = snapshot.redacted(&Redactor::new(vec!["token".to_string()]));
+        for notification in &redacted.notifications {
+            let metadata = notification.metadata.as_ref().unwrap();
+            assert_eq!(metadata["token"], crate::redact::REDACTED);
+            assert_eq!(metadata["order"], "1234");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Lock-free client state for IronNotify SDK.
+
+use crate::types::ConnectionState;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tokio::sync::watch;
+
+/// A state value that fits in a byte.
+pub(crate) trait AtomicState: Copy + PartialEq {
+    fn to_u8(self) -> u8;
+    fn from_u8(value: u8) -> Self;
+}
+
+impl AtomicState for bool {
+    fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    fn from_u8(value: u8) -> Self {
+        value != 0
+    }
+}
+
+impl AtomicState for ConnectionState {
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Disconnected => 0,
+            Self::Connecting => 1,
+            Self::Connected => 2,
+            Self::Reconnecting => 3,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Connecting,
+            2 => Self::Connected,
+            3 => Self::Reconnecting,
+            _ => Self::Disconnected,
+        }
+    }
+}
+
+/// A value read without locking, whose changes can be awaited.
+///
+/// Reads and writes go through an atomic; observers are notified of changes on a
+/// watch channel, after the new value is visible.
+pub(crate) struct WatchedState<T> {
+    value: AtomicU8,
+    changes: watch::Sender<T>,
+}
+
+impl<T: AtomicState> WatchedState<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: AtomicU8::new(value.to_u8()),
+            changes: watch::channel(value).0,
+        }
+    }
+
+    pub fn get(&self) -> T {
+        T::from_u8(self.value.load(Ordering::Acquire))
+    }
+
+    /// Sets the value, returning the previous one. Observers are only notified when
+    /// it changed.
+    pub fn set(&self, value: T) -> T {
+        let previous = T::from_u8(self.value.swap(value.to_u8(), Ordering::AcqRel));
+        if previous != value {
+            self.changes.send_if_modified(|current| {
+                let latest = T::from_u8(self.value.load(Ordering::Acquire));
+                let modified = *current != latest;
+                *current = latest;
+                modified
+            });
+        }
+        previous
+    }
+
+    /// Subscribes to changes of the value.
+    pub fn subscribe(&self) -> watch::Receiver<T> {
+        self.changes.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    const WRITERS: u8 = 4;
+
+    /// Runs `WRITERS` threads that set the state at the same time, returning the
+    /// `(previous, new)` pair of every set.
+    fn race(
+        state: &Arc<WatchedState<ConnectionState>>,
+        round: u8,
+    ) -> Vec<(ConnectionState, ConnectionState)> {
+        let barrier = Arc::new(Barrier::new(WRITERS as usize));
+        let writers: Vec<_> = (0..WRITERS)
+            .map(|writer| {
+                let state = Arc::clone(state);
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    (0..50u8)
+                        .map(|i| {
+                            let value = ConnectionState::from_u8(writer.wrapping_add(i) ^ round);
+                            (state.set(value), value)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        writers.into_iter().flat_map(|writer| writer.join().unwrap()).collect()
+    }
+
+    // `NotifyClient::connection_state` reads the atomic and `watch_connection_state`
+    // the channel, so the two must agree once the writers are done.
+    #[test]
+    fn racing_writers_leave_the_watch_channel_on_the_final_value() {
+        let state = Arc::new(WatchedState::new(ConnectionState::Disconnected));
+        let mut changes = state.subscribe();
+        for round in 0..200 {
+            let before = *changes.borrow_and_update();
+            race(&state, round);
+            let after = state.get();
+            assert_eq!(*changes.borrow(), after, "round {}", round);
+            if before != after {
+                assert!(changes.has_changed().unwrap(), "round {}", round);
+            }
+            changes.borrow_and_update();
+        }
+    }
+
+    #[test]
+    fn racing_writers_see_one_chain_of_transitions() {
+        let state = Arc::new(WatchedState::new(ConnectionState::Disconnected));
+        for round in 0..200 {
+            let before = state.get();
+            let sets = race(&state, round);
+            let after = state.get();
+
+            // Every swap hands its value to exactly one later swap, so apart from the
+            // first and last value, each state is left as often as it is entered.
+            for value in (0..4).map(ConnectionState::from_u8) {
+                let left = sets.iter().filter(|(previous, _)| *previous == value).count();
+                let entered = sets.iter().filter(|(_, new)| *new == value).count();
+                let expected = (before == value) as isize - (after == value) as isize;
+                assert_eq!(left as isize - entered as isize, expected, "round {}", round);
+            }
+        }
+    }
+}
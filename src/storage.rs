@@ -0,0 +1,321 @@
+//! Local file storage for IronNotify SDK.
+
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::ffi::OsString;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Age after which a lock file is assumed to be left behind by a crashed process.
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(10);
+
+/// Delay between attempts to take a lock held by another process.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+#[cfg(test)]
+thread_local! {
+    /// Directory tests on this thread use instead of the home directory.
+    pub(crate) static TEST_STORAGE_DIR: std::cell::RefCell<Option<PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Gets the directory holding the SDK's local state.
+pub(crate) fn storage_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_STORAGE_DIR.with(|dir| dir.borrow().clone()) {
+        return dir;
+    }
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ironnotify")
+}
+
+/// A JSON file in local storage.
+#[derive(Debug)]
+pub(crate) struct JsonFile {
+    path: PathBuf,
+}
+
+/// A lock was not free by the deadline.
+#[derive(Debug)]
+pub(crate) struct LockTimedOut;
+
+/// A held cross-process lock on a `JsonFile`, released when dropped.
+///
+/// The lock file holds a token naming its owner. A lock broken as stale may have been
+/// taken by someone else since, so it is only removed if it still holds this token.
+/// Holders that may keep the lock for a while call `touch` so it is not broken as stale.
+pub(crate) struct FileLock {
+    path: PathBuf,
+    token: String,
+}
+
+impl FileLock {
+    /// Gets a token unique to this lock: the process ID and a random nonce.
+    fn new_token() -> String {
+        format!(
+            "{}:{:016x}",
+            std::process::id(),
+            RandomState::new().build_hasher().finish()
+        )
+    }
+
+    /// Checks whether the lock file still holds this lock's token.
+    fn is_held(&self) -> bool {
+        fs::read_to_string(&self.path).is_ok_and(|owner| owner == self.token)
+    }
+
+    /// Resets the lock's age, so other processes do not break it as stale.
+    pub fn touch(&self) {
+        if !self.is_held() {
+            return;
+        }
+        if let Ok(file) = fs::OpenOptions::new().write(true).open(&self.path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if self.is_held() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl JsonFile {
+    /// Creates a handle for the named file in the storage directory.
+    pub fn new(name: &str) -> Self {
+        Self {
+            path: storage_dir().join(name),
+        }
+    }
+
+    /// Creates a handle for a file at any path.
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Gets the file's path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the file contents, if it exists.
+    pub fn read(&self) -> Option<String> {
+        fs::read_to_string(&self.path).ok()
+    }
+
+    /// Gets the file's size in bytes, if it exists.
+    pub fn size(&self) -> Option<u64> {
+        fs::metadata(&self.path).ok().map(|metadata| metadata.len())
+    }
+
+    /// Deletes the file, if it exists.
+    pub fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+
+    /// Serializes a value and writes it to the file, creating the directory if needed.
+    ///
+    /// The contents are written to a temporary file and moved into place, so readers
+    /// in other processes never see a partial write. Returns whether the write succeeded.
+    pub fn write<T: Serialize + ?Sized>(&self, value: &T) -> bool {
+        self.write_changed(value, None)
+    }
+
+    /// Writes a value like `write`, skipping the write if it serializes to `current`,
+    /// the contents last read from the file.
+    pub fn write_changed<T: Serialize + ?Sized>(&self, value: &T, current: Option<&str>) -> bool {
+        let Ok(json) = serde_json::to_string(value) else {
+            return false;
+        };
+        if current == Some(json.as_str()) {
+            return true;
+        }
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        let temp = self.sibling(&format!(".{}.tmp", std::process::id()));
+        if fs::write(&temp, json).is_err() {
+            return false;
+        }
+        if fs::rename(&temp, &self.path).is_err() {
+            let _ = fs::remove_file(&temp);
+            return false;
+        }
+        true
+    }
+
+    /// Takes the file's cross-process lock, waiting while another process holds it.
+    ///
+    /// The lock is a `.lock` file next to this one, created exclusively, so it needs no
+    /// OS locking APIs. Locks older than 10 seconds are broken. Returns `None` if the
+    /// lock file cannot be created at all, e.g. because the directory is read-only.
+    ///
+    /// Waits on the calling thread, so async code takes it on a blocking thread.
+    pub fn lock(&self) -> Option<FileLock> {
+        self.lock_until(None).unwrap_or(None)
+    }
+
+    /// Takes the lock like `lock`, giving up once `deadline` passes.
+    pub fn lock_until(&self, deadline: Option<Instant>) -> Result<Option<FileLock>, LockTimedOut> {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        let path = self.sibling(".lock");
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let token = FileLock::new_token();
+                    let _ = file.write_all(token.as_bytes());
+                    return Ok(Some(FileLock { path, token }));
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if let Some(owner) = stale_owner(&path) {
+                        break_stale_lock(&path, &owner);
+                    } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(LockTimedOut);
+                    } else {
+                        thread::sleep(LOCK_RETRY_INTERVAL);
+                    }
+                }
+                Err(_) => return Ok(None),
+            }
+        }
+    }
+
+    /// Gets the path of a file next to this one, named with the given suffix.
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = OsString::from(self.path.as_os_str());
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+}
+
+/// Gets the token in a lock file older than `LOCK_STALE_AFTER`.
+fn stale_owner(path: &Path) -> Option<String> {
+    let age = fs::metadata(path).and_then(|metadata| metadata.modified()).ok()?.elapsed().ok()?;
+    if age <= LOCK_STALE_AFTER {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+/// Breaks a stale lock file last seen holding the token `owner`.
+///
+/// Several processes may find the same lock stale at once, and the first to break it
+/// may take the lock again before the others act. So breaking takes a second lock, and
+/// the lock file is only removed if it is still stale and still holds `owner`.
+fn break_stale_lock(path: &Path, owner: &str) {
+    let mut breaker = OsString::from(path.as_os_str());
+    breaker.push(".break");
+    let breaker = PathBuf::from(breaker);
+    match fs::OpenOptions::new().write(true).create_new(true).open(&breaker) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            // Left behind by a process that crashed while breaking the lock.
+            if stale_owner(&breaker).is_some() {
+                let _ = fs::remove_file(&breaker);
+            }
+            return;
+        }
+        Err(_) => return,
+    }
+    if stale_owner(path).as_deref() == Some(owner) {
+        let _ = fs::remove_file(path);
+    }
+    let _ = fs::remove_file(&breaker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+
+    fn make_stale(path: &Path) {
+        let file = fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - LOCK_STALE_AFTER * 2).unwrap();
+    }
+
+    #[test]
+    fn a_broken_stale_lock_does_not_release_its_successor() {
+        let storage = TempStorage::new();
+        let file = JsonFile::at(storage.path().join("state.json"));
+        let lock_path = file.sibling(".lock");
+
+        let stale = file.lock().unwrap();
+        make_stale(&lock_path);
+        let current = file.lock().unwrap();
+
+        drop(stale);
+        assert!(lock_path.exists());
+        drop(current);
+        assert!(!lock_path.exists());
+    }
+
+    #[test]
+    fn racing_stale_lock_breakers_take_the_lock_one_at_a_time() {
+        let storage = TempStorage::new();
+        let path = storage.path().join("state.json");
+        let lock_path = JsonFile::at(path.clone()).sibling(".lock");
+        fs::write(&lock_path, "crashed").unwrap();
+        make_stale(&lock_path);
+
+        let holders = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(8));
+        let breakers: Vec<_> = (0..8)
+            .map(|_| {
+                let (path, holders, barrier) =
+                    (path.clone(), Arc::clone(&holders), Arc::clone(&barrier));
+                thread::spawn(move || {
+                    barrier.wait();
+                    let lock = JsonFile::at(path).lock().unwrap();
+                    let holding = holders.fetch_add(1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(2));
+                    holders.fetch_sub(1, Ordering::SeqCst);
+                    drop(lock);
+                    holding
+                })
+            })
+            .collect();
+
+        for breaker in breakers {
+            assert_eq!(breaker.join().unwrap(), 0);
+        }
+        assert!(!lock_path.exists());
+        let leftovers = fs::read_dir(storage.path()).unwrap().count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn touched_locks_are_not_broken() {
+        let storage = TempStorage::new();
+        let file = JsonFile::at(storage.path().join("state.json"));
+        let lock_path = file.sibling(".lock");
+
+        let held = file.lock().unwrap();
+        make_stale(&lock_path);
+        held.touch();
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert!(file.lock_until(Some(deadline)).is_err());
+    }
+
+    #[test]
+    fn locks_time_out_while_held() {
+        let storage = TempStorage::new();
+        let file = JsonFile::at(storage.path().join("state.json"));
+        let _held = file.lock().unwrap();
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert!(file.lock_until(Some(deadline)).is_err());
+    }
+}
@@ -0,0 +1,579 @@
+//! Local notification store for IronNotify SDK.
+
+use crate::realtime::RealtimeMessage;
+use crate::types::Notification;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tokio::sync::broadcast;
+
+/// Number of deleted notification IDs a store remembers by default.
+pub const DEFAULT_TOMBSTONE_CAPACITY: usize = 1_000;
+
+/// Number of store changes buffered for each lagging receiver.
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// What happened to a stored notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoreChangeKind {
+    /// The notification was added or replaced.
+    Upserted,
+    /// The notification was read, unread, snoozed, or unsnoozed.
+    Updated,
+    /// The notification was removed or deleted.
+    Removed,
+}
+
+/// A change to a `NotificationStore`, from `NotificationStore::changes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreChange {
+    pub kind: StoreChangeKind,
+    /// ID of the notification.
+    pub id: String,
+}
+
+/// Sender of a store's changes. A cloned store gets a channel of its own.
+#[derive(Debug)]
+struct ChangeSender(broadcast::Sender<StoreChange>);
+
+impl Default for ChangeSender {
+    fn default() -> Self {
+        Self(broadcast::channel(CHANGE_CHANNEL_CAPACITY).0)
+    }
+}
+
+impl Clone for ChangeSender {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// IDs of deleted notifications, so stale copies are not stored again. The oldest
+/// deletions are forgotten first once over capacity.
+#[derive(Debug, Clone)]
+struct Tombstones {
+    capacity: usize,
+    deleted_at: HashMap<String, DateTime<Utc>>,
+    /// The same IDs, oldest deletion first.
+    by_time: BTreeSet<(DateTime<Utc>, String)>,
+}
+
+impl Tombstones {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            deleted_at: HashMap::new(),
+            by_time: BTreeSet::new(),
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.deleted_at.contains_key(id)
+    }
+
+    /// Remembers a deletion, or moves a known one to `at`.
+    fn insert(&mut self, id: &str, at: DateTime<Utc>) {
+        if let Some(previous) = self.deleted_at.insert(id.to_string(), at) {
+            self.by_time.remove(&(previous, id.to_string()));
+        }
+        self.by_time.insert((at, id.to_string()));
+        while self.by_time.len() > self.capacity {
+            let Some((_, oldest)) = self.by_time.pop_first() else {
+                break;
+            };
+            self.deleted_at.remove(&oldest);
+        }
+    }
+}
+
+/// Read state of the notifications sharing a group key, from
+/// `NotificationStore::group_states`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupReadState {
+    /// Number of notifications in the group.
+    pub total: usize,
+    /// Number of them that are effectively unread; see
+    /// `Notification::is_effectively_unread`.
+    pub unread: usize,
+    /// When the newest notification in the group was created.
+    pub latest_at: DateTime<Utc>,
+}
+
+/// Notifications kept by the application, with per-group read state that is updated
+/// as notifications are added, read, snoozed, or removed.
+///
+/// Notifications without a group key are stored but not counted in any group.
+/// Deleted notifications are remembered, up to `DEFAULT_TOMBSTONE_CAPACITY` of them,
+/// so a list fetched before the deletion cannot store them again.
+/// A snooze ending or a notification expiring changes the counts without any call to
+/// the store; those changes are applied when the counts are next read.
+///
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use ironnotify::{Notification, NotificationStore};
+///
+/// let now = Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap();
+/// let notification = |id: &str| -> Notification {
+///     serde_json::from_value(serde_json::json!({
+///         "id": id,
+///         "eventType": "comment.added",
+///         "title": "New comment",
+///         "severity": "info",
+///         "groupKey": "thread-1",
+///         "read": false,
+///         "createdAt": now - Duration::minutes(5),
+///     }))
+///     .unwrap()
+/// };
+///
+/// let mut store = NotificationStore::new();
+/// store.upsert(notification("a"));
+/// store.upsert(notification("b"));
+/// let mut expiring = notification("c");
+/// expiring.expires_at = Some(now - Duration::minutes(1));
+/// store.upsert(expiring);
+///
+/// // Expired unread notifications do not count.
+/// let group = store.group_states_at(now)["thread-1"];
+/// assert_eq!((group.total, group.unread), (3, 2));
+///
+/// // Snoozed unread notifications do not count until the snooze ends.
+/// store.snooze("a", now + Duration::hours(1));
+/// assert_eq!(store.group_states_at(now)["thread-1"].unread, 1);
+/// assert_eq!(store.group_states_at(now + Duration::hours(2))["thread-1"].unread, 2);
+///
+/// // Unsnoozing restores the count, and reading lowers it.
+/// store.snooze("b", now + Duration::days(1));
+/// store.unsnooze("b");
+/// store.mark_as_read("a");
+/// assert_eq!(store.group_states_at(now + Duration::hours(2))["thread-1"].unread, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NotificationStore {
+    notifications: HashMap<String, Notification>,
+    /// IDs of the notifications in each group.
+    members: HashMap<String, HashSet<String>>,
+    groups: HashMap<String, GroupReadState>,
+    /// IDs of the notifications counted as unread in `groups`.
+    counted_unread: HashSet<String>,
+    /// Snooze ends and expirations after `evaluated_at`, when a notification's
+    /// unread state may change.
+    transitions: BTreeSet<(DateTime<Utc>, String)>,
+    /// The time the counts in `groups` are correct for.
+    evaluated_at: DateTime<Utc>,
+    tombstones: Tombstones,
+    changes: ChangeSender,
+}
+
+impl Default for NotificationStore {
+    fn default() -> Self {
+        Self {
+            notifications: HashMap::new(),
+            members: HashMap::new(),
+            groups: HashMap::new(),
+            counted_unread: HashSet::new(),
+            transitions: BTreeSet::new(),
+            evaluated_at: DateTime::<Utc>::MIN_UTC,
+            tombstones: Tombstones::new(DEFAULT_TOMBSTONE_CAPACITY),
+            changes: ChangeSender::default(),
+        }
+    }
+}
+
+impl NotificationStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty store remembering up to `capacity` deleted notifications.
+    pub fn with_tombstone_capacity(capacity: usize) -> Self {
+        Self {
+            tombstones: Tombstones::new(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a store holding the given notifications.
+    pub fn from_notifications(notifications: impl IntoIterator<Item = Notification>) -> Self {
+        let mut store = Self::new();
+        for notification in notifications {
+            store.upsert(notification);
+        }
+        store
+    }
+
+    /// Subscribes to the store's changes.
+    pub fn changes(&self) -> broadcast::Receiver<StoreChange> {
+        self.changes.0.subscribe()
+    }
+
+    /// Adds a notification, or replaces the one with the same ID, returning the
+    /// replaced one.
+    ///
+    /// Notifications that were deleted are not stored again.
+    pub fn upsert(&mut self, notification: Notification) -> Option<Notification> {
+        if self.tombstones.contains(&notification.id) {
+            return None;
+        }
+        let id = notification.id.clone();
+        let previous = self.take(&id);
+        self.insert(notification);
+        self.notify(StoreChangeKind::Upserted, id);
+        previous
+    }
+
+    /// Removes a notification, returning it. Unlike `delete`, it can be stored again.
+    pub fn remove(&mut self, id: &str) -> Option<Notification> {
+        let notification = self.take(id)?;
+        self.notify(StoreChangeKind::Removed, id.to_string());
+        Some(notification)
+    }
+
+    /// Removes a notification deleted on the server, returning it, and keeps it from
+    /// being stored again. It is remembered even if it is not stored.
+    pub fn delete(&mut self, id: &str) -> Option<Notification> {
+        self.tombstones.insert(id, Utc::now());
+        self.remove(id)
+    }
+
+    /// Checks whether a notification was deleted and is still remembered.
+    pub fn is_deleted(&self, id: &str) -> bool {
+        self.tombstones.contains(id)
+    }
+
+    /// Applies a real-time message: new notifications are upserted, read ones marked
+    /// as read, and deleted ones deleted. Returns whether the store changed.
+    pub fn apply(&mut self, message: &RealtimeMessage) -> bool {
+        match message {
+            RealtimeMessage::NotificationNew(notification) => {
+                let stored = !self.is_deleted(&notification.id);
+                self.upsert((**notification).clone());
+                stored
+            }
+            RealtimeMessage::NotificationRead { id } => self.mark_as_read(id),
+            RealtimeMessage::NotificationDeleted { id } => self.delete(id).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Takes a notification out of the store and its group's counts.
+    fn take(&mut self, id: &str) -> Option<Notification> {
+        let notification = self.notifications.remove(id)?;
+        self.forget_transitions(&notification);
+        let counted = self.counted_unread.remove(id);
+
+        if let Some(ref key) = notification.group_key {
+            let emptied = self.members.get_mut(key).is_some_and(|members| {
+                members.remove(id);
+                members.is_empty()
+            });
+            if emptied {
+                self.members.remove(key);
+                self.groups.remove(key);
+            } else if let Some(group) = self.groups.get_mut(key) {
+                group.total -= 1;
+                if counted {
+                    group.unread -= 1;
+                }
+                if group.latest_at == notification.created_at {
+                    group.latest_at = self.members[key]
+                        .iter()
+                        .map(|id| self.notifications[id].created_at)
+                        .max()
+                        .unwrap_or(group.latest_at);
+                }
+            }
+        }
+        Some(notification)
+    }
+
+    /// Marks a notification as read. Returns false if it is not stored.
+    pub fn mark_as_read(&mut self, id: &str) -> bool {
+        self.update(id, |notification| notification.read = true)
+    }
+
+    /// Marks a notification as unread. Returns false if it is not stored.
+    pub fn mark_as_unread(&mut self, id: &str) -> bool {
+        self.update(id, |notification| notification.read = false)
+    }
+
+    /// Snoozes a notification until the given time. Returns false if it is not stored.
+    pub fn snooze(&mut self, id: &str, until: DateTime<Utc>) -> bool {
+        self.update(id, |notification| notification.snoozed_until = Some(until))
+    }
+
+    /// Ends a notification's snooze. Returns false if it is not stored.
+    pub fn unsnooze(&mut self, id: &str) -> bool {
+        self.update(id, |notification| notification.snoozed_until = None)
+    }
+
+    /// Gets a stored notification.
+    pub fn get(&self, id: &str) -> Option<&Notification> {
+        self.notifications.get(id)
+    }
+
+    /// Gets every stored notification, in no particular order.
+    pub fn notifications(&self) -> impl Iterator<Item = &Notification> {
+        self.notifications.values()
+    }
+
+    /// Gets the number of stored notifications.
+    pub fn len(&self) -> usize {
+        self.notifications.len()
+    }
+
+    /// Checks whether the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    /// Gets the read state of each group, by group key, as of now.
+    pub fn group_states(&mut self) -> &HashMap<String, GroupReadState> {
+        self.group_states_at(Utc::now())
+    }
+
+    /// Gets the read state of each group, by group key, as of `now`.
+    ///
+    /// Only the notifications whose snooze ended or that expired since the last call
+    /// are looked at again. Going back in time recounts every notification.
+    pub fn group_states_at(&mut self, now: DateTime<Utc>) -> &HashMap<String, GroupReadState> {
+        if now < self.evaluated_at {
+            let notifications: Vec<Notification> =
+                self.notifications.drain().map(|(_, notification)| notification).collect();
+            *self = Self {
+                evaluated_at: now,
+                tombstones: std::mem::replace(&mut self.tombstones, Tombstones::new(0)),
+                changes: std::mem::take(&mut self.changes),
+                ..Self::default()
+            };
+            for notification in notifications {
+                self.insert(notification);
+            }
+            return &self.groups;
+        }
+
+        self.evaluated_at = now;
+        while let Some((at, id)) = self.transitions.first().cloned() {
+            if at > now {
+                break;
+            }
+            self.transitions.remove(&(at, id.clone()));
+            if let Some(notification) = self.notifications.remove(&id) {
+                self.uncount(&notification);
+                self.count(&notification);
+                self.notifications.insert(id, notification);
+            }
+        }
+        &self.groups
+    }
+
+    /// Changes a stored notification and updates its group's counts.
+    fn update(&mut self, id: &str, change: impl FnOnce(&mut Notification)) -> bool {
+        let Some(mut notification) = self.notifications.remove(id) else {
+            return false;
+        };
+        self.forget_transitions(&notification);
+        self.uncount(&notification);
+        change(&mut notification);
+        self.count(&notification);
+        self.remember_transitions(&notification);
+        self.notifications.insert(notification.id.clone(), notification);
+        self.notify(StoreChangeKind::Updated, id.to_string());
+        true
+    }
+
+    fn notify(&self, kind: StoreChangeKind, id: String) {
+        let _ = self.changes.0.send(StoreChange { kind, id });
+    }
+
+    /// Adds a notification that is not stored yet.
+    fn insert(&mut self, notification: Notification) {
+        if let Some(ref key) = notification.group_key {
+            self.members
+                .entry(key.clone())
+                .or_default()
+                .insert(notification.id.clone());
+            let group = self.groups.entry(key.clone()).or_insert(GroupReadState {
+                total: 0,
+                unread: 0,
+                latest_at: notification.created_at,
+            });
+            group.total += 1;
+            group.latest_at = group.latest_at.max(notification.created_at);
+        }
+        self.count(&notification);
+        self.remember_transitions(&notification);
+        self.notifications.insert(notification.id.clone(), notification);
+    }
+
+    /// Adds a notification to its group's unread count if it is unread at `evaluated_at`.
+    fn count(&mut self, notification: &Notification) {
+        let Some(ref key) = notification.group_key else {
+            return;
+        };
+        if !notification.is_effectively_unread(self.evaluated_at) {
+            return;
+        }
+        if self.counted_unread.insert(notification.id.clone()) {
+            if let Some(group) = self.groups.get_mut(key) {
+                group.unread += 1;
+            }
+        }
+    }
+
+    /// Takes a notification out of its group's unread count.
+    fn uncount(&mut self, notification: &Notification) {
+        let Some(ref key) = notification.group_key else {
+            return;
+        };
+        if self.counted_unread.remove(&notification.id) {
+            if let Some(group) = self.groups.get_mut(key) {
+                group.unread -= 1;
+            }
+        }
+    }
+
+    /// Records when a notification's snooze ends or it expires, if after `evaluated_at`.
+    fn remember_transitions(&mut self, notification: &Notification) {
+        for at in [notification.snoozed_until, notification.expires_at]
+            .into_iter()
+            .flatten()
+            .filter(|at| *at > self.evaluated_at)
+        {
+            self.transitions.insert((at, notification.id.clone()));
+        }
+    }
+
+    fn forget_transitions(&mut self, notification: &Notification) {
+        for at in [notification.snoozed_until, notification.expires_at]
+            .into_iter()
+            .flatten()
+        {
+            self.transitions.remove(&(at, notification.id.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::NotifyClient;
+    use crate::test_support::{notification_json, options, runtime, MockResponse, MockServer};
+    use crate::websocket::Opcode;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn notification(id: &str) -> Notification {
+        serde_json::from_value(notification_json(id, Utc::now())).unwrap()
+    }
+
+    #[test]
+    fn deleted_notifications_are_not_stored_again() {
+        let mut store = NotificationStore::from_notifications([notification("a")]);
+        let mut changes = store.changes();
+
+        assert!(store.delete("a").is_some());
+        assert!(store.delete("b").is_none());
+        assert!(store.upsert(notification("a")).is_none());
+        assert!(store.upsert(notification("b")).is_none());
+        assert!(store.is_empty());
+
+        // Removing is local; the notification can come back.
+        store.upsert(notification("c"));
+        store.remove("c");
+        store.upsert(notification("c"));
+
+        let changes: Vec<(StoreChangeKind, String)> =
+            std::iter::from_fn(|| changes.try_recv().ok())
+                .map(|change| (change.kind, change.id))
+                .collect();
+        assert_eq!(
+            changes,
+            [
+                (StoreChangeKind::Removed, "a".to_string()),
+                (StoreChangeKind::Upserted, "c".to_string()),
+                (StoreChangeKind::Removed, "c".to_string()),
+                (StoreChangeKind::Upserted, "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_oldest_tombstones_are_forgotten_first() {
+        let mut store = NotificationStore::with_tombstone_capacity(2);
+        store.delete("a");
+        store.delete("b");
+        // Deleting again makes it the newest.
+        store.delete("a");
+        store.delete("c");
+
+        assert!(!store.is_deleted("b"));
+        assert!(store.is_deleted("a") && store.is_deleted("c"));
+        store.upsert(notification("b"));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn tombstones_survive_recounting() {
+        let mut store = NotificationStore::new();
+        store.delete("a");
+        store.group_states_at(Utc::now());
+        store.group_states_at(Utc::now() - chrono::Duration::days(1));
+        assert!(store.is_deleted("a"));
+    }
+
+    #[test]
+    fn a_delete_pushed_during_a_list_fetch_is_not_undone() {
+        let server = MockServer::start(|request| {
+            if request.path.starts_with("/api/v1/notifications") {
+                // Listed before the deletion, and answered after it.
+                let listed = serde_json::json!([
+                    notification_json("a", Utc::now()),
+                    notification_json("b", Utc::now()),
+                ]);
+                return MockResponse::json(200, listed)
+                    .delayed(Duration::from_millis(300));
+            }
+            MockResponse::websocket(|socket| {
+                std::thread::sleep(Duration::from_millis(50));
+                let deleted = r#"{"type":"notification_deleted","data":{"id":"a"}}"#;
+                socket.send_text(deleted).unwrap();
+                while let Ok(frame) = socket.read(Duration::from_secs(10)) {
+                    if matches!(frame, Some(ref frame) if frame.opcode == Opcode::Close) {
+                        return;
+                    }
+                }
+            })
+        });
+
+        let client = NotifyClient::new(options(&server)).unwrap();
+        let store = Arc::new(Mutex::new(NotificationStore::from_notifications([
+            notification("a"),
+        ])));
+        let mut changes = store.lock().changes();
+        let pushed = Arc::clone(&store);
+        client.on_realtime_message(move |message| {
+            pushed.lock().apply(message);
+        });
+
+        runtime().block_on(async {
+            client.connect();
+            let listed = client.get_notifications(None, None, false).await.unwrap();
+            assert!(store.lock().is_deleted("a"), "the delete did not arrive during the fetch");
+            for notification in listed {
+                store.lock().upsert(notification);
+            }
+        });
+        client.disconnect();
+
+        let store = store.lock();
+        assert!(store.get("a").is_none());
+        assert!(store.get("b").is_some());
+        let removed = changes.try_recv().unwrap();
+        assert_eq!((removed.kind, removed.id.as_str()), (StoreChangeKind::Removed, "a"));
+        let upserted = changes.try_recv().unwrap();
+        assert_eq!((upserted.kind, upserted.id.as_str()), (StoreChangeKind::Upserted, "b"));
+        assert!(changes.try_recv().is_err());
+    }
+}
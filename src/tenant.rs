@@ -0,0 +1,405 @@
+//! Per-tenant clients for IronNotify SDK.
+
+use crate::builder::EventBuilder;
+use crate::client::NotifyClient;
+use crate::error::Error;
+use crate::metrics::TenantMetrics;
+use crate::query::NotificationQuery;
+use crate::search::SearchHit;
+use crate::types::{Notification, NotificationPayload, SendResult};
+use parking_lot::RwLock;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A tenant requests are made for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tenant {
+    /// The tenant's API key, or its ID when `registered` or sent in `tenant_header`.
+    pub name: Arc<str>,
+    /// Whether `name` is an ID whose API key was registered with
+    /// `NotifyClient::register_tenant_key`, so the key is looked up when sending.
+    pub registered: bool,
+}
+
+impl Tenant {
+    /// Gets the tenant of a persisted entry from its `tenant` and `tenant_id` fields.
+    pub fn restore(tenant: Option<&str>, tenant_id: Option<&str>) -> Option<Self> {
+        match (tenant_id, tenant) {
+            (Some(id), _) => Some(Self {
+                name: Arc::from(id),
+                registered: true,
+            }),
+            (None, Some(tenant)) => Some(Self {
+                name: Arc::from(tenant),
+                registered: false,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Gets the `tenant` and `tenant_id` fields to persist for the current tenant. The
+/// key of a registered tenant is never persisted, only its ID.
+pub(crate) fn persisted() -> (Option<String>, Option<String>) {
+    match current() {
+        Some(tenant) if tenant.registered => (None, Some(tenant.name.to_string())),
+        Some(tenant) => (Some(tenant.name.to_string()), None),
+        None => (None, None),
+    }
+}
+
+/// API keys registered with `NotifyClient::register_tenant_key`, by tenant ID.
+///
+/// Kept in memory only, so the keys never reach the offline queue.
+#[derive(Debug, Default)]
+pub(crate) struct TenantKeys {
+    keys: RwLock<HashMap<Arc<str>, Arc<str>>>,
+}
+
+impl TenantKeys {
+    /// Registers or replaces a tenant's key.
+    pub fn register(&self, tenant_id: &str, api_key: &str) {
+        self.keys.write().insert(Arc::from(tenant_id), Arc::from(api_key));
+    }
+
+    /// Gets a tenant's registered key.
+    pub fn get(&self, tenant_id: &str) -> Option<Arc<str>> {
+        self.keys.read().get(tenant_id).cloned()
+    }
+
+    /// Checks whether a tenant's key is registered.
+    pub fn contains(&self, tenant_id: &str) -> bool {
+        self.keys.read().contains_key(tenant_id)
+    }
+}
+
+thread_local! {
+    /// Tenant of the request being made on this thread, if any.
+    static CURRENT_TENANT: RefCell<Option<Tenant>> = const { RefCell::new(None) };
+}
+
+/// Gets the tenant of the request being made on this thread.
+pub(crate) fn current() -> Option<Tenant> {
+    CURRENT_TENANT.with(|tenant| tenant.borrow().clone())
+}
+
+/// Restores the previous tenant when dropped.
+struct TenantGuard {
+    previous: Option<Tenant>,
+}
+
+impl TenantGuard {
+    fn enter(tenant: Option<Tenant>) -> Self {
+        Self {
+            previous: CURRENT_TENANT.with(|current| current.replace(tenant)),
+        }
+    }
+}
+
+impl Drop for TenantGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_TENANT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` as the tenant, for work done outside the request's future.
+pub(crate) fn with<R>(tenant: Option<Tenant>, f: impl FnOnce() -> R) -> R {
+    let _guard = TenantGuard::enter(tenant);
+    f()
+}
+
+/// Runs a future with a tenant set during every poll, so the requests it makes use
+/// the tenant's credentials.
+pub(crate) struct Scoped<F> {
+    tenant: Option<Tenant>,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _guard = TenantGuard::enter(self.tenant.clone());
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Runs a future as the tenant. `None` runs it with the client's own credentials.
+pub(crate) fn scoped<F: Future>(tenant: Option<Tenant>, future: F) -> Scoped<F> {
+    Scoped {
+        tenant,
+        future: Box::pin(future),
+    }
+}
+
+/// A client that makes every request on behalf of one tenant.
+///
+/// Created with `NotifyClient::with_tenant`. It shares the client's connection pool,
+/// offline queue, and options. Without `tenant_header`, the tenant is an API key sent
+/// instead of the client's own; with it, the tenant is an ID sent in that header
+/// alongside the client's key. Notifications queued while offline remember their
+/// tenant and are flushed with its credentials.
+///
+/// Remembering a tenant that is an API key means writing the key, in plaintext, to
+/// the offline queue and mutation files or the Postgres queue. To keep keys out of
+/// them, register each key under a stable ID with `NotifyClient::register_tenant_key`
+/// and pass the ID to `with_tenant`: only the ID is stored, and the key is looked up
+/// again when the notification is flushed.
+#[derive(Clone)]
+pub struct TenantScopedClient {
+    client: NotifyClient,
+    tenant: Tenant,
+}
+
+impl TenantScopedClient {
+    pub(crate) fn new(client: NotifyClient, tenant: Tenant) -> Self {
+        Self { client, tenant }
+    }
+
+    /// Gets the tenant's API key or ID.
+    pub fn tenant(&self) -> &str {
+        &self.tenant.name
+    }
+
+    /// Gets the shared client.
+    pub fn client(&self) -> &NotifyClient {
+        &self.client
+    }
+
+    fn scope<F: Future>(&self, future: F) -> Scoped<F> {
+        scoped(Some(self.tenant.clone()), future)
+    }
+
+    /// Sends a simple notification as the tenant.
+    pub async fn notify(&self, event_type: &str, title: &str) -> SendResult {
+        self.scope(self.client.notify(event_type, title)).await
+    }
+
+    /// Creates an event builder that sends as the tenant.
+    pub fn event(&self, event_type: impl Into<String>) -> EventBuilder {
+        self.client
+            .event(event_type)
+            .tenant(self.tenant.clone())
+    }
+
+    /// Sends a notification payload as the tenant.
+    pub async fn send_payload(&self, payload: &NotificationPayload) -> SendResult {
+        self.scope(self.client.send_payload(payload)).await
+    }
+
+    /// Sends a notification payload as the tenant, failing with a typed error.
+    pub async fn try_send_payload(&self, payload: &NotificationPayload) -> Result<SendResult, Error> {
+        self.scope(self.client.try_send_payload(payload)).await
+    }
+
+    /// Gets the tenant's notifications.
+    pub async fn get_notifications(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        unread_only: bool,
+    ) -> Result<Vec<Notification>, String> {
+        self.scope(self.client.get_notifications(limit, offset, unread_only))
+            .await
+    }
+
+    /// Gets the tenant's notifications matching a query.
+    pub async fn query_notifications(
+        &self,
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, String> {
+        self.scope(self.client.query_notifications(query)).await
+    }
+
+    /// Searches the tenant's notifications for text.
+    pub async fn search_notifications(
+        &self,
+        text: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<SearchHit>, Error> {
+        self.scope(self.client.search_notifications(text, query))
+            .await
+    }
+
+    /// Gets the tenant's unread notification count.
+    pub async fn get_unread_count(&self) -> Result<i32, String> {
+        self.scope(self.client.get_unread_count()).await
+    }
+
+    /// Marks one of the tenant's notifications as read.
+    pub async fn mark_as_read(&self, notification_id: &str) -> Result<bool, String> {
+        self.scope(self.client.mark_as_read(notification_id)).await
+    }
+
+    /// Marks all of the tenant's notifications as read.
+    pub async fn mark_all_as_read(&self) -> Result<bool, String> {
+        self.scope(self.client.mark_all_as_read()).await
+    }
+
+    /// Gets the request metrics of the tenant.
+    pub fn metrics(&self) -> TenantMetrics {
+        self.client.tenant_metrics(&self.tenant.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, MockResponse, MockServer, TempStorage};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn tenant(name: &str) -> Option<Tenant> {
+        Tenant::restore(Some(name), None)
+    }
+
+    #[test]
+    fn nested_tenants_are_restored_in_turn() {
+        assert_eq!(current(), None);
+        with(tenant("a"), || {
+            assert_eq!(current(), tenant("a"));
+            with(None, || assert_eq!(current(), None));
+            with(tenant("b"), || assert_eq!(current(), tenant("b")));
+            assert_eq!(current(), tenant("a"));
+        });
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn scoped_futures_keep_their_tenant_across_threads() {
+        let runtime = test_support::runtime();
+        let seen = runtime.block_on(async {
+            let task = tokio::spawn(scoped(tenant("a"), async {
+                let mut seen = Vec::new();
+                for _ in 0..10 {
+                    seen.push(current());
+                    tokio::task::yield_now().await;
+                }
+                seen
+            }));
+            task.await.unwrap()
+        });
+        assert!(seen.iter().all(|seen| *seen == tenant("a")));
+        assert_eq!(current(), None);
+    }
+
+    fn authorization(server: &MockServer) -> Vec<(String, Option<String>)> {
+        server
+            .requests_to("POST", "/api/v1/notify")
+            .into_iter()
+            .map(|request| {
+                let header = request.headers.get("x-tenant-id").cloned();
+                (request.headers["authorization"].clone(), header)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tenants_send_with_their_key_or_header() {
+        let _storage = TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(200, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        let mut options = test_support::options(&server);
+        options.tenant_header = Some("X-Tenant-Id".to_string());
+        let headed = NotifyClient::new(options).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(client.with_tenant("ak_test_tenant").notify("e", "t").await.success);
+            assert!(client.notify("e", "t").await.success);
+            assert!(headed.with_tenant("acme").event("e").with_title("t").send().await.success);
+        });
+        assert_eq!(
+            authorization(&server),
+            [
+                ("Bearer ak_test_tenant".to_string(), None),
+                ("Bearer ak_test_mock".to_string(), None),
+                ("Bearer ak_test_mock".to_string(), Some("acme".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn queued_notifications_are_flushed_as_their_tenant() {
+        let _storage = TempStorage::new();
+        let up = Arc::new(AtomicBool::new(false));
+        let serving = Arc::clone(&up);
+        let server = MockServer::start(move |_| match serving.load(Ordering::SeqCst) {
+            true => MockResponse::json(200, json!({})),
+            false => MockResponse::json(503, json!({})),
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(client.with_tenant("ak_test_tenant").notify("e", "t").await.queued);
+            assert_eq!(client.queued_notifications()[0].tenant.as_deref(), Some("ak_test_tenant"));
+            up.store(true, Ordering::SeqCst);
+            client.flush().await;
+        });
+        assert_eq!(client.queue_size(), 0);
+        let flushed = authorization(&server).pop().unwrap();
+        assert_eq!(flushed, ("Bearer ak_test_tenant".to_string(), None));
+    }
+
+    #[test]
+    fn registered_tenants_queue_only_their_id() {
+        let storage = TempStorage::new();
+        let up = Arc::new(AtomicBool::new(false));
+        let serving = Arc::clone(&up);
+        let server = MockServer::start(move |_| match serving.load(Ordering::SeqCst) {
+            true => MockResponse::json(200, json!({})),
+            false => MockResponse::json(503, json!({})),
+        });
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        client.register_tenant_key("acme", "ak_test_acme");
+
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(client.with_tenant("acme").notify("e", "t").await.queued);
+            let queued = client.queued_notifications();
+            assert_eq!(queued[0].tenant, None);
+            assert_eq!(queued[0].tenant_id.as_deref(), Some("acme"));
+            let file = std::fs::read_to_string(storage.path().join("offline_queue.test.json"));
+            assert!(!file.unwrap().contains("ak_test_acme"));
+
+            up.store(true, Ordering::SeqCst);
+            client.flush().await;
+        });
+        assert_eq!(client.queue_size(), 0);
+        let flushed = authorization(&server).pop().unwrap();
+        assert_eq!(flushed, ("Bearer ak_test_acme".to_string(), None));
+    }
+
+    #[test]
+    fn queued_notifications_wait_for_their_tenant_key() {
+        let _storage = TempStorage::new();
+        let server = MockServer::start(|_| MockResponse::json(503, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        client.register_tenant_key("acme", "ak_test_acme");
+        let runtime = test_support::runtime();
+        runtime.block_on(async {
+            assert!(client.with_tenant("acme").notify("e", "t").await.queued);
+        });
+        drop(client);
+
+        // A restarted process, which has not registered the key yet.
+        let server = MockServer::start(|_| MockResponse::json(200, json!({})));
+        let client = NotifyClient::new(test_support::options(&server)).unwrap();
+        runtime.block_on(async {
+            client.flush().await;
+            assert_eq!(client.queue_size(), 1);
+            assert!(authorization(&server).is_empty());
+
+            client.register_tenant_key("acme", "ak_test_acme");
+            client.flush().await;
+        });
+        assert_eq!(client.queue_size(), 0);
+        assert_eq!(authorization(&server), [("Bearer ak_test_acme".to_string(), None)]);
+    }
+}
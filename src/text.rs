@@ -0,0 +1,216 @@
+//! Text helpers for IronNotify SDK.
+//!
+//! The server cuts titles and messages that are too long at a fixed number of
+//! characters, which can split an emoji or an accented letter in two. The helpers
+//! here shorten text on grapheme cluster boundaries instead, so what is shown in
+//! the dashboard is always whole characters followed by an ellipsis.
+//!
+//! With the `graphemes` feature, clusters follow Unicode's extended grapheme cluster
+//! rules. Without it, combining marks, variation selectors, emoji modifiers and
+//! tags, zero-width joiner sequences, and flag pairs are still kept together.
+
+/// Maximum number of characters the server keeps of a title.
+pub const MAX_TITLE_CHARS: usize = 200;
+
+/// Maximum number of characters the server keeps of a message.
+pub const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Appended to shortened text.
+pub const ELLIPSIS: &str = "\u{2026}";
+
+/// Metadata key holding the full title of a notification whose title was truncated.
+pub const TRUNCATED_TITLE_KEY: &str = "truncated_title_full";
+
+/// Metadata key holding the full message of a notification whose message was
+/// truncated.
+pub const TRUNCATED_MESSAGE_KEY: &str = "truncated_message_full";
+
+/// Truncates text to at most `max` characters, including the ellipsis appended when
+/// it is shortened. Text is only cut between grapheme clusters, so a shortened result
+/// may be a few characters shorter than `max`.
+///
+/// ```
+/// use ironnotify::text::truncate_graphemes;
+///
+/// assert_eq!(truncate_graphemes("Deploy finished", 20), "Deploy finished");
+/// assert_eq!(truncate_graphemes("Deploy finished", 8), "Deploy\u{2026}");
+/// // A family emoji is seven characters joined by zero-width joiners.
+/// assert_eq!(truncate_graphemes("Hi 👨‍👩‍👧‍👦 and more", 9), "Hi\u{2026}");
+/// ```
+pub fn truncate_graphemes(text: &str, max: usize) -> String {
+    shorten(text, max).unwrap_or_else(|| text.to_string())
+}
+
+/// Truncates text like `truncate_graphemes`, or returns `None` if it already fits.
+pub(crate) fn shorten(text: &str, max: usize) -> Option<String> {
+    if text.chars().count() <= max {
+        return None;
+    }
+
+    let budget = max.saturating_sub(ELLIPSIS.chars().count());
+    let mut end = 0;
+    let mut chars = 0;
+    for cluster in graphemes(text) {
+        let count = cluster.chars().count();
+        if chars + count > budget {
+            break;
+        }
+        chars += count;
+        end += cluster.len();
+    }
+    Some(format!("{}{}", text[..end].trim_end(), ELLIPSIS))
+}
+
+/// Turns an event type into a title: the words between `.`, `_`, `-`, and `:` are
+/// joined with spaces and the first letter is capitalized. Other letters keep their
+/// case, so acronyms stay intact.
+///
+/// ```
+/// use ironnotify::text::humanize_event_type;
+///
+/// assert_eq!(humanize_event_type("order.created"), "Order created");
+/// assert_eq!(humanize_event_type("payment_failed"), "Payment failed");
+/// assert_eq!(humanize_event_type("billing.invoice_paid"), "Billing invoice paid");
+/// assert_eq!(humanize_event_type("API.key_rotated"), "API key rotated");
+/// assert_eq!(humanize_event_type("Deploy.Finished"), "Deploy Finished");
+/// assert_eq!(humanize_event_type("..sync.."), "Sync");
+/// ```
+pub fn humanize_event_type(event_type: &str) -> String {
+    let words: Vec<&str> = event_type
+        .split(['.', '_', '-', ':'])
+        .filter(|word| !word.is_empty())
+        .collect();
+    let text = words.join(" ");
+
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+#[cfg(feature = "graphemes")]
+fn graphemes(text: &str) -> impl Iterator<Item = &str> {
+    unicode_segmentation::UnicodeSegmentation::graphemes(text, true)
+}
+
+#[cfg(not(feature = "graphemes"))]
+fn graphemes(text: &str) -> impl Iterator<Item = &str> {
+    Clusters { rest: text }
+}
+
+/// Splits text into approximate grapheme clusters.
+#[cfg(not(feature = "graphemes"))]
+struct Clusters<'a> {
+    rest: &'a str,
+}
+
+#[cfg(not(feature = "graphemes"))]
+impl<'a> Iterator for Clusters<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut previous = first;
+        let mut flags = usize::from(is_regional_indicator(first));
+        let mut end = self.rest.len();
+
+        for (index, c) in chars {
+            let joined = previous == ZERO_WIDTH_JOINER && !c.is_whitespace();
+            let flag = is_regional_indicator(c) && flags % 2 == 1;
+            if !(is_extending(c) || joined || flag) {
+                end = index;
+                break;
+            }
+            flags += usize::from(is_regional_indicator(c));
+            previous = c;
+        }
+
+        let (cluster, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(cluster)
+    }
+}
+
+#[cfg(not(feature = "graphemes"))]
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Checks whether a character attaches to the one before it.
+#[cfg(not(feature = "graphemes"))]
+fn is_extending(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{0591}'..='\u{05BD}'
+            | '\u{0610}'..='\u{061A}'
+            | '\u{064B}'..='\u{065F}'
+            | '\u{0900}'..='\u{0903}'
+            | '\u{093A}'..='\u{094F}'
+            | '\u{0E31}'
+            | '\u{0E34}'..='\u{0E3A}'
+            | '\u{0E47}'..='\u{0E4E}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{3099}'..='\u{309A}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+            | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+#[cfg(not(feature = "graphemes"))]
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_that_fits_is_left_alone() {
+        assert_eq!(shorten("Deploy", 6), None);
+        assert_eq!(shorten("", 0), None);
+        assert_eq!(shorten("Deploy", 5).as_deref(), Some("Depl\u{2026}"));
+    }
+
+    #[test]
+    fn shortened_text_fits_within_the_limit() {
+        let text = "Disk usage on db-1 is above 90% 🔥 and rising";
+        for max in 0..text.chars().count() {
+            let shortened = shorten(text, max).unwrap();
+            assert!(shortened.chars().count() <= max.max(1), "{:?} at {}", shortened, max);
+            assert!(shortened.ends_with(ELLIPSIS));
+        }
+    }
+
+    #[test]
+    fn clusters_are_never_split() {
+        let cases = [
+            // "e" and a combining acute accent.
+            ("Caf\u{0065}\u{0301} open", 5, "Caf\u{2026}"),
+            ("Caf\u{0065}\u{0301} open", 6, "Caf\u{0065}\u{0301}\u{2026}"),
+            // A thumbs up with a skin tone modifier.
+            ("Ok 👍🏽 done", 5, "Ok\u{2026}"),
+            ("Ok 👍🏽 done", 6, "Ok 👍🏽\u{2026}"),
+            // Two flags, each a pair of regional indicators.
+            ("🇩🇪🇫🇷 match", 4, "🇩🇪\u{2026}"),
+            ("🇩🇪🇫🇷 match", 3, "🇩🇪\u{2026}"),
+            ("🇩🇪🇫🇷 match", 2, "\u{2026}"),
+        ];
+        for (text, max, expected) in cases {
+            assert_eq!(truncate_graphemes(text, max), expected, "{:?} at {}", text, max);
+        }
+    }
+
+    #[test]
+    fn trailing_whitespace_is_trimmed_before_the_ellipsis() {
+        assert_eq!(truncate_graphemes("Deploy   finished", 10), "Deploy\u{2026}");
+    }
+}
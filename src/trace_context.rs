@@ -0,0 +1,44 @@
+//! OpenTelemetry trace context propagation for IronNotify SDK.
+
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+
+/// Metadata key under which trace and span IDs are added to payloads.
+pub const TRACING_METADATA_KEY: &str = "_tracing";
+
+/// The span context active when a notification is sent.
+pub(crate) struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    pub flags: u8,
+    pub trace_state: String,
+}
+
+impl TraceContext {
+    /// Gets the context of the current span, if one is active.
+    pub fn current() -> Option<Self> {
+        let context = Context::current();
+        let span = context.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: span_context.trace_id().to_string(),
+            span_id: span_context.span_id().to_string(),
+            flags: span_context.trace_flags().to_u8(),
+            trace_state: span_context.trace_state().header(),
+        })
+    }
+
+    /// Formats the W3C `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Gets the metadata value added under `_tracing`.
+    pub fn metadata(&self) -> serde_json::Value {
+        serde_json::json!({ "trace_id": self.trace_id, "span_id": self.span_id })
+    }
+}
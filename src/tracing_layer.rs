@@ -0,0 +1,338 @@
+//! Tracing integration for IronNotify SDK.
+
+use crate::client::NotifyClient;
+use crate::types::{truncate_chars, SeverityLevel};
+use parking_lot::Mutex;
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Maximum number of message characters used as the notification title.
+const LOG_TITLE_LENGTH: usize = 100;
+
+thread_local! {
+    /// Set while the layer is building or sending a notification on this thread.
+    static IN_SEND: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as sending until dropped.
+struct SendGuard {
+    previous: bool,
+}
+
+impl SendGuard {
+    fn enter() -> Self {
+        Self {
+            previous: IN_SEND.with(|flag| flag.replace(true)),
+        }
+    }
+}
+
+impl Drop for SendGuard {
+    fn drop(&mut self) {
+        IN_SEND.with(|flag| flag.set(self.previous));
+    }
+}
+
+/// Runs a future with the send guard held during every poll, so events the SDK's
+/// dependencies emit while sending are not turned into notifications.
+struct Guarded<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for Guarded<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Self::Output> {
+        let _guard = SendGuard::enter();
+        self.0.as_mut().poll(cx)
+    }
+}
+
+/// Where the layer gets its client.
+#[derive(Clone)]
+enum ClientSource {
+    Client(NotifyClient),
+    #[cfg(feature = "global-client")]
+    Global,
+}
+
+/// Rate limit and deduplication state.
+struct Admission {
+    sent: VecDeque<Instant>,
+    recent: HashMap<u64, Instant>,
+}
+
+/// A `tracing_subscriber` layer that sends events at or above a level as notifications.
+///
+/// The event's message becomes the title, truncated to 100 characters, and the full
+/// message; its fields and target become metadata; and its level sets the severity.
+/// Sends are detached, rate limited, and deduplicated by callsite and message, and
+/// events emitted while sending are ignored.
+pub struct IronNotifyLayer {
+    source: ClientSource,
+    min_level: Level,
+    event_type: String,
+    dedup_window: Duration,
+    max_sends: usize,
+    rate_window: Duration,
+    admission: Mutex<Admission>,
+}
+
+impl IronNotifyLayer {
+    /// Creates a layer sending with the given client.
+    ///
+    /// Defaults to `ERROR` events, the `app.log` event type, a 60 second deduplication
+    /// window, and at most 10 notifications per minute.
+    pub fn new(client: NotifyClient) -> Self {
+        Self::with_source(ClientSource::Client(client))
+    }
+
+    /// Creates a layer sending with the global client.
+    ///
+    /// Events are ignored until the global client is initialized.
+    #[cfg(feature = "global-client")]
+    pub fn global() -> Self {
+        Self::with_source(ClientSource::Global)
+    }
+
+    fn with_source(source: ClientSource) -> Self {
+        Self {
+            source,
+            min_level: Level::ERROR,
+            event_type: "app.log".to_string(),
+            dedup_window: Duration::from_secs(60),
+            max_sends: 10,
+            rate_window: Duration::from_secs(60),
+            admission: Mutex::new(Admission {
+                sent: VecDeque::new(),
+                recent: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Sets the least severe level that is sent.
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Sets the event type of the notifications.
+    pub fn event_type(mut self, event_type: impl Into<String>) -> Self {
+        self.event_type = event_type.into();
+        self
+    }
+
+    /// Sets how long a repeated event from the same callsite with the same message
+    /// is suppressed.
+    pub fn dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = window;
+        self
+    }
+
+    /// Sets the maximum number of notifications sent per `window`. Events over the
+    /// limit are dropped.
+    pub fn rate_limit(mut self, max: usize, window: Duration) -> Self {
+        self.max_sends = max;
+        self.rate_window = window;
+        self
+    }
+
+    fn client(&self) -> Option<NotifyClient> {
+        match self.source {
+            ClientSource::Client(ref client) => Some(client.clone()),
+            #[cfg(feature = "global-client")]
+            ClientSource::Global => crate::global::get_client().ok().cloned(),
+        }
+    }
+
+    /// Checks whether an event with the given key may be sent now, recording it if so.
+    fn admit(&self, key: u64, now: Instant) -> bool {
+        let mut admission = self.admission.lock();
+
+        let dedup_window = self.dedup_window;
+        admission
+            .recent
+            .retain(|_, seen| now.duration_since(*seen) < dedup_window);
+        if admission.recent.contains_key(&key) {
+            return false;
+        }
+
+        while admission
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= self.rate_window)
+        {
+            admission.sent.pop_front();
+        }
+        if admission.sent.len() >= self.max_sends {
+            return false;
+        }
+
+        admission.sent.push_back(now);
+        admission.recent.insert(key, now);
+        true
+    }
+}
+
+impl<S: Subscriber> Layer<S> for IronNotifyLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if IN_SEND.with(Cell::get) {
+            return;
+        }
+        let metadata = event.metadata();
+        if *metadata.level() > self.min_level {
+            return;
+        }
+        let _guard = SendGuard::enter();
+
+        let Some(client) = self.client() else {
+            return;
+        };
+
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        let message = fields
+            .message
+            .unwrap_or_else(|| metadata.name().to_string());
+
+        let mut hasher = DefaultHasher::new();
+        metadata.callsite().hash(&mut hasher);
+        message.hash(&mut hasher);
+        if !self.admit(hasher.finish(), Instant::now()) {
+            return;
+        }
+
+        let mut builder = client
+            .event(&self.event_type)
+            .with_title(truncate_chars(&message, LOG_TITLE_LENGTH))
+            .with_message(message)
+            .with_severity(severity(metadata.level()))
+            .with_metadata("target", metadata.target());
+        for (key, value) in fields.values {
+            builder = builder.with_metadata(key, value);
+        }
+
+        client.spawn(Box::pin(Guarded(Box::pin(async move {
+            let _ = builder.send().await;
+        }))));
+    }
+}
+
+/// Maps a tracing level to a severity.
+fn severity(level: &Level) -> SeverityLevel {
+    match *level {
+        Level::ERROR => SeverityLevel::Error,
+        Level::WARN => SeverityLevel::Warning,
+        _ => SeverityLevel::Info,
+    }
+}
+
+/// Collects an event's message and fields.
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    values: Vec<(String, serde_json::Value)>,
+}
+
+impl FieldVisitor {
+    fn record(&mut self, field: &Field, value: serde_json::Value) {
+        if field.name() == "message" {
+            self.message = Some(match value {
+                serde_json::Value::String(message) => message,
+                other => other.to_string(),
+            });
+        } else {
+            self.values.push((field.name().to_string(), value));
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NotifyOptions;
+
+    fn layer() -> IronNotifyLayer {
+        let mut options = NotifyOptions::new("ak_test_layer");
+        options.enable_offline_queue = false;
+        IronNotifyLayer::new(NotifyClient::new(options).unwrap())
+    }
+
+    #[test]
+    fn repeated_events_are_suppressed_within_the_dedup_window() {
+        let layer = layer().dedup_window(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(layer.admit(1, start));
+        assert!(!layer.admit(1, start + Duration::from_secs(59)));
+        assert!(layer.admit(2, start + Duration::from_secs(59)));
+        assert!(layer.admit(1, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn sends_over_the_rate_limit_are_dropped_until_the_window_passes() {
+        let layer = layer().rate_limit(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(layer.admit(1, start));
+        assert!(layer.admit(2, start + Duration::from_secs(1)));
+        assert!(!layer.admit(3, start + Duration::from_secs(2)));
+        assert!(layer.admit(3, start + Duration::from_secs(10)));
+        assert!(!layer.admit(4, start + Duration::from_secs(10)));
+        assert!(layer.admit(4, start + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn a_dropped_event_is_not_remembered_as_sent() {
+        let layer = layer()
+            .rate_limit(1, Duration::from_secs(10))
+            .dedup_window(Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(layer.admit(1, start));
+        assert!(!layer.admit(2, start + Duration::from_secs(1)));
+        assert!(layer.admit(2, start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn levels_map_to_severities() {
+        assert_eq!(severity(&Level::ERROR), SeverityLevel::Error);
+        assert_eq!(severity(&Level::WARN), SeverityLevel::Warning);
+        assert_eq!(severity(&Level::INFO), SeverityLevel::Info);
+        assert_eq!(severity(&Level::DEBUG), SeverityLevel::Info);
+        assert_eq!(severity(&Level::TRACE), SeverityLevel::Info);
+    }
+}
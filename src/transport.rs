@@ -1,22 +1,184 @@
 //! HTTP transport for IronNotify SDK.
 
-use crate::types::{Notification, NotificationPayload, SendResult};
-use reqwest::Client;
-use serde::Deserialize;
-use std::time::Duration;
+use crate::api_version::{ApiVersion, API_VERSION_HEADER};
+use crate::auth::{AuthState, TokenCache};
+#[cfg(any(test, feature = "chaos"))]
+use crate::chaos::{Chaos, ChaosOutcome};
+use crate::clock::ClockSkew;
+use crate::codec::{JsonCodec, WireCodec, JSON_CONTENT_TYPE};
+use crate::config::{NotifyOptions, OptionsIssue};
+use crate::debug::DebugSwitch;
+use crate::error::{Error, ResponseError, TimeoutPhase};
+use crate::event_types::EventTypeDefinition;
+use crate::http_cache::{CachedValue, HttpCache};
+use crate::limiter::ConcurrencyLimit;
+use crate::metrics::Metrics;
+use crate::multipart;
+use crate::mutations::Mutation;
+#[cfg(feature = "tls-pinning")]
+use crate::pinning::{self, PinMismatches};
+use crate::query::{encode_query_value, page_params, CountGroupBy, NotificationQuery};
+use crate::rate_limit::RateLimits;
+use crate::reconfigure::LiveConfig;
+#[cfg(feature = "record")]
+use crate::record::TrafficRecorder;
+use crate::redact::Redactor;
+use crate::runtime::Runtime;
+use crate::search::{decode_hits, SearchHit};
+use crate::tenant::{self, TenantKeys};
+#[cfg(feature = "otel")]
+use crate::trace_context::TraceContext;
+use crate::types::{
+    DownloadInfo, Notification, NotificationEvent, NotificationPayload, NotificationRef,
+    ProjectInfo, SendResult,
+};
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use reqwest::header::{HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, Method, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Domain whose hosts receive the API key when downloading attachments.
+const IRONNOTIFY_DOMAIN: &str = "ironnotify.com";
+
+/// Maximum number of distinct URLs kept in the conditional request cache.
+const HTTP_CACHE_MAX_ENTRIES: usize = 64;
+
+/// How long the HTTP client keeps idle connections unless `pool_idle_timeout` is set.
+const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Headers set by the SDK or the HTTP client, which payloads cannot override.
+const RESERVED_HEADERS: [&str; 12] = [
+    "authorization",
+    "proxy-authorization",
+    "host",
+    "content-type",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "accept",
+    "cookie",
+    "x-content-hash",
+    API_VERSION_HEADER,
+    "traceparent",
+];
 
 /// HTTP transport for communicating with the IronNotify API.
 pub struct Transport {
     base_url: String,
-    api_key: String,
-    debug: bool,
+    /// Base URL joined with the path prefix, without a trailing slash.
+    api_root: String,
+    /// Precomputed `Authorization` header, or `None` when the API key is not a valid
+    /// header value.
+    authorization: RwLock<Option<HeaderValue>>,
+    /// How long the HTTP client keeps idle connections, or zero when it keeps none.
+    pool_idle_timeout: Duration,
+    /// When the last request finished.
+    last_request: Mutex<Option<Instant>>,
+    debug: DebugSwitch,
     client: Client,
+    metrics: Arc<Metrics>,
+    redactor: Redactor,
+    cache: Option<HttpCache>,
+    codec: Box<dyn WireCodec>,
+    json_fallback: AtomicBool,
+    api_version: ApiVersion,
+    attachment_timeout: Duration,
+    error_body_max_bytes: usize,
+    max_download_bytes: u64,
+    sends: ConcurrencyLimit,
+    reads: ConcurrencyLimit,
+    rate_limits: RateLimits,
+    clock_skew: ClockSkew,
+    auth: AuthState,
+    tokens: Option<TokenCache>,
+    tenant_header: Option<HeaderName>,
+    tenant_keys: TenantKeys,
+    /// Certificates the pinning verifier rejected, to tell pin mismatches apart from
+    /// other connection failures.
+    #[cfg(feature = "tls-pinning")]
+    pin_mismatches: Arc<PinMismatches>,
+    #[cfg(feature = "record")]
+    recorder: Option<TrafficRecorder>,
+    #[cfg(any(test, feature = "chaos"))]
+    chaos: Option<Chaos>,
+    #[cfg(any(test, feature = "chaos"))]
+    runtime: Arc<dyn Runtime>,
+}
+
+/// Why a request produced no response.
+enum RequestFailure {
+    /// The request could not be built or sent.
+    Http(reqwest::Error),
+    /// The token provider failed.
+    Token(String),
+    /// The server answered `406 Not Acceptable` to the requested API version.
+    ApiVersion(String),
+    /// The server's certificate does not match the pins. Holds the presented pin.
+    #[cfg(feature = "tls-pinning")]
+    PinMismatch(String),
+}
+
+impl From<reqwest::Error> for RequestFailure {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl RequestFailure {
+    /// Gets the phase that timed out, if the request timed out.
+    fn timeout_phase(&self) -> Option<TimeoutPhase> {
+        match self {
+            Self::Http(e) if e.is_timeout() && e.is_connect() => Some(TimeoutPhase::Connect),
+            Self::Http(e) if e.is_timeout() => Some(TimeoutPhase::Response),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(phase) = self.timeout_phase() {
+            return write!(f, "{}", Error::Timeout(phase));
+        }
+        match self {
+            Self::Http(e) => write!(f, "{}", e),
+            Self::Token(message) => write!(f, "{}", message),
+            Self::ApiVersion(version) => {
+                write!(f, "{}", Error::UnsupportedApiVersion(version.clone()))
+            }
+            #[cfg(feature = "tls-pinning")]
+            Self::PinMismatch(pin) => write!(f, "{}", Error::PinMismatch(pin.clone())),
+        }
+    }
+}
+
+impl From<RequestFailure> for Error {
+    fn from(failure: RequestFailure) -> Self {
+        match failure {
+            RequestFailure::ApiVersion(version) => Error::UnsupportedApiVersion(version),
+            #[cfg(feature = "tls-pinning")]
+            RequestFailure::PinMismatch(pin) => Error::PinMismatch(pin),
+            failure => match failure.timeout_phase() {
+                Some(phase) => Error::Timeout(phase),
+                None => Error::Transport(failure.to_string()),
+            },
+        }
+    }
 }
 
 #[derive(Deserialize)]
 struct SendResponse {
     #[serde(rename = "notificationId")]
     notification_id: Option<String>,
+    #[serde(default, rename = "attachmentUrls")]
+    attachment_urls: Vec<String>,
 }
 
 #[derive(Deserialize)]
@@ -24,178 +186,1337 @@ struct ErrorResponse {
     error: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct AttachmentResponse {
+    url: String,
+}
+
 #[derive(Deserialize)]
 struct CountResponse {
     count: i32,
 }
 
+#[derive(Deserialize)]
+struct AffectedResponse {
+    #[serde(alias = "affected")]
+    count: u64,
+}
+
+#[derive(Deserialize)]
+struct GroupedCountResponse {
+    counts: HashMap<String, i32>,
+}
+
 impl Transport {
     /// Creates a new Transport.
-    pub fn new(base_url: String, api_key: String, timeout: Duration, debug: bool) -> Self {
-        let client = Client::builder()
-            .timeout(timeout)
-            .build()
-            .expect("Failed to create HTTP client");
+    pub fn new(
+        options: &NotifyOptions,
+        metrics: Arc<Metrics>,
+        redactor: Redactor,
+        runtime: Arc<dyn Runtime>,
+        config: &Arc<LiveConfig>,
+    ) -> Self {
+        let mut client = Client::builder()
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout);
+        if let Some(timeout) = options.pool_idle_timeout {
+            client = client.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = options.pool_max_idle_per_host {
+            client = client.pool_max_idle_per_host(max);
+        }
+        if let Some(interval) = options.tcp_keepalive {
+            client = client.tcp_keepalive(interval);
+        }
+        if options.http2_prior_knowledge {
+            client = client.http2_prior_knowledge();
+        }
+        #[cfg(feature = "tls-pinning")]
+        let pin_mismatches = Arc::new(PinMismatches::default());
+        #[cfg(feature = "tls-pinning")]
+        if let Some(ref pins) = options.certificate_pins {
+            // Validation ensures pinned clients use an HTTPS URL with a host.
+            let host = Url::parse(&options.api_base_url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            let config = pinning::client_config(&host, pins, Arc::clone(&pin_mismatches));
+            client = client.use_preconfigured_tls(config);
+        }
+        let client = client.build().expect("Failed to create HTTP client");
+
+        #[cfg(feature = "record")]
+        let recorder = options.record_traffic.clone().map(|path| {
+            TrafficRecorder::open(
+                path,
+                options.record_max_bytes,
+                options.record_max_files,
+                redactor.clone(),
+                options.api_key.clone(),
+            )
+        });
 
         Self {
-            base_url,
-            api_key,
-            debug,
+            base_url: options.api_base_url.clone(),
+            api_root: join_url(&options.api_base_url, &[options.api_path_prefix.as_str()]),
+            authorization: RwLock::new(bearer_header(&options.api_key)),
+            pool_idle_timeout: if options.pool_max_idle_per_host == Some(0) {
+                Duration::ZERO
+            } else {
+                options.pool_idle_timeout.unwrap_or(DEFAULT_POOL_IDLE_TIMEOUT)
+            },
+            last_request: Mutex::new(None),
+            debug: DebugSwitch::transport(config),
             client,
+            metrics,
+            redactor,
+            cache: if options.http_cache {
+                Some(HttpCache::new(HTTP_CACHE_MAX_ENTRIES))
+            } else {
+                None
+            },
+            codec: options.wire_format.codec(),
+            json_fallback: AtomicBool::new(false),
+            api_version: options.api_version.clone(),
+            attachment_timeout: options.attachment_timeout,
+            error_body_max_bytes: options.error_body_max_bytes,
+            max_download_bytes: options.max_download_bytes,
+            sends: ConcurrencyLimit::new(
+                "sends",
+                options.max_concurrent_sends,
+                options.concurrency_timeout,
+                Arc::clone(&runtime),
+            ),
+            reads: ConcurrencyLimit::new(
+                "reads",
+                options.max_concurrent_reads,
+                options.concurrency_timeout,
+                Arc::clone(&runtime),
+            ),
+            rate_limits: RateLimits::new(config),
+            clock_skew: ClockSkew::new(DebugSwitch::transport(config)),
+            auth: AuthState::new(DebugSwitch::transport(config)),
+            tokens: options
+                .token_provider
+                .clone()
+                .map(|provider| TokenCache::new(provider, DebugSwitch::transport(config))),
+            tenant_header: options
+                .tenant_header
+                .as_deref()
+                .and_then(|name| HeaderName::from_bytes(name.as_bytes()).ok()),
+            tenant_keys: TenantKeys::default(),
+            #[cfg(feature = "tls-pinning")]
+            pin_mismatches,
+            #[cfg(feature = "record")]
+            recorder,
+            #[cfg(any(test, feature = "chaos"))]
+            chaos: options.chaos.clone(),
+            #[cfg(any(test, feature = "chaos"))]
+            runtime,
+        }
+    }
+
+    /// Gets the API keys registered for tenant IDs.
+    pub fn tenant_keys(&self) -> &TenantKeys {
+        &self.tenant_keys
+    }
+
+    /// Gets the concurrency limit for sends and other writes.
+    pub fn send_limit(&self) -> &ConcurrencyLimit {
+        &self.sends
+    }
+
+    /// Gets the concurrency limit for reads.
+    pub fn read_limit(&self) -> &ConcurrencyLimit {
+        &self.reads
+    }
+
+    /// Gets the rate limit reported by the latest response.
+    pub fn rate_limits(&self) -> &RateLimits {
+        &self.rate_limits
+    }
+
+    /// Gets the server clock offset estimated from response `Date` headers.
+    pub fn clock_skew(&self) -> &ClockSkew {
+        &self.clock_skew
+    }
+
+    /// Gets whether the API accepts the credentials.
+    pub fn auth(&self) -> &AuthState {
+        &self.auth
+    }
+
+    /// Gets the `Authorization` header value of new requests.
+    pub fn authorization(&self) -> Option<HeaderValue> {
+        self.authorization.read().clone()
+    }
+
+    /// Gets the `Authorization` header value of a request made now: a token from
+    /// the provider when one is set, or the current API key.
+    pub async fn resolve_authorization(&self) -> Result<Option<HeaderValue>, String> {
+        let current = self.authorization();
+        match self.tokens {
+            Some(ref tokens) if current.is_some() => {
+                let token = tokens.get(None).await?;
+                bearer_header(&token)
+                    .map(Some)
+                    .ok_or_else(|| "Token contains invalid characters".to_string())
+            }
+            _ => Ok(current),
+        }
+    }
+
+    /// Replaces the API key used for new requests.
+    pub fn set_api_key(&self, api_key: &str) -> Result<(), Error> {
+        let value = bearer_header(api_key).ok_or_else(|| {
+            Error::Config(OptionsIssue::InvalidApiKey.message().to_string())
+        })?;
+        *self.authorization.write() = Some(value);
+        Ok(())
+    }
+
+    /// Starts a request to an API endpoint path, e.g. `notifications/read-all`, with
+    /// the `Authorization` header set.
+    fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.request(method, self.api_url(path)))
+    }
+
+    /// Sets the `Authorization` header on a request. The header value is shared, not
+    /// copied.
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match *self.authorization.read() {
+            Some(ref value) => request.header(AUTHORIZATION, value.clone()),
+            None => request,
+        }
+    }
+
+    /// Gets the codec for outgoing bodies, honoring a previous fallback to JSON.
+    fn active_codec(&self) -> &dyn WireCodec {
+        if self.json_fallback.load(Ordering::Relaxed) {
+            &JsonCodec
+        } else {
+            self.codec.as_ref()
+        }
+    }
+
+    /// Encodes a request body with the given codec.
+    fn encode<T: Serialize>(codec: &dyn WireCodec, value: &T) -> Result<Vec<u8>, Error> {
+        let value = serde_json::to_value(value).map_err(|e| Error::Codec(e.to_string()))?;
+        codec.encode(&value)
+    }
+
+    /// Decodes a response body according to its `Content-Type`.
+    async fn decode<T: DeserializeOwned>(&self, response: Response) -> Result<T, Error> {
+        let content_type = header_value(&response, CONTENT_TYPE.as_str());
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?;
+        self.decode_bytes(content_type.as_deref(), &bytes)
+    }
+
+    /// Decodes bytes with the configured codec when `content_type` matches it, or as JSON.
+    fn decode_bytes<T: DeserializeOwned>(
+        &self,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<T, Error> {
+        let uses_codec =
+            content_type.is_some_and(|ct| ct.starts_with(self.codec.content_type()));
+
+        let value = if uses_codec {
+            self.codec.decode(bytes)?
+        } else {
+            JsonCodec.decode(bytes)?
+        };
+        serde_json::from_value(value).map_err(|e| Error::Codec(e.to_string()))
+    }
+
+    /// Builds the error for a non-success response.
+    ///
+    /// When the body is not a JSON error (e.g. an HTML page from a proxy), it is
+    /// kept truncated to `error_body_max_bytes`.
+    async fn response_error(&self, response: Response) -> ResponseError {
+        let status = response.status().as_u16();
+        let request_id = header_value(&response, "x-request-id");
+        let server = header_value(&response, "server");
+        let content_type = header_value(&response, CONTENT_TYPE.as_str());
+        let bytes = response.bytes().await.unwrap_or_default();
+
+        let message = self
+            .decode_bytes::<ErrorResponse>(content_type.as_deref(), &bytes)
+            .ok()
+            .and_then(|error| error.error);
+        let body = if message.is_none() {
+            truncate_body(&bytes, self.error_body_max_bytes)
+        } else {
+            None
+        };
+
+        ResponseError {
+            status,
+            message,
+            body,
+            request_id,
+            server,
+        }
+    }
+
+    /// Posts an encoded notification payload, as a multipart request with a part per
+    /// attachment when it has any.
+    async fn post_payload(
+        &self,
+        codec: &dyn WireCodec,
+        payload: &NotificationPayload,
+        content_hash: &str,
+    ) -> Result<Result<Response, RequestFailure>, Error> {
+        let body = codec.encode(&payload.wire_value())?;
+        let (content_type, body) = match payload.attached() {
+            Some(attachments) => multipart::form_data(codec.content_type(), &body, attachments),
+            None => (codec.content_type().to_string(), body),
+        };
+
+        let mut request = self
+            .request(Method::POST, "notify")
+            .header(CONTENT_TYPE, content_type)
+            .header(ACCEPT, codec.content_type())
+            .header("X-Content-Hash", content_hash);
+        if let Some(ref headers) = payload.request_headers {
+            for (name, value) in headers {
+                let tenant_header = self.tenant_header.as_ref().map(HeaderName::as_str);
+                if !is_reserved_header(name, tenant_header) {
+                    request = request.header(name.as_str(), value.as_str());
+                }
+            }
+        }
+
+        if payload.attached().is_some() {
+            request = request.timeout(self.attachment_timeout);
+        }
+
+        #[cfg(feature = "otel")]
+        let request = with_trace_headers(request);
+
+        let warm = self.idle_for().is_some_and(|idle| idle < self.pool_idle_timeout);
+        let result = self.execute("/api/v1/notify", request.body(body)).await;
+        self.metrics.record_send_connection(warm);
+        Ok(result)
+    }
+
+    /// Builds the URL for an API endpoint path.
+    fn api_url(&self, path: &str) -> String {
+        let mut url = String::with_capacity(self.api_root.len() + path.len() + 1);
+        url.push_str(&self.api_root);
+        url.push('/');
+        url.push_str(path.trim_start_matches('/'));
+        url
+    }
+
+    /// Returns the cached value on `304 Not Modified`, recording cache hits and misses.
+    fn cached(&self, key: &str, response: &Response) -> Option<CachedValue> {
+        let cache = self.cache.as_ref()?;
+        let value = if response.status() == StatusCode::NOT_MODIFIED {
+            cache.get(key)
+        } else {
+            None
+        };
+        self.metrics.record_cache(value.is_some());
+        value
+    }
+
+    /// Clears cached list responses after a mutation.
+    fn invalidate_cache(&self) {
+        if let Some(ref cache) = self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Sends a request, recording its latency and outcome.
+    ///
+    /// Every API call goes through here, so authentication and chaos rules apply to
+    /// all endpoints.
+    async fn execute(
+        &self,
+        endpoint: &'static str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Response, RequestFailure> {
+        let started = Instant::now();
+        let result = self.send_authorized(request).await;
+        #[cfg(feature = "tls-pinning")]
+        let result = match result {
+            Err(RequestFailure::Http(e)) if e.is_connect() => match self.pin_mismatches.take() {
+                Some(pin) => Err(RequestFailure::PinMismatch(pin)),
+                None => Err(RequestFailure::Http(e)),
+            },
+            result => result,
+        };
+        self.record(endpoint, &result, started);
+        *self.last_request.lock() = Some(Instant::now());
+        match result {
+            Ok(response) if response.status() == StatusCode::NOT_ACCEPTABLE => {
+                Err(RequestFailure::ApiVersion(self.api_version.to_string()))
+            }
+            result => result,
+        }
+    }
+
+    /// Sends a request, replacing its `Authorization` header with the token provider's
+    /// token if one is configured.
+    ///
+    /// A `401` triggers one forced token refresh and retry.
+    async fn send_authorized(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Response, RequestFailure> {
+        let mut request = request.build()?;
+        let tenant = tenant::current();
+        if self.is_trusted_url(request.url()) {
+            if let Ok(version) = HeaderValue::from_str(self.api_version.as_str()) {
+                request.headers_mut().insert(API_VERSION_HEADER, version);
+            }
+            match (tenant, &self.tenant_header) {
+                (Some(tenant), Some(name)) => {
+                    let value = HeaderValue::from_str(&tenant.name).map_err(|_| {
+                        RequestFailure::Token("Tenant ID contains invalid characters".to_string())
+                    })?;
+                    request.headers_mut().insert(name.clone(), value);
+                }
+                (Some(tenant), None) if request.headers().contains_key(AUTHORIZATION) => {
+                    let key = match tenant.registered {
+                        true => self.tenant_keys.get(&tenant.name).ok_or_else(|| {
+                            RequestFailure::Token(format!(
+                                "No API key registered for tenant {}",
+                                tenant.name
+                            ))
+                        })?,
+                        false => tenant.name,
+                    };
+                    set_bearer(&mut request, &key)?;
+                    return Ok(self.dispatch(request).await?);
+                }
+                _ => {}
+            }
+        }
+        let Some(ref tokens) = self.tokens else {
+            return Ok(self.dispatch(request).await?);
+        };
+        if !request.headers().contains_key(AUTHORIZATION) {
+            return Ok(self.dispatch(request).await?);
+        }
+
+        let token = tokens.get(None).await.map_err(RequestFailure::Token)?;
+        set_bearer(&mut request, &token)?;
+        let retry = request.try_clone();
+
+        let response = self.dispatch(request).await?;
+        let Some(mut retry) = retry else {
+            return Ok(response);
+        };
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let token = tokens.get(Some(&token)).await.map_err(RequestFailure::Token)?;
+        set_bearer(&mut retry, &token)?;
+        Ok(self.dispatch(retry).await?)
+    }
+
+    /// Sends a request, recording the exchange when traffic recording is enabled.
+    async fn dispatch(&self, request: reqwest::Request) -> reqwest::Result<Response> {
+        #[cfg(feature = "record")]
+        if let Some(ref recorder) = self.recorder {
+            let exchange = recorder.request(&request);
+            let started = Instant::now();
+            let result = self.send_request(request).await;
+            return recorder.finish(exchange, result, started.elapsed()).await;
+        }
+        self.send_request(request).await
+    }
+
+    #[cfg(not(any(test, feature = "chaos")))]
+    async fn send_request(&self, request: reqwest::Request) -> reqwest::Result<Response> {
+        self.client.execute(request).await
+    }
+
+    /// Sends a request unless a chaos rule delays, fails, or answers it.
+    #[cfg(any(test, feature = "chaos"))]
+    async fn send_request(&self, mut request: reqwest::Request) -> reqwest::Result<Response> {
+        let Some(ref chaos) = self.chaos else {
+            return self.client.execute(request).await;
+        };
+
+        let (delay, outcome) = chaos.plan(request.method());
+        if let Some(delay) = delay {
+            self.runtime.sleep(delay).await;
+        }
+
+        match outcome {
+            ChaosOutcome::Pass => self.client.execute(request).await,
+            ChaosOutcome::Fail => {
+                // reqwest rejects unsupported schemes before connecting, which yields a
+                // genuine `reqwest::Error` without touching the network.
+                *request.url_mut() = Url::parse("chaos://injected-failure").expect("valid URL");
+                self.client.execute(request).await
+            }
+            ChaosOutcome::Status(status) => {
+                let status =
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                let response = http::Response::builder()
+                    .status(status)
+                    .body(Vec::<u8>::new())
+                    .expect("valid response");
+                Ok(Response::from(response))
+            }
+        }
+    }
+
+    /// Records the latency, outcome, rate limit, and server time of a request,
+    /// including failures and timeouts.
+    fn record(
+        &self,
+        endpoint: &'static str,
+        result: &Result<Response, RequestFailure>,
+        started: Instant,
+    ) {
+        let status = result.as_ref().ok().map(|r| r.status().as_u16());
+        self.metrics.record_request(endpoint, status, started.elapsed());
+        if self.debug.is_verbose() {
+            // Only the endpoint template is logged: URLs may carry IDs and search text,
+            // and failures may quote them.
+            let outcome = status.map_or_else(|| "no response".to_string(), |s| s.to_string());
+            println!(
+                "[IronNotify] {} -> {} in {:?}",
+                endpoint,
+                outcome,
+                started.elapsed()
+            );
+        }
+        if let Ok(response) = result {
+            self.rate_limits.observe(response.headers());
+            self.clock_skew.observe(response.headers());
         }
     }
 
     /// Sends a notification payload.
+    ///
+    /// One send permit is held for the whole call, including the retry after a JSON fallback.
     pub async fn send(&self, payload: &NotificationPayload) -> SendResult {
-        if self.debug {
+        let content_hash = payload.content_hash();
+        let mut result = self.post(payload, &content_hash).await;
+        result.content_hash = Some(content_hash);
+        result
+    }
+
+    async fn post(&self, payload: &NotificationPayload, content_hash: &str) -> SendResult {
+        let _permit = match self.sends.acquire().await {
+            Ok(permit) => permit,
+            Err(e) => return SendResult::failure(e.to_string()),
+        };
+
+        if self.debug.is_enabled() {
             println!("[IronNotify] Sending notification: {}", payload.event_type);
         }
+        if self.debug.is_verbose() {
+            if let Some(attachments) = payload.attached() {
+                println!(
+                    "[IronNotify] Attachments: {} files, {} bytes",
+                    attachments.len(),
+                    payload.attachment_size()
+                );
+            }
+            if let Some(ref metadata) = self.redactor.redact_payload(payload).metadata {
+                println!(
+                    "[IronNotify] Metadata: {}",
+                    serde_json::to_string(metadata).unwrap_or_default()
+                );
+            }
+        }
 
-        let result = self
-            .client
-            .post(format!("{}/api/v1/notify", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(payload)
-            .send()
-            .await;
+        let codec = self.active_codec();
+        let mut result = match self.post_payload(codec, payload, content_hash).await {
+            Ok(result) => result,
+            Err(e) => return SendResult::failure(e.to_string()),
+        };
+
+        let unsupported = matches!(
+            result,
+            Ok(ref response) if response.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+        if unsupported && codec.content_type() != JSON_CONTENT_TYPE {
+            if self.debug.is_enabled() {
+                println!(
+                    "[IronNotify] Server rejected {}, falling back to JSON",
+                    codec.content_type()
+                );
+            }
+            self.json_fallback.store(true, Ordering::Relaxed);
+            result = match self.post_payload(&JsonCodec, payload, content_hash).await {
+                Ok(result) => result,
+                Err(e) => return SendResult::failure(e.to_string()),
+            };
+        }
 
         match result {
             Ok(response) => {
-                if response.status().is_success() {
-                    if let Ok(data) = response.json::<SendResponse>().await {
-                        SendResult::success(data.notification_id)
-                    } else {
-                        SendResult::success(None)
+                let status = response.status();
+                let own_key = tenant::current().is_none();
+                if status.is_success() {
+                    if own_key {
+                        self.auth.clear();
                     }
+                    let mut result = match self.decode::<SendResponse>(response).await {
+                        Ok(data) => {
+                            let mut result = SendResult::success(data.notification_id);
+                            result.attachment_urls = data.attachment_urls;
+                            result
+                        }
+                        Err(_) => SendResult::success(None),
+                    };
+                    result.status = Some(status.as_u16());
+                    result
                 } else {
-                    let status = response.status();
-                    if let Ok(error) = response.json::<ErrorResponse>().await {
-                        SendResult::failure(
-                            error
-                                .error
-                                .unwrap_or_else(|| format!("HTTP {}", status)),
-                        )
-                    } else {
-                        SendResult::failure(format!("HTTP {}", status))
+                    let error = self.response_error(response).await.to_string();
+                    let rejected =
+                        status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN;
+                    if rejected && own_key {
+                        self.auth.fail(status.as_u16(), error.clone());
                     }
+                    let mut result = SendResult::failure(error);
+                    result.status = Some(status.as_u16());
+                    result
                 }
             }
             Err(e) => SendResult::failure(e.to_string()),
         }
     }
 
+    /// Uploads a metadata value as an attachment, returning its URL.
+    pub async fn upload_attachment(&self, value: &serde_json::Value) -> Result<String, Error> {
+        let _permit = self.sends.acquire().await?;
+
+        let body = Self::encode(&JsonCodec, value)?;
+
+        let request = self
+            .request(Method::POST, "attachments")
+            .header(CONTENT_TYPE, JSON_CONTENT_TYPE)
+            .timeout(self.attachment_timeout)
+            .body(body);
+        let result = self.execute("/api/v1/attachments", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                let data: AttachmentResponse = self.decode(response).await?;
+                if self.debug.is_enabled() {
+                    println!("[IronNotify] Uploaded attachment: {}", data.url);
+                }
+                Ok(data.url)
+            }
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Checks if a URL is served by IronNotify, so that it may receive the API key.
+    fn is_trusted_url(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let base_host = Url::parse(&self.base_url)
+            .ok()
+            .and_then(|base| base.host_str().map(str::to_string));
+
+        base_host.as_deref() == Some(host)
+            || host == IRONNOTIFY_DOMAIN
+            || host.ends_with(&format!(".{}", IRONNOTIFY_DOMAIN))
+    }
+
+    /// Streams an attachment into `writer`.
+    ///
+    /// `url_or_id` is either an absolute URL or an attachment ID. The API key is
+    /// only sent to IronNotify hosts.
+    pub async fn download_attachment<W>(
+        &self,
+        url_or_id: &str,
+        writer: &mut W,
+    ) -> Result<DownloadInfo, Error>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let _permit = self.reads.acquire().await?;
+
+        let url = match Url::parse(url_or_id) {
+            Ok(url) => url,
+            Err(_) => Url::parse(&self.api_url(&format!("attachments/{}", url_or_id)))
+                .map_err(|e| Error::Config(e.to_string()))?,
+        };
+
+        let mut request = self.client.get(url.clone()).timeout(self.attachment_timeout);
+        if self.is_trusted_url(&url) {
+            request = self.authorize(request);
+        } else if self.debug.is_enabled() {
+            println!("[IronNotify] Downloading from external host without credentials");
+        }
+
+        let result = self.execute("/api/v1/attachments/{id}", request).await;
+
+        let mut response = match result {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) if response.status() == StatusCode::FORBIDDEN => {
+                return Err(Error::AttachmentExpired)
+            }
+            Ok(response) => return Err(Error::Http(self.response_error(response).await)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let limit = self.max_download_bytes;
+        if response.content_length().is_some_and(|length| length > limit) {
+            return Err(Error::DownloadTooLarge { limit });
+        }
+
+        let content_type = header_value(&response, CONTENT_TYPE.as_str());
+        let mut bytes: u64 = 0;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?
+        {
+            bytes += chunk.len() as u64;
+            if bytes > limit {
+                return Err(Error::DownloadTooLarge { limit });
+            }
+            writer
+                .write_all(&chunk)
+                .await
+                .map_err(|e| Error::Transport(e.to_string()))?;
+        }
+        writer
+            .flush()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?;
+
+        Ok(DownloadInfo {
+            content_type,
+            bytes,
+        })
+    }
+
     /// Gets notifications.
     pub async fn get_notifications(
         &self,
-        limit: Option<i32>,
-        offset: Option<i32>,
-        unread_only: bool,
-    ) -> Result<Vec<Notification>, String> {
-        let mut url = format!("{}/api/v1/notifications", self.base_url);
-        let mut params = Vec::new();
+        query: &NotificationQuery,
+    ) -> Result<Vec<Notification>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let path = with_params("notifications", query);
+        let cache_key = cache_key(&path);
+
+        let mut request = self
+            .request(Method::GET, &path)
+            .header(ACCEPT, self.active_codec().content_type());
+        if let Some(ref cache) = self.cache {
+            request = cache.apply(&cache_key, request);
+        }
+
+        let result = self.execute("/api/v1/notifications", request).await;
 
-        if let Some(l) = limit {
-            params.push(format!("limit={}", l));
+        match result {
+            Ok(response) => {
+                if let Some(CachedValue::Notifications(items)) = self.cached(&cache_key, &response)
+                {
+                    return Ok(items);
+                }
+                if response.status().is_success() {
+                    let headers = response.headers().clone();
+                    let value = self.decode(response).await?;
+                    let items = self.api_version.decode_notifications(value)?;
+                    if let Some(ref cache) = self.cache {
+                        let value = CachedValue::Notifications(items.clone());
+                        cache.store(&cache_key, &headers, value);
+                    }
+                    Ok(items)
+                } else {
+                    Err(Error::Http(self.response_error(response).await))
+                }
+            }
+            Err(e) => Err(e.into()),
         }
-        if let Some(o) = offset {
-            params.push(format!("offset={}", o));
+    }
+
+    /// Searches notifications by text, mapping 404 to `Error::Unsupported`.
+    pub async fn search_notifications(
+        &self,
+        text: &str,
+        query: &NotificationQuery,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let mut params = vec![format!("q={}", encode_query_value(text))];
+        params.extend(query.to_params());
+        let path = format!("notifications/search?{}", params.join("&"));
+        let request = self
+            .request(Method::GET, &path)
+            .header(ACCEPT, self.active_codec().content_type());
+        let result = self.execute("/api/v1/notifications/search", request).await;
+
+        match result {
+            Ok(response) => match response.status() {
+                status if status.is_success() => {
+                    let value = self.decode(response).await?;
+                    decode_hits(&self.api_version, value)
+                }
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                    Err(Error::Unsupported("notification search".to_string()))
+                }
+                _ => Err(Error::Http(self.response_error(response).await)),
+            },
+            Err(e) => Err(e.into()),
         }
-        if unread_only {
-            params.push("unread_only=true".to_string());
+    }
+
+    /// Gets notifications as JSON and passes each to `visit` borrowed from the response
+    /// body, returning how many were visited.
+    ///
+    /// Skips the codec, the conditional request cache, and the intermediate
+    /// `serde_json::Value`. Strings borrow from the body unless they contain escapes,
+    /// so large lists are read without allocating every field.
+    pub async fn get_notifications_raw<F>(
+        &self,
+        query: &NotificationQuery,
+        mut visit: F,
+    ) -> Result<usize, Error>
+    where
+        F: FnMut(NotificationRef<'_>),
+    {
+        let _permit = self.reads.acquire().await?;
+
+        let request = self
+            .request(Method::GET, &with_params("notifications", query))
+            .header(ACCEPT, JSON_CONTENT_TYPE);
+        let result = self.execute("/api/v1/notifications", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                let bytes = response
+                    .bytes()
+                    .await
+                    .map_err(|e| Error::Transport(e.to_string()))?;
+                let items: Vec<NotificationRef<'_>> =
+                    serde_json::from_slice(&bytes).map_err(|e| Error::Codec(e.to_string()))?;
+                let count = items.len();
+                items.into_iter().for_each(&mut visit);
+                Ok(count)
+            }
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
         }
+    }
+
+    /// Gets the unread notification count.
+    pub async fn get_unread_count(&self, user_id: Option<&str>) -> Result<i32, String> {
+        let _permit = self.reads.acquire().await.map_err(|e| e.to_string())?;
+
+        let path = "notifications/unread-count";
+        let cache_key = match user_id {
+            Some(user_id) => cache_key(&format!("{}?user_id={}", path, user_id)),
+            None => cache_key(path),
+        };
 
-        if !params.is_empty() {
-            url = format!("{}?{}", url, params.join("&"));
+        let mut request = self.request(Method::GET, path);
+        if let Some(user_id) = user_id {
+            request = request.query(&[("user_id", user_id)]);
+        }
+        if let Some(ref cache) = self.cache {
+            request = cache.apply(&cache_key, request);
         }
 
-        let result = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await;
+        let result = self.execute("/api/v1/notifications/unread-count", request).await;
 
         match result {
             Ok(response) => {
+                if let Some(CachedValue::Count(count)) = self.cached(&cache_key, &response) {
+                    return Ok(count);
+                }
                 if response.status().is_success() {
-                    response
-                        .json()
-                        .await
-                        .map_err(|e| e.to_string())
+                    let headers = response.headers().clone();
+                    let data: CountResponse =
+                        self.decode(response).await.map_err(|e| e.to_string())?;
+                    if let Some(ref cache) = self.cache {
+                        cache.store(&cache_key, &headers, CachedValue::Count(data.count));
+                    }
+                    Ok(data.count)
                 } else {
-                    Err(format!("HTTP {}", response.status()))
+                    Err(self.response_error(response).await.to_string())
                 }
             }
             Err(e) => Err(e.to_string()),
         }
     }
 
-    /// Gets the unread notification count.
-    pub async fn get_unread_count(&self) -> Result<i32, String> {
-        let result = self
-            .client
-            .get(format!("{}/api/v1/notifications/unread-count", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await;
+    /// Gets unread counts grouped by the given field.
+    pub async fn get_unread_counts(
+        &self,
+        group_by: CountGroupBy,
+        user_id: Option<&str>,
+    ) -> Result<HashMap<String, i32>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let mut request = self
+            .request(Method::GET, "notifications/unread-count")
+            .query(&[("group_by", group_by.as_str())]);
+        if let Some(user_id) = user_id {
+            request = request.query(&[("user_id", user_id)]);
+        }
+
+        let result = self.execute("/api/v1/notifications/unread-count", request).await;
 
         match result {
             Ok(response) => {
                 if response.status().is_success() {
-                    let data: CountResponse = response.json().await.map_err(|e| e.to_string())?;
-                    Ok(data.count)
+                    let data: GroupedCountResponse = self.decode(response).await?;
+                    Ok(data.counts)
                 } else {
-                    Err(format!("HTTP {}", response.status()))
+                    Err(Error::Http(self.response_error(response).await))
                 }
             }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gets the activity history of a notification.
+    pub async fn get_notification_history(
+        &self,
+        notification_id: &str,
+    ) -> Result<Vec<NotificationEvent>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let request = self
+            .request(Method::GET, &format!("notifications/{}/history", notification_id))
+            .header(ACCEPT, self.active_codec().content_type());
+        let result = self.execute("/api/v1/notifications/{id}/history", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => self.decode(response).await,
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Applies a mutation to a single notification.
+    pub async fn mutate(&self, notification_id: &str, mutation: &Mutation) -> Result<(), Error> {
+        let _permit = self.sends.acquire().await?;
+
+        let path = format!("notifications/{}/{}", notification_id, mutation.action());
+        let mut request = self.request(Method::POST, &path);
+        if let Mutation::Acknowledge { ref note } = *mutation {
+            request = request.json(&serde_json::json!({ "note": note }));
+        }
+        let result = self.execute(mutation.endpoint(), request).await;
+        self.invalidate_cache();
+
+        match result {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Snoozes a notification until the given time.
+    pub async fn snooze(&self, notification_id: &str, until: DateTime<Utc>) -> Result<bool, String> {
+        let _permit = self.sends.acquire().await.map_err(|e| e.to_string())?;
+
+        let request = self
+            .request(Method::POST, &format!("notifications/{}/snooze", notification_id))
+            .json(&serde_json::json!({ "until": until }));
+        let result = self.execute("/api/v1/notifications/{id}/snooze", request).await;
+        self.invalidate_cache();
+
+        match result {
+            Ok(response) if response.status().is_success() => Ok(true),
+            Ok(response) => Err(self.response_error(response).await.to_string()),
             Err(e) => Err(e.to_string()),
         }
     }
 
-    /// Marks a notification as read.
-    pub async fn mark_as_read(&self, notification_id: &str) -> Result<bool, String> {
-        let result = self
-            .client
-            .post(format!(
-                "{}/api/v1/notifications/{}/read",
-                self.base_url, notification_id
-            ))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await;
+    /// Cancels a notification's snooze.
+    pub async fn unsnooze(&self, notification_id: &str) -> Result<bool, String> {
+        let _permit = self.sends.acquire().await.map_err(|e| e.to_string())?;
+
+        let request =
+            self.request(Method::DELETE, &format!("notifications/{}/snooze", notification_id));
+        let result = self.execute("/api/v1/notifications/{id}/snooze", request).await;
+        self.invalidate_cache();
 
         match result {
-            Ok(response) => Ok(response.status().is_success()),
+            Ok(response) if response.status().is_success() => Ok(true),
+            Ok(response) => Err(self.response_error(response).await.to_string()),
             Err(e) => Err(e.to_string()),
         }
     }
 
     /// Marks all notifications as read.
     pub async fn mark_all_as_read(&self) -> Result<bool, String> {
-        let result = self
-            .client
-            .post(format!("{}/api/v1/notifications/read-all", self.base_url))
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await;
+        let _permit = self.sends.acquire().await.map_err(|e| e.to_string())?;
+
+        let request = self.request(Method::POST, "notifications/read-all");
+        let result = self.execute("/api/v1/notifications/read-all", request).await;
+        self.invalidate_cache();
 
         match result {
-            Ok(response) => Ok(response.status().is_success()),
+            Ok(response) if response.status().is_success() => Ok(true),
+            Ok(response) => Err(self.response_error(response).await.to_string()),
             Err(e) => Err(e.to_string()),
         }
     }
 
+    /// Archives the notifications matching a query, returning how many were affected.
+    pub async fn archive_matching(&self, query: &NotificationQuery) -> Result<u64, Error> {
+        let request = self.request(Method::POST, &with_params("notifications/archive", query));
+        self.bulk(request, "/api/v1/notifications/archive", query.dry_run).await
+    }
+
+    /// Deletes the notifications matching a query, returning how many were affected.
+    pub async fn delete_matching(&self, query: &NotificationQuery) -> Result<u64, Error> {
+        let request = self.request(Method::DELETE, &with_params("notifications", query));
+        self.bulk(request, "/api/v1/notifications", query.dry_run).await
+    }
+
+    /// Sends a bulk operation request, mapping 404 to `Error::Unsupported`.
+    async fn bulk(
+        &self,
+        request: reqwest::RequestBuilder,
+        endpoint: &'static str,
+        dry_run: bool,
+    ) -> Result<u64, Error> {
+        let _permit = self.sends.acquire().await?;
+
+        let request = request.header(ACCEPT, self.active_codec().content_type());
+        let result = self.execute(endpoint, request).await;
+        if !dry_run {
+            self.invalidate_cache();
+        }
+
+        match result {
+            Ok(response) => match response.status() {
+                status if status.is_success() => {
+                    let affected: AffectedResponse = self.decode(response).await?;
+                    Ok(affected.count)
+                }
+                StatusCode::UNAUTHORIZED => Err(Error::InvalidApiKey),
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                    Err(Error::Unsupported("bulk notification operations".to_string()))
+                }
+                _ => Err(Error::Http(self.response_error(response).await)),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Lists event type definitions.
+    pub async fn list_event_types(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<EventTypeDefinition>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let params = page_params(limit, offset);
+        let path = if params.is_empty() {
+            "event-types".to_string()
+        } else {
+            format!("event-types?{}", params.join("&"))
+        };
+
+        let request = self
+            .request(Method::GET, &path)
+            .header(ACCEPT, self.active_codec().content_type());
+        let result = self.execute("/api/v1/event-types", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => self.decode(response).await,
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gets an event type definition, or `None` if it is not registered.
+    pub async fn get_event_type(&self, name: &str) -> Result<Option<EventTypeDefinition>, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let request = self
+            .request(Method::GET, &format!("event-types/{}", encode_query_value(name)))
+            .header(ACCEPT, self.active_codec().content_type());
+        let result = self.execute("/api/v1/event-types/{name}", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => self.decode(response).await.map(Some),
+            Ok(response) if response.status() == StatusCode::NOT_FOUND => Ok(None),
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Creates or replaces an event type definition.
+    pub async fn upsert_event_type(
+        &self,
+        definition: &EventTypeDefinition,
+    ) -> Result<EventTypeDefinition, Error> {
+        let _permit = self.sends.acquire().await?;
+
+        let codec = self.active_codec();
+        let body = Self::encode(codec, definition)?;
+
+        let path = format!("event-types/{}", encode_query_value(&definition.name));
+        let request = self
+            .request(Method::PUT, &path)
+            .header(CONTENT_TYPE, codec.content_type())
+            .header(ACCEPT, codec.content_type())
+            .body(body);
+        let result = self.execute("/api/v1/event-types/{name}", request).await;
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                Ok(self.decode(response).await.unwrap_or_else(|_| definition.clone()))
+            }
+            Ok(response) => Err(Error::Http(self.response_error(response).await)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gets the project associated with the API key.
+    pub async fn get_project(&self) -> Result<ProjectInfo, Error> {
+        let _permit = self.reads.acquire().await?;
+
+        let request = self.request(Method::GET, "project");
+        let result = self.execute("/api/v1/project", request).await;
+
+        match result {
+            Ok(response) => match response.status() {
+                status if status.is_success() => {
+                    if tenant::current().is_none() {
+                        self.auth.clear();
+                    }
+                    response
+                        .json()
+                        .await
+                        .map_err(|e| Error::Transport(e.to_string()))
+                }
+                StatusCode::UNAUTHORIZED => Err(Error::InvalidApiKey),
+                _ => Err(Error::Http(self.response_error(response).await)),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gets how long ago the last request finished, or `None` before the first one.
+    pub fn idle_for(&self) -> Option<Duration> {
+        self.last_request.lock().map(|at| at.elapsed())
+    }
+
+    /// Sends `HEAD /health` so the pooled connection is not closed for being idle.
+    pub async fn keep_warm(&self) {
+        let request = self.client.head(join_url(&self.base_url, &["health"]));
+        if let Err(e) = self.execute("/health", request).await {
+            if self.debug.is_enabled() {
+                println!("[IronNotify] Keepalive ping failed: {}", e);
+            }
+        }
+    }
+
     /// Checks if the API is reachable.
     pub async fn is_online(&self) -> bool {
-        if let Ok(response) = self
-            .client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-        {
+        let request = self.client.get(join_url(&self.base_url, &["health"]));
+        let result = self.execute("/health", request).await;
+
+        if let Ok(response) = result {
             response.status().is_success()
         } else {
             false
         }
     }
 }
+
+/// Adds W3C trace context headers for the current span, if one is active.
+#[cfg(feature = "otel")]
+fn with_trace_headers(mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    if let Some(trace) = TraceContext::current() {
+        request = request.header("traceparent", trace.traceparent());
+        if !trace.trace_state.is_empty() {
+            request = request.header("tracestate", trace.trace_state);
+        }
+    }
+    request
+}
+
+/// Sets a request's `Authorization` header to a bearer token.
+fn set_bearer(request: &mut reqwest::Request, token: &str) -> Result<(), RequestFailure> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+        .map_err(|_| RequestFailure::Token("Token contains invalid characters".to_string()))?;
+    value.set_sensitive(true);
+    request.headers_mut().insert(AUTHORIZATION, value);
+    Ok(())
+}
+
+/// Checks whether a header is set by the SDK, so a payload's `request_headers` may
+/// not override it.
+pub(crate) fn is_reserved_header(name: &str, tenant_header: Option<&str>) -> bool {
+    RESERVED_HEADERS
+        .iter()
+        .chain(tenant_header.as_ref())
+        .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
+/// Gets the HTTP cache key of a URL, separating the responses of each tenant.
+fn cache_key(url: &str) -> String {
+    match tenant::current() {
+        Some(tenant) => format!("{}#{}", crate::hash::sha256_hex(tenant.name.as_bytes()), url),
+        None => url.to_string(),
+    }
+}
+
+/// Gets a response header as a string.
+fn header_value(response: &Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Converts a response body to text, truncated to `max_bytes`.
+fn truncate_body(bytes: &[u8], max_bytes: usize) -> Option<String> {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(max_bytes)]);
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else if bytes.len() > max_bytes {
+        Some(format!("{}...", text))
+    } else {
+        Some(text.to_string())
+    }
+}
+
+/// Appends a query's parameters to an endpoint path.
+fn with_params(path: &str, query: &NotificationQuery) -> String {
+    let params = query.to_params();
+    if params.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, params.join("&"))
+    }
+}
+
+/// Builds the `Authorization` header for an API key, marked sensitive so it is left
+/// out of `Debug` output. Returns `None` if the key is not a valid header value.
+fn bearer_header(api_key: &str) -> Option<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", api_key)).ok()?;
+    value.set_sensitive(true);
+    Some(value)
+}
+
+/// Joins URL path segments onto a base URL, normalizing slashes.
+///
+/// The base URL may include a path prefix, with or without a trailing slash.
+fn join_url(base: &str, segments: &[&str]) -> String {
+    let mut url = base.trim_end_matches('/').to_string();
+    for segment in segments {
+        let segment = segment.trim_matches('/');
+        if !segment.is_empty() {
+            url.push('/');
+            url.push_str(segment);
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::DebugSwitch;
+    use crate::runtime::default_runtime;
+
+    /// Every endpoint the transport requests, relative to the API root.
+    const ENDPOINTS: &[&str] = &[
+        "notify",
+        "attachments",
+        "attachments/att-1",
+        "notifications",
+        "notifications?limit=10",
+        "notifications/search?q=disk",
+        "notifications/unread-count",
+        "notifications/n-1/history",
+        "notifications/n-1/read",
+        "notifications/n-1/unread",
+        "notifications/n-1/archive",
+        "notifications/n-1/ack",
+        "notifications/n-1/snooze",
+        "notifications/read-all",
+        "notifications/archive",
+        "event-types",
+        "event-types/order.created",
+        "project",
+    ];
+
+    fn transport(base_url: &str, prefix: &str) -> Transport {
+        let mut options = NotifyOptions::new("ak_test_x");
+        options.api_base_url = base_url.to_string();
+        options.api_path_prefix = prefix.to_string();
+        let config = LiveConfig::new(&options);
+        let metrics = Arc::new(Metrics::new(
+            options.slow_request_threshold,
+            DebugSwitch::transport(&config),
+        ));
+        Transport::new(&options, metrics, Redactor::default(), default_runtime(), &config)
+    }
+
+    #[test]
+    fn base_url_shapes_produce_correct_endpoint_urls() {
+        let shapes = [
+            ("https://host", "https://host"),
+            ("https://host/", "https://host"),
+            ("https://host/prefix", "https://host/prefix"),
+            ("https://host/prefix/", "https://host/prefix"),
+            ("https://host/ironnotify/gateway//", "https://host/ironnotify/gateway"),
+        ];
+        for (base_url, root) in shapes {
+            let transport = transport(base_url, "/api/v1");
+            for endpoint in ENDPOINTS {
+                let url = transport.api_url(endpoint);
+                assert_eq!(url, format!("{}/api/v1/{}", root, endpoint), "{}", base_url);
+                assert!(Url::parse(&url).is_ok(), "{}", url);
+                assert_eq!(transport.api_url(&format!("/{}", endpoint)), url);
+            }
+            assert_eq!(join_url(base_url, &["health"]), format!("{}/health", root));
+        }
+    }
+
+    #[test]
+    fn path_prefix_shapes_are_normalized() {
+        let shapes = [
+            ("/api/v1", "https://host/gw/api/v1/notify"),
+            ("api/v1", "https://host/gw/api/v1/notify"),
+            ("/api/v1/", "https://host/gw/api/v1/notify"),
+            ("/v2", "https://host/gw/v2/notify"),
+            ("", "https://host/gw/notify"),
+            ("/", "https://host/gw/notify"),
+        ];
+        for (prefix, expected) in shapes {
+            assert_eq!(transport("https://host/gw/", prefix).api_url("notify"), expected);
+        }
+    }
+
+    #[test]
+    fn requests_carry_a_sensitive_authorization_header() {
+        let transport = transport("https://host", "/api/v1");
+        let request = transport.request(Method::GET, "project").build().unwrap();
+        let authorization = &request.headers()[AUTHORIZATION];
+        assert_eq!(authorization, "Bearer ak_test_x");
+        assert!(authorization.is_sensitive());
+        assert!(!format!("{:?}", request.headers()).contains("ak_test_x"));
+    }
+
+    #[test]
+    fn join_url_skips_empty_segments() {
+        assert_eq!(join_url("https://host/", &["", "/a/", "b"]), "https://host/a/b");
+        assert_eq!(join_url("https://host", &[]), "https://host");
+    }
+}
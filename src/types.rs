@@ -1,11 +1,31 @@
 //! Type definitions for IronNotify SDK.
 
-use chrono::{DateTime, Utc};
+use crate::api_version::parse_actions;
+use crate::hash::{canonical_json, sha256_hex};
+use crate::redact::REDACTED;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::value::RawValue;
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 
-/// Severity level for notifications.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// Maximum number of title characters shown by `Display` implementations.
+const DISPLAY_TITLE_LENGTH: usize = 80;
+
+/// Truncates text to `max` characters, appending an ellipsis when shortened.
+pub(crate) fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Severity level for notifications, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum SeverityLevel {
     #[default]
@@ -29,6 +49,58 @@ impl std::fmt::Display for SeverityLevel {
     }
 }
 
+impl std::str::FromStr for SeverityLevel {
+    type Err = String;
+
+    /// Parses a severity name as written by `Display`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Self::Info),
+            "success" => Ok(Self::Success),
+            "warning" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            "critical" => Ok(Self::Critical),
+            _ => Err(format!("Unknown severity: {}", s)),
+        }
+    }
+}
+
+/// Whether an API key belongs to the live or the test environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyEnvironment {
+    /// An `ak_live_` key.
+    Live,
+    /// An `ak_test_` key.
+    Test,
+    /// A key without a recognized prefix.
+    Other,
+}
+
+impl KeyEnvironment {
+    /// Gets the environment of an API key from its prefix.
+    pub fn from_api_key(api_key: &str) -> Self {
+        if api_key.starts_with("ak_live_") {
+            Self::Live
+        } else if api_key.starts_with("ak_test_") {
+            Self::Test
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl std::fmt::Display for KeyEnvironment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Live => "live",
+            Self::Test => "test",
+            Self::Other => "other",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 /// WebSocket connection state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ConnectionState {
@@ -51,6 +123,60 @@ impl std::fmt::Display for ConnectionState {
     }
 }
 
+/// When a notification expires, as a relative duration or an absolute time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiresIn {
+    /// Expires after a chrono duration.
+    Chrono(Duration),
+    /// Expires after a std duration.
+    Std(std::time::Duration),
+    /// Expires at a specific time.
+    At(DateTime<Utc>),
+}
+
+impl ExpiresIn {
+    /// Resolves the expiration time relative to `now`.
+    ///
+    /// Durations too large to represent saturate to the maximum representable time
+    /// rather than wrapping or expiring immediately.
+    pub fn resolve(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Chrono(duration) => saturating_add(now, duration),
+            Self::Std(duration) => match Duration::from_std(duration) {
+                Ok(duration) => saturating_add(now, duration),
+                Err(_) => DateTime::<Utc>::MAX_UTC,
+            },
+            Self::At(time) => time,
+        }
+    }
+}
+
+fn saturating_add(now: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+    now.checked_add_signed(duration).unwrap_or(if duration < Duration::zero() {
+        DateTime::<Utc>::MIN_UTC
+    } else {
+        DateTime::<Utc>::MAX_UTC
+    })
+}
+
+impl From<Duration> for ExpiresIn {
+    fn from(duration: Duration) -> Self {
+        Self::Chrono(duration)
+    }
+}
+
+impl From<std::time::Duration> for ExpiresIn {
+    fn from(duration: std::time::Duration) -> Self {
+        Self::Std(duration)
+    }
+}
+
+impl From<DateTime<Utc>> for ExpiresIn {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self::At(time)
+    }
+}
+
 /// Action button on a notification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationAction {
@@ -101,7 +227,148 @@ impl NotificationAction {
     }
 }
 
+/// A set of delivery channels, combined with `|`.
+///
+/// ```
+/// use ironnotify::DeliveryChannels;
+///
+/// let channels = DeliveryChannels::EMAIL | DeliveryChannels::PUSH;
+/// assert!(channels.contains(DeliveryChannels::EMAIL));
+/// assert!(!channels.contains(DeliveryChannels::IN_APP));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DeliveryChannels(u8);
+
+impl DeliveryChannels {
+    /// No channels.
+    pub const NONE: Self = Self(0);
+    /// The in-app notification feed.
+    pub const IN_APP: Self = Self(1);
+    /// Email.
+    pub const EMAIL: Self = Self(1 << 1);
+    /// Mobile push.
+    pub const PUSH: Self = Self(1 << 2);
+    /// The project's webhooks.
+    pub const WEBHOOK: Self = Self(1 << 3);
+    /// Every channel.
+    pub const ALL: Self = Self(0b1111);
+
+    /// Names of the channels, as sent to the API.
+    const NAMES: [(Self, &'static str); 4] = [
+        (Self::IN_APP, "in_app"),
+        (Self::EMAIL, "email"),
+        (Self::PUSH, "push"),
+        (Self::WEBHOOK, "webhook"),
+    ];
+
+    /// Checks whether every channel in `other` is in this set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Checks whether the set has no channels.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Gets the set without the channels in `other`.
+    pub fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Gets the API names of the channels in the set, e.g. `["email", "push"]`.
+    pub fn names(self) -> Vec<String> {
+        Self::NAMES
+            .iter()
+            .filter(|(channel, _)| self.contains(*channel))
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+}
+
+impl std::ops::BitOr for DeliveryChannels {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for DeliveryChannels {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl std::fmt::Display for DeliveryChannels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        write!(f, "{}", self.names().join(", "))
+    }
+}
+
+/// How the server collapses notifications sharing a group key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBehavior {
+    /// Each notification replaces the previous one in the group.
+    Replace,
+    /// Notifications are stacked under the group's card.
+    Stack,
+    /// Only the group summary is shown, with a count.
+    Count,
+}
+
+/// Wire names of the fields `NotificationPayload` models, which `extra` must not use.
+pub(crate) const PAYLOAD_FIELDS: [&str; 24] = [
+    "eventType",
+    "title",
+    "message",
+    "severity",
+    "metadata",
+    "actions",
+    "userId",
+    "deviceId",
+    "sessionId",
+    "groupKey",
+    "deduplicationKey",
+    "deduplicationWindowSeconds",
+    "expiresAt",
+    "tags",
+    "category",
+    "ordered",
+    "requiresAck",
+    "ackDeadlineSeconds",
+    "channels",
+    "requestHeaders",
+    "rollout",
+    "groupSummaryTemplate",
+    "groupCountBehavior",
+    "attachments",
+];
+
 /// Payload for creating a notification.
+///
+/// Fields the SDK does not model go in `extra`, sent as top-level body fields under
+/// their own names while the modeled fields keep their camelCase names:
+///
+/// ```
+/// use ironnotify::NotificationPayload;
+///
+/// let mut payload = NotificationPayload::new("order.created", "New order");
+/// payload.extra.insert("workflow_id".to_string(), "wf_42".into());
+///
+/// let body = serde_json::to_value(&payload).unwrap();
+/// assert_eq!(body["eventType"], "order.created");
+/// assert_eq!(body["workflow_id"], "wf_42");
+///
+/// // Modeled fields are not collected into `extra` when reading it back.
+/// let read: NotificationPayload = serde_json::from_value(body).unwrap();
+/// assert_eq!(read.event_type, "order.created");
+/// assert_eq!(read.extra.len(), 1);
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NotificationPayload {
@@ -117,12 +384,80 @@ pub struct NotificationPayload {
     pub actions: Option<Vec<NotificationAction>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deduplication_key: Option<String>,
+    /// Seconds the server drops payloads with the same deduplication key, overriding
+    /// its default window.
+    ///
+    /// ```
+    /// use ironnotify::NotificationPayload;
+    ///
+    /// let mut payload = NotificationPayload::new("heartbeat.missed", "Heartbeat missed");
+    /// payload.deduplication_key = Some("heartbeat:api-1".to_string());
+    /// payload.deduplication_window_seconds = Some(300);
+    ///
+    /// let body = serde_json::to_value(&payload).unwrap();
+    /// assert_eq!(body["deduplicationWindowSeconds"], 300);
+    /// let parsed: NotificationPayload = serde_json::from_value(body).unwrap();
+    /// assert_eq!(parsed.deduplication_window_seconds, Some(300));
+    /// ```
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplication_window_seconds: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// User-facing category, overriding the server default for the event type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Send strictly after earlier ordered payloads with the same group key.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ordered: bool,
+    /// Requires a recipient to acknowledge the notification.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_ack: bool,
+    /// Seconds the recipient has to acknowledge before the server escalates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ack_deadline_seconds: Option<u64>,
+    /// Channels to deliver through, e.g. `email`, overriding the project default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<String>>,
+    /// HTTP headers sent with this payload's request, e.g. for gateway routing. Kept
+    /// in the offline queue but left out of the request body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_headers: Option<BTreeMap<String, String>>,
+    /// Percentage of the audience, in (0, 100], the server delivers to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<f32>,
+    /// Summary shown on the collapsed group card, with `{{count}}` and metadata
+    /// placeholders rendered by the server.
+    #[serde(
+        default,
+        rename = "groupSummaryTemplate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub group_summary: Option<String>,
+    /// How the server collapses the group.
+    #[serde(
+        default,
+        rename = "groupCountBehavior",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub group_behavior: Option<GroupBehavior>,
+    /// Files sent as parts of a multipart request. Kept in the offline queue, base64
+    /// encoded, but left out of the payload part.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<Attachment>>,
+    /// Body fields the SDK does not model, e.g. for a self-hosted server. Keys must
+    /// not be the wire name of a modeled field; `EventBuilder` rejects those.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 impl NotificationPayload {
@@ -136,14 +471,153 @@ impl NotificationPayload {
             metadata: None,
             actions: None,
             user_id: None,
+            device_id: None,
+            session_id: None,
             group_key: None,
             deduplication_key: None,
+            deduplication_window_seconds: None,
             expires_at: None,
+            tags: None,
+            category: None,
+            ordered: false,
+            requires_ack: false,
+            ack_deadline_seconds: None,
+            channels: None,
+            request_headers: None,
+            rollout: None,
+            group_summary: None,
+            group_behavior: None,
+            attachments: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Gets the group key that orders this payload, if it is ordered.
+    pub fn ordering_key(&self) -> Option<&str> {
+        if self.ordered {
+            self.group_key.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Gets a stable SHA-256 hash of the payload's content, as lowercase hex.
+    ///
+    /// Covers the fields the server deduplicates on: the event type, title, message,
+    /// severity, metadata, actions, user, device and session IDs, group key,
+    /// deduplication key, tags, and category. Every other field is left out:
+    /// `deduplication_window_seconds`, `expires_at`, `ordered`, `requires_ack`,
+    /// `ack_deadline_seconds`, `channels`, `request_headers`, `rollout`,
+    /// `group_summary`, `group_behavior`, `attachments`, and `extra`.
+    ///
+    /// Metadata key order does not affect the result, and the hash is the same
+    /// across runs, platforms, and crate versions.
+    pub fn content_hash(&self) -> String {
+        let content = serde_json::json!({
+            "eventType": self.event_type,
+            "title": self.title,
+            "message": self.message,
+            "severity": self.severity.unwrap_or_default(),
+            "metadata": self.metadata,
+            "actions": self.actions,
+            "userId": self.user_id,
+            "deviceId": self.device_id,
+            "sessionId": self.session_id,
+            "groupKey": self.group_key,
+            "deduplicationKey": self.deduplication_key,
+            "tags": self.tags,
+            "category": self.category,
+        });
+
+        let mut canonical = String::new();
+        canonical_json(&content, &mut canonical);
+        sha256_hex(canonical.as_bytes())
+    }
+
+    /// Gets the size in bytes of the payload's JSON body, as sent to the API.
+    pub fn estimated_wire_size(&self) -> usize {
+        let bytes = if self.request_headers.is_some() || self.attachments.is_some() {
+            serde_json::to_vec(&self.wire_value())
+        } else {
+            serde_json::to_vec(self)
+        };
+        bytes.map(|bytes| bytes.len()).unwrap_or(0)
+    }
+
+    /// Gets the total size in bytes of the attachments' contents.
+    pub fn attachment_size(&self) -> usize {
+        self.attachments
+            .iter()
+            .flatten()
+            .map(|attachment| attachment.content.len())
+            .sum()
+    }
+
+    /// Gets the attachments to send, if there are any.
+    pub(crate) fn attached(&self) -> Option<&[Attachment]> {
+        self.attachments
+            .as_deref()
+            .filter(|attachments| !attachments.is_empty())
+    }
+
+    /// Gets the request body, without the fields sent as headers or parts.
+    pub(crate) fn wire_value(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(object) = value.as_object_mut() {
+            object.remove("requestHeaders");
+            object.remove("attachments");
+        }
+        value
+    }
+}
+
+/// Copies a received notification's content for sending it again, e.g. to escalate
+/// it to another user.
+///
+/// Carries over the event type, title, message, severity, metadata, actions, group
+/// key, tags, and category. Recipients, read state, and timestamps are not copied,
+/// and `expiresAt` only when it is still in the future.
+impl From<&Notification> for NotificationPayload {
+    fn from(notification: &Notification) -> Self {
+        Self {
+            message: notification.message.clone(),
+            severity: Some(notification.severity),
+            metadata: notification.metadata.clone(),
+            actions: notification.actions.clone(),
+            group_key: notification.group_key.clone(),
+            expires_at: notification.expires_at.filter(|at| *at > Utc::now()),
+            tags: notification.tags.clone(),
+            category: notification.category.clone(),
+            ..Self::new(notification.event_type.clone(), notification.title.clone())
         }
     }
 }
 
 /// A notification received from the server.
+///
+/// Fields the SDK does not model are kept in `extra` and serialized back under
+/// their own names:
+///
+/// ```
+/// use ironnotify::Notification;
+///
+/// let body = serde_json::json!({
+///     "id": "n_1",
+///     "eventType": "order.created",
+///     "title": "New order",
+///     "severity": "info",
+///     "read": false,
+///     "createdAt": "2024-01-01T00:00:00Z",
+///     "workflowId": "wf_42",
+/// });
+/// let notification: Notification = serde_json::from_value(body.clone()).unwrap();
+/// assert_eq!(notification.event_type, "order.created");
+/// assert_eq!(notification.extra["workflowId"], "wf_42");
+/// assert!(!notification.extra.contains_key("eventType"));
+///
+/// let written = serde_json::to_value(&notification).unwrap();
+/// assert_eq!(written["workflowId"], "wf_42");
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Notification {
@@ -160,20 +634,458 @@ pub struct Notification {
     #[serde(default)]
     pub user_id: Option<String>,
     #[serde(default)]
+    pub device_id: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
     pub group_key: Option<String>,
     pub read: bool,
     pub created_at: DateTime<Utc>,
     #[serde(default)]
     pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    /// User-facing category, e.g. `Activity` or `Alerts`.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Whether a recipient must acknowledge the notification.
+    #[serde(default)]
+    pub requires_ack: bool,
+    /// Who acknowledged the notification.
+    #[serde(default)]
+    pub acknowledged_by: Option<String>,
+    /// When the notification was acknowledged.
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Channels the notification was delivered through, when the server reports them.
+    #[serde(default)]
+    pub delivered_via: Option<Vec<String>>,
+    /// Number of notifications in this notification's group, when the server reports it.
+    #[serde(default)]
+    pub group_count: Option<i32>,
+    /// Fields sent by the server that the SDK does not model.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Notification {
+    /// Checks whether the notification is snoozed at the given time.
+    pub fn is_snoozed_at(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    /// Checks whether the notification is currently snoozed.
+    pub fn is_snoozed(&self) -> bool {
+        self.is_snoozed_at(Utc::now())
+    }
+
+    /// Checks whether the notification counts as unread at the given time: not read,
+    /// not snoozed, and not expired.
+    ///
+    /// Archived notifications are not returned by the server, so they never count.
+    pub fn is_effectively_unread(&self, now: DateTime<Utc>) -> bool {
+        let expired = self.expires_at.is_some_and(|at| at <= now);
+        !self.read && !self.is_snoozed_at(now) && !expired
+    }
+
+    /// Checks whether the notification requires acknowledgement and has not been acknowledged.
+    pub fn is_awaiting_ack(&self) -> bool {
+        self.requires_ack && self.acknowledged_at.is_none()
+    }
+
+    /// Gets a hash of the notification's content, excluding its ID, read state, and snooze.
+    ///
+    /// Metadata key order does not affect the result. The value is only comparable
+    /// within one process, so do not persist it.
+    pub fn content_fingerprint(&self) -> u64 {
+        let metadata: Option<BTreeMap<&String, &serde_json::Value>> =
+            self.metadata.as_ref().map(|metadata| metadata.iter().collect());
+        let content = serde_json::json!({
+            "eventType": self.event_type,
+            "title": self.title,
+            "message": self.message,
+            "severity": self.severity,
+            "metadata": metadata,
+            "actions": self.actions,
+            "userId": self.user_id,
+            "deviceId": self.device_id,
+            "sessionId": self.session_id,
+            "groupKey": self.group_key,
+            "groupCount": self.group_count,
+            "createdAt": self.created_at,
+            "expiresAt": self.expires_at,
+            "tags": self.tags,
+            "category": self.category,
+        });
+
+        let mut hasher = DefaultHasher::new();
+        content.to_string().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Formats the notification like `Debug`, with metadata values replaced by `[REDACTED]`.
+    pub fn redacted_debug(&self) -> String {
+        let metadata = self.metadata.as_ref().map(|metadata| {
+            metadata
+                .keys()
+                .map(|key| (key.clone(), serde_json::Value::from(REDACTED)))
+                .collect()
+        });
+        format!(
+            "{:?}",
+            Notification {
+                metadata,
+                ..self.clone()
+            }
+        )
+    }
+}
+
+impl std::fmt::Display for Notification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} [{}] \"{}\" ({}, created {})",
+            self.id,
+            self.event_type,
+            self.severity,
+            truncate_chars(&self.title, DISPLAY_TITLE_LENGTH),
+            if self.read { "read" } else { "unread" },
+            self.created_at.to_rfc3339()
+        )
+    }
+}
+
+/// A notification borrowed from a response body, for reading large lists without
+/// allocating every field.
+///
+/// Strings borrow from the body unless they contain escapes. Metadata and actions
+/// are kept as raw JSON; `to_notification` parses them. Both the v1 and v2 API
+/// shapes are accepted. Fields the SDK does not model are skipped, so
+/// `to_notification` leaves `extra` empty.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRef<'a> {
+    #[serde(borrow)]
+    pub id: Cow<'a, str>,
+    #[serde(borrow)]
+    pub event_type: Cow<'a, str>,
+    #[serde(borrow)]
+    pub title: Cow<'a, str>,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub message: Option<Cow<'a, str>>,
+    pub severity: SeverityLevel,
+    #[serde(default, borrow)]
+    pub metadata: Option<&'a RawValue>,
+    #[serde(default, borrow)]
+    pub actions: Option<&'a RawValue>,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub user_id: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub device_id: Option<Cow<'a, str>>,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub session_id: Option<Cow<'a, str>>,
+    #[serde(default, borrow, alias = "threadId", deserialize_with = "borrowed::str")]
+    pub group_key: Option<Cow<'a, str>>,
+    pub read: bool,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    #[serde(default, borrow, deserialize_with = "borrowed::strs")]
+    pub tags: Option<Vec<Cow<'a, str>>>,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub category: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub requires_ack: bool,
+    #[serde(default, borrow, deserialize_with = "borrowed::str")]
+    pub acknowledged_by: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    #[serde(default, borrow, deserialize_with = "borrowed::strs")]
+    pub delivered_via: Option<Vec<Cow<'a, str>>>,
+    #[serde(default)]
+    pub group_count: Option<i32>,
+}
+
+impl NotificationRef<'_> {
+    /// Checks whether the notification is snoozed at the given time.
+    pub fn is_snoozed_at(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    /// Copies the notification into an owned `Notification`, parsing its metadata and actions.
+    pub fn to_notification(&self) -> Result<Notification, serde_json::Error> {
+        let owned = |value: &Option<Cow<'_, str>>| value.as_deref().map(str::to_string);
+        Ok(Notification {
+            id: self.id.to_string(),
+            event_type: self.event_type.to_string(),
+            title: self.title.to_string(),
+            message: owned(&self.message),
+            severity: self.severity,
+            metadata: self.metadata.map(|raw| serde_json::from_str(raw.get())).transpose()?,
+            actions: self.actions.map(|raw| parse_actions(raw.get())).transpose()?,
+            user_id: owned(&self.user_id),
+            device_id: owned(&self.device_id),
+            session_id: owned(&self.session_id),
+            group_key: owned(&self.group_key),
+            read: self.read,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            snoozed_until: self.snoozed_until,
+            tags: self
+                .tags
+                .as_ref()
+                .map(|tags| tags.iter().map(|tag| tag.to_string()).collect()),
+            category: owned(&self.category),
+            requires_ack: self.requires_ack,
+            acknowledged_by: owned(&self.acknowledged_by),
+            acknowledged_at: self.acknowledged_at,
+            delivered_via: self
+                .delivered_via
+                .as_ref()
+                .map(|channels| channels.iter().map(|channel| channel.to_string()).collect()),
+            group_count: self.group_count,
+            extra: HashMap::new(),
+        })
+    }
+}
+
+/// Borrowing deserializers for optional strings.
+///
+/// serde only borrows a `Cow<str>` that is a field itself; one inside an `Option`
+/// or `Vec` is always copied.
+mod borrowed {
+    use serde::{Deserialize, Deserializer};
+    use std::borrow::Cow;
+
+    #[derive(Deserialize)]
+    struct Text<'a>(#[serde(borrow)] Cow<'a, str>);
+
+    pub fn str<'de: 'a, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Cow<'a, str>>, D::Error> {
+        Ok(Option::<Text<'a>>::deserialize(deserializer)?.map(|text| text.0))
+    }
+
+    pub fn strs<'de: 'a, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<Cow<'a, str>>>, D::Error> {
+        let texts = Option::<Vec<Text<'a>>>::deserialize(deserializer)?;
+        Ok(texts.map(|texts| texts.into_iter().map(|text| text.0).collect()))
+    }
+}
+
+/// Project details returned when verifying credentials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInfo {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    /// Key environment, `live` or `test`.
+    pub environment: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub rate_limit_tier: Option<String>,
+}
+
+/// An entry in a notification's activity history.
+///
+/// Unknown entry kinds from newer servers are returned as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationEvent {
+    Created {
+        timestamp: DateTime<Utc>,
+    },
+    Delivered {
+        timestamp: DateTime<Utc>,
+    },
+    Read {
+        timestamp: DateTime<Utc>,
+    },
+    ActionTriggered {
+        action: String,
+        actor: Option<String>,
+        timestamp: DateTime<Utc>,
+    },
+    Expired {
+        timestamp: DateTime<Utc>,
+    },
+    Other {
+        kind: String,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl NotificationEvent {
+    /// Gets when the event happened.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match *self {
+            Self::Created { timestamp }
+            | Self::Delivered { timestamp }
+            | Self::Read { timestamp }
+            | Self::ActionTriggered { timestamp, .. }
+            | Self::Expired { timestamp }
+            | Self::Other { timestamp, .. } => timestamp,
+        }
+    }
+}
+
+/// Wire representation of a history entry.
+#[derive(Deserialize)]
+struct RawNotificationEvent {
+    #[serde(rename = "type", alias = "kind")]
+    kind: String,
+    timestamp: DateTime<Utc>,
+    #[serde(default)]
+    action: Option<String>,
+    #[serde(default)]
+    actor: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for NotificationEvent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawNotificationEvent::deserialize(deserializer)?;
+        let timestamp = raw.timestamp;
+        Ok(match raw.kind.as_str() {
+            "created" => Self::Created { timestamp },
+            "delivered" => Self::Delivered { timestamp },
+            "read" => Self::Read { timestamp },
+            "action_triggered" => match raw.action {
+                Some(action) => Self::ActionTriggered {
+                    action,
+                    actor: raw.actor,
+                    timestamp,
+                },
+                None => Self::Other {
+                    kind: raw.kind,
+                    timestamp,
+                },
+            },
+            "expired" => Self::Expired { timestamp },
+            _ => Self::Other {
+                kind: raw.kind,
+                timestamp,
+            },
+        })
+    }
+}
+
+/// A file sent with a notification, from `EventBuilder::attach_file`.
+///
+/// ```
+/// use ironnotify::Attachment;
+///
+/// let attachment = Attachment::new("report.csv", "text/csv", b"day,errors\n1,4\n".to_vec());
+/// let stored = serde_json::to_value(&attachment).unwrap();
+/// assert_eq!(stored["content"], "ZGF5LGVycm9ycwoxLDQK");
+/// let read: Attachment = serde_json::from_value(stored).unwrap();
+/// assert_eq!(read, attachment);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    /// File name shown to recipients.
+    pub name: String,
+    /// MIME type, e.g. `image/png`.
+    pub content_type: String,
+    /// File contents, serialized as base64.
+    #[serde(with = "base64_content")]
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    /// Creates an attachment.
+    pub fn new(
+        name: impl Into<String>,
+        content_type: impl Into<String>,
+        content: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            content_type: content_type.into(),
+            content: content.into(),
+        }
+    }
+}
+
+mod base64_content {
+    use super::{Engine, STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(content: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(content))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Details of a downloaded attachment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadInfo {
+    /// Value of the `Content-Type` response header.
+    pub content_type: Option<String>,
+    /// Number of bytes written.
+    pub bytes: u64,
 }
 
 /// Result of sending a notification.
+///
+/// `Display` gives a one-line summary suitable for logs.
 #[derive(Debug, Clone)]
 pub struct SendResult {
     pub success: bool,
     pub notification_id: Option<String>,
     pub error: Option<String>,
     pub queued: bool,
+    /// Why the notification was queued instead of sent.
+    pub queue_reason: Option<QueueReason>,
+    /// `content_hash` of the payload, also sent as the `X-Content-Hash` header.
+    pub content_hash: Option<String>,
+    /// URLs the server stored the payload's attachments at, in the order they were
+    /// attached.
+    pub attachment_urls: Vec<String>,
+    /// HTTP status of the API's response, or `None` if the request got no response.
+    pub status: Option<u16>,
+}
+
+/// Why a notification was queued instead of sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueReason {
+    /// The API could not be reached.
+    Offline,
+    /// The circuit breaker is open after repeated failures.
+    CircuitOpen,
+    /// The notification is deferred until quiet hours end.
+    QuietHours,
+    /// Sending is paused with `NotifyClient::pause`.
+    Paused,
+    /// The rate limit is below `rate_limit_threshold` until the window resets.
+    RateLimited,
+    /// An earlier ordered notification with the same group key is still queued.
+    GroupOrder,
+}
+
+impl std::fmt::Display for QueueReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Offline => "offline",
+            Self::CircuitOpen => "circuit open",
+            Self::QuietHours => "quiet hours",
+            Self::Paused => "paused",
+            Self::RateLimited => "rate limited",
+            Self::GroupOrder => "group order",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 impl SendResult {
@@ -184,6 +1096,10 @@ impl SendResult {
             notification_id,
             error: None,
             queued: false,
+            queue_reason: None,
+            content_hash: None,
+            attachment_urls: Vec::new(),
+            status: None,
         }
     }
 
@@ -194,16 +1110,209 @@ impl SendResult {
             notification_id: None,
             error: Some(error.into()),
             queued: false,
+            queue_reason: None,
+            content_hash: None,
+            attachment_urls: Vec::new(),
+            status: None,
+        }
+    }
+
+    /// Checks whether the failure points at the API being unavailable rather than at
+    /// the request: no response, a 5xx status, or 429 Too Many Requests.
+    pub(crate) fn is_outage(&self) -> bool {
+        match self.status {
+            Some(status) => status >= 500 || status == 429,
+            None => true,
         }
     }
 
-    /// Creates a queued result.
+    /// Creates a queued result for a notification that could not reach the API.
     pub fn queued(error: impl Into<String>) -> Self {
+        Self::queued_with_reason(QueueReason::Offline, error)
+    }
+
+    /// Creates a queued result with the given reason.
+    pub fn queued_with_reason(reason: QueueReason, error: impl Into<String>) -> Self {
         Self {
             success: false,
             notification_id: None,
             error: Some(error.into()),
             queued: true,
+            queue_reason: Some(reason),
+            content_hash: None,
+            attachment_urls: Vec::new(),
+            status: None,
+        }
+    }
+}
+
+/// A successfully sent notification, published on the in-process local event bus.
+#[derive(Debug, Clone)]
+pub struct LocalEvent {
+    /// Server-assigned notification ID, when the API returned one.
+    pub notification_id: Option<String>,
+    /// The payload that was sent.
+    pub payload: NotificationPayload,
+}
+
+impl std::fmt::Display for SendResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let error = self.error.as_deref().unwrap_or("unknown error");
+        if self.success {
+            match self.notification_id {
+                Some(ref id) => write!(f, "sent ({})", id),
+                None => write!(f, "sent"),
+            }
+        } else if self.queued {
+            write!(f, "queued: {}", error)
+        } else {
+            write!(f, "failed: {}", error)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Hashes recorded when content hashing was added. A change here changes the
+    /// `X-Content-Hash` of every send, so it needs a release note, not a new value.
+    const MINIMAL_HASH: &str = "f26500a597d351456939fa8a10d282c6155f7becdda515ee4c2efe6510dee904";
+    const FULL_HASH: &str = "1c4b7f9f42c4c45a09cb36848f62c0a82c7374562b607faa315e4888e0a79161";
+
+    fn full_payload(metadata: &[(&str, serde_json::Value)]) -> NotificationPayload {
+        let mut payload = NotificationPayload::new("order.created", "New order");
+        payload.message = Some("Order #1234 for café".to_string());
+        payload.severity = Some(SeverityLevel::Error);
+        payload.metadata = Some(
+            metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        );
+        payload.actions = Some(vec![NotificationAction::with_url("View", "/orders/1234")]);
+        payload.user_id = Some("user-1".to_string());
+        payload.group_key = Some("orders".to_string());
+        payload.deduplication_key = Some("order:1234".to_string());
+        payload.tags = Some(vec!["a".to_string(), "b".to_string()]);
+        payload.category = Some("Activity".to_string());
+        payload
+    }
+
+    fn metadata() -> Vec<(&'static str, serde_json::Value)> {
+        vec![
+            ("total", serde_json::json!(42)),
+            ("customer", serde_json::json!({ "tier": "gold", "name": "Zoë" })),
+            ("items", serde_json::json!([1, "two", null, true])),
+        ]
+    }
+
+    #[test]
+    fn content_hash_of_a_minimal_payload_is_fixed() {
+        let payload = NotificationPayload::new("order.created", "New order");
+        assert_eq!(payload.content_hash(), MINIMAL_HASH);
+    }
+
+    #[test]
+    fn content_hash_of_a_full_payload_is_fixed() {
+        assert_eq!(full_payload(&metadata()).content_hash(), FULL_HASH);
+    }
+
+    #[test]
+    fn content_hash_ignores_metadata_order() {
+        let mut reversed = metadata();
+        reversed.reverse();
+        assert_eq!(full_payload(&reversed).content_hash(), FULL_HASH);
+
+        // Many keys make differing HashMap iteration orders likely.
+        let keys: Vec<String> = (0..64).map(|i| format!("key-{}", i)).collect();
+        let forward: Vec<(&str, serde_json::Value)> =
+            keys.iter().map(|key| (key.as_str(), serde_json::json!(key))).collect();
+        let backward: Vec<(&str, serde_json::Value)> = forward.iter().rev().cloned().collect();
+        assert_eq!(
+            full_payload(&forward).content_hash(),
+            full_payload(&backward).content_hash()
+        );
+    }
+
+    #[test]
+    fn content_hash_ignores_delivery_settings() {
+        let mut payload = full_payload(&metadata());
+        payload.expires_at = Some(Utc::now());
+        payload.ordered = true;
+        payload.requires_ack = true;
+        payload.ack_deadline_seconds = Some(60);
+        payload.deduplication_window_seconds = Some(300);
+        payload.channels = Some(vec!["email".to_string()]);
+        payload.rollout = Some(50.0);
+        payload.group_summary = Some("{{count}} orders".to_string());
+        payload.extra.insert("region".to_string(), serde_json::json!("eu"));
+        assert_eq!(payload.content_hash(), FULL_HASH);
+
+        payload.title = "Other order".to_string();
+        assert_ne!(payload.content_hash(), FULL_HASH);
+    }
+
+    #[test]
+    fn received_notifications_convert_to_their_content_only() {
+        use crate::test_support::{received_notification, resent_payload};
+
+        let future = Utc::now() + chrono::Duration::hours(1);
+        let payload = NotificationPayload::from(&received_notification(future));
+        let expected = resent_payload(Some(future));
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        let past = Utc::now() - chrono::Duration::hours(1);
+        let payload = NotificationPayload::from(&received_notification(past));
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::to_value(resent_payload(None)).unwrap()
+        );
+    }
+
+    #[test]
+    fn notification_ref_borrows_optional_strings_from_the_body() {
+        let body = r#"{
+            "id": "n1", "eventType": "order.created", "title": "Order", "severity": "info",
+            "message": "Shipped", "userId": "u1", "threadId": "orders", "category": "a\"b",
+            "tags": ["billing", "eu"], "deliveredVia": ["email"],
+            "read": false, "createdAt": "2026-10-14T12:00:00Z"
+        }"#;
+        let item: NotificationRef<'_> = serde_json::from_str(body).unwrap();
+        let borrowed = |value: &Option<Cow<'_, str>>| matches!(value, Some(Cow::Borrowed(_)));
+        assert!(borrowed(&item.message));
+        assert!(borrowed(&item.user_id));
+        assert!(borrowed(&item.group_key));
+        assert!(item.tags.iter().flatten().all(|tag| matches!(tag, Cow::Borrowed(_))));
+        assert!(matches!(item.delivered_via.as_deref(), Some([Cow::Borrowed("email")])));
+        // Escaped strings cannot borrow.
+        assert!(matches!(item.category, Some(Cow::Owned(ref category)) if category == "a\"b"));
+        assert_eq!(item.session_id, None);
+    }
+
+    #[test]
+    fn expirations_too_far_away_saturate() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap();
+        let max = DateTime::<Utc>::MAX_UTC;
+        let min = DateTime::<Utc>::MIN_UTC;
+
+        assert_eq!(ExpiresIn::Std(std::time::Duration::MAX).resolve(now), max);
+        assert_eq!(ExpiresIn::Chrono(Duration::MAX).resolve(now), max);
+        assert_eq!(ExpiresIn::Chrono(Duration::MIN).resolve(now), min);
+        assert_eq!(ExpiresIn::Chrono(Duration::seconds(1)).resolve(max), max);
+        assert_eq!(ExpiresIn::Std(std::time::Duration::from_secs(1)).resolve(max), max);
+        assert_eq!(ExpiresIn::Chrono(Duration::seconds(-1)).resolve(min), min);
+        assert_eq!(ExpiresIn::At(max).resolve(now), max);
+
+        // Durations that fit are added exactly, up to the last representable time.
+        let to_max = max - now;
+        assert_eq!(ExpiresIn::Chrono(to_max).resolve(now), max);
+        assert_eq!(ExpiresIn::Std(to_max.to_std().unwrap()).resolve(now), max);
+        let hour = std::time::Duration::from_secs(3600);
+        assert_eq!(ExpiresIn::Std(hour).resolve(now), now + Duration::hours(1));
+    }
+}
@@ -0,0 +1,676 @@
+//! Minimal WebSocket client (RFC 6455) for IronNotify SDK.
+//!
+//! Covers what the real-time connection needs: the client handshake over TCP or TLS,
+//! masked frames, fragmented messages, and reads that time out without losing a
+//! partly received frame.
+
+use crate::config::NotifyOptions;
+use crate::hash::sha1;
+#[cfg(feature = "tls-pinning")]
+use crate::pinning::{self, PinMismatches};
+use base64::Engine;
+use reqwest::Url;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+#[cfg(feature = "tls-pinning")]
+use std::sync::Arc;
+use std::time::Duration;
+
+/// GUID appended to the handshake key (RFC 6455 section 1.3).
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest accepted handshake response head.
+const MAX_HEAD_LEN: usize = 16 * 1024;
+
+/// Largest accepted message, across all of its fragments.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Frame opcodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x0 => Self::Continuation,
+            0x1 => Self::Text,
+            0x2 => Self::Binary,
+            0x8 => Self::Close,
+            0x9 => Self::Ping,
+            0xA => Self::Pong,
+            _ => return None,
+        })
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+
+    /// Checks whether this is a control frame, which may arrive between the
+    /// fragments of a message.
+    fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// A decoded frame. Frames returned by `WebSocket::read` are whole messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+/// Gets the `Sec-WebSocket-Accept` value the server answers a handshake key with.
+pub(crate) fn accept_key(key: &str) -> String {
+    let digest = sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// Encodes a final frame, masking the payload if `mask` is given. Clients must
+/// mask their frames; servers must not.
+pub(crate) fn encode_frame(opcode: Opcode, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode.to_u8());
+
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    match mask {
+        Some(key) => {
+            frame.extend_from_slice(&key);
+            frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ key[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+/// Decodes the frame at the start of `buffer` and removes it, unmasking the payload.
+///
+/// Returns `None`, leaving the buffer as is, until it holds a whole frame.
+pub(crate) fn decode_frame(buffer: &mut Vec<u8>) -> io::Result<Option<Frame>> {
+    if buffer.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buffer[0] & 0x80 != 0;
+    let opcode = Opcode::from_u8(buffer[0] & 0x0F)
+        .ok_or_else(|| invalid_data(format!("unknown opcode {:#x}", buffer[0] & 0x0F)))?;
+    let masked = buffer[1] & 0x80 != 0;
+
+    let (len, mut offset) = match buffer[1] & 0x7F {
+        126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as u64, 4),
+        127 if buffer.len() >= 10 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&buffer[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        126 | 127 => return Ok(None),
+        len => (len as u64, 2),
+    };
+    if len > MAX_MESSAGE_LEN as u64 {
+        return Err(invalid_data(format!("frame of {} bytes is too large", len)));
+    }
+
+    let mask = if masked {
+        if buffer.len() < offset + 4 {
+            return Ok(None);
+        }
+        let key = [
+            buffer[offset],
+            buffer[offset + 1],
+            buffer[offset + 2],
+            buffer[offset + 3],
+        ];
+        offset += 4;
+        Some(key)
+    } else {
+        None
+    };
+
+    let end = offset + len as usize;
+    if buffer.len() < end {
+        return Ok(None);
+    }
+    let mut payload: Vec<u8> = buffer.drain(..end).skip(offset).collect();
+    if let Some(key) = mask {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= key[i % 4];
+        }
+    }
+    Ok(Some(Frame {
+        fin,
+        opcode,
+        payload,
+    }))
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn tls_error(error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, format!("TLS: {}", error))
+}
+
+/// How `wss://` connections are secured, taken from the client's options.
+#[derive(Clone, Default)]
+pub(crate) struct TlsSettings {
+    /// TLS configuration pinning the WebSocket host to `certificate_pins`, used
+    /// instead of native TLS when pins are set.
+    #[cfg(feature = "tls-pinning")]
+    pinned: Option<(Arc<rustls::ClientConfig>, Arc<PinMismatches>)>,
+}
+
+impl TlsSettings {
+    /// Gets the settings for the client's `ws_url`.
+    #[cfg_attr(not(feature = "tls-pinning"), allow(unused_variables))]
+    pub fn new(options: &NotifyOptions) -> Self {
+        #[cfg(feature = "tls-pinning")]
+        if let Some(ref pins) = options.certificate_pins {
+            let host = Url::parse(&options.ws_url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default();
+            let mismatches = Arc::new(PinMismatches::default());
+            let mut config = pinning::client_config(&host, pins, Arc::clone(&mismatches));
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+            return Self {
+                pinned: Some((Arc::new(config), mismatches)),
+            };
+        }
+        Self::default()
+    }
+
+    /// Opens a TLS session with `host` over `tcp`, completing the handshake.
+    fn connect(&self, host: &str, tcp: TcpStream) -> io::Result<Stream> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        #[cfg(feature = "tls-pinning")]
+        if let Some((ref config, ref mismatches)) = self.pinned {
+            let mut tcp = tcp;
+            let name = rustls::ServerName::try_from(host).map_err(tls_error)?;
+            let mut session =
+                rustls::ClientConnection::new(Arc::clone(config), name).map_err(tls_error)?;
+            while session.is_handshaking() {
+                if let Err(error) = session.complete_io(&mut tcp) {
+                    return Err(match mismatches.take() {
+                        Some(pin) => io::Error::new(
+                            io::ErrorKind::ConnectionAborted,
+                            crate::error::Error::PinMismatch(pin),
+                        ),
+                        None => error,
+                    });
+                }
+            }
+            return Ok(Stream::Pinned(Box::new(rustls::StreamOwned::new(session, tcp))));
+        }
+
+        let connector = native_tls::TlsConnector::new().map_err(tls_error)?;
+        let stream = connector.connect(host, tcp).map_err(tls_error)?;
+        Ok(Stream::Tls(Box::new(stream)))
+    }
+}
+
+/// A plain or TLS connection.
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<native_tls::TlsStream<TcpStream>>),
+    #[cfg(feature = "tls-pinning")]
+    Pinned(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+    /// The server end of a TLS connection, for tests.
+    #[cfg(all(test, feature = "tls-pinning"))]
+    Server(Box<rustls::StreamOwned<rustls::ServerConnection, TcpStream>>),
+}
+
+impl Stream {
+    fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            Self::Tls(stream) => stream.get_ref(),
+            #[cfg(feature = "tls-pinning")]
+            Self::Pinned(stream) => &stream.sock,
+            #[cfg(all(test, feature = "tls-pinning"))]
+            Self::Server(stream) => &stream.sock,
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(stream) => stream.read(buf),
+            #[cfg(feature = "tls-pinning")]
+            Self::Pinned(stream) => stream.read(buf),
+            #[cfg(all(test, feature = "tls-pinning"))]
+            Self::Server(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(stream) => stream.write(buf),
+            #[cfg(feature = "tls-pinning")]
+            Self::Pinned(stream) => stream.write(buf),
+            #[cfg(all(test, feature = "tls-pinning"))]
+            Self::Server(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(stream) => stream.flush(),
+            #[cfg(feature = "tls-pinning")]
+            Self::Pinned(stream) => stream.flush(),
+            #[cfg(all(test, feature = "tls-pinning"))]
+            Self::Server(stream) => stream.flush(),
+        }
+    }
+}
+
+/// One end of a WebSocket connection.
+pub(crate) struct WebSocket {
+    stream: Stream,
+    /// Whether this is the client end, which masks its frames.
+    client: bool,
+    /// Received bytes not decoded yet.
+    buffer: Vec<u8>,
+    /// Opcode and payload of a fragmented message still being received.
+    message: Option<(Opcode, Vec<u8>)>,
+    /// State of the xorshift generator for frame masks.
+    seed: u64,
+}
+
+impl WebSocket {
+    /// Opens a `ws://` or `wss://` URL, sending `authorization` as the
+    /// `Authorization` header of the handshake.
+    ///
+    /// `timeout` bounds connecting, the TLS handshake, and the WebSocket handshake. A
+    /// certificate not matching pinned keys fails with an `Error::PinMismatch` inside
+    /// the returned error.
+    pub fn connect(
+        url: &str,
+        authorization: Option<&str>,
+        timeout: Duration,
+        tls_settings: &TlsSettings,
+    ) -> io::Result<Self> {
+        let url = Url::parse(url).map_err(|error| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", url, error))
+        })?;
+        let tls = match url.scheme() {
+            "wss" | "https" => true,
+            "ws" | "http" => false,
+            scheme => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported WebSocket scheme {}", scheme),
+                ))
+            }
+        };
+        let host = url.host_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "WebSocket URL has no host")
+        })?;
+        let port = url.port_or_known_default().unwrap_or(if tls { 443 } else { 80 });
+
+        let tcp = connect_tcp(host, port, timeout)?;
+        tcp.set_nodelay(true)?;
+        tcp.set_read_timeout(Some(timeout))?;
+        tcp.set_write_timeout(Some(timeout))?;
+        let stream = if tls {
+            tls_settings.connect(host, tcp)?
+        } else {
+            Stream::Plain(tcp)
+        };
+
+        let mut socket = Self::new(stream, true);
+        socket.handshake(&url, authorization)?;
+        Ok(socket)
+    }
+
+    /// Wraps the server end of a connection whose handshake has been answered.
+    #[cfg(test)]
+    pub fn server(stream: TcpStream) -> Self {
+        Self::new(Stream::Plain(stream), false)
+    }
+
+    /// Wraps the server end of a TLS connection whose handshake has been answered.
+    #[cfg(all(test, feature = "tls-pinning"))]
+    pub fn tls_server(stream: rustls::StreamOwned<rustls::ServerConnection, TcpStream>) -> Self {
+        Self::new(Stream::Server(Box::new(stream)), false)
+    }
+
+    fn new(stream: Stream, client: bool) -> Self {
+        Self {
+            stream,
+            client,
+            buffer: Vec::new(),
+            message: None,
+            seed: RandomState::new().build_hasher().finish() | 1,
+        }
+    }
+
+    fn handshake(&mut self, url: &Url, authorization: Option<&str>) -> io::Result<()> {
+        let mut nonce = [0u8; 16];
+        nonce[..8].copy_from_slice(&self.next_random().to_be_bytes());
+        nonce[8..].copy_from_slice(&self.next_random().to_be_bytes());
+        let key = base64::engine::general_purpose::STANDARD.encode(nonce);
+
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\
+             User-Agent: ironnotify-rust/{}\r\n",
+            target,
+            host,
+            key,
+            env!("CARGO_PKG_VERSION")
+        );
+        if let Some(authorization) = authorization {
+            request.push_str(&format!("Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+        self.stream.write_all(request.as_bytes())?;
+        self.stream.flush()?;
+
+        let head = self.read_head()?;
+        let mut lines = head.split("\r\n");
+        let status = lines.next().unwrap_or_default();
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("WebSocket upgrade refused: {}", status),
+            ));
+        }
+        let accept = lines.find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("sec-websocket-accept")
+                .then(|| value.trim().to_string())
+        });
+        if accept.as_deref() != Some(accept_key(&key).as_str()) {
+            return Err(invalid_data("WebSocket upgrade has a wrong Sec-WebSocket-Accept"));
+        }
+        Ok(())
+    }
+
+    /// Reads the handshake response head, keeping any bytes after it buffered.
+    fn read_head(&mut self) -> io::Result<String> {
+        let mut chunk = [0u8; 1024];
+        loop {
+            if let Some(end) = self.buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                let head: Vec<u8> = self.buffer.drain(..end + 4).collect();
+                return Ok(String::from_utf8_lossy(&head).into_owned());
+            }
+            if self.buffer.len() > MAX_HEAD_LEN {
+                return Err(invalid_data("WebSocket upgrade response is too large"));
+            }
+            let read = self.stream.read(&mut chunk)?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+
+    fn next_random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+
+    /// Gets a handle to the underlying TCP connection, to shut it down from another
+    /// thread.
+    pub fn shutdown_handle(&self) -> io::Result<TcpStream> {
+        self.stream.tcp().try_clone()
+    }
+
+    /// Sends a frame.
+    pub fn send(&mut self, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+        let mask = if self.client {
+            Some((self.next_random() as u32).to_be_bytes())
+        } else {
+            None
+        };
+        self.stream.write_all(&encode_frame(opcode, payload, mask))?;
+        self.stream.flush()
+    }
+
+    /// Sends a text message.
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.send(Opcode::Text, text.as_bytes())
+    }
+
+    /// Sends a close frame and shuts the connection down, ignoring errors.
+    pub fn close(&mut self) {
+        let _ = self.send(Opcode::Close, &1000u16.to_be_bytes());
+        let _ = self.stream.tcp().shutdown(Shutdown::Both);
+    }
+
+    /// Reads the next whole message or control frame, waiting at most `timeout`.
+    ///
+    /// Returns `None` if nothing complete arrived in time; bytes received so far are
+    /// kept for the next call.
+    pub fn read(&mut self, timeout: Duration) -> io::Result<Option<Frame>> {
+        self.stream
+            .tcp()
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))?;
+        let mut chunk = [0u8; 8192];
+        loop {
+            while let Some(frame) = decode_frame(&mut self.buffer)? {
+                if let Some(message) = self.assemble(frame)? {
+                    return Ok(Some(message));
+                }
+            }
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Joins the fragments of a message, passing control frames through.
+    fn assemble(&mut self, frame: Frame) -> io::Result<Option<Frame>> {
+        if frame.opcode.is_control() {
+            return Ok(Some(frame));
+        }
+        let (opcode, mut payload) = match (frame.opcode, self.message.take()) {
+            (Opcode::Continuation, Some((opcode, mut payload))) => {
+                payload.extend_from_slice(&frame.payload);
+                (opcode, payload)
+            }
+            (Opcode::Continuation, None) => {
+                return Err(invalid_data("continuation frame without a message"))
+            }
+            (_, Some(_)) => return Err(invalid_data("message started inside another one")),
+            (opcode, None) => (opcode, frame.payload),
+        };
+        if payload.len() > MAX_MESSAGE_LEN {
+            return Err(invalid_data("message is too large"));
+        }
+        if !frame.fin {
+            self.message = Some((opcode, std::mem::take(&mut payload)));
+            return Ok(None);
+        }
+        Ok(Some(Frame {
+            fin: true,
+            opcode,
+            payload,
+        }))
+    }
+}
+
+/// Connects to the first reachable address of a host.
+fn connect_tcp(host: &str, port: u16, timeout: Duration) -> io::Result<TcpStream> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let mut last_error = None;
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(error) => last_error = Some(error),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no address found for {}", host))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_key_matches_the_rfc_example() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn frames_round_trip_at_each_length_encoding() {
+        for len in [0, 125, 126, 0xFFFF, 0x10000] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            for mask in [None, Some([1, 2, 3, 4])] {
+                let mut buffer = encode_frame(Opcode::Binary, &payload, mask);
+                let frame = decode_frame(&mut buffer).unwrap().unwrap();
+                assert!(frame.fin);
+                assert_eq!(frame.opcode, Opcode::Binary);
+                assert_eq!(frame.payload, payload);
+                assert!(buffer.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn partial_frames_stay_buffered() {
+        let whole = encode_frame(Opcode::Text, b"hello", Some([9, 8, 7, 6]));
+        for split in 0..whole.len() {
+            let mut buffer = whole[..split].to_vec();
+            assert_eq!(decode_frame(&mut buffer).unwrap(), None);
+            assert_eq!(buffer.len(), split);
+            buffer.extend_from_slice(&whole[split..]);
+            assert_eq!(decode_frame(&mut buffer).unwrap().unwrap().payload, b"hello");
+        }
+    }
+
+    #[test]
+    fn fragments_are_joined_around_control_frames() {
+        let (client, server) = connected_pair();
+        let mut client = WebSocket::new(Stream::Plain(client), true);
+        let mut server = server;
+
+        let mut first = encode_frame(Opcode::Text, b"hel", None);
+        first[0] &= 0x7F;
+        server.write_all(&first).unwrap();
+        server.write_all(&encode_frame(Opcode::Ping, b"p", None)).unwrap();
+        server.write_all(&encode_frame(Opcode::Continuation, b"lo", None)).unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let ping = client.read(timeout).unwrap().unwrap();
+        assert_eq!((ping.opcode, ping.payload), (Opcode::Ping, b"p".to_vec()));
+        let text = client.read(timeout).unwrap().unwrap();
+        assert_eq!((text.opcode, text.payload), (Opcode::Text, b"hello".to_vec()));
+        assert_eq!(client.read(Duration::from_millis(10)).unwrap(), None);
+    }
+
+    fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[cfg(feature = "tls-pinning")]
+    fn connect_pinned(pins: crate::PinSet) -> io::Result<WebSocket> {
+        use crate::test_support::{self, MockResponse, MockServer, TrustedTestCa};
+
+        let server = MockServer::start_tls(|_| {
+            MockResponse::websocket(|socket| {
+                let _ = socket.send_text("hello");
+                let _ = socket.read(Duration::from_secs(1));
+            })
+        });
+        let _trusted = TrustedTestCa::new();
+        let mut options = test_support::options(&server);
+        options.certificate_pins = Some(pins);
+        WebSocket::connect(
+            &options.ws_url,
+            None,
+            Duration::from_secs(5),
+            &TlsSettings::new(&options),
+        )
+    }
+
+    #[cfg(feature = "tls-pinning")]
+    #[test]
+    fn pinned_connections_accept_a_matching_key() {
+        use crate::test_support::{TLS_CA_PIN, TLS_CERT_PIN};
+
+        let mut socket = connect_pinned(crate::PinSet::new(TLS_CERT_PIN)).unwrap();
+        let frame = socket.read(Duration::from_secs(5)).unwrap().unwrap();
+        assert_eq!(frame.payload, b"hello");
+
+        let pins = crate::PinSet::new(TLS_CA_PIN).with_backup(TLS_CERT_PIN);
+        assert!(connect_pinned(pins).is_ok());
+    }
+
+    #[cfg(feature = "tls-pinning")]
+    #[test]
+    fn pinned_connections_reject_other_keys() {
+        use crate::error::Error;
+        use crate::test_support::{TLS_CA_PIN, TLS_CERT_PIN};
+
+        let error = connect_pinned(crate::PinSet::new(TLS_CA_PIN)).err().unwrap();
+        let inner = error.get_ref().and_then(|inner| inner.downcast_ref::<Error>());
+        assert!(matches!(inner, Some(Error::PinMismatch(pin)) if pin == TLS_CERT_PIN));
+    }
+}